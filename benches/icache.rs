@@ -0,0 +1,187 @@
+//! 命令キャッシュのベンチマーク。
+//!
+//! `tests/bin` (環境変数 `RISCV_TESTS_DIR` で変更可) に riscv-tests のバイナリがあればそれぞれを実行し、
+//! あわせて合成したワークロード (短いループ、ページを跨ぐジャンプ) の実行時間を計測する。
+//!
+//! ```bash
+//! cargo bench --bench icache
+//! ```
+
+use rv32imc::bus::Bus;
+use rv32imc::bus::default_bus::DefaultBus;
+use rv32imc::cpu::{Cpu, StopReason};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 1 回の `run` で実行する命令数
+const BUDGET: u32 = 10_000;
+
+/// 各ワークロードの繰り返し回数
+const ITERATIONS: u32 = 20;
+
+fn i_type(opcode: u32, funct3: u32, rd: u32, rs1: u32, imm: i32) -> u32 {
+    ((imm as u32 & 0xfff) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+fn r_type(funct7: u32, funct3: u32, rd: u32, rs1: u32, rs2: u32) -> u32 {
+    (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | 0b0110011
+}
+
+fn s_type(funct3: u32, rs1: u32, rs2: u32, imm: i32) -> u32 {
+    let imm = imm as u32 & 0xfff;
+    ((imm >> 5) << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | ((imm & 0x1f) << 7) | 0b0100011
+}
+
+fn addi(rd: u32, rs1: u32, imm: i32) -> u32 {
+    i_type(0b0010011, 0b000, rd, rs1, imm)
+}
+
+fn lui(rd: u32, imm: u32) -> u32 {
+    (imm << 12) | (rd << 7) | 0b0110111
+}
+
+fn jal(rd: u32, offset: i32) -> u32 {
+    let imm = offset as u32;
+    (((imm >> 20) & 1) << 31) | (((imm >> 1) & 0x3ff) << 21) | (((imm >> 11) & 1) << 20)
+        | (((imm >> 12) & 0xff) << 12) | (rd << 7) | 0b1101111
+}
+
+fn branch(funct3: u32, rs1: u32, rs2: u32, offset: i32) -> u32 {
+    let imm = offset as u32;
+    (((imm >> 12) & 1) << 31) | (((imm >> 5) & 0x3f) << 25) | (rs2 << 20) | (rs1 << 15)
+        | (funct3 << 12) | (((imm >> 1) & 0xf) << 8) | (((imm >> 11) & 1) << 7) | 0b1100011
+}
+
+const ECALL: u32 = 0x0000_0073;
+
+/// `rd` に `val` を設定する lui + addi
+fn li(rd: u32, val: u32) -> [u32; 2] {
+    let hi = val.wrapping_add(0x800) >> 12;
+    let lo = val.wrapping_sub(hi << 12) as i32;
+    [lui(rd, hi), addi(rd, rd, lo)]
+}
+
+/// 1 ページ内の短いループを `count` 回まわすプログラム
+fn tight_loop(bus: &mut DefaultBus, count: u32) {
+    let [li_hi, li_lo] = li(2, count);
+    let program = [
+        li_hi,
+        li_lo,
+        addi(1, 1, 1),
+        addi(3, 3, 3),
+        branch(0b001, 1, 2, -8), // bne x1, x2, -8
+        ECALL,
+    ];
+    for (i, inst) in program.iter().enumerate() {
+        bus.write32(i as u32 * 4, *inst).unwrap();
+    }
+}
+
+/// ALU 演算・乗算・ロード/ストアを組み合わせた計算の多いループを `count` 回まわすプログラム。
+/// ループ本体を展開して分岐を減らし、命令のディスパッチの速度を測る
+fn compute(bus: &mut DefaultBus, count: u32) {
+    const UNROLL: usize = 8;
+    let body = [
+        r_type(0b0000000, 0b000, 5, 5, 1),    // add  x5, x5, x1
+        r_type(0b0000000, 0b100, 6, 6, 5),    // xor  x6, x6, x5
+        i_type(0b0010011, 0b001, 7, 6, 3),    // slli x7, x6, 3
+        i_type(0b0010011, 0b101, 8, 6, 5),    // srli x8, x6, 5
+        r_type(0b0000000, 0b100, 6, 7, 8),    // xor  x6, x7, x8
+        r_type(0b0000001, 0b000, 9, 6, 1),    // mul  x9, x6, x1
+        addi(10, 10, 7),
+        r_type(0b0100000, 0b000, 11, 9, 10),  // sub  x11, x9, x10
+        r_type(0b0000000, 0b111, 12, 11, 6),  // and  x12, x11, x6
+        r_type(0b0000000, 0b110, 13, 12, 5),  // or   x13, x12, x5
+        r_type(0b0000000, 0b011, 14, 13, 9),  // sltu x14, x13, x9
+        r_type(0b0000000, 0b000, 15, 15, 14), // add  x15, x15, x14
+        s_type(0b010, 20, 15, 0),             // sw   x15, 0(x20)
+        i_type(0b0000011, 0b010, 16, 20, 0),  // lw   x16, 0(x20)
+    ];
+    let mut program = li(2, count).to_vec();
+    program.push(lui(20, 0x2)); // コードとは別のページをデータ領域に使う
+    for _ in 0..UNROLL {
+        program.extend_from_slice(&body);
+    }
+    program.push(addi(1, 1, 1));
+    let loop_len = (body.len() * UNROLL + 1) as i32 * 4;
+    program.push(branch(0b001, 1, 2, -loop_len)); // bne x1, x2, loop
+    program.push(ECALL);
+    for (i, inst) in program.iter().enumerate() {
+        bus.write32(i as u32 * 4, *inst).unwrap();
+    }
+}
+
+/// `pages` 個のページを順にジャンプで渡り歩くループを `count` 回まわすプログラム
+fn page_hopping(bus: &mut DefaultBus, pages: u32, count: u32) {
+    let [li_hi, li_lo] = li(2, count);
+    bus.write32(0x0, li_hi).unwrap();
+    bus.write32(0x4, li_lo).unwrap();
+    bus.write32(0x8, jal(0, 0x1000 - 8)).unwrap();
+    for page in 1..=pages {
+        let base = page * 0x1000;
+        bus.write32(base, addi(3, 3, 1)).unwrap();
+        bus.write32(base + 4, jal(0, 0x1000 - 4)).unwrap();
+    }
+    let base = (pages + 1) * 0x1000;
+    bus.write32(base, addi(1, 1, 1)).unwrap();
+    bus.write32(base + 4, branch(0b101, 1, 2, 8)).unwrap(); // bge x1, x2, +8
+    bus.write32(base + 8, jal(0, 0x1000 - base as i32 - 8)).unwrap();
+    bus.write32(base + 12, ECALL).unwrap();
+}
+
+/// ecall (またはタイムアウト) まで実行し、実行した命令数を返す
+fn run_to_ecall(cpu: &mut Cpu, bus: &mut DefaultBus, max_insts: u64) -> u64 {
+    let mut total = 0;
+    while total < max_insts {
+        let (reason, executed) = cpu.run(bus, BUDGET);
+        total += executed as u64;
+        match reason {
+            StopReason::Trap(8 | 9 | 11) => break,
+            StopReason::Waiting => bus.clint.fast_forward(),
+            _ => {}
+        }
+    }
+    total
+}
+
+/// ワークロードを `ITERATIONS` 回実行し、最も速かった回の実行時間を表示する
+fn bench(name: &str, setup: impl Fn(&mut DefaultBus), max_insts: u64) {
+    let mut best = Duration::MAX;
+    let mut insts = 0;
+    for _ in 0..ITERATIONS {
+        let mut bus = DefaultBus::new(1024 * 1024);
+        setup(&mut bus);
+        let mut cpu = Cpu::new(0x0);
+        let start = Instant::now();
+        insts = run_to_ecall(&mut cpu, &mut bus, max_insts);
+        best = best.min(start.elapsed());
+    }
+    let mips = insts as f64 / best.as_secs_f64() / 1e6;
+    println!("{:<32} {:>12} insts {:>10.3} ms {:>8.1} MIPS", name, insts, best.as_secs_f64() * 1e3, mips);
+}
+
+fn riscv_tests() -> Vec<PathBuf> {
+    let dir = env::var("RISCV_TESTS_DIR").unwrap_or_else(|_| "tests/bin".to_string());
+    let Ok(entries) = fs::read_dir(Path::new(&dir)) else { return Vec::new() };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("bin"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn main() {
+    bench("tight_loop", |bus| tight_loop(bus, 1_000_000), u64::MAX);
+    bench("compute", |bus| compute(bus, 30_000), u64::MAX);
+    bench("page_hopping (8 pages)", |bus| page_hopping(bus, 8, 50_000), u64::MAX);
+    bench("page_hopping (64 pages)", |bus| page_hopping(bus, 64, 10_000), u64::MAX);
+
+    for path in riscv_tests() {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let data = fs::read(&path).unwrap();
+        bench(&name, |bus| bus.load(0, &data).unwrap(), 1_000_000);
+    }
+}
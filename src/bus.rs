@@ -1,45 +1,106 @@
 pub mod default_bus;
+pub mod memory_map;
+pub mod device;
 pub mod mock_bus;
 pub mod plic;
 pub mod clint;
+pub mod htif;
+
+use device::AccessWidth;
 
 #[cfg(test)]
 mod tests;
 
+/// バスアクセスの失敗理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusError {
+    /// 何もマップされていないアドレスへのアクセス
+    Unmapped,
+    /// ROM など書き込みできない領域への書き込み
+    ReadOnly,
+    /// デバイスが対応していない幅・アラインメントのアクセス
+    UnsupportedAccess,
+}
+
 /// メモリの読み書きを行うためのバス。
 /// 実装は外側に任せる。
+/// アクセスに失敗した場合は `BusError` を返し、CPU 側でアクセスフォールトとして扱う。
 pub trait Bus {
-    fn read8(&mut self, addr: u32) -> u8;
-    fn read16(&mut self, addr: u32) -> u16;
-    fn read32(&mut self, addr: u32) -> u32;
+    fn read8(&mut self, addr: u32) -> Result<u8, BusError>;
+    fn read16(&mut self, addr: u32) -> Result<u16, BusError>;
+    fn read32(&mut self, addr: u32) -> Result<u32, BusError>;
+
+    fn write8(&mut self, addr: u32, val: u8) -> Result<(), BusError>;
+    fn write16(&mut self, addr: u32, val: u16) -> Result<(), BusError>;
+    fn write32(&mut self, addr: u32, val: u32) -> Result<(), BusError>;
+
+    /// `addr` への `width` の書き込みが成功するか、副作用なしで確認する。
+    /// ミスアラインなストアをバイト単位で書き込む前に、途中で失敗しないことを確かめるために使う。
+    /// デフォルトは常に成功するものとする
+    fn check_write(&self, _addr: u32, _width: AccessWidth) -> Result<(), BusError> {
+        Ok(())
+    }
+
+    /// ホストから `addr` 以降に `data` を書き込む (プログラムのロード用。ROM にも書き込める)。
+    /// デフォルトは `write8` で 1 バイトずつ書き込む
+    fn load(&mut self, addr: u32, data: &[u8]) -> Result<(), BusError> {
+        for (i, byte) in data.iter().enumerate() {
+            let addr = u32::try_from(i).ok().and_then(|i| addr.checked_add(i)).ok_or(BusError::Unmapped)?;
+            self.write8(addr, *byte)?;
+        }
+        Ok(())
+    }
+
+    /// ハート `hart` への PLIC からの割り込み要求レベルを取得する（デフォルトは false）
+    fn get_interrupt_level(&self, _hart: usize) -> bool {
+        false
+    }
 
-    fn write8(&mut self, addr: u32, val: u8);
-    fn write16(&mut self, addr: u32, val: u16);
-    fn write32(&mut self, addr: u32, val: u32);
+    /// ハート `hart` の S モードの外部割り込み (mip.SEIP) の要求レベルを取得する。
+    /// 接続していない場合は None (デフォルト) で、mip.SEIP はソフトウェアが書き込んだ値のままになる
+    fn get_supervisor_interrupt_level(&self, _hart: usize) -> Option<bool> {
+        None
+    }
 
-    /// PLIC からの割り込み要求レベルを取得する（デフォルトは false）
-    fn get_interrupt_level(&self) -> bool {
+    /// ハート `hart` のタイマー割り込み要求レベルを取得する（デフォルトは false）
+    fn get_timer_interrupt_level(&self, _hart: usize) -> bool {
         false
     }
 
-    /// タイマー割り込み要求レベルを取得する（デフォルトは false）
-    fn get_timer_interrupt_level(&self) -> bool {
+    /// ハート `hart` のソフトウェア割り込み要求レベルを取得する（デフォルトは false）
+    fn get_software_interrupt_level(&self, _hart: usize) -> bool {
         false
     }
 
-    /// ソフトウェア割り込み要求レベルを取得する（デフォルトは false）
-    fn get_software_interrupt_level(&self) -> bool {
+    /// ハート `hart` への S モードのソフトウェア割り込み要求 (ACLINT の SSWI) を取り出す（デフォルトは false）。
+    /// true を返した場合、CPU は mip.SSIP を立てる
+    fn take_supervisor_software_interrupt(&mut self, _hart: usize) -> bool {
         false
     }
 
-    /// クロックを進める
-    fn tick(&mut self) {}
+    /// CLINT の mtime の現在値を取得する（デフォルトは 0）
+    fn get_mtime(&self) -> u64 {
+        0
+    }
+
+    /// クロックを `cycles` サイクル進める。
+    /// CPU は前回から実行した命令数 (1 命令 1 サイクル) を渡し、WFI で待機中は 1 ステップを 1 サイクルとして渡す
+    fn tick(&mut self, _cycles: u32) {}
+
+    /// CPU が命令キャッシュに載せた物理ページ (ページ番号) を通知する。
+    /// バスは以降のこのページへの書き込みを記録し、`take_code_write` で CPU に返す
+    fn watch_code_page(&mut self, _page: u32) {}
+
+    /// 監視中のページへの書き込みがあれば、そのページ番号を 1 つ取り出す (デフォルトは記録しない)
+    fn take_code_write(&mut self) -> Option<u32> {
+        None
+    }
 
-    /// PLIC からの割り込みを取得する (Claim)
-    fn plic_claim(&mut self) -> u32 {
+    /// ハート `hart` の M モードのコンテキストで PLIC からの割り込みを取得する (Claim)
+    fn plic_claim(&mut self, _hart: usize) -> u32 {
         0
     }
 
-    /// PLIC に割り込みの完了を通知する (Complete)
-    fn plic_complete(&mut self, _source_id: u32) {}
+    /// ハート `hart` の M モードのコンテキストで PLIC に割り込みの完了を通知する (Complete)
+    fn plic_complete(&mut self, _hart: usize, _source_id: u32) {}
 }
@@ -1,82 +1,319 @@
+use std::time::Instant;
+
+use super::BusError;
+use super::device::{AccessWidth, Device, SubWordAccess};
+
+/// CLINT が扱えるハート数の上限 (mtimecmp を並べられる数)
+pub const MAX_HART_COUNT: usize = 4095;
+
+/// CLINT 配置での mtimecmp の先頭 (0x4000 + 8*hart)
+const CLINT_MTIMECMP_BASE: u32 = 0x4000;
+/// CLINT 配置での mtime
+const CLINT_MTIME: u32 = 0xbff8;
+/// ACLINT の MTIMER での mtime (mtimecmp は 0x0000 + 8*hart)
+const MTIMER_MTIME: u32 = 0x7ff8;
+/// msip・setssip を並べる範囲の終わり
+const SWI_END: u32 = 0x4000;
+
+/// mtime の進め方 (timebase)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timebase {
+    /// CPU の `cycles` サイクルごとに mtime を `ticks` 進める。
+    /// 例えば 100 MHz の仮想コアに 10 MHz の timebase なら `ticks: 1, cycles: 10`
+    Cycles { ticks: u32, cycles: u32 },
+    /// ホストの単調増加する時計から、`hz` の周波数で mtime を進める
+    HostClock { hz: u64 },
+}
+
+impl Default for Timebase {
+    /// 1 サイクルごとに 1 進める
+    fn default() -> Self {
+        Timebase::Cycles { ticks: 1, cycles: 1 }
+    }
+}
+
+/// ホストの時計で mtime を進める場合の基準点
+#[derive(Debug, Clone, Copy)]
+struct HostAnchor {
+    /// 基準点のホストの時刻
+    instant: Instant,
+    /// 基準点の mtime
+    mtime: u64,
+    /// 最後に設定した mtime (これと異なる場合は、ゲストやホストが mtime を書き換えたものとして基準点を取り直す)
+    last_mtime: u64,
+}
+
+/// CLINT のレジスタの配置。
+/// 状態は `Clint` が持ち、この配置に従ってオフセットをレジスタに対応させる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClintRegion {
+    /// SiFive CLINT 互換の配置 (msip: 0x0000 + 4*hart, mtimecmp: 0x4000 + 8*hart, mtime: 0xbff8)
+    Clint,
+    /// ACLINT の MSWI (msip: 0x0000 + 4*hart)
+    Mswi,
+    /// ACLINT の MTIMER (mtimecmp: 0x0000 + 8*hart, mtime: 0x7ff8)
+    Mtimer,
+    /// ACLINT の SSWI (setssip: 0x0000 + 4*hart)
+    Sswi,
+}
+
+impl ClintRegion {
+    /// 領域のサイズ
+    pub fn size(self) -> u32 {
+        match self {
+            ClintRegion::Clint => 0x1_0000,
+            ClintRegion::Mswi | ClintRegion::Sswi => 0x4000,
+            ClintRegion::Mtimer => 0x8000,
+        }
+    }
+}
+
 /// CLINT (Core Local Interruptor)
 /// タイマー割り込みとソフトウェア割り込みを管理する
-
 pub struct Clint {
     /// Machine Software Interrupt Pending (MSIP)
-    /// Hart 0 用 (4バイト)
-    pub msip: u32,
+    /// ハートごと (4バイト)
+    pub msip: Vec<u32>,
     /// Machine Timer Compare Register (mtimecmp)
-    /// Hart 0 用 (8バイト)
-    pub mtimecmp: u64,
+    /// ハートごと (8バイト)
+    pub mtimecmp: Vec<u64>,
     /// Machine Real Time Counter (mtime)
     /// システム共有 (8バイト)
     pub mtime: u64,
+    /// SSWI の setssip に書き込まれた、S モードのソフトウェア割り込み要求 (CPU が mip.SSIP に取り出すまで保持する)
+    pub ssip: Vec<bool>,
+    /// 32 ビットより幅の狭いアクセスの扱い
+    pub sub_word: SubWordAccess,
+    /// mtime の進め方
+    timebase: Timebase,
+    /// `Timebase::Cycles` の `cycles` が 1 の場合の `ticks` (それ以外は 0)
+    ticks_per_cycle: u64,
+    /// `Timebase::Cycles` で、まだ mtime に反映していないサイクル数 (`ticks` 倍したもの)
+    pending_cycles: u64,
+    /// `Timebase::HostClock` の基準点
+    host_anchor: Option<HostAnchor>,
+}
+
+impl Default for Clint {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Clint {
+    /// ハート 1 つ分の CLINT を作る
     pub fn new() -> Self {
+        Self::with_harts(1)
+    }
+
+    /// `hart_count` 個 (最大 `MAX_HART_COUNT`) のハート分のレジスタを持つ CLINT を作る
+    pub fn with_harts(hart_count: usize) -> Self {
+        assert!(hart_count <= MAX_HART_COUNT, "CLINT のハート数は最大 {} です", MAX_HART_COUNT);
         Self {
-            msip: 0,
-            mtimecmp: 0,
+            msip: vec![0; hart_count],
+            mtimecmp: vec![0; hart_count],
             mtime: 0,
+            ssip: vec![false; hart_count],
+            sub_word: SubWordAccess::Merge,
+            timebase: Timebase::default(),
+            ticks_per_cycle: 1,
+            pending_cycles: 0,
+            host_anchor: None,
         }
     }
 
-    pub fn read(&self, addr: u32) -> u32 {
-        match addr {
-            // 0x0000: msip for hart 0
-            0x0000 => self.msip,
-            // 0x4000: mtimecmp for hart 0 (low 32-bit)
-            0x4000 => (self.mtimecmp & 0xffff_ffff) as u32,
-            // 0x4004: mtimecmp for hart 0 (high 32-bit)
-            0x4004 => (self.mtimecmp >> 32) as u32,
-            // 0xbff8: mtime (low 32-bit)
-            0xbff8 => (self.mtime & 0xffff_ffff) as u32,
-            // 0xbffc: mtime (high 32-bit)
-            0xbffc => (self.mtime >> 32) as u32,
-            _ => 0,
+    /// mtime の進め方
+    pub fn timebase(&self) -> Timebase {
+        self.timebase
+    }
+
+    /// mtime の進め方を設定する。次の `tick` から、現在の mtime を起点に新しい進め方で進める
+    pub fn set_timebase(&mut self, timebase: Timebase) {
+        match timebase {
+            Timebase::Cycles { cycles, .. } => assert!(cycles > 0, "timebase のサイクル数は 1 以上です"),
+            Timebase::HostClock { hz } => assert!(hz > 0, "timebase の周波数は 1 以上です"),
         }
+        self.timebase = timebase;
+        self.ticks_per_cycle = match timebase {
+            Timebase::Cycles { ticks, cycles: 1 } => ticks as u64,
+            _ => 0,
+        };
+        self.pending_cycles = 0;
+        self.host_anchor = None;
     }
 
+    /// ハート数
+    pub fn hart_count(&self) -> usize {
+        self.msip.len()
+    }
+
+    /// CLINT 配置でのレジスタを読み出す
+    pub fn read(&self, addr: u32) -> u32 {
+        self.read_region(ClintRegion::Clint, addr)
+    }
+
+    /// CLINT 配置でのレジスタに書き込む
     pub fn write(&mut self, addr: u32, val: u32) {
-        match addr {
-            // 0x0000: msip for hart 0
-            0x0000 => {
-                // 下位1ビットのみが有効
-                self.msip = val & 1;
-            }
-            // 0x4000: mtimecmp for hart 0 (low 32-bit)
-            0x4000 => {
-                self.mtimecmp = (self.mtimecmp & !0xffff_ffff) | (val as u64);
-            }
-            // 0x4004: mtimecmp for hart 0 (high 32-bit)
-            0x4004 => {
-                self.mtimecmp = (self.mtimecmp & 0xffff_ffff) | ((val as u64) << 32);
+        self.write_region(ClintRegion::Clint, addr, val);
+    }
+
+    /// `region` の配置でのレジスタを読み出す。存在しないハートのレジスタは 0
+    pub fn read_region(&self, region: ClintRegion, addr: u32) -> u32 {
+        match (region, addr) {
+            // 0x0000 + 4*hart: msip
+            (ClintRegion::Clint | ClintRegion::Mswi, 0x0000..SWI_END) => {
+                self.msip.get((addr / 4) as usize).copied().unwrap_or(0)
             }
-            // 0xbff8: mtime (low 32-bit)
-            0xbff8 => {
-                self.mtime = (self.mtime & !0xffff_ffff) | (val as u64);
+            // mtime (low / high 32-bit)
+            (ClintRegion::Clint, CLINT_MTIME..0xc000) => Self::read_half(self.mtime, addr - CLINT_MTIME),
+            (ClintRegion::Mtimer, MTIMER_MTIME..0x8000) => Self::read_half(self.mtime, addr - MTIMER_MTIME),
+            // mtimecmp (low / high 32-bit)
+            (ClintRegion::Clint, CLINT_MTIMECMP_BASE..CLINT_MTIME) => self.read_mtimecmp(addr - CLINT_MTIMECMP_BASE),
+            (ClintRegion::Mtimer, 0x0000..MTIMER_MTIME) => self.read_mtimecmp(addr),
+            // setssip や、レジスタのないオフセットは 0 と読める
+            _ => 0,
+        }
+    }
+
+    /// `region` の配置でのレジスタに書き込む。存在しないハートへの書き込みは無視する
+    pub fn write_region(&mut self, region: ClintRegion, addr: u32, val: u32) {
+        match (region, addr) {
+            // 0x0000 + 4*hart: msip
+            (ClintRegion::Clint | ClintRegion::Mswi, 0x0000..SWI_END) => {
+                if let Some(msip) = self.msip.get_mut((addr / 4) as usize) {
+                    // 下位1ビットのみが有効
+                    *msip = val & 1;
+                }
             }
-            // 0xbffc: mtime (high 32-bit)
-            0xbffc => {
-                self.mtime = (self.mtime & 0xffff_ffff) | ((val as u64) << 32);
+            // mtime (low / high 32-bit)
+            (ClintRegion::Clint, CLINT_MTIME..0xc000) => Self::write_half(&mut self.mtime, addr - CLINT_MTIME, val),
+            (ClintRegion::Mtimer, MTIMER_MTIME..0x8000) => Self::write_half(&mut self.mtime, addr - MTIMER_MTIME, val),
+            // mtimecmp (low / high 32-bit)
+            (ClintRegion::Clint, CLINT_MTIMECMP_BASE..CLINT_MTIME) => self.write_mtimecmp(addr - CLINT_MTIMECMP_BASE, val),
+            (ClintRegion::Mtimer, 0x0000..MTIMER_MTIME) => self.write_mtimecmp(addr, val),
+            // 0x0000 + 4*hart: setssip (1 を書き込むと S モードのソフトウェア割り込みを要求する)
+            (ClintRegion::Sswi, 0x0000..SWI_END) => {
+                if val & 1 != 0
+                    && let Some(ssip) = self.ssip.get_mut((addr / 4) as usize)
+                {
+                    *ssip = true;
+                }
             }
             _ => {}
         }
     }
 
-    /// タイマーを進める
-    pub fn tick(&mut self) {
-        self.mtime = self.mtime.wrapping_add(1);
+    /// 64 ビットのレジスタ `reg` の、`offset` (0: 下位, 4: 上位) の 32 ビットを読み出す
+    fn read_half(reg: u64, offset: u32) -> u32 {
+        (reg >> (offset * 8)) as u32
+    }
+
+    /// 64 ビットのレジスタ `reg` の、`offset` (0: 下位, 4: 上位) の 32 ビットに書き込む
+    fn write_half(reg: &mut u64, offset: u32, val: u32) {
+        let shift = offset * 8;
+        *reg = (*reg & !(0xffff_ffff << shift)) | ((val as u64) << shift);
+    }
+
+    fn read_mtimecmp(&self, offset: u32) -> u32 {
+        match self.mtimecmp.get((offset / 8) as usize) {
+            Some(&mtimecmp) => Self::read_half(mtimecmp, offset % 8),
+            None => 0,
+        }
+    }
+
+    fn write_mtimecmp(&mut self, offset: u32, val: u32) {
+        if let Some(mtimecmp) = self.mtimecmp.get_mut((offset / 8) as usize) {
+            Self::write_half(mtimecmp, offset % 8, val);
+        }
+    }
+
+    /// CPU の `cycles` サイクル分、`timebase` に従ってタイマーを進める
+    #[inline(always)]
+    pub fn tick(&mut self, cycles: u32) {
+        // 1 サイクルごとに進める場合は割り算を省く (ブロックを移るたびに呼ばれるため)
+        if self.ticks_per_cycle != 0 {
+            self.mtime = self.mtime.wrapping_add(cycles as u64 * self.ticks_per_cycle);
+        } else {
+            self.tick_slow(cycles);
+        }
+    }
+
+    #[inline(never)]
+    fn tick_slow(&mut self, cycles: u32) {
+        match self.timebase {
+            Timebase::Cycles { ticks, cycles: period } => {
+                let total = self.pending_cycles + cycles as u64 * ticks as u64;
+                self.mtime = self.mtime.wrapping_add(total / period as u64);
+                self.pending_cycles = total % period as u64;
+            }
+            Timebase::HostClock { hz } => self.sync_host_clock(hz),
+        }
+    }
+
+    /// mtime をホストの時計に合わせる
+    fn sync_host_clock(&mut self, hz: u64) {
+        let now = Instant::now();
+        let anchor = match self.host_anchor {
+            Some(anchor) if anchor.last_mtime == self.mtime => anchor,
+            // 初回か、mtime が書き換えられていれば、現在の mtime を基準点にする
+            _ => HostAnchor { instant: now, mtime: self.mtime, last_mtime: self.mtime },
+        };
+        let elapsed = now.duration_since(anchor.instant).as_nanos();
+        self.mtime = anchor.mtime.wrapping_add((elapsed * hz as u128 / 1_000_000_000) as u64);
+        self.host_anchor = Some(HostAnchor { last_mtime: self.mtime, ..anchor });
+    }
+
+    /// mtime を、まだ来ていない mtimecmp のうち最も早いものまで進める。
+    /// WFI で待機中のホストが、次のタイマー割り込みまで時間を早送りするために使う
+    pub fn fast_forward(&mut self) {
+        if let Some(&next) = self.mtimecmp.iter().filter(|&&cmp| cmp > self.mtime).min() {
+            self.mtime = next;
+        }
+    }
+
+    /// ハート `hart` のタイマー割り込みが発生しているか
+    pub fn get_timer_interrupt_level(&self, hart: usize) -> bool {
+        self.mtimecmp.get(hart).is_some_and(|&mtimecmp| self.mtime >= mtimecmp)
+    }
+
+    /// ハート `hart` のソフトウェア割り込みが発生しているか
+    pub fn get_software_interrupt_level(&self, hart: usize) -> bool {
+        self.msip.get(hart).is_some_and(|&msip| (msip & 1) != 0)
+    }
+
+    /// ハート `hart` への S モードのソフトウェア割り込み要求 (SSWI) を取り出す
+    pub fn take_supervisor_software_interrupt(&mut self, hart: usize) -> bool {
+        self.ssip.get_mut(hart).is_some_and(std::mem::take)
+    }
+
+    /// `region` の配置でのバスからの読み出し。64 ビットのレジスタは 32 ビットずつのレジスタとして扱い、
+    /// 幅の狭いアクセスは `sub_word` に従ってバイトレーン単位で読み書きする
+    pub fn read_access(&mut self, region: ClintRegion, offset: u32, width: AccessWidth) -> Result<u32, BusError> {
+        self.sub_word.check(offset, width)?;
+        Ok(width.extract(offset, self.read_region(region, offset & !3)))
+    }
+
+    /// `region` の配置でのバスからの書き込み
+    pub fn write_access(&mut self, region: ClintRegion, offset: u32, width: AccessWidth, val: u32) -> Result<(), BusError> {
+        self.sub_word.check(offset, width)?;
+        let word = offset & !3;
+        let merged = width.merge(offset, self.read_region(region, word), val);
+        self.write_region(region, word, merged);
+        Ok(())
+    }
+}
+
+/// CLINT 配置でのバスからのアクセス
+impl Device for Clint {
+    fn read(&mut self, offset: u32, width: AccessWidth) -> Result<u32, BusError> {
+        self.read_access(ClintRegion::Clint, offset, width)
     }
 
-    /// タイマー割り込みが発生しているか
-    pub fn get_timer_interrupt_level(&self) -> bool {
-        self.mtime >= self.mtimecmp
+    fn write(&mut self, offset: u32, width: AccessWidth, val: u32) -> Result<(), BusError> {
+        self.write_access(ClintRegion::Clint, offset, width, val)
     }
 
-    /// ソフトウェア割り込みが発生しているか
-    pub fn get_software_interrupt_level(&self) -> bool {
-        (self.msip & 1) != 0
+    fn check_write(&self, offset: u32, width: AccessWidth) -> Result<(), BusError> {
+        self.sub_word.check(offset, width)
     }
 }
@@ -1,8 +1,10 @@
-use super::Bus;
-use super::plic::Plic;
+use super::{Bus, BusError};
+use super::device::{AccessWidth, Device};
+use super::plic::{Plic, SOURCE_COUNT};
 use super::clint::Clint;
 use std::fs;
 use std::io;
+use std::ops::Range;
 
 pub const PLIC_BASE: u32 = 0x0c00_0000;
 pub const PLIC_SIZE: u32 = 0x0040_0000;
@@ -10,123 +12,215 @@ pub const PLIC_SIZE: u32 = 0x0040_0000;
 pub const CLINT_BASE: u32 = 0x0200_0000;
 pub const CLINT_SIZE: u32 = 0x0001_0000;
 
+const PLIC_RANGE: Range<u32> = PLIC_BASE..PLIC_BASE + PLIC_SIZE;
+const CLINT_RANGE: Range<u32> = CLINT_BASE..CLINT_BASE + CLINT_SIZE;
+
 pub struct DefaultBus {
-    pub memory: Vec<u8>,
+    /// RAM (ホストからの書き込みは、命令キャッシュへの通知のため `load` を経由する)
+    memory: Vec<u8>,
     pub plic: Plic,
     pub clint: Clint,
+    /// CPU の命令キャッシュに載っているページのビットマップ
+    code_pages: Vec<u64>,
+    /// 命令キャッシュに載っているページへの書き込み (CPU が取り出すまで保持する)
+    code_writes: Vec<u32>,
 }
 
 impl DefaultBus {
     pub fn new(size: usize) -> Self {
+        let page_count = size.div_ceil(4096);
         Self {
             memory: vec![0; size],
             plic: Plic::new(),
             clint: Clint::new(),
+            code_pages: vec![0; page_count.div_ceil(64)],
+            code_writes: Vec::new(),
         }
     }
 
+    /// `hart_count` 個のハートで共有するバスを作る。
+    /// CLINT はハートごとの msip・mtimecmp を持ち、PLIC はハートごとに M モードと S モードのコンテキストを持つ
+    pub fn with_harts(size: usize, hart_count: usize) -> Self {
+        let mut bus = Self::new(size);
+        bus.plic = Plic::with_harts(SOURCE_COUNT, hart_count);
+        bus.clint = Clint::with_harts(hart_count);
+        bus
+    }
+
+    /// RAM の内容 (読み出し専用)
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// ファイルの内容をそのまま `offset` から書き込む。メモリに収まらない場合はエラーにする
     pub fn load_bin(&mut self, path: &str, offset: usize) -> io::Result<()> {
         let data = fs::read(path)?;
-        for (i, byte) in data.iter().enumerate() {
-            if offset + i < self.memory.len() {
-                self.memory[offset + i] = *byte;
-            }
+        let addr = u32::try_from(offset).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "offset is out of memory"))?;
+        self.load(addr, &data)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "binary does not fit in memory"))
+    }
+
+    /// ホストから `addr` 以降に `data` を書き込む。メモリに収まらない場合は何も書き込まずにエラーにする
+    pub fn load(&mut self, addr: u32, data: &[u8]) -> Result<(), BusError> {
+        if data.is_empty() {
+            return Ok(());
         }
+        let range = self.ram_range(addr, data.len())?;
+        self.memory[range.clone()].copy_from_slice(data);
+        self.note_ram_write(range);
         Ok(())
     }
+
+    /// RAM への書き込みが命令キャッシュに載っているページに掛かっていれば記録する
+    #[inline(always)]
+    fn note_ram_write(&mut self, range: Range<usize>) {
+        for page in (range.start >> 12)..=((range.end - 1) >> 12) {
+            let (word, bit) = (page / 64, 1u64 << (page % 64));
+            if (self.code_pages[word] & bit) != 0 {
+                self.code_pages[word] &= !bit;
+                self.code_writes.push(page as u32);
+            }
+        }
+    }
+
+    /// RAM 上の `addr` から `size` バイトの範囲を返す。範囲外ならアクセスエラー
+    #[inline(always)]
+    fn ram_range(&self, addr: u32, size: usize) -> Result<Range<usize>, BusError> {
+        let start = addr as usize;
+        match start.checked_add(size) {
+            Some(end) if end <= self.memory.len() => Ok(start..end),
+            _ => Err(BusError::Unmapped),
+        }
+    }
 }
 
 impl Bus for DefaultBus {
-    fn read8(&mut self, addr: u32) -> u8 {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.read(addr - PLIC_BASE) as u8
-        } else if addr >= CLINT_BASE && addr < CLINT_BASE + CLINT_SIZE {
-            self.clint.read(addr - CLINT_BASE) as u8
+    fn read8(&mut self, addr: u32) -> Result<u8, BusError> {
+        if PLIC_RANGE.contains(&addr) {
+            Device::read(&mut self.plic, addr - PLIC_BASE, AccessWidth::Byte).map(|val| val as u8)
+        } else if CLINT_RANGE.contains(&addr) {
+            Device::read(&mut self.clint, addr - CLINT_BASE, AccessWidth::Byte).map(|val| val as u8)
         } else {
-            self.memory[addr as usize]
+            let range = self.ram_range(addr, 1)?;
+            Ok(self.memory[range.start])
         }
     }
 
-    fn read16(&mut self, addr: u32) -> u16 {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.read(addr - PLIC_BASE) as u16
-        } else if addr >= CLINT_BASE && addr < CLINT_BASE + CLINT_SIZE {
-            self.clint.read(addr - CLINT_BASE) as u16
+    fn read16(&mut self, addr: u32) -> Result<u16, BusError> {
+        if PLIC_RANGE.contains(&addr) {
+            Device::read(&mut self.plic, addr - PLIC_BASE, AccessWidth::Half).map(|val| val as u16)
+        } else if CLINT_RANGE.contains(&addr) {
+            Device::read(&mut self.clint, addr - CLINT_BASE, AccessWidth::Half).map(|val| val as u16)
         } else {
-            let addr = addr as usize;
-            u16::from_le_bytes([self.memory[addr], self.memory[addr + 1]])
+            let range = self.ram_range(addr, 2)?;
+            Ok(u16::from_le_bytes(self.memory[range].try_into().unwrap()))
         }
     }
 
-    fn read32(&mut self, addr: u32) -> u32 {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.read(addr - PLIC_BASE)
-        } else if addr >= CLINT_BASE && addr < CLINT_BASE + CLINT_SIZE {
-            self.clint.read(addr - CLINT_BASE)
+    fn read32(&mut self, addr: u32) -> Result<u32, BusError> {
+        if PLIC_RANGE.contains(&addr) {
+            Device::read(&mut self.plic, addr - PLIC_BASE, AccessWidth::Word)
+        } else if CLINT_RANGE.contains(&addr) {
+            Device::read(&mut self.clint, addr - CLINT_BASE, AccessWidth::Word)
         } else {
-            let addr = addr as usize;
-            u32::from_le_bytes([
-                self.memory[addr],
-                self.memory[addr + 1],
-                self.memory[addr + 2],
-                self.memory[addr + 3],
-            ])
+            let range = self.ram_range(addr, 4)?;
+            Ok(u32::from_le_bytes(self.memory[range].try_into().unwrap()))
         }
     }
 
-    fn write8(&mut self, addr: u32, val: u8) {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.write(addr - PLIC_BASE, val as u32);
-        } else if addr >= CLINT_BASE && addr < CLINT_BASE + CLINT_SIZE {
-            self.clint.write(addr - CLINT_BASE, val as u32);
+    fn write8(&mut self, addr: u32, val: u8) -> Result<(), BusError> {
+        if PLIC_RANGE.contains(&addr) {
+            Device::write(&mut self.plic, addr - PLIC_BASE, AccessWidth::Byte, val as u32)?;
+        } else if CLINT_RANGE.contains(&addr) {
+            Device::write(&mut self.clint, addr - CLINT_BASE, AccessWidth::Byte, val as u32)?;
         } else {
-            self.memory[addr as usize] = val;
+            let range = self.ram_range(addr, 1)?;
+            self.memory[range.start] = val;
+            self.note_ram_write(range);
         }
+        Ok(())
     }
 
-    fn write16(&mut self, addr: u32, val: u16) {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.write(addr - PLIC_BASE, val as u32);
-        } else if addr >= CLINT_BASE && addr < CLINT_BASE + CLINT_SIZE {
-            self.clint.write(addr - CLINT_BASE, val as u32);
+    fn write16(&mut self, addr: u32, val: u16) -> Result<(), BusError> {
+        if PLIC_RANGE.contains(&addr) {
+            Device::write(&mut self.plic, addr - PLIC_BASE, AccessWidth::Half, val as u32)?;
+        } else if CLINT_RANGE.contains(&addr) {
+            Device::write(&mut self.clint, addr - CLINT_BASE, AccessWidth::Half, val as u32)?;
         } else {
-            let addr = addr as usize;
-            self.memory[addr..addr + 2].copy_from_slice(&val.to_le_bytes());
+            let range = self.ram_range(addr, 2)?;
+            self.memory[range.clone()].copy_from_slice(&val.to_le_bytes());
+            self.note_ram_write(range);
         }
+        Ok(())
     }
 
-    fn write32(&mut self, addr: u32, val: u32) {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.write(addr - PLIC_BASE, val);
-        } else if addr >= CLINT_BASE && addr < CLINT_BASE + CLINT_SIZE {
-            self.clint.write(addr - CLINT_BASE, val);
+    fn write32(&mut self, addr: u32, val: u32) -> Result<(), BusError> {
+        if PLIC_RANGE.contains(&addr) {
+            Device::write(&mut self.plic, addr - PLIC_BASE, AccessWidth::Word, val)?;
+        } else if CLINT_RANGE.contains(&addr) {
+            Device::write(&mut self.clint, addr - CLINT_BASE, AccessWidth::Word, val)?;
         } else {
-            let addr = addr as usize;
-            self.memory[addr..addr + 4].copy_from_slice(&val.to_le_bytes());
+            let range = self.ram_range(addr, 4)?;
+            self.memory[range.clone()].copy_from_slice(&val.to_le_bytes());
+            self.note_ram_write(range);
         }
+        Ok(())
+    }
+
+    fn check_write(&self, addr: u32, width: AccessWidth) -> Result<(), BusError> {
+        if PLIC_RANGE.contains(&addr) {
+            self.plic.check_write(addr - PLIC_BASE, width)
+        } else if CLINT_RANGE.contains(&addr) {
+            self.clint.check_write(addr - CLINT_BASE, width)
+        } else {
+            self.ram_range(addr, width.bytes() as usize).map(|_| ())
+        }
+    }
+
+    fn load(&mut self, addr: u32, data: &[u8]) -> Result<(), BusError> {
+        DefaultBus::load(self, addr, data)
+    }
+
+    fn get_interrupt_level(&self, hart: usize) -> bool {
+        self.plic.get_interrupt_level(hart)
+    }
+
+    fn get_supervisor_interrupt_level(&self, hart: usize) -> Option<bool> {
+        self.plic.get_supervisor_interrupt_level(hart)
+    }
+
+    fn get_timer_interrupt_level(&self, hart: usize) -> bool {
+        self.clint.get_timer_interrupt_level(hart)
+    }
+
+    fn get_software_interrupt_level(&self, hart: usize) -> bool {
+        self.clint.get_software_interrupt_level(hart)
     }
 
-    fn get_interrupt_level(&self) -> bool {
-        self.plic.get_interrupt_level()
+    fn get_mtime(&self) -> u64 {
+        self.clint.mtime
     }
 
-    fn get_timer_interrupt_level(&self) -> bool {
-        self.clint.get_timer_interrupt_level()
+    fn tick(&mut self, cycles: u32) {
+        self.clint.tick(cycles);
     }
 
-    fn get_software_interrupt_level(&self) -> bool {
-        self.clint.get_software_interrupt_level()
+    fn watch_code_page(&mut self, page: u32) {
+        let page = page as usize;
+        if page / 64 < self.code_pages.len() {
+            self.code_pages[page / 64] |= 1 << (page % 64);
+        }
     }
 
-    fn tick(&mut self) {
-        self.clint.tick();
+    fn take_code_write(&mut self) -> Option<u32> {
+        self.code_writes.pop()
     }
 
-    fn plic_claim(&mut self) -> u32 {
-        self.plic.claim()
+    fn plic_claim(&mut self, hart: usize) -> u32 {
+        self.plic.claim(hart)
     }
 
-    fn plic_complete(&mut self, source_id: u32) {
-        self.plic.complete(source_id);
+    fn plic_complete(&mut self, hart: usize, source_id: u32) {
+        self.plic.complete(hart, source_id);
     }
 }
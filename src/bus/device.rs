@@ -0,0 +1,84 @@
+use std::any::Any;
+
+use super::BusError;
+
+/// バスアクセスの幅
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessWidth {
+    Byte,
+    Half,
+    Word,
+}
+
+impl AccessWidth {
+    /// アクセスするバイト数
+    pub fn bytes(self) -> u32 {
+        match self {
+            AccessWidth::Byte => 1,
+            AccessWidth::Half => 2,
+            AccessWidth::Word => 4,
+        }
+    }
+
+    /// アクセスする値のマスク
+    fn mask(self) -> u32 {
+        match self {
+            AccessWidth::Byte => 0xff,
+            AccessWidth::Half => 0xffff,
+            AccessWidth::Word => 0xffff_ffff,
+        }
+    }
+
+    /// `offset` へのアクセスで読み出す値を、それを含むワード `word` から取り出す
+    pub fn extract(self, offset: u32, word: u32) -> u32 {
+        (word >> ((offset & 3) * 8)) & self.mask()
+    }
+
+    /// `offset` への `val` の書き込みを、それを含むワード `word` の他のバイトを保ったまま合成する
+    pub fn merge(self, offset: u32, word: u32, val: u32) -> u32 {
+        let shift = (offset & 3) * 8;
+        let mask = self.mask() << shift;
+        (word & !mask) | ((val << shift) & mask)
+    }
+}
+
+/// 32 ビットのレジスタに対する、レジスタより幅の狭いアクセスの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubWordAccess {
+    /// バイトレーン単位で扱う。読み出しは該当するバイトを取り出し、書き込みはレジスタの他のバイトを保って合成する
+    #[default]
+    Merge,
+    /// アクセスエラー (アクセスフォールト) にする
+    Fault,
+}
+
+impl SubWordAccess {
+    /// `offset` への `width` のアクセスを受け付けるか確認する。
+    /// 自然なアラインメントでないアクセスは、ワードを跨ぐ可能性があるため常にエラーにする
+    pub fn check(self, offset: u32, width: AccessWidth) -> Result<(), BusError> {
+        if !offset.is_multiple_of(width.bytes()) || (width != AccessWidth::Word && self == SubWordAccess::Fault) {
+            return Err(BusError::UnsupportedAccess);
+        }
+        Ok(())
+    }
+}
+
+/// `MemoryMap` に配置する MMIO デバイス。
+/// アドレスはデバイスの先頭からのオフセットで渡され、値は下位ビットに詰めて受け渡す。
+/// 失敗した場合は `BusError` を返し、CPU 側でアクセスフォールトとして扱う
+pub trait Device: Any {
+    /// `offset` から `width` 分を読み出す
+    fn read(&mut self, offset: u32, width: AccessWidth) -> Result<u32, BusError>;
+
+    /// `offset` から `width` 分に `val` を書き込む
+    fn write(&mut self, offset: u32, width: AccessWidth, val: u32) -> Result<(), BusError>;
+
+    /// `offset` から `width` 分への書き込みを受け付けるか、副作用なしで確認する (デフォルトは常に受け付ける)。
+    /// `write` がエラーを返すアクセスは、ここでも同じエラーを返すようにする
+    fn check_write(&self, _offset: u32, _width: AccessWidth) -> Result<(), BusError> {
+        Ok(())
+    }
+
+    /// クロックを `cycles` サイクル進める (デフォルトは何もしない)
+    fn tick(&mut self, _cycles: u32) {}
+}
@@ -0,0 +1,123 @@
+use super::{Bus, BusError};
+
+/// HTIF のデバイス番号: システムコール (終了コードを含む)
+const DEVICE_SYSCALL: u8 = 0;
+/// HTIF のデバイス番号: コンソール
+const DEVICE_CONSOLE: u8 = 1;
+
+/// コンソールのコマンド: 1 文字出力
+const CONSOLE_PUTCHAR: u8 = 1;
+
+/// システムコール番号 (riscv-pk と同じ)
+const SYS_WRITE: u64 = 64;
+const SYS_EXIT: u64 = 93;
+
+/// 対応していないシステムコールの戻り値 (-ENOSYS)
+const ENOSYS: u64 = -38i64 as u64;
+/// 不正なアドレスを渡されたシステムコールの戻り値 (-EFAULT)
+const EFAULT: u64 = -14i64 as u64;
+
+/// HTIF (Host-Target Interface)。
+/// riscv-tests やベアメタルのプログラムが `tohost` に書き込んだ要求を、ホストが `poll` で取り出して処理する。
+///
+/// `tohost` の値は bit 63-56 がデバイス、bit 55-48 がコマンド、bit 47-0 がペイロード。
+/// - デバイス 0 (システムコール): ペイロードの bit 0 が 1 なら終了 (終了コードはペイロード >> 1)。
+///   0 ならペイロードは引数を並べた 64 ビット × 8 の領域 (magic_mem) のアドレスで、SYS_write・SYS_exit を処理する。
+///   SYS_write はバッファがバスの外に出たところで打ち切る
+/// - デバイス 1 (コンソール): コマンド 1 で 1 文字出力する
+///
+/// 応答が必要な要求には、処理後に `fromhost` へ応答を書き込む
+pub struct Htif {
+    /// `tohost` のアドレス
+    pub tohost: u32,
+    /// `fromhost` のアドレス (None の場合は応答を書き込まない)
+    pub fromhost: Option<u32>,
+    /// コンソールへの出力 (ホストが取り出すまで保持する)
+    pub output: Vec<u8>,
+    /// プログラムが終了した場合の終了コード
+    pub exit_code: Option<u32>,
+}
+
+impl Htif {
+    pub fn new(tohost: u32, fromhost: Option<u32>) -> Self {
+        Self {
+            tohost,
+            fromhost,
+            output: Vec::new(),
+            exit_code: None,
+        }
+    }
+
+    /// `tohost` に要求があれば処理して `tohost` を 0 に戻す。
+    /// プログラムが終了していれば終了コードを返す (riscv-tests では 0 が成功、それ以外は失敗したテストの番号)
+    pub fn poll<B: Bus>(&mut self, bus: &mut B) -> Result<Option<u32>, BusError> {
+        if self.exit_code.is_some() {
+            return Ok(self.exit_code);
+        }
+        let request = Self::read64(bus, self.tohost)?;
+        if request == 0 {
+            return Ok(None);
+        }
+        Self::write64(bus, self.tohost, 0)?;
+
+        let device = (request >> 56) as u8;
+        let command = (request >> 48) as u8;
+        let payload = request & 0xffff_ffff_ffff;
+        match (device, command) {
+            (DEVICE_SYSCALL, 0) if payload & 1 != 0 => {
+                self.exit_code = Some((payload >> 1) as u32);
+            }
+            (DEVICE_SYSCALL, 0) => {
+                let magic_mem = payload as u32;
+                let which = Self::read64(bus, magic_mem)?;
+                let mut arg = |i: u32| Self::read64(bus, magic_mem.wrapping_add(8 * i));
+                let (arg0, arg1, arg2) = (arg(1)?, arg(2)?, arg(3)?);
+                let ret = match which {
+                    SYS_EXIT => {
+                        self.exit_code = Some(arg0 as u32);
+                        return Ok(self.exit_code);
+                    }
+                    // バッファがバスの外に出たところで打ち切り、書き込めたバイト数を返す
+                    SYS_WRITE if arg0 == 1 || arg0 == 2 => {
+                        let mut written = 0;
+                        while written < arg2.min(u32::MAX as u64) {
+                            let Ok(byte) = bus.read8((arg1 as u32).wrapping_add(written as u32)) else { break };
+                            self.output.push(byte);
+                            written += 1;
+                        }
+                        if written == 0 && arg2 != 0 { EFAULT } else { written }
+                    }
+                    _ => ENOSYS,
+                };
+                Self::write64(bus, magic_mem, ret)?;
+                self.respond(bus, request, 1)?;
+            }
+            (DEVICE_CONSOLE, CONSOLE_PUTCHAR) => {
+                self.output.push(payload as u8);
+                self.respond(bus, request, 0x100 | (payload & 0xff))?;
+            }
+            // 対応していない要求は読み捨てる
+            _ => {}
+        }
+        Ok(self.exit_code)
+    }
+
+    /// 要求 `request` と同じデバイス・コマンドで、`payload` を `fromhost` に書き込む
+    fn respond<B: Bus>(&self, bus: &mut B, request: u64, payload: u64) -> Result<(), BusError> {
+        match self.fromhost {
+            Some(fromhost) => Self::write64(bus, fromhost, (request & !0xffff_ffff_ffff) | payload),
+            None => Ok(()),
+        }
+    }
+
+    fn read64<B: Bus>(bus: &mut B, addr: u32) -> Result<u64, BusError> {
+        let low = bus.read32(addr)?;
+        let high = bus.read32(addr.wrapping_add(4))?;
+        Ok(((high as u64) << 32) | low as u64)
+    }
+
+    fn write64<B: Bus>(bus: &mut B, addr: u32, val: u64) -> Result<(), BusError> {
+        bus.write32(addr, val as u32)?;
+        bus.write32(addr.wrapping_add(4), (val >> 32) as u32)
+    }
+}
@@ -0,0 +1,377 @@
+use std::any::Any;
+
+use super::{Bus, BusError};
+use super::device::{AccessWidth, Device};
+use super::plic::Plic;
+use super::clint::{Clint, ClintRegion};
+use super::default_bus::PLIC_SIZE;
+
+/// 領域を登録できなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    /// サイズが 0 か、アドレス空間の終わりを超えている
+    InvalidRange,
+    /// 登録済みの領域 (先頭アドレス `base`) と重なっている
+    Overlap { base: u32 },
+}
+
+/// `MemoryMap::add_device` で登録したデバイスの識別子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceId(usize);
+
+/// 領域の割り当て先
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    /// RAM / ROM (`memories` のインデックス)
+    Memory(usize),
+    Plic,
+    /// CLINT (レジスタの配置)
+    Clint(ClintRegion),
+    /// MMIO デバイス (`devices` のインデックス)
+    Device(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    base: u32,
+    /// 最後のアドレス (アドレス空間の終わりまでの領域を表せるように、終端を含む側で持つ)
+    last: u32,
+    target: Target,
+}
+
+impl Region {
+    #[inline(always)]
+    fn contains(&self, addr: u32) -> bool {
+        self.base <= addr && addr <= self.last
+    }
+}
+
+/// RAM / ROM の内容
+struct Memory {
+    data: Vec<u8>,
+    /// false の場合 (ROM) はゲストからの書き込みをアクセスエラーにする
+    writable: bool,
+}
+
+/// RAM・ROM・MMIO デバイスを任意のアドレスに配置できるバス。
+/// 領域は先頭アドレス順に並べて保持し、直前にアクセスした領域を優先して、外れた場合は二分探索で引く
+pub struct MemoryMap {
+    /// 先頭アドレス順に並べた領域
+    regions: Vec<Region>,
+    memories: Vec<Memory>,
+    devices: Vec<Box<dyn Device>>,
+    pub plic: Plic,
+    pub clint: Clint,
+    /// PLIC を配置したか (配置していなければ割り込みを発生させない)
+    plic_mapped: bool,
+    /// CLINT の mtimecmp (CLINT か MTIMER) を配置したか
+    timer_mapped: bool,
+    /// CLINT の msip (CLINT か MSWI) を配置したか
+    mswi_mapped: bool,
+    /// 直前にアクセスした領域 (`regions` のインデックス)
+    last_region: usize,
+    /// CPU の命令キャッシュに載っている物理ページのビットマップ (32 ビットのアドレス空間全体)
+    code_pages: Vec<u64>,
+    /// 命令キャッシュに載っているページへの書き込み (CPU が取り出すまで保持する)
+    code_writes: Vec<u32>,
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMap {
+    /// 何も配置していないメモリマップを作る
+    pub fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+            memories: Vec::new(),
+            devices: Vec::new(),
+            plic: Plic::new(),
+            clint: Clint::new(),
+            plic_mapped: false,
+            timer_mapped: false,
+            mswi_mapped: false,
+            last_region: 0,
+            code_pages: vec![0; (1 << 20) / 64],
+            code_writes: Vec::new(),
+        }
+    }
+
+    /// `base` から `size` バイトの RAM を配置する
+    pub fn add_ram(&mut self, base: u32, size: u32) -> Result<(), MapError> {
+        let idx = self.check_region(base, size)?;
+        self.memories.push(Memory { data: vec![0; size as usize], writable: true });
+        self.insert_region(idx, base, size, Target::Memory(self.memories.len() - 1));
+        Ok(())
+    }
+
+    /// `base` に `data` を内容とする ROM を配置する
+    pub fn add_rom(&mut self, base: u32, data: &[u8]) -> Result<(), MapError> {
+        let size = u32::try_from(data.len()).map_err(|_| MapError::InvalidRange)?;
+        let idx = self.check_region(base, size)?;
+        self.memories.push(Memory { data: data.to_vec(), writable: false });
+        self.insert_region(idx, base, size, Target::Memory(self.memories.len() - 1));
+        Ok(())
+    }
+
+    /// `base` から `size` バイトに MMIO デバイスを配置する
+    pub fn add_device<D: Device>(&mut self, base: u32, size: u32, device: D) -> Result<DeviceId, MapError> {
+        let idx = self.check_region(base, size)?;
+        self.devices.push(Box::new(device));
+        let id = self.devices.len() - 1;
+        self.insert_region(idx, base, size, Target::Device(id));
+        Ok(DeviceId(id))
+    }
+
+    /// PLIC を `base` に配置する
+    pub fn map_plic(&mut self, base: u32) -> Result<(), MapError> {
+        let idx = self.check_region(base, PLIC_SIZE)?;
+        self.insert_region(idx, base, PLIC_SIZE, Target::Plic);
+        self.plic_mapped = true;
+        Ok(())
+    }
+
+    /// CLINT を `base` に配置する
+    pub fn map_clint(&mut self, base: u32) -> Result<(), MapError> {
+        self.map_clint_region(ClintRegion::Clint, base)
+    }
+
+    /// CLINT のレジスタを `region` の配置で `base` に配置する。
+    /// ACLINT の MSWI・MTIMER・SSWI はそれぞれ別のアドレスに配置でき、状態は `clint` で共有する
+    pub fn map_clint_region(&mut self, region: ClintRegion, base: u32) -> Result<(), MapError> {
+        let idx = self.check_region(base, region.size())?;
+        self.insert_region(idx, base, region.size(), Target::Clint(region));
+        match region {
+            ClintRegion::Clint => {
+                self.timer_mapped = true;
+                self.mswi_mapped = true;
+            }
+            ClintRegion::Mtimer => self.timer_mapped = true,
+            ClintRegion::Mswi => self.mswi_mapped = true,
+            ClintRegion::Sswi => {}
+        }
+        Ok(())
+    }
+
+    /// 登録したデバイスを取得する。`T` が登録したデバイスの型と異なる場合は None
+    pub fn device<T: Device>(&self, id: DeviceId) -> Option<&T> {
+        let device: &dyn Any = self.devices.get(id.0)?.as_ref();
+        device.downcast_ref()
+    }
+
+    /// 登録したデバイスを取得する (書き換え用)
+    pub fn device_mut<T: Device>(&mut self, id: DeviceId) -> Option<&mut T> {
+        let device: &mut dyn Any = self.devices.get_mut(id.0)?.as_mut();
+        device.downcast_mut()
+    }
+
+    /// ホストから RAM / ROM の `addr` 以降に `data` を書き込む (ROM にも書き込める)。
+    /// 1 つの RAM / ROM の領域に収まらない場合はアクセスエラー
+    pub fn load(&mut self, addr: u32, data: &[u8]) -> Result<(), BusError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let size = u32::try_from(data.len()).map_err(|_| BusError::Unmapped)?;
+        let region = self.find(addr)?;
+        let Target::Memory(idx) = region.target else { return Err(BusError::Unmapped) };
+        let offset = Self::offset_in(&region, addr, size)?;
+        self.memories[idx].data[offset..offset + data.len()].copy_from_slice(data);
+        self.note_write(addr, size);
+        Ok(())
+    }
+
+    /// 新しい領域が既存の領域と重ならないか確認し、挿入する位置を返す
+    fn check_region(&self, base: u32, size: u32) -> Result<usize, MapError> {
+        let last = size.checked_sub(1)
+            .and_then(|len| base.checked_add(len))
+            .ok_or(MapError::InvalidRange)?;
+        let idx = self.regions.partition_point(|region| region.base < base);
+        if let Some(prev) = idx.checked_sub(1).map(|i| &self.regions[i])
+            && prev.last >= base
+        {
+            return Err(MapError::Overlap { base: prev.base });
+        }
+        if let Some(next) = self.regions.get(idx)
+            && next.base <= last
+        {
+            return Err(MapError::Overlap { base: next.base });
+        }
+        Ok(idx)
+    }
+
+    fn insert_region(&mut self, idx: usize, base: u32, size: u32, target: Target) {
+        self.regions.insert(idx, Region { base, last: base + (size - 1), target });
+        self.last_region = 0;
+    }
+
+    /// `addr` を含む領域を引く
+    #[inline(always)]
+    fn find(&mut self, addr: u32) -> Result<Region, BusError> {
+        if let Some(region) = self.regions.get(self.last_region)
+            && region.contains(addr)
+        {
+            return Ok(*region);
+        }
+        let idx = self.regions.partition_point(|region| region.last < addr);
+        match self.regions.get(idx) {
+            Some(region) if region.contains(addr) => {
+                self.last_region = idx;
+                Ok(*region)
+            }
+            _ => Err(BusError::Unmapped),
+        }
+    }
+
+    /// `addr` から `size` バイトが領域内に収まっていれば、領域の先頭からのオフセットを返す
+    #[inline(always)]
+    fn offset_in(region: &Region, addr: u32, size: u32) -> Result<usize, BusError> {
+        if region.last - addr < size - 1 {
+            return Err(BusError::Unmapped);
+        }
+        Ok((addr - region.base) as usize)
+    }
+
+    /// 書き込みが命令キャッシュに載っているページに掛かっていれば記録する
+    #[inline(always)]
+    fn note_write(&mut self, addr: u32, size: u32) {
+        for page in (addr >> 12)..=((addr + (size - 1)) >> 12) {
+            let (word, bit) = ((page / 64) as usize, 1u64 << (page % 64));
+            if (self.code_pages[word] & bit) != 0 {
+                self.code_pages[word] &= !bit;
+                self.code_writes.push(page);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn read(&mut self, addr: u32, width: AccessWidth) -> Result<u32, BusError> {
+        let region = self.find(addr)?;
+        let offset = Self::offset_in(&region, addr, width.bytes())?;
+        match region.target {
+            Target::Memory(idx) => {
+                let size = width.bytes() as usize;
+                let mut bytes = [0; 4];
+                bytes[..size].copy_from_slice(&self.memories[idx].data[offset..offset + size]);
+                Ok(u32::from_le_bytes(bytes))
+            }
+            Target::Plic => Device::read(&mut self.plic, offset as u32, width),
+            Target::Clint(region) => self.clint.read_access(region, offset as u32, width),
+            Target::Device(idx) => self.devices[idx].read(offset as u32, width),
+        }
+    }
+
+    #[inline(always)]
+    fn write(&mut self, addr: u32, width: AccessWidth, val: u32) -> Result<(), BusError> {
+        let region = self.find(addr)?;
+        let offset = Self::offset_in(&region, addr, width.bytes())?;
+        match region.target {
+            Target::Memory(idx) => {
+                let memory = &mut self.memories[idx];
+                if !memory.writable {
+                    return Err(BusError::ReadOnly);
+                }
+                let size = width.bytes() as usize;
+                memory.data[offset..offset + size].copy_from_slice(&val.to_le_bytes()[..size]);
+                self.note_write(addr, width.bytes());
+            }
+            Target::Plic => Device::write(&mut self.plic, offset as u32, width, val)?,
+            Target::Clint(region) => self.clint.write_access(region, offset as u32, width, val)?,
+            Target::Device(idx) => self.devices[idx].write(offset as u32, width, val)?,
+        }
+        Ok(())
+    }
+}
+
+impl Bus for MemoryMap {
+    fn read8(&mut self, addr: u32) -> Result<u8, BusError> {
+        self.read(addr, AccessWidth::Byte).map(|val| val as u8)
+    }
+
+    fn read16(&mut self, addr: u32) -> Result<u16, BusError> {
+        self.read(addr, AccessWidth::Half).map(|val| val as u16)
+    }
+
+    fn read32(&mut self, addr: u32) -> Result<u32, BusError> {
+        self.read(addr, AccessWidth::Word)
+    }
+
+    fn write8(&mut self, addr: u32, val: u8) -> Result<(), BusError> {
+        self.write(addr, AccessWidth::Byte, val as u32)
+    }
+
+    fn write16(&mut self, addr: u32, val: u16) -> Result<(), BusError> {
+        self.write(addr, AccessWidth::Half, val as u32)
+    }
+
+    fn write32(&mut self, addr: u32, val: u32) -> Result<(), BusError> {
+        self.write(addr, AccessWidth::Word, val)
+    }
+
+    fn check_write(&self, addr: u32, width: AccessWidth) -> Result<(), BusError> {
+        // `find` は直前の領域を更新するため使わずに引く
+        let idx = self.regions.partition_point(|region| region.last < addr);
+        let region = self.regions.get(idx).filter(|region| region.contains(addr)).ok_or(BusError::Unmapped)?;
+        let offset = Self::offset_in(region, addr, width.bytes())? as u32;
+        match region.target {
+            Target::Memory(idx) if !self.memories[idx].writable => Err(BusError::ReadOnly),
+            Target::Memory(_) => Ok(()),
+            Target::Plic => self.plic.check_write(offset, width),
+            Target::Clint(_) => self.clint.check_write(offset, width),
+            Target::Device(idx) => self.devices[idx].check_write(offset, width),
+        }
+    }
+
+    fn load(&mut self, addr: u32, data: &[u8]) -> Result<(), BusError> {
+        MemoryMap::load(self, addr, data)
+    }
+
+    fn get_interrupt_level(&self, hart: usize) -> bool {
+        self.plic_mapped && self.plic.get_interrupt_level(hart)
+    }
+
+    fn get_supervisor_interrupt_level(&self, hart: usize) -> Option<bool> {
+        self.plic.get_supervisor_interrupt_level(hart).filter(|_| self.plic_mapped)
+    }
+
+    fn get_timer_interrupt_level(&self, hart: usize) -> bool {
+        self.timer_mapped && self.clint.get_timer_interrupt_level(hart)
+    }
+
+    fn get_software_interrupt_level(&self, hart: usize) -> bool {
+        self.mswi_mapped && self.clint.get_software_interrupt_level(hart)
+    }
+
+    fn take_supervisor_software_interrupt(&mut self, hart: usize) -> bool {
+        self.clint.take_supervisor_software_interrupt(hart)
+    }
+
+    fn get_mtime(&self) -> u64 {
+        self.clint.mtime
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.clint.tick(cycles);
+        for device in &mut self.devices {
+            device.tick(cycles);
+        }
+    }
+
+    fn watch_code_page(&mut self, page: u32) {
+        self.code_pages[(page / 64) as usize] |= 1 << (page % 64);
+    }
+
+    fn take_code_write(&mut self) -> Option<u32> {
+        self.code_writes.pop()
+    }
+
+    fn plic_claim(&mut self, hart: usize) -> u32 {
+        self.plic.claim(hart)
+    }
+
+    fn plic_complete(&mut self, hart: usize, source_id: u32) {
+        self.plic.complete(hart, source_id);
+    }
+}
@@ -1,66 +1,96 @@
-pub(crate) use crate::bus::Bus;
+pub(crate) use crate::bus::{Bus, BusError};
+use crate::bus::device::AccessWidth;
+
+/// MockBus のメモリサイズ
+const MEMORY_SIZE: usize = 131072;
 
 // テストコードでのみ使用している構造体のため通常ビルド時に未使用の警告が出る(邪魔なので握り潰す)
 #[allow(unused)]
 pub(crate) struct MockBus {
-    memory: [u8; 131072],
+    memory: [u8; MEMORY_SIZE],
+    /// `get_mtime` で返す CLINT の mtime
+    pub(crate) mtime: u64,
 }
 
 impl MockBus {
     #[allow(unused)]
     pub(crate) fn new() -> Self {
         Self {
-            memory: [0; 131072],
+            memory: [0; MEMORY_SIZE],
+            mtime: 0,
         }
     }
 
     #[allow(unused)]
     pub(crate) fn write_inst32(&mut self, addr: u32, inst: u32) {
-        self.write32(addr, inst);
+        self.write32(addr, inst).unwrap();
     }
 
     #[allow(unused)]
     pub(crate) fn write_inst16(&mut self, addr: u32, inst: u16) {
-        self.write16(addr, inst);
+        self.write16(addr, inst).unwrap();
     }
 
     #[allow(unused)]
     pub(crate) fn clear(&mut self) {
-        self.memory = [0; 131072];
+        self.memory = [0; MEMORY_SIZE];
+    }
+
+    /// `addr` から `size` バイトがメモリ内に収まっているか確認する
+    fn check(addr: u32, size: usize) -> Result<usize, BusError> {
+        let addr = addr as usize;
+        if addr + size <= MEMORY_SIZE {
+            Ok(addr)
+        } else {
+            Err(BusError::Unmapped)
+        }
     }
 }
 
 impl Bus for MockBus {
-    fn read8(&mut self, addr: u32) -> u8 {
-        self.memory[addr as usize]
+    fn read8(&mut self, addr: u32) -> Result<u8, BusError> {
+        let addr = Self::check(addr, 1)?;
+        Ok(self.memory[addr])
     }
 
-    fn read16(&mut self, addr: u32) -> u16 {
-        let addr = addr as usize;
-        u16::from_le_bytes([self.memory[addr], self.memory[addr + 1]])
+    fn read16(&mut self, addr: u32) -> Result<u16, BusError> {
+        let addr = Self::check(addr, 2)?;
+        Ok(u16::from_le_bytes([self.memory[addr], self.memory[addr + 1]]))
     }
 
-    fn read32(&mut self, addr: u32) -> u32 {
-        let addr = addr as usize;
-        u32::from_le_bytes([
+    fn read32(&mut self, addr: u32) -> Result<u32, BusError> {
+        let addr = Self::check(addr, 4)?;
+        Ok(u32::from_le_bytes([
             self.memory[addr],
             self.memory[addr + 1],
             self.memory[addr + 2],
             self.memory[addr + 3],
-        ])
+        ]))
     }
 
-    fn write8(&mut self, addr: u32, val: u8) {
-        self.memory[addr as usize] = val;
+    fn write8(&mut self, addr: u32, val: u8) -> Result<(), BusError> {
+        let addr = Self::check(addr, 1)?;
+        self.memory[addr] = val;
+        Ok(())
     }
 
-    fn write16(&mut self, addr: u32, val: u16) {
-        let addr = addr as usize;
+    fn write16(&mut self, addr: u32, val: u16) -> Result<(), BusError> {
+        let addr = Self::check(addr, 2)?;
         self.memory[addr..addr + 2].copy_from_slice(&val.to_le_bytes());
+        Ok(())
     }
 
-    fn write32(&mut self, addr: u32, val: u32) {
-        let addr = addr as usize;
+    fn write32(&mut self, addr: u32, val: u32) -> Result<(), BusError> {
+        let addr = Self::check(addr, 4)?;
         self.memory[addr..addr + 4].copy_from_slice(&val.to_le_bytes());
+        Ok(())
+    }
+
+    fn check_write(&self, addr: u32, width: AccessWidth) -> Result<(), BusError> {
+        Self::check(addr, width.bytes() as usize).map(|_| ())
+    }
+
+    fn get_mtime(&self) -> u64 {
+        self.mtime
     }
 }
@@ -1,153 +1,472 @@
-/// PLIC (Platform-Level Interrupt Controller)
-/// 割り込みソースの最大数（とりあえず31とする。0はソースなし用）
+use super::BusError;
+use super::device::{AccessWidth, Device, SubWordAccess};
+
+/// `Plic::new` で作る PLIC の割り込みソース数 (0 はソースなし用)
 pub const SOURCE_COUNT: usize = 32;
 
+/// 割り込みソース数の上限 (ID 1〜1023。0 はソースなし用)
+pub const MAX_SOURCE_COUNT: usize = 1024;
+
+/// 優先度レジスタ (0x000000 + 4*id)
+const PRIORITY_BASE: u32 = 0x000000;
+/// pending ビット (0x001000 + 4*word)
+const PENDING_BASE: u32 = 0x001000;
+/// コンテキストごとの enable ビット (0x002000 + 0x80*ctx + 4*word)
+const ENABLE_BASE: u32 = 0x002000;
+const ENABLE_STRIDE: u32 = 0x80;
+/// コンテキストごとの threshold (0x200000 + 0x1000*ctx) と claim/complete (+4)
+const CONTEXT_BASE: u32 = 0x200000;
+const CONTEXT_STRIDE: u32 = 0x1000;
+const CLAIM_COMPLETE: u32 = 0x4;
+
+/// 割り込みソースのトリガー方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TriggerMode {
+    /// レベルトリガー。信号が High の間は、Complete のたびに再び pending になる
+    #[default]
+    Level,
+    /// エッジトリガー。pending か Claim 中に来たエッジは 1 つにまとめてゲートウェイに保持し、Complete で再送する
+    Edge,
+    /// エッジトリガー。pending か Claim 中に来たエッジを数えておき、Complete のたびに 1 つずつ再送する
+    EdgeCounting,
+}
+
+/// 割り込みを受け取るコンテキスト (ハートの特権モードごとの通知先)
+#[derive(Debug, Clone, Default)]
+pub struct PlicContext {
+    /// 割り込みを有効にするかどうかのビットマスク (ソース 32 個ずつのワード)
+    pub enabled: Vec<u32>,
+    /// 割り込みを受け付ける優先度の閾値
+    pub threshold: u32,
+}
+
+/// 1 つのハートに接続するコンテキスト
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HartContexts {
+    /// mip.MEIP に接続するコンテキスト
+    pub machine: usize,
+    /// mip.SEIP に接続するコンテキスト (None の場合、SEIP はソフトウェアが管理する)
+    pub supervisor: Option<usize>,
+}
+
+/// PLIC (Platform-Level Interrupt Controller)
 pub struct Plic {
+    /// 割り込みソース数 (ID 0 を含む)
+    source_count: usize,
     /// 各割り込みソースの優先度 (0x000000 + 4*id)
-    pub priorities: [u32; SOURCE_COUNT],
-    /// 割り込みが発生しているソースのビットマスク (0x001000)
-    pub pending: u32,
-    /// 割り込みを有効にするかどうかのビットマスク (0x002000)
-    pub enabled: u32,
-    /// 割り込みを受け付ける優先度の閾値 (0x200000)
-    pub threshold: u32,
+    pub priorities: Vec<u32>,
+    /// 割り込みが発生しているソースのビットマスク (0x001000 + 4*word)
+    pub pending: Vec<u32>,
     /// 現在処理中の割り込み ID を管理するビットマスク
     /// RISC-V PLIC の仕様では、Claim すると ID が返り、Complete されるまでその ID は再送されない。
-    pub claimed: u32,
-    /// 外部からの割り込み信号（レベルトリガー用）
-    pub ip: u32,
+    pub claimed: Vec<u32>,
+    /// 外部からの割り込み信号 (エッジトリガーのソースではエッジの検出に使う)
+    pub ip: Vec<u32>,
+    /// 各割り込みソースのトリガー方式
+    triggers: Vec<TriggerMode>,
+    /// エッジトリガーのゲートウェイが保持している、まだ pending にしていないエッジの数
+    edge_counts: Vec<u32>,
+    /// 割り込みを受け取るコンテキスト
+    pub contexts: Vec<PlicContext>,
+    /// 各ハートに接続するコンテキスト (インデックスが mhartid)
+    pub harts: Vec<HartContexts>,
+    /// 32 ビットより幅の狭いアクセスの扱い
+    pub sub_word: SubWordAccess,
+}
+
+impl Default for Plic {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Plic {
+    /// `SOURCE_COUNT` 個のソースと、M モード用のコンテキストを 1 つ持つ PLIC を作る
     pub fn new() -> Self {
+        Self::with_config(SOURCE_COUNT, 1)
+    }
+
+    /// `source_count` 個のソース (ID 0 を含む、最大 `MAX_SOURCE_COUNT`) と `context_count` 個のコンテキストを持つ PLIC を作る。
+    /// コンテキスト 0 をハート 0 の mip.MEIP に接続する
+    pub fn with_config(source_count: usize, context_count: usize) -> Self {
+        assert!(source_count <= MAX_SOURCE_COUNT, "PLIC のソース数は最大 {} です", MAX_SOURCE_COUNT);
+        let words = source_count.div_ceil(32);
         Self {
-            priorities: [0; SOURCE_COUNT],
-            pending: 0,
-            enabled: 0,
-            threshold: 0,
-            claimed: 0,
-            ip: 0,
+            source_count,
+            priorities: vec![0; source_count],
+            pending: vec![0; words],
+            claimed: vec![0; words],
+            ip: vec![0; words],
+            triggers: vec![TriggerMode::Level; source_count],
+            edge_counts: vec![0; source_count],
+            contexts: vec![PlicContext { enabled: vec![0; words], threshold: 0 }; context_count],
+            harts: vec![HartContexts { machine: 0, supervisor: None }],
+            sub_word: SubWordAccess::Merge,
+        }
+    }
+
+    /// `source_count` 個のソースと、`hart_count` 個のハートそれぞれに M モードと S モードのコンテキストを持つ PLIC を作る。
+    /// ハート h の M モードはコンテキスト 2h、S モードはコンテキスト 2h+1 になる (一般的な SoC の配置)
+    pub fn with_harts(source_count: usize, hart_count: usize) -> Self {
+        let mut plic = Self::with_config(source_count, hart_count * 2);
+        plic.harts = (0..hart_count)
+            .map(|hart| HartContexts { machine: hart * 2, supervisor: Some(hart * 2 + 1) })
+            .collect();
+        plic
+    }
+
+    /// 割り込みソース数 (ID 0 を含む)
+    pub fn source_count(&self) -> usize {
+        self.source_count
+    }
+
+    /// 有効なソース ID か
+    fn is_source(&self, id: u32) -> bool {
+        id > 0 && (id as usize) < self.source_count
+    }
+
+    /// ビットマスクのワード `word` のうち、存在するソースのビット
+    fn source_mask(&self, word: usize) -> u32 {
+        let first = word * 32;
+        let mut mask = if first + 32 <= self.source_count {
+            u32::MAX
+        } else {
+            (1u32 << (self.source_count - first)) - 1
+        };
+        if word == 0 {
+            mask &= !1; // ID 0 はソースなし
+        }
+        mask
+    }
+
+    fn test_bit(bits: &[u32], id: u32) -> bool {
+        (bits[(id / 32) as usize] >> (id % 32)) & 1 == 1
+    }
+
+    fn set_bit(bits: &mut [u32], id: u32, value: bool) {
+        let (word, bit) = ((id / 32) as usize, 1 << (id % 32));
+        if value {
+            bits[word] |= bit;
+        } else {
+            bits[word] &= !bit;
+        }
+    }
+
+    /// コンテキスト `context` でソース `id` の割り込みを有効・無効にする
+    pub fn set_enabled(&mut self, context: usize, id: u32, enabled: bool) {
+        if self.is_source(id) && context < self.contexts.len() {
+            Self::set_bit(&mut self.contexts[context].enabled, id, enabled);
+        }
+    }
+
+    /// コンテキスト `context` でソース `id` の割り込みが有効か
+    pub fn is_enabled(&self, context: usize, id: u32) -> bool {
+        self.is_source(id) && context < self.contexts.len() && Self::test_bit(&self.contexts[context].enabled, id)
+    }
+
+    /// ソース `id` のトリガー方式を設定する。ゲートウェイが保持しているエッジは破棄する
+    pub fn set_trigger_mode(&mut self, id: u32, mode: TriggerMode) {
+        if self.is_source(id) {
+            self.triggers[id as usize] = mode;
+            self.edge_counts[id as usize] = 0;
+        }
+    }
+
+    /// ソース `id` のトリガー方式
+    pub fn trigger_mode(&self, id: u32) -> TriggerMode {
+        if self.is_source(id) {
+            self.triggers[id as usize]
+        } else {
+            TriggerMode::Level
+        }
+    }
+
+    /// エッジトリガーのソース `id` のゲートウェイが保持している、まだ pending にしていないエッジの数
+    pub fn latched_edges(&self, id: u32) -> u32 {
+        if self.is_source(id) {
+            self.edge_counts[id as usize]
+        } else {
+            0
         }
     }
 
+    /// ソース `id` が保留中か
+    pub fn is_pending(&self, id: u32) -> bool {
+        self.is_source(id) && Self::test_bit(&self.pending, id)
+    }
+
+    /// ソース `id` が Claim されて処理中か
+    pub fn is_claimed(&self, id: u32) -> bool {
+        self.is_source(id) && Self::test_bit(&self.claimed, id)
+    }
+
     pub fn read(&mut self, addr: u32) -> u32 {
         match addr {
-            // 0x000000..0x000080: priority
-            0x000000..=0x00007c => {
-                let id = (addr / 4) as usize;
-                if id < SOURCE_COUNT {
-                    self.priorities[id]
+            // 0x000000..0x001000: priority
+            PRIORITY_BASE..PENDING_BASE => {
+                let id = (addr - PRIORITY_BASE) / 4;
+                if self.is_source(id) {
+                    self.priorities[id as usize]
                 } else {
                     0
                 }
             }
-            // 0x001000: pending
-            0x001000 => self.pending,
-            // 0x002000: enabled
-            0x002000 => self.enabled,
-            // 0x200000: threshold
-            0x200000 => self.threshold,
-            // 0x200004: claim
-            0x200004 => self.claim(),
+            // 0x001000..0x001080: pending
+            PENDING_BASE..0x001080 => {
+                let word = ((addr - PENDING_BASE) / 4) as usize;
+                self.pending.get(word).copied().unwrap_or(0)
+            }
+            // 0x002000 + 0x80*ctx: enable
+            ENABLE_BASE..CONTEXT_BASE => {
+                let context = ((addr - ENABLE_BASE) / ENABLE_STRIDE) as usize;
+                let word = ((addr - ENABLE_BASE) % ENABLE_STRIDE / 4) as usize;
+                self.contexts
+                    .get(context)
+                    .and_then(|context| context.enabled.get(word))
+                    .copied()
+                    .unwrap_or(0)
+            }
+            // 0x200000 + 0x1000*ctx: threshold, claim
+            CONTEXT_BASE.. => {
+                let context = ((addr - CONTEXT_BASE) / CONTEXT_STRIDE) as usize;
+                if context >= self.contexts.len() {
+                    return 0;
+                }
+                match (addr - CONTEXT_BASE) % CONTEXT_STRIDE {
+                    0 => self.contexts[context].threshold,
+                    CLAIM_COMPLETE => self.claim_context(context),
+                    _ => 0,
+                }
+            }
             _ => 0,
         }
     }
 
     pub fn write(&mut self, addr: u32, val: u32) {
         match addr {
-            // 0x000000..0x000080: priority
-            0x000000..=0x00007c => {
-                let id = (addr / 4) as usize;
-                if id < SOURCE_COUNT {
-                    self.priorities[id] = val;
+            // 0x000000..0x001000: priority
+            PRIORITY_BASE..PENDING_BASE => {
+                let id = (addr - PRIORITY_BASE) / 4;
+                if self.is_source(id) {
+                    self.priorities[id as usize] = val;
+                }
+            }
+            // 0x001000..0x001080: pending (RO)
+            PENDING_BASE..0x001080 => {}
+            // 0x002000 + 0x80*ctx: enable
+            ENABLE_BASE..CONTEXT_BASE => {
+                let context = ((addr - ENABLE_BASE) / ENABLE_STRIDE) as usize;
+                let word = ((addr - ENABLE_BASE) % ENABLE_STRIDE / 4) as usize;
+                let mask = if word < self.pending.len() { self.source_mask(word) } else { 0 };
+                if let Some(enabled) = self.contexts
+                    .get_mut(context)
+                    .and_then(|context| context.enabled.get_mut(word))
+                {
+                    *enabled = val & mask;
+                }
+            }
+            // 0x200000 + 0x1000*ctx: threshold, complete
+            CONTEXT_BASE.. => {
+                let context = ((addr - CONTEXT_BASE) / CONTEXT_STRIDE) as usize;
+                if context >= self.contexts.len() {
+                    return;
+                }
+                match (addr - CONTEXT_BASE) % CONTEXT_STRIDE {
+                    0 => self.contexts[context].threshold = val,
+                    CLAIM_COMPLETE => self.complete_context(context, val),
+                    _ => {}
                 }
             }
-            // 0x001000: pending (RO)
-            0x001000 => {}
-            // 0x002000: enabled
-            0x002000 => self.enabled = val,
-            // 0x200000: threshold
-            0x200000 => self.threshold = val,
-            // 0x200004: complete
-            0x200004 => self.complete(val),
             _ => {}
         }
     }
 
-    pub fn claim(&mut self) -> u32 {
-        let mut max_priority = 0;
-        let mut max_id = 0;
+    /// コンテキスト `context` に通知すべき割り込みのうち、最も優先度が高いもの (同じ優先度なら ID が小さいもの) を探す
+    fn highest_pending(&self, context: usize) -> Option<u32> {
+        let context = self.contexts.get(context)?;
+        let mut max_priority = context.threshold;
+        let mut max_id = None;
 
-        // 有効かつ保留中で、かつ現在処理中（Claimed）ではないものを対象とする
-        let pending_enabled_not_claimed = self.pending & self.enabled & !self.claimed;
-        for id in 1..SOURCE_COUNT {
-            if (pending_enabled_not_claimed >> id) & 1 == 1 {
-                if self.priorities[id] > max_priority {
-                    max_priority = self.priorities[id];
-                    max_id = id as u32;
+        // pending には !claimed の条件が含まれている (claim 時にクリアされるため)
+        for (word, (&pending, &enabled)) in self.pending.iter().zip(&context.enabled).enumerate() {
+            let mut bits = pending & enabled;
+            while bits != 0 {
+                let id = word as u32 * 32 + bits.trailing_zeros();
+                bits &= bits - 1;
+                if self.priorities[id as usize] > max_priority {
+                    max_priority = self.priorities[id as usize];
+                    max_id = Some(id);
                 }
             }
         }
+        max_id
+    }
 
-        if max_id > 0 && max_priority > self.threshold {
-            // Claim されたら pending をクリアし、claimed にセットする
-            self.pending &= !(1 << max_id);
-            self.claimed |= 1 << max_id;
-            max_id
-        } else {
-            0
+    /// コンテキスト `context` から割り込みを取得する (Claim)
+    pub fn claim_context(&mut self, context: usize) -> u32 {
+        match self.highest_pending(context) {
+            Some(id) => {
+                // Claim されたら pending をクリアし、claimed にセットする
+                Self::set_bit(&mut self.pending, id, false);
+                Self::set_bit(&mut self.claimed, id, true);
+                id
+            }
+            None => 0,
         }
     }
 
-    pub fn complete(&mut self, source_id: u32) {
-        if source_id > 0 && source_id < SOURCE_COUNT as u32 {
-            // claimed ビットをクリアする
-            self.claimed &= !(1 << source_id);
+    /// コンテキスト `context` から割り込みの完了を通知する (Complete)。
+    /// 仕様に従い、そのコンテキストで有効になっていないソースの完了通知は無視する
+    pub fn complete_context(&mut self, context: usize, source_id: u32) {
+        if !self.is_enabled(context, source_id) {
+            return;
+        }
+        // claimed ビットをクリアする
+        Self::set_bit(&mut self.claimed, source_id, false);
+        match self.triggers[source_id as usize] {
             // レベルトリガーの考慮：もしデバイス信号がまだ High なら再度 pending にする
-            if (self.ip >> source_id) & 1 == 1 {
-                self.pending |= 1 << source_id;
+            TriggerMode::Level => {
+                if Self::test_bit(&self.ip, source_id) {
+                    Self::set_bit(&mut self.pending, source_id, true);
+                }
             }
+            // エッジトリガー：ゲートウェイが保持しているエッジがあれば 1 つ再送する
+            TriggerMode::Edge | TriggerMode::EdgeCounting => {
+                let count = &mut self.edge_counts[source_id as usize];
+                if *count > 0 {
+                    *count -= 1;
+                    Self::set_bit(&mut self.pending, source_id, true);
+                }
+            }
+        }
+    }
+
+    /// エッジトリガーのゲートウェイにエッジを 1 つ渡す
+    fn latch_edge(&mut self, source_id: u32) {
+        if !Self::test_bit(&self.pending, source_id) && !Self::test_bit(&self.claimed, source_id) {
+            Self::set_bit(&mut self.pending, source_id, true);
+            return;
+        }
+        // pending か Claim 中なら、Complete まで保持する
+        let count = &mut self.edge_counts[source_id as usize];
+        match self.triggers[source_id as usize] {
+            TriggerMode::EdgeCounting => *count = count.saturating_add(1),
+            // pending のまま来たエッジは、pending の 1 つにまとめる
+            _ if Self::test_bit(&self.pending, source_id) => {}
+            _ => *count = 1,
         }
     }
 
-    /// 外部からの割り込み信号をセットする（レベルトリガーを想定）
+    /// ハート `hart` の M モードのコンテキストから割り込みを取得する (Claim)。
+    /// コンテキストを接続していないハートには 0 を返す
+    pub fn claim(&mut self, hart: usize) -> u32 {
+        match self.harts.get(hart) {
+            Some(contexts) => self.claim_context(contexts.machine),
+            None => 0,
+        }
+    }
+
+    /// ハート `hart` の M モードのコンテキストから割り込みの完了を通知する (Complete)
+    pub fn complete(&mut self, hart: usize, source_id: u32) {
+        if let Some(contexts) = self.harts.get(hart) {
+            self.complete_context(contexts.machine, source_id);
+        }
+    }
+
+    /// 外部からの割り込み信号をセットする。
+    /// エッジトリガーのソースでは、信号が Low から High になったときにエッジとして扱う
     pub fn set_interrupt(&mut self, source_id: u32) {
-        if source_id > 0 && source_id < SOURCE_COUNT as u32 {
-            self.ip |= 1 << source_id;
-            // Claimed でなければ pending に反映
-            if (self.claimed >> source_id) & 1 == 0 {
-                self.pending |= 1 << source_id;
+        if self.is_source(source_id) {
+            let was_high = Self::test_bit(&self.ip, source_id);
+            Self::set_bit(&mut self.ip, source_id, true);
+            match self.triggers[source_id as usize] {
+                TriggerMode::Level => {
+                    // Claimed でなければ pending に反映
+                    if !Self::test_bit(&self.claimed, source_id) {
+                        Self::set_bit(&mut self.pending, source_id, true);
+                    }
+                }
+                TriggerMode::Edge | TriggerMode::EdgeCounting => {
+                    if !was_high {
+                        self.latch_edge(source_id);
+                    }
+                }
             }
         }
     }
 
-    /// 外部からの割り込み信号をクリアする
+    /// 外部からの割り込み信号をクリアする。
+    /// エッジトリガーのソースでは、受け付けたエッジ (pending とゲートウェイが保持しているもの) はそのまま残す
     pub fn clear_interrupt(&mut self, source_id: u32) {
-        if source_id > 0 && source_id < SOURCE_COUNT as u32 {
-            self.ip &= !(1 << source_id);
-            // pending もクリア（ただし、すでに Claimed なものは pending はすでに 0 なはず）
-            self.pending &= !(1 << source_id);
+        if self.is_source(source_id) {
+            Self::set_bit(&mut self.ip, source_id, false);
+            if self.triggers[source_id as usize] == TriggerMode::Level {
+                // pending もクリア（ただし、すでに Claimed なものは pending はすでに 0 なはず）
+                Self::set_bit(&mut self.pending, source_id, false);
+            }
         }
     }
 
-    /// CPU への割り込み通知が必要かどうかを判定する
-    pub fn get_interrupt_level(&self) -> bool {
+    /// エッジトリガーのソースにパルス (信号の High → Low) を送る
+    pub fn pulse_interrupt(&mut self, source_id: u32) {
+        self.set_interrupt(source_id);
+        self.clear_interrupt(source_id);
+    }
+
+    /// コンテキスト `context` への割り込み通知が必要かどうかを判定する
+    pub fn context_interrupt_level(&self, context: usize) -> bool {
         // 有効かつ保留中（かつ未処理）の割り込みの中で、最大の優先度が閾値を超えているか
-        // pending にはすでに !claimed のロジックが含まれている（claim 時にクリアされるため）
-        let pending_enabled = self.pending & self.enabled;
-        if pending_enabled == 0 {
-            return false;
+        self.highest_pending(context).is_some()
+    }
+
+    /// ハート `hart` への割り込み通知 (mip.MEIP) が必要かどうかを判定する
+    pub fn get_interrupt_level(&self, hart: usize) -> bool {
+        self.harts.get(hart).is_some_and(|contexts| self.context_interrupt_level(contexts.machine))
+    }
+
+    /// ハート `hart` の S モードの外部割り込み (mip.SEIP) の信号。S モードのコンテキストを接続していなければ None
+    pub fn get_supervisor_interrupt_level(&self, hart: usize) -> Option<bool> {
+        self.harts
+            .get(hart)
+            .and_then(|contexts| contexts.supervisor)
+            .map(|context| self.context_interrupt_level(context))
+    }
+}
+
+/// バスからのアクセス。幅の狭いアクセスは `sub_word` に従ってバイトレーン単位で読み書きする。
+/// claim/complete レジスタはアクセス自体に副作用があるため、`sub_word` に関係なく 32 ビットのアクセスのみ受け付ける
+impl Device for Plic {
+    fn read(&mut self, offset: u32, width: AccessWidth) -> Result<u32, BusError> {
+        self.sub_word.check(offset, width)?;
+        let word = offset & !3;
+        if is_claim_complete(word) && width != AccessWidth::Word {
+            return Err(BusError::UnsupportedAccess);
         }
+        Ok(width.extract(offset, Plic::read(self, word)))
+    }
 
-        let mut max_priority = 0;
-        for id in 1..SOURCE_COUNT {
-            if (pending_enabled >> id) & 1 == 1 {
-                if self.priorities[id] > max_priority {
-                    max_priority = self.priorities[id];
-                }
-            }
+    fn write(&mut self, offset: u32, width: AccessWidth, val: u32) -> Result<(), BusError> {
+        self.check_write(offset, width)?;
+        let word = offset & !3;
+        if is_claim_complete(word) {
+            Plic::write(self, word, val);
+            return Ok(());
         }
+        let merged = width.merge(offset, Plic::read(self, word), val);
+        Plic::write(self, word, merged);
+        Ok(())
+    }
 
-        max_priority > self.threshold
+    fn check_write(&self, offset: u32, width: AccessWidth) -> Result<(), BusError> {
+        self.sub_word.check(offset, width)?;
+        if is_claim_complete(offset & !3) && width != AccessWidth::Word {
+            return Err(BusError::UnsupportedAccess);
+        }
+        Ok(())
     }
 }
+
+/// `offset` がいずれかのコンテキストの claim/complete レジスタか
+fn is_claim_complete(offset: u32) -> bool {
+    offset >= CONTEXT_BASE && (offset - CONTEXT_BASE) % CONTEXT_STRIDE == CLAIM_COMPLETE
+}
@@ -1,2 +1,4 @@
 mod plic_tests;
 mod clint_tests;
+mod memory_map_tests;
+mod htif_tests;
@@ -1,6 +1,9 @@
+use crate::bus::clint::{Clint, ClintRegion, Timebase};
 use crate::bus::default_bus::{DefaultBus, CLINT_BASE};
-use crate::bus::Bus;
-use crate::cpu::Cpu;
+use crate::bus::device::SubWordAccess;
+use crate::bus::memory_map::MemoryMap;
+use crate::bus::{Bus, BusError};
+use crate::cpu::{Cpu, StepResult, StopReason};
 
 #[test]
 fn test_clint_timer_interrupt() {
@@ -9,7 +12,7 @@ fn test_clint_timer_interrupt() {
 
     // メモリに NOP 命令を配置
     for i in (0..0x100).step_by(4) {
-        bus.write32(i, 0x00000013); // addi x0, x0, 0
+        bus.write32(i, 0x00000013).unwrap(); // addi x0, x0, 0
     }
 
     // mie.MTIE (ビット7) をセット
@@ -20,20 +23,23 @@ fn test_clint_timer_interrupt() {
     cpu.csr.write(0x305, 0x100).unwrap();
 
     // mtimecmp を 10 に設定
-    bus.write32(CLINT_BASE + 0x4000, 10);
-    bus.write32(CLINT_BASE + 0x4004, 0);
+    bus.write32(CLINT_BASE + 0x4000, 10).unwrap();
+    bus.write32(CLINT_BASE + 0x4004, 0).unwrap();
 
-    // 9ステップ実行 (mtime が 9 になる)
+    // 9ステップ実行 (9 命令実行したので mtime が 9 になる)
     for _ in 0..9 {
         cpu.step(&mut bus);
     }
+    assert_eq!(bus.clint.mtime, 9);
     
     // CPU の PC を 0 に戻す
     cpu.pc = 0;
     
-    // 次のステップで mtime が 10 になり、割り込みが発生するはず
+    // 次のステップの命令で mtime が 10 になり、その次のステップの開始時に割り込みが発生するはず
     // mtime=10, mtimecmp=10 -> interrupt!
     cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 4);
+    cpu.step(&mut bus);
 
     // 割り込みハンドラ (mtvec = 0x100) に飛んでいるはず
     assert_eq!(cpu.pc, 0x100);
@@ -47,7 +53,7 @@ fn test_clint_software_interrupt() {
     let mut cpu = Cpu::new(0);
 
     // メモリに NOP 命令を配置
-    bus.write32(0, 0x00000013);
+    bus.write32(0, 0x00000013).unwrap();
 
     // mie.MSIE (ビット3) をセット
     cpu.csr.write(0x304, 1 << 3).unwrap();
@@ -57,7 +63,7 @@ fn test_clint_software_interrupt() {
     cpu.csr.write(0x305, 0x200).unwrap();
 
     // msip を 1 に設定
-    bus.write32(CLINT_BASE + 0x0000, 1);
+    bus.write32(CLINT_BASE, 1).unwrap(); // msip (offset 0x0000)
 
     // 次のステップで割り込みが発生するはず
     cpu.step(&mut bus);
@@ -79,3 +85,290 @@ fn test_clint_mtime_increment() {
     cpu.step(&mut bus);
     assert_eq!(bus.clint.mtime, 2);
 }
+
+#[test]
+fn test_clint_mtime_follows_retired_instructions() {
+    let mut bus = DefaultBus::new(0x2000);
+    let mut cpu = Cpu::new(0);
+
+    // ページ全体の NOP と、先頭へのジャンプ (0xffc: jal x0, -0xffc)
+    for addr in (0..0xffc).step_by(4) {
+        bus.write32(addr, 0x00000013).unwrap();
+    }
+    bus.write32(0xffc, 0x804ff06f).unwrap();
+
+    // ブロックを連結して何命令まとめて実行しても、mtime は実行した命令数だけ進む
+    let (reason, executed) = cpu.run(&mut bus, 5000);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 5000);
+    assert_eq!(bus.clint.mtime, 5000);
+
+    // 1 命令ずつ実行しても同じ
+    for _ in 0..10 {
+        cpu.step_one(&mut bus);
+    }
+    assert_eq!(bus.clint.mtime, 5010);
+}
+
+#[test]
+fn test_clint_timebase_ratio() {
+    let mut bus = DefaultBus::new(0x2000);
+    let mut cpu = Cpu::new(0);
+    for addr in (0..0xffc).step_by(4) {
+        bus.write32(addr, 0x00000013).unwrap();
+    }
+    bus.write32(0xffc, 0x804ff06f).unwrap();
+
+    // 100 MHz の仮想コアに 10 MHz の timebase (10 サイクルごとに 1)
+    bus.clint.set_timebase(Timebase::Cycles { ticks: 1, cycles: 10 });
+    assert_eq!(bus.clint.timebase(), Timebase::Cycles { ticks: 1, cycles: 10 });
+    cpu.run(&mut bus, 95);
+    assert_eq!(bus.clint.mtime, 9);
+
+    // 端数のサイクルは次に持ち越す
+    cpu.run(&mut bus, 5);
+    assert_eq!(bus.clint.mtime, 10);
+
+    // 1 サイクルごとに 3 進める
+    bus.clint.set_timebase(Timebase::Cycles { ticks: 3, cycles: 1 });
+    cpu.run(&mut bus, 4);
+    assert_eq!(bus.clint.mtime, 22);
+}
+
+#[test]
+fn test_clint_timebase_advances_while_waiting() {
+    let mut bus = DefaultBus::new(0x1000);
+    let mut cpu = Cpu::new(0);
+
+    // WFI, NOP
+    bus.write32(0, 0x10500073).unwrap();
+    bus.write32(4, 0x00000013).unwrap();
+    cpu.csr.write(0x304, 1 << 7).unwrap();
+    bus.write32(CLINT_BASE + 0x4000, 5).unwrap();
+
+    // WFI の実行で 1 進み、待機中は 1 ステップごとに 1 進む
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Waiting));
+    assert_eq!(bus.clint.mtime, 1);
+    for mtime in 2..=5 {
+        let (result, _) = cpu.step(&mut bus);
+        assert!(matches!(result, StepResult::Waiting));
+        assert_eq!(bus.clint.mtime, mtime);
+    }
+
+    // mtime が mtimecmp に達したので待機が終わる
+    cpu.step(&mut bus);
+    assert!(!cpu.is_waiting());
+    assert_eq!(cpu.pc, 8);
+}
+
+#[test]
+fn test_clint_timebase_host_clock() {
+    let mut clint = Clint::new();
+    clint.set_timebase(Timebase::HostClock { hz: 1000 });
+
+    // 最初の tick で基準点を取り、以降はホストの経過時間で進む (サイクル数には依存しない)
+    clint.tick(1_000_000);
+    assert!(clint.mtime < 1000);
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    clint.tick(0);
+    assert!(clint.mtime >= 20);
+
+    // mtime が書き換えられた場合は、その値から進める
+    clint.write(0xbff8, 5);
+    clint.write(0xbffc, 0x1);
+    clint.tick(0);
+    assert!(clint.mtime >= 0x1_0000_0005 && clint.mtime < 0x1_0000_0005 + 1000);
+}
+
+#[test]
+fn test_clint_fast_forward_wfi() {
+    let mut bus = DefaultBus::new(0x1000);
+    let mut cpu = Cpu::new(0);
+
+    // WFI, NOP
+    bus.write32(0, 0x10500073).unwrap();
+    bus.write32(4, 0x00000013).unwrap();
+    cpu.csr.write(0x304, 1 << 7).unwrap();
+    bus.write32(CLINT_BASE + 0x4000, 1000).unwrap();
+
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Waiting));
+
+    // 待機中のホストは mtime を mtimecmp まで早送りできる
+    bus.clint.fast_forward();
+    assert_eq!(bus.clint.mtime, 1000);
+
+    cpu.step(&mut bus);
+    assert!(!cpu.is_waiting());
+    assert_eq!(cpu.pc, 8);
+}
+
+#[test]
+fn test_clint_sub_word_write_merges() {
+    let mut bus = DefaultBus::new(0x1000);
+    bus.clint.mtimecmp[0] = 0x1122_3344_5566_7788;
+
+    // sb で mtimecmp+1 に書き込んでも、他のバイトは変わらない
+    bus.write8(CLINT_BASE + 0x4001, 0xaa).unwrap();
+    assert_eq!(bus.clint.mtimecmp[0], 0x1122_3344_5566_aa88);
+
+    // 上位ワードへの sh
+    bus.write16(CLINT_BASE + 0x4006, 0xbbcc).unwrap();
+    assert_eq!(bus.clint.mtimecmp[0], 0xbbcc_3344_5566_aa88);
+
+    // msip は下位 1 ビットのみ有効
+    bus.write8(CLINT_BASE + 0x0001, 0xff).unwrap();
+    assert_eq!(bus.clint.msip[0], 0);
+    bus.write8(CLINT_BASE, 0xff).unwrap();
+    assert_eq!(bus.clint.msip[0], 1);
+}
+
+#[test]
+fn test_clint_sub_word_read_shifts() {
+    let mut bus = DefaultBus::new(0x1000);
+    bus.clint.mtime = 0x0102_0304_0506_0708;
+
+    assert_eq!(bus.read8(CLINT_BASE + 0xbff8), Ok(0x08));
+    assert_eq!(bus.read8(CLINT_BASE + 0xbffa), Ok(0x06));
+    assert_eq!(bus.read8(CLINT_BASE + 0xbfff), Ok(0x01));
+    assert_eq!(bus.read16(CLINT_BASE + 0xbffa), Ok(0x0506));
+    assert_eq!(bus.read16(CLINT_BASE + 0xbffc), Ok(0x0304));
+
+    // ワードを跨ぎうる、アラインされていないアクセスはエラー
+    assert_eq!(bus.read16(CLINT_BASE + 0xbff9), Err(BusError::UnsupportedAccess));
+    assert_eq!(bus.read32(CLINT_BASE + 0xbffa), Err(BusError::UnsupportedAccess));
+}
+
+#[test]
+fn test_clint_sub_word_fault_policy() {
+    let mut bus = DefaultBus::new(0x1000);
+    bus.clint.sub_word = SubWordAccess::Fault;
+    bus.clint.mtimecmp[0] = 0x1234;
+
+    assert_eq!(bus.read8(CLINT_BASE + 0x4000), Err(BusError::UnsupportedAccess));
+    assert_eq!(bus.write16(CLINT_BASE + 0x4000, 0), Err(BusError::UnsupportedAccess));
+    assert_eq!(bus.clint.mtimecmp[0], 0x1234);
+    assert_eq!(bus.read32(CLINT_BASE + 0x4000), Ok(0x1234));
+}
+
+#[test]
+fn test_clint_sub_word_guest_access() {
+    let mut bus = DefaultBus::new(0x1000);
+    let mut cpu = Cpu::new(0);
+    cpu.csr.mtvec = 0x100;
+    cpu.regs[1] = 0x5a;
+    cpu.regs[5] = CLINT_BASE + 0x4000;
+    cpu.regs[6] = CLINT_BASE + 0xbff8;
+    bus.clint.mtime = 0x00ab_0000;
+
+    // 0x0: sb  x1, 1(x5)
+    // 0x4: lbu x2, 2(x6)
+    bus.write32(0x0, 0x001280a3).unwrap();
+    bus.write32(0x4, 0x00234103).unwrap();
+
+    let (reason, executed) = cpu.run(&mut bus, 2);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 2);
+    assert_eq!(bus.clint.mtimecmp[0], 0x5a00);
+    assert_eq!(cpu.regs[2], 0xab);
+
+    // 狭いアクセスをエラーにする場合、ゲストにはストアアクセスフォールトとして見える
+    bus.clint.sub_word = SubWordAccess::Fault;
+    cpu.pc = 0x0;
+    let (reason, _) = cpu.run(&mut bus, 2);
+    assert_eq!(reason, StopReason::Trap(7));
+    assert_eq!(cpu.csr.mtval, CLINT_BASE + 0x4001);
+}
+
+#[test]
+fn test_clint_multi_hart_layout() {
+    let mut bus = DefaultBus::new(0x1000);
+    bus.clint = Clint::with_harts(4);
+    assert_eq!(bus.clint.hart_count(), 4);
+    bus.clint.mtimecmp.fill(u64::MAX);
+
+    // msip はハートごとに 0x0000 + 4*hart
+    bus.write32(CLINT_BASE + 0x8, 1).unwrap();
+    assert_eq!(bus.clint.msip, vec![0, 0, 1, 0]);
+    assert!(bus.get_software_interrupt_level(2));
+    assert!(!bus.get_software_interrupt_level(0));
+
+    // mtimecmp はハートごとに 0x4000 + 8*hart
+    bus.write32(CLINT_BASE + 0x4018, 5).unwrap();
+    bus.write32(CLINT_BASE + 0x401c, 0).unwrap();
+    assert_eq!(bus.clint.mtimecmp[3], 5);
+    assert_eq!(bus.read32(CLINT_BASE + 0x4018), Ok(5));
+    bus.clint.mtime = 5;
+    assert!(bus.get_timer_interrupt_level(3));
+    assert!(!bus.get_timer_interrupt_level(1));
+
+    // 存在しないハートのレジスタは 0 と読め、書き込みは無視する
+    bus.write32(CLINT_BASE + 0x10, 1).unwrap();
+    assert_eq!(bus.read32(CLINT_BASE + 0x10), Ok(0));
+    assert_eq!(bus.read32(CLINT_BASE + 0x4020), Ok(0));
+    assert!(!bus.get_software_interrupt_level(4));
+    assert!(!bus.get_timer_interrupt_level(4));
+}
+
+#[test]
+fn test_clint_fast_forward_to_earliest_hart() {
+    let mut clint = Clint::with_harts(3);
+    clint.mtime = 100;
+    clint.mtimecmp = vec![50, 300, 200];
+
+    // すでに来ている mtimecmp は飛ばし、次に来るものまで進める
+    clint.fast_forward();
+    assert_eq!(clint.mtime, 200);
+    clint.fast_forward();
+    assert_eq!(clint.mtime, 300);
+    clint.fast_forward();
+    assert_eq!(clint.mtime, 300);
+}
+
+#[test]
+fn test_aclint_layout() {
+    let mut bus = MemoryMap::new();
+    bus.clint = Clint::with_harts(2);
+    bus.map_clint_region(ClintRegion::Mswi, 0x0200_0000).unwrap();
+    bus.map_clint_region(ClintRegion::Mtimer, 0x0200_4000).unwrap();
+    bus.map_clint_region(ClintRegion::Sswi, 0x0200_c000).unwrap();
+
+    // MSWI: msip は 0x0000 + 4*hart
+    bus.write32(0x0200_0004, 1).unwrap();
+    assert!(bus.get_software_interrupt_level(1));
+    assert!(!bus.get_software_interrupt_level(0));
+
+    // MTIMER: mtimecmp は 0x0000 + 8*hart、mtime は 0x7ff8
+    bus.write32(0x0200_4008, 0x10).unwrap();
+    bus.write32(0x0200_400c, 0x1).unwrap();
+    assert_eq!(bus.clint.mtimecmp[1], 0x1_0000_0010);
+    bus.write32(0x0200_bff8, 0x10).unwrap();
+    bus.write32(0x0200_bffc, 0x1).unwrap();
+    assert_eq!(bus.clint.mtime, 0x1_0000_0010);
+    assert_eq!(bus.read32(0x0200_bffc), Ok(0x1));
+    assert!(bus.get_timer_interrupt_level(1));
+
+    // SSWI: setssip は 0x0000 + 4*hart。常に 0 と読め、1 の書き込みで要求する
+    bus.write32(0x0200_c004, 0).unwrap();
+    assert!(!bus.take_supervisor_software_interrupt(1));
+    bus.write8(0x0200_c004, 1).unwrap();
+    assert_eq!(bus.read32(0x0200_c004), Ok(0));
+    assert!(!bus.take_supervisor_software_interrupt(0));
+    assert!(bus.take_supervisor_software_interrupt(1));
+    assert!(!bus.take_supervisor_software_interrupt(1));
+
+    // 領域の外はアクセスエラー
+    assert_eq!(bus.read32(0x0201_0000), Err(BusError::Unmapped));
+}
+
+#[test]
+fn test_aclint_mswi_without_mtimer() {
+    let mut bus = MemoryMap::new();
+    bus.map_clint_region(ClintRegion::Mswi, 0x0200_0000).unwrap();
+
+    // MTIMER を配置していなければタイマー割り込みは発生しない
+    assert!(!bus.get_timer_interrupt_level(0));
+    bus.write32(0x0200_0000, 1).unwrap();
+    assert!(bus.get_software_interrupt_level(0));
+}
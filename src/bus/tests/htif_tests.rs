@@ -0,0 +1,148 @@
+use crate::bus::Bus;
+use crate::bus::htif::Htif;
+use crate::bus::memory_map::MemoryMap;
+use crate::cpu::Cpu;
+
+#[test]
+fn test_htif_exit_code() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x2000).unwrap();
+    let mut htif = Htif::new(0x8000_1000, Some(0x8000_1040));
+
+    assert_eq!(htif.poll(&mut bus), Ok(None));
+
+    // riscv-tests の失敗: (テスト番号 << 1) | 1
+    bus.write32(0x8000_1000, (5 << 1) | 1).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(Some(5)));
+    assert_eq!(bus.read32(0x8000_1000), Ok(0));
+    // 終了後は終了コードを返し続ける
+    assert_eq!(htif.poll(&mut bus), Ok(Some(5)));
+
+    // 成功は 1 (終了コード 0)
+    let mut htif = Htif::new(0x8000_1000, None);
+    bus.write32(0x8000_1000, 1).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(Some(0)));
+
+    // tohost が配置されていなければエラー
+    let mut htif = Htif::new(0x1000, None);
+    assert!(htif.poll(&mut bus).is_err());
+}
+
+#[test]
+fn test_htif_console_putchar() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x2000).unwrap();
+    let mut htif = Htif::new(0x8000_1000, Some(0x8000_1040));
+
+    // デバイス 1 (コンソール)、コマンド 1 (putchar)
+    bus.write32(0x8000_1000, b'A' as u32).unwrap();
+    bus.write32(0x8000_1004, 0x0101_0000).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(None));
+    assert_eq!(htif.output, b"A");
+    assert_eq!(bus.read32(0x8000_1000), Ok(0));
+    assert_eq!(bus.read32(0x8000_1004), Ok(0));
+    assert_eq!(bus.read32(0x8000_1040), Ok(0x141));
+    assert_eq!(bus.read32(0x8000_1044), Ok(0x0101_0000));
+}
+
+#[test]
+fn test_htif_syscall() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x2000).unwrap();
+    let mut htif = Htif::new(0x8000_1000, Some(0x8000_1040));
+
+    // magic_mem: SYS_write(1, buf, 5)
+    bus.load(0x8000_1200, b"hello").unwrap();
+    bus.write32(0x8000_1100, 64).unwrap();
+    bus.write32(0x8000_1108, 1).unwrap();
+    bus.write32(0x8000_1110, 0x8000_1200).unwrap();
+    bus.write32(0x8000_1118, 5).unwrap();
+    bus.write32(0x8000_1000, 0x8000_1100).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(None));
+    assert_eq!(htif.output, b"hello");
+    // 戻り値は magic_mem[0] に書き込み、fromhost で完了を通知する
+    assert_eq!(bus.read32(0x8000_1100), Ok(5));
+    assert_eq!(bus.read32(0x8000_1040), Ok(1));
+
+    // 対応していないシステムコールは -ENOSYS を返す
+    bus.write32(0x8000_1100, 57).unwrap();
+    bus.write32(0x8000_1000, 0x8000_1100).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(None));
+    assert_eq!(bus.read32(0x8000_1100), Ok(-38i32 as u32));
+    assert_eq!(bus.read32(0x8000_1104), Ok(0xffff_ffff));
+
+    // SYS_exit(3)
+    bus.write32(0x8000_1100, 93).unwrap();
+    bus.write32(0x8000_1104, 0).unwrap();
+    bus.write32(0x8000_1108, 3).unwrap();
+    bus.write32(0x8000_1000, 0x8000_1100).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(Some(3)));
+}
+
+#[test]
+fn test_htif_syscall_magic_mem_at_end_of_address_space() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x2000).unwrap();
+    bus.add_ram(0xffff_f000, 0x1000).unwrap();
+    let mut htif = Htif::new(0x8000_1000, Some(0x8000_1040));
+
+    // magic_mem の引数がアドレス空間の終わりを超えて 0 に回り込む (配置されていないためエラー)
+    bus.write32(0xffff_fff8, 64).unwrap();
+    bus.write32(0x8000_1000, 0xffff_fff8).unwrap();
+    bus.write32(0x8000_1004, 0).unwrap();
+    assert!(htif.poll(&mut bus).is_err());
+}
+
+#[test]
+fn test_htif_syscall_write_stops_at_bus_error() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x2000).unwrap();
+    let mut htif = Htif::new(0x8000_1000, Some(0x8000_1040));
+
+    // SYS_write(1, RAM の最後の 3 バイト, 巨大な長さ) は RAM の終わりで打ち切る
+    bus.load(0x8000_1ffd, b"end").unwrap();
+    bus.write32(0x8000_1100, 64).unwrap();
+    bus.write32(0x8000_1108, 1).unwrap();
+    bus.write32(0x8000_1110, 0x8000_1ffd).unwrap();
+    bus.write32(0x8000_1118, 0xffff_ffff).unwrap();
+    bus.write32(0x8000_111c, 0xffff).unwrap();
+    bus.write32(0x8000_1000, 0x8000_1100).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(None));
+    assert_eq!(htif.output, b"end");
+    assert_eq!(bus.read32(0x8000_1100), Ok(3));
+    assert_eq!(bus.read32(0x8000_1104), Ok(0));
+
+    // バッファがまったく読めなければ -EFAULT を返す
+    bus.write32(0x8000_1100, 64).unwrap();
+    bus.write32(0x8000_1110, 0x1000).unwrap();
+    bus.write32(0x8000_1000, 0x8000_1100).unwrap();
+    assert_eq!(htif.poll(&mut bus), Ok(None));
+    assert_eq!(htif.output, b"end");
+    assert_eq!(bus.read32(0x8000_1100), Ok(-14i32 as u32));
+    assert_eq!(bus.read32(0x8000_1104), Ok(0xffff_ffff));
+    assert_eq!(bus.read32(0x8000_1040), Ok(1));
+}
+
+#[test]
+fn test_htif_program_writes_tohost() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x2000).unwrap();
+    let mut htif = Htif::new(0x8000_1000, None);
+
+    // 0x8000_0000: lui x5, 0x80001
+    // 0x8000_0004: addi x6, x0, 7
+    // 0x8000_0008: sw x6, 0(x5)
+    // 0x8000_000c: sw x0, 4(x5)
+    // 0x8000_0010: jal x0, 0
+    bus.load(0x8000_0000, &[
+        0xb7, 0x12, 0x00, 0x80,
+        0x13, 0x03, 0x70, 0x00,
+        0x23, 0xa0, 0x62, 0x00,
+        0x23, 0xa2, 0x02, 0x00,
+        0x6f, 0x00, 0x00, 0x00,
+    ]).unwrap();
+
+    let mut cpu = Cpu::new(0x8000_0000);
+    cpu.run(&mut bus, 100);
+    assert_eq!(htif.poll(&mut bus), Ok(Some(3)));
+}
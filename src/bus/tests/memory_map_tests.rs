@@ -0,0 +1,207 @@
+use crate::bus::{Bus, BusError};
+use crate::bus::device::{AccessWidth, Device};
+use crate::bus::memory_map::{MapError, MemoryMap};
+use crate::bus::default_bus::{CLINT_BASE, PLIC_BASE};
+use crate::cpu::{Cpu, StopReason};
+
+/// アクセスを記録するだけのテスト用デバイス
+#[derive(Default)]
+struct RecordingDevice {
+    last_write: Option<(u32, AccessWidth, u32)>,
+    ticks: u32,
+}
+
+impl Device for RecordingDevice {
+    fn read(&mut self, offset: u32, width: AccessWidth) -> Result<u32, BusError> {
+        match width {
+            AccessWidth::Word => Ok(0x1000_0000 | offset),
+            _ => Err(BusError::Unmapped),
+        }
+    }
+
+    fn write(&mut self, offset: u32, width: AccessWidth, val: u32) -> Result<(), BusError> {
+        self.last_write = Some((offset, width, val));
+        Ok(())
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.ticks += cycles;
+    }
+}
+
+#[test]
+fn test_memory_map_ram_at_high_address() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x1000).unwrap();
+
+    bus.write32(0x8000_0000, 0x1234_5678).unwrap();
+    assert_eq!(bus.read32(0x8000_0000), Ok(0x1234_5678));
+    assert_eq!(bus.read16(0x8000_0002), Ok(0x1234));
+    assert_eq!(bus.read8(0x8000_0001), Ok(0x56));
+
+    bus.write8(0x8000_0fff, 0xab).unwrap();
+    assert_eq!(bus.read8(0x8000_0fff), Ok(0xab));
+
+    // 領域の外や、領域の終わりを跨ぐアクセスはエラー
+    assert_eq!(bus.read32(0x0000_0000), Err(BusError::Unmapped));
+    assert_eq!(bus.read8(0x8000_1000), Err(BusError::Unmapped));
+    assert_eq!(bus.read32(0x8000_0ffe), Err(BusError::Unmapped));
+    assert_eq!(bus.write16(0x8000_0fff, 0), Err(BusError::Unmapped));
+}
+
+#[test]
+fn test_memory_map_overlap_detection() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x1000).unwrap();
+    bus.add_ram(0x1000, 0x1000).unwrap();
+
+    assert_eq!(bus.add_ram(0x8000_0800, 0x1000), Err(MapError::Overlap { base: 0x8000_0000 }));
+    assert_eq!(bus.add_ram(0x7fff_f000, 0x1001), Err(MapError::Overlap { base: 0x8000_0000 }));
+    assert_eq!(bus.add_rom(0x0800, &[0; 0x1000]), Err(MapError::Overlap { base: 0x1000 }));
+    assert_eq!(bus.add_ram(0x8000_0000, 0), Err(MapError::InvalidRange));
+    assert_eq!(bus.add_ram(0xffff_f000, 0x2000), Err(MapError::InvalidRange));
+
+    // 隣接する領域は配置できる。アドレス空間の終わりまでの領域も配置できる
+    bus.add_ram(0x2000, 0x1000).unwrap();
+    bus.add_ram(0xffff_f000, 0x1000).unwrap();
+    bus.write8(0xffff_ffff, 0x5a).unwrap();
+    assert_eq!(bus.read8(0xffff_ffff), Ok(0x5a));
+    assert_eq!(bus.read16(0xffff_ffff), Err(BusError::Unmapped));
+
+    // 失敗した登録は何も配置しない
+    assert_eq!(bus.read8(0x0800), Err(BusError::Unmapped));
+}
+
+#[test]
+fn test_memory_map_rom() {
+    let mut bus = MemoryMap::new();
+    bus.add_rom(0x1000, &[0x13, 0x00, 0x00, 0x00]).unwrap();
+
+    assert_eq!(bus.read32(0x1000), Ok(0x0000_0013));
+    assert_eq!(bus.write32(0x1000, 0), Err(BusError::ReadOnly));
+    assert_eq!(bus.read32(0x1000), Ok(0x0000_0013));
+
+    // ホストからは書き込める
+    bus.load(0x1000, &[0x73, 0x00]).unwrap();
+    assert_eq!(bus.read32(0x1000), Ok(0x0000_0073));
+    assert_eq!(bus.load(0x1002, &[0; 4]), Err(BusError::Unmapped));
+}
+
+#[test]
+fn test_memory_map_device() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x1000).unwrap();
+    let id = bus.add_device(0x4000_0000, 0x100, RecordingDevice::default()).unwrap();
+
+    // デバイスにはデバイスの先頭からのオフセットとアクセス幅が渡される
+    assert_eq!(bus.read32(0x4000_0010), Ok(0x1000_0010));
+    assert_eq!(bus.read8(0x4000_0010), Err(BusError::Unmapped));
+    bus.write16(0x4000_0022, 0xbeef).unwrap();
+    assert_eq!(
+        bus.device::<RecordingDevice>(id).unwrap().last_write,
+        Some((0x22, AccessWidth::Half, 0xbeef))
+    );
+
+    bus.tick(1);
+    bus.tick(2);
+    assert_eq!(bus.device::<RecordingDevice>(id).unwrap().ticks, 3);
+    bus.device_mut::<RecordingDevice>(id).unwrap().ticks = 0;
+    assert_eq!(bus.device::<RecordingDevice>(id).unwrap().ticks, 0);
+}
+
+#[test]
+fn test_memory_map_check_write() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x1000).unwrap();
+    bus.add_rom(0x1000, &[0; 4]).unwrap();
+    bus.add_device(0x4000_0000, 0x100, RecordingDevice::default()).unwrap();
+    bus.map_plic(PLIC_BASE).unwrap();
+
+    assert_eq!(bus.check_write(0x8000_0ffc, AccessWidth::Word), Ok(()));
+    assert_eq!(bus.check_write(0x8000_0ffe, AccessWidth::Word), Err(BusError::Unmapped));
+    assert_eq!(bus.check_write(0x1000, AccessWidth::Byte), Err(BusError::ReadOnly));
+    assert_eq!(bus.check_write(0x2000, AccessWidth::Byte), Err(BusError::Unmapped));
+    // デバイスは読み出せない幅でも書き込める (読み出しの副作用は起こさない)
+    assert_eq!(bus.check_write(0x4000_0010, AccessWidth::Byte), Ok(()));
+    // claim/complete レジスタは 32 ビットの書き込みのみ
+    assert_eq!(bus.check_write(PLIC_BASE + 0x200004, AccessWidth::Word), Ok(()));
+    assert_eq!(bus.check_write(PLIC_BASE + 0x200004, AccessWidth::Byte), Err(BusError::UnsupportedAccess));
+}
+
+#[test]
+fn test_memory_map_misaligned_store_checks_writability() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x1000).unwrap();
+    bus.add_rom(0x8000_1000, &[0; 4]).unwrap();
+    let id = bus.add_device(0x4000_0000, 0x100, RecordingDevice::default()).unwrap();
+    // 0x8000_0000: sw x2, 0(x1)
+    // 0x8000_0004: sh x2, 0(x3)
+    bus.load(0x8000_0000, &[0x23, 0xa0, 0x20, 0x00, 0x23, 0x90, 0x21, 0x00]).unwrap();
+
+    let mut cpu = Cpu::new(0x8000_0000);
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Emulate;
+    cpu.regs[1] = 0x8000_0ffe;
+    cpu.regs[2] = 0x1234_5678;
+    cpu.regs[3] = 0x4000_0023;
+
+    // ROM を跨ぐストアは、RAM 側のバイトも書き込まずにアクセスフォールトになる
+    assert_eq!(cpu.run(&mut bus, 1).0, StopReason::Trap(7));
+    assert_eq!(cpu.csr.mtval, 0x8000_0ffe);
+    assert_eq!(bus.read16(0x8000_0ffe), Ok(0));
+
+    // バイト単位で読み出せないデバイスにも、読み出さずに書き込む
+    cpu.pc = 0x8000_0004;
+    assert_eq!(cpu.run(&mut bus, 1).0, StopReason::BudgetExhausted);
+    assert_eq!(
+        bus.device::<RecordingDevice>(id).unwrap().last_write,
+        Some((0x24, AccessWidth::Byte, 0x56))
+    );
+}
+
+#[test]
+fn test_memory_map_interrupt_controllers() {
+    let mut bus = MemoryMap::new();
+
+    // CLINT を配置していなければタイマー割り込みは発生しない (mtimecmp の初期値は 0)
+    assert!(!bus.get_timer_interrupt_level(0));
+
+    bus.map_clint(CLINT_BASE).unwrap();
+    bus.map_plic(PLIC_BASE).unwrap();
+    assert!(bus.get_timer_interrupt_level(0));
+
+    bus.write32(CLINT_BASE + 0x4000, 10).unwrap();
+    assert_eq!(bus.clint.mtimecmp[0], 10);
+    assert!(!bus.get_timer_interrupt_level(0));
+
+    bus.write32(CLINT_BASE, 1).unwrap();
+    assert!(bus.get_software_interrupt_level(0));
+
+    bus.write32(PLIC_BASE + 4, 1).unwrap();
+    bus.write32(PLIC_BASE + 0x2000, 1 << 1).unwrap();
+    bus.plic.set_interrupt(1);
+    assert!(bus.get_interrupt_level(0));
+    assert_eq!(bus.read32(PLIC_BASE + 0x200004), Ok(1));
+}
+
+#[test]
+fn test_memory_map_run_program_from_high_ram() {
+    let mut cpu = Cpu::new(0x8000_0000);
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x2000).unwrap();
+
+    // 0x8000_0000: addi x1, x1, 1
+    // 0x8000_0004: ecall
+    bus.load(0x8000_0000, &[0x93, 0x80, 0x10, 0x00, 0x73, 0x00, 0x00, 0x00]).unwrap();
+    let (reason, executed) = cpu.run(&mut bus, 10);
+    assert_eq!(reason, StopReason::Trap(11));
+    assert_eq!(executed, 1);
+    assert_eq!(cpu.regs[1], 1);
+
+    // ホストが命令を書き換えると、次の実行で検出して命令キャッシュを破棄する
+    // 0x8000_0000: addi x1, x1, 2
+    bus.load(0x8000_0000, &[0x93, 0x80, 0x20, 0x00]).unwrap();
+    cpu.pc = 0x8000_0000;
+    let (reason, _) = cpu.run(&mut bus, 10);
+    assert_eq!(reason, StopReason::Trap(11));
+    assert_eq!(cpu.regs[1], 3);
+}
@@ -1,4 +1,6 @@
-use crate::bus::plic::Plic;
+use crate::bus::plic::{Plic, TriggerMode, MAX_SOURCE_COUNT};
+use crate::bus::device::{AccessWidth, Device, SubWordAccess};
+use crate::bus::BusError;
 
 #[test]
 fn test_plic_basic_priority_threshold() {
@@ -12,17 +14,17 @@ fn test_plic_basic_priority_threshold() {
     plic.write(0x002000, 1 << 1);
     
     // まだ割り込みは発生していない
-    assert!(!plic.get_interrupt_level());
+    assert!(!plic.get_interrupt_level(0));
     
     // ソース 1 の割り込みを発生させる
     plic.set_interrupt(1);
     
     // 優先度 5 > 閾値 3 なので割り込みが発生するはず
-    assert!(plic.get_interrupt_level());
+    assert!(plic.get_interrupt_level(0));
     
     // 閾値を 6 に上げると割り込みが止まるはず
     plic.write(0x200000, 6);
-    assert!(!plic.get_interrupt_level());
+    assert!(!plic.get_interrupt_level(0));
 }
 
 #[test]
@@ -54,7 +56,7 @@ fn test_plic_claim_complete() {
 
     // デバイス信号がまだ High (set_interrupt されたまま) なので、再度 Pending になるはず
     assert_eq!(plic.read(0x001000), 1 << 2);
-    assert!(plic.get_interrupt_level());
+    assert!(plic.get_interrupt_level(0));
 
     // 再度 Claim すると 2 が返る
     assert_eq!(plic.read(0x200004), 2);
@@ -70,9 +72,9 @@ fn test_cpu_plic_access() {
     let mut cpu = Cpu::new(0);
 
     // PLIC 設定 (Source 1)
-    bus.write32(0x0c000004, 5); // Priority
-    bus.write32(0x0c002000, 1 << 1); // Enable
-    bus.write32(0x0c200000, 3); // Threshold
+    bus.write32(0x0c000004, 5).unwrap(); // Priority
+    bus.write32(0x0c002000, 1 << 1).unwrap(); // Enable
+    bus.write32(0x0c200000, 3).unwrap(); // Threshold
 
     // 割り込み発生
     bus.plic.set_interrupt(1);
@@ -95,3 +97,252 @@ fn test_plic_pending_mask() {
     
     assert_eq!(plic.read(0x001000), (1 << 1) | (1 << 3));
 }
+
+#[test]
+fn test_plic_sub_word_access() {
+    let mut plic = Plic::new();
+
+    // 優先度レジスタへの sb は他のバイトを保って合成する
+    plic.write(0x000004, 0x0102_0304);
+    Device::write(&mut plic, 0x000005, AccessWidth::Byte, 0xaa).unwrap();
+    assert_eq!(plic.priorities[1], 0x0102_aa04);
+    assert_eq!(Device::read(&mut plic, 0x000006, AccessWidth::Half), Ok(0x0102));
+
+    // enable の各バイトを個別に書き込める
+    Device::write(&mut plic, 0x002001, AccessWidth::Byte, 0x01).unwrap();
+    assert!(plic.is_enabled(0, 8));
+    plic.set_interrupt(9);
+    assert_eq!(Device::read(&mut plic, 0x001001, AccessWidth::Byte), Ok(0x02));
+    assert_eq!(Device::read(&mut plic, 0x001000, AccessWidth::Byte), Ok(0x00));
+}
+
+#[test]
+fn test_plic_claim_complete_requires_word_access() {
+    let mut plic = Plic::new();
+    plic.write(0x000004, 1);
+    plic.write(0x002000, 1 << 1);
+    plic.set_interrupt(1);
+
+    // claim/complete への狭いアクセスはエラーで、claim も行わない
+    assert_eq!(Device::read(&mut plic, 0x200004, AccessWidth::Byte), Err(BusError::UnsupportedAccess));
+    assert!(!plic.is_claimed(1));
+    assert_eq!(Device::read(&mut plic, 0x200004, AccessWidth::Word), Ok(1));
+
+    assert_eq!(Device::write(&mut plic, 0x200004, AccessWidth::Half, 1), Err(BusError::UnsupportedAccess));
+    assert!(plic.is_claimed(1));
+    Device::write(&mut plic, 0x200004, AccessWidth::Word, 1).unwrap();
+    assert!(!plic.is_claimed(1));
+}
+
+#[test]
+fn test_plic_sub_word_fault_policy() {
+    let mut plic = Plic::new();
+    plic.sub_word = SubWordAccess::Fault;
+
+    assert_eq!(Device::write(&mut plic, 0x000004, AccessWidth::Byte, 1), Err(BusError::UnsupportedAccess));
+    assert_eq!(plic.priorities[1], 0);
+    assert_eq!(Device::read(&mut plic, 0x200000, AccessWidth::Half), Err(BusError::UnsupportedAccess));
+    Device::write(&mut plic, 0x000004, AccessWidth::Word, 1).unwrap();
+    assert_eq!(plic.priorities[1], 1);
+}
+
+#[test]
+fn test_plic_spec_layout_with_multiple_contexts() {
+    let mut plic = Plic::with_config(64, 2);
+    assert_eq!(plic.source_count(), 64);
+
+    // ID 40 の優先度は 0x000000 + 4*40、pending はワード 1 (0x001004)
+    plic.write(0x0000a0, 7);
+    assert_eq!(plic.priorities[40], 7);
+    plic.set_interrupt(40);
+    assert_eq!(plic.read(0x001004), 1 << 8);
+
+    // コンテキスト 1 の enable は 0x002000 + 0x80 + 4*word
+    plic.write(0x002084, 1 << 8);
+    assert!(plic.is_enabled(1, 40));
+    assert!(!plic.is_enabled(0, 40));
+    assert!(!plic.get_interrupt_level(0));
+    assert!(plic.context_interrupt_level(1));
+
+    // コンテキスト 1 の threshold は 0x201000、claim/complete は 0x201004
+    plic.write(0x201000, 7);
+    assert!(!plic.context_interrupt_level(1));
+    plic.write(0x201000, 6);
+    assert_eq!(plic.read(0x200004), 0);
+    assert_eq!(plic.read(0x201004), 40);
+    assert!(plic.is_claimed(40));
+
+    // 有効になっていないコンテキストからの complete は無視される
+    plic.write(0x200004, 40);
+    assert!(plic.is_claimed(40));
+    plic.write(0x201004, 40);
+    assert!(!plic.is_claimed(40));
+}
+
+#[test]
+fn test_plic_contexts_are_independent() {
+    let mut plic = Plic::with_config(32, 2);
+    plic.harts[0].supervisor = Some(1);
+    plic.priorities[1] = 1;
+    plic.priorities[2] = 5;
+    plic.set_enabled(0, 1, true);
+    plic.set_enabled(1, 2, true);
+    plic.set_enabled(1, 1, true);
+
+    plic.set_interrupt(1);
+    assert!(plic.get_interrupt_level(0));
+    assert_eq!(plic.get_supervisor_interrupt_level(0), Some(true));
+
+    // 閾値はコンテキストごと
+    plic.contexts[1].threshold = 1;
+    assert_eq!(plic.get_supervisor_interrupt_level(0), Some(false));
+    plic.set_interrupt(2);
+    assert_eq!(plic.get_supervisor_interrupt_level(0), Some(true));
+
+    // Claim した割り込みはどのコンテキストにも通知されない
+    assert_eq!(plic.claim_context(1), 2);
+    assert_eq!(plic.get_supervisor_interrupt_level(0), Some(false));
+    assert_eq!(plic.claim(0), 1);
+    assert!(!plic.get_interrupt_level(0));
+
+    // S モードのコンテキストを接続していなければ None
+    assert_eq!(Plic::new().get_supervisor_interrupt_level(0), None);
+}
+
+#[test]
+fn test_plic_max_sources() {
+    let mut plic = Plic::with_config(MAX_SOURCE_COUNT, 1);
+
+    // ID 1023 の優先度は 0xffc、pending と enable はワード 31
+    plic.write(0x000ffc, 3);
+    plic.write(0x00207c, 1 << 31);
+    plic.set_interrupt(1023);
+    assert_eq!(plic.read(0x00107c), 1 << 31);
+    assert!(plic.get_interrupt_level(0));
+    assert_eq!(plic.claim(0), 1023);
+    plic.complete(0, 1023);
+    assert!(!plic.is_claimed(1023));
+}
+
+#[test]
+fn test_plic_out_of_range_sources_are_ignored() {
+    let mut plic = Plic::with_config(40, 1);
+
+    // 存在しないソースの priority・enable は 0 に固定され、信号も無視する
+    plic.write(0x0000a0, 5);
+    assert_eq!(plic.read(0x0000a0), 0);
+    plic.write(0x002004, u32::MAX);
+    assert_eq!(plic.read(0x002004), 0xff);
+    plic.set_interrupt(40);
+    plic.set_interrupt(2000);
+    assert_eq!(plic.read(0x001004), 0);
+
+    // ID 0 (ソースなし) も有効にできない
+    plic.write(0x002000, u32::MAX);
+    assert_eq!(plic.read(0x002000), !1);
+
+    // 存在しないコンテキストへのアクセスは無視する
+    plic.write(0x201000, 1);
+    assert_eq!(plic.read(0x201000), 0);
+    assert_eq!(plic.read(0x201004), 0);
+}
+
+#[test]
+fn test_plic_edge_pulse_during_claim() {
+    let mut plic = Plic::new();
+    plic.priorities[1] = 1;
+    plic.set_enabled(0, 1, true);
+    plic.set_trigger_mode(1, TriggerMode::Edge);
+    assert_eq!(plic.trigger_mode(1), TriggerMode::Edge);
+
+    // パルスが終わっても pending は残る
+    plic.pulse_interrupt(1);
+    assert!(plic.is_pending(1));
+    assert_eq!(plic.claim(0), 1);
+
+    // Claim 中のパルスは 1 つにまとめてゲートウェイに保持する
+    plic.pulse_interrupt(1);
+    plic.pulse_interrupt(1);
+    assert!(!plic.is_pending(1));
+    assert_eq!(plic.latched_edges(1), 1);
+    assert!(!plic.get_interrupt_level(0));
+
+    // Complete で保持していたエッジが再送される
+    plic.complete(0, 1);
+    assert!(plic.is_pending(1));
+    assert_eq!(plic.latched_edges(1), 0);
+    assert_eq!(plic.claim(0), 1);
+    plic.complete(0, 1);
+    assert!(!plic.is_pending(1));
+    assert!(!plic.get_interrupt_level(0));
+
+    // pending のまま来たパルスも 1 つにまとめる
+    plic.pulse_interrupt(1);
+    plic.pulse_interrupt(1);
+    assert_eq!(plic.latched_edges(1), 0);
+    assert_eq!(plic.claim(0), 1);
+    plic.complete(0, 1);
+    assert!(!plic.is_pending(1));
+}
+
+#[test]
+fn test_plic_edge_counting_gateway() {
+    let mut plic = Plic::new();
+    plic.priorities[2] = 1;
+    plic.set_enabled(0, 2, true);
+    plic.set_trigger_mode(2, TriggerMode::EdgeCounting);
+
+    // pending 中と Claim 中のパルスを数えておく
+    plic.pulse_interrupt(2);
+    plic.pulse_interrupt(2);
+    assert_eq!(plic.claim(0), 2);
+    plic.pulse_interrupt(2);
+    assert_eq!(plic.latched_edges(2), 2);
+
+    // Complete のたびに 1 つずつ再送する
+    for remaining in [1, 0] {
+        plic.complete(0, 2);
+        assert_eq!(plic.latched_edges(2), remaining);
+        assert_eq!(plic.claim(0), 2);
+    }
+    plic.complete(0, 2);
+    assert!(!plic.get_interrupt_level(0));
+    assert_eq!(plic.claim(0), 0);
+
+    // トリガー方式を変えると、保持していたエッジは破棄する
+    plic.pulse_interrupt(2);
+    assert_eq!(plic.claim(0), 2);
+    plic.pulse_interrupt(2);
+    plic.set_trigger_mode(2, TriggerMode::Level);
+    assert_eq!(plic.latched_edges(2), 0);
+    plic.complete(0, 2);
+    assert!(!plic.is_pending(2));
+}
+
+#[test]
+fn test_plic_edge_and_level_sources_coexist() {
+    let mut plic = Plic::new();
+    plic.priorities[1] = 1;
+    plic.priorities[3] = 2;
+    plic.set_enabled(0, 1, true);
+    plic.set_enabled(0, 3, true);
+    plic.set_trigger_mode(3, TriggerMode::Edge);
+
+    // エッジトリガーのソースは、信号が High のままでもエッジ 1 回分しか pending にならない
+    plic.set_interrupt(3);
+    plic.set_interrupt(3);
+    assert_eq!(plic.claim(0), 3);
+    plic.complete(0, 3);
+    assert!(!plic.is_pending(3));
+
+    // 信号を下げて上げ直すと、新しいエッジになる
+    plic.clear_interrupt(3);
+    plic.set_interrupt(3);
+    assert!(plic.is_pending(3));
+
+    // レベルトリガーのソースは、信号が下がると pending も下がる
+    plic.set_interrupt(1);
+    plic.clear_interrupt(1);
+    assert!(!plic.is_pending(1));
+    assert_eq!(plic.claim(0), 3);
+}
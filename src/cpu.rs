@@ -5,22 +5,53 @@ mod privilege_mode;
 mod rv32i;
 mod rv32m;
 mod rv32c;
+mod rv32a;
+mod rv32f;
+mod bitmanip;
 mod zicsr;
 
 #[cfg(test)]
 mod interrupt;
+#[cfg(test)]
+mod run;
 mod instructions;
+mod mem_access;
+mod pmp;
+mod mmu;
+mod icache;
+mod micro_op;
+mod block;
 
 use super::bus;
-use csr::Csr;
+use csr::{Csr, MIP_SEIP, MSTATUS_MIE, MSTATUS_SIE};
 use privilege_mode::PrivilegeMode;
 use crate::cpu::instructions::Instruction;
+use pmp::AccessType;
+use mmu::Tlb;
+use icache::InstructionCache;
+use micro_op::MicroOp;
+use block::{Block, BlockCache, NO_BLOCK};
+pub use mem_access::MisalignedAccess;
+pub use icache::{ICacheStats, DEFAULT_ICACHE_CAPACITY};
 
 #[derive(Debug)]
 pub enum StepResult {
     Ok(u32),
     Trap(u32),
     Jumped,
+    /// WFI により割り込み待ちの状態 (pc は WFI の次の命令を指す)
+    Waiting,
+}
+
+/// `Cpu::run` が停止した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// 指定した命令数を実行した
+    BudgetExhausted,
+    /// トラップが発生した (例外コード)。pc はトラップハンドラを指す
+    Trap(u32),
+    /// WFI により割り込み待ちの状態
+    Waiting,
 }
 
 /// 4KB ページ
@@ -29,14 +60,64 @@ const PAGE_SIZE: usize = 4096;
 /// RISC-V C 拡張を考慮して 2 byte ごとに命令を格納する
 const ENTRY_COUNT: usize = PAGE_SIZE / 2;
 
-/// 命令キャッシュのページ定義
-type InstructionCachePage = [Instruction; ENTRY_COUNT];
+/// 命令キャッシュのページ定義。
+/// 実行時に参照する `ops` を小さく保つため、専用のハンドラを持たない命令用に元の命令を `insts` に分けて持つ
+#[derive(Clone, Copy)]
+struct InstructionCachePage {
+    ops: [MicroOp; ENTRY_COUNT],
+    insts: [Instruction; ENTRY_COUNT],
+}
+
+impl InstructionCachePage {
+    /// 命令が 1 つもキャッシュされていないページ
+    const EMPTY: InstructionCachePage = InstructionCachePage {
+        ops: [MicroOp::NONE; ENTRY_COUNT],
+        insts: [Instruction::None; ENTRY_COUNT],
+    };
+
+    /// 仮想アドレス `pc` の命令 `inst` を登録する
+    fn set(&mut self, entry_idx: usize, inst: Instruction, pc: u32) {
+        self.ops[entry_idx] = MicroOp::new(inst, pc);
+        self.insts[entry_idx] = inst;
+    }
+
+    /// 同じページに収まる連続した命令の組を、1 つのマイクロ命令に融合する。
+    /// 後半の命令のエントリは、そこへジャンプしてきた場合のためにそのまま残す
+    fn fuse_pairs(&mut self) {
+        for entry_idx in 0..ENTRY_COUNT {
+            let first = self.ops[entry_idx];
+            if first.is_none() {
+                continue;
+            }
+            let next_idx = entry_idx + first.size() as usize / 2;
+            let Some(&second) = self.ops.get(next_idx) else { continue };
+            if next_idx * 2 + second.size() as usize > PAGE_SIZE {
+                continue;
+            }
+            if let Some(fused) = MicroOp::fuse(first, second) {
+                self.ops[entry_idx] = fused;
+            }
+        }
+    }
+}
+
+/// LR.W で獲得した予約。
+/// 自身のストアとは仮想アドレスで、他のハートの書き込みとは物理アドレスで照合する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Reservation {
+    vaddr: u32,
+    paddr: u32,
+}
 
 /// CPU の内部状態
 pub struct Cpu {
     /// 32本の汎用レジスタ (x0-x31)
     pub regs: [u32; 32],
 
+    /// 32本の浮動小数点レジスタ (f0-f31)
+    /// 単精度の値は上位 32 ビットを 1 で埋めた NaN-boxing 形式で格納する
+    pub fregs: [u64; 32],
+
     /// プログラムカウンタ
     pub pc: u32,
 
@@ -46,100 +127,359 @@ pub struct Cpu {
     /// 特権モード
     pub mode: PrivilegeMode,
 
-    /// 命令キャッシュ
-    pages: Vec<Option<InstructionCachePage>>,
+    /// ミスアラインなロード/ストアの扱い
+    pub misaligned_access: MisalignedAccess,
+
+    /// 命令キャッシュに載っているページへの書き込みを検出して、キャッシュを自動的に破棄するか。
+    /// false の場合、書き換えたコードを実行するには fence.i 等で明示的にキャッシュを破棄する必要がある
+    pub invalidate_on_code_write: bool,
+
+    /// 命令キャッシュ (仮想ページ番号で管理する)
+    icache: InstructionCache,
 
-    /// 現在参照しているページ
-    current_page: InstructionCachePage,
+    /// 基本ブロックのキャッシュ (`run` でジャンプ先のページを引かずに実行を続けるために使う)
+    blocks: BlockCache,
+
+    /// 現在参照しているページの命令キャッシュのスロット
+    current_slot: usize,
 
     /// 命令キャッシュのページ番号
     current_page_num: u32,
+
+    /// 現在参照しているページの物理アドレス
+    current_page_paddr: u32,
+
+    /// 命令キャッシュに載せた物理ページのビットマップ (書き込みの検出用)
+    code_pages: Vec<u64>,
+
+    /// 命令キャッシュを参照したときの特権モード
+    fetch_mode: PrivilegeMode,
+
+    /// アドレス変換の TLB
+    tlb: Tlb,
+
+    /// 直近のトラップで mtval に格納する値 (フォールトアドレス等)
+    trap_value: u32,
+
+    /// LR.W で獲得した予約 (ワード単位)
+    reservation: Option<Reservation>,
+
+    /// WFI を実行して割り込みを待っているか
+    waiting: bool,
 }
 
 impl Cpu {
     pub fn new(pc: u32) -> Self {
         Self {
             regs: [0; 32],
+            fregs: [0; 32],
             pc,
             csr: Csr::default(),
             mode: PrivilegeMode::Machine,
-            pages: vec![None; 1024],
-            current_page: [Instruction::None; ENTRY_COUNT],
+            misaligned_access: MisalignedAccess::default(),
+            invalidate_on_code_write: true,
+            icache: InstructionCache::new(DEFAULT_ICACHE_CAPACITY),
+            blocks: BlockCache::new(),
+            current_slot: 0,
             current_page_num: 0xffffffff, // 最初は必ずキャッシュミスするように
+            current_page_paddr: 0,
+            code_pages: vec![0; (1 << 20) / 64],
+            fetch_mode: PrivilegeMode::Machine,
+            tlb: Tlb::new(),
+            trap_value: 0,
+            reservation: None,
+            waiting: false,
         }
     }
 
-    /// 1ステップ実行
+    /// 1ステップ実行。
+    /// 現在のページの終わりか、最初のジャンプ・トラップまで命令を実行する
     pub fn step<B: bus::Bus>(&mut self, bus: &mut B) -> (StepResult, u32) {
+        // テスト時は 1 命令ずつ実行するように制限 (バスの状態が変わる可能性があるため)
+        let limit = if cfg!(test) { 1 } else { u32::MAX };
+        self.step_limited(bus, limit, false)
+    }
+
+    /// 命令を 1 つだけ実行する
+    pub fn step_one<B: bus::Bus>(&mut self, bus: &mut B) -> (StepResult, u32) {
+        self.step_limited(bus, 1, false)
+    }
+
+    /// 最大 `budget` 命令を実行する。
+    /// トラップの発生か WFI による待機で途中で停止し、停止した理由とリタイアした命令数 (minstret の増分) を返す。
+    /// トラップした命令はリタイアしないため数えない
+    /// ジャンプやページの境界では、割り込みが発生しない限り `step` に戻らず次の基本ブロックへ連結して実行を続ける
+    pub fn run<B: bus::Bus>(&mut self, bus: &mut B, budget: u32) -> (StopReason, u32) {
+        let mut executed = 0;
+        while executed < budget {
+            let (result, clock) = self.step_limited(bus, budget - executed, true);
+            executed += clock;
+            match result {
+                StepResult::Trap(code) => return (StopReason::Trap(code), executed),
+                StepResult::Waiting => return (StopReason::Waiting, executed),
+                _ => {}
+            }
+        }
+        (StopReason::BudgetExhausted, executed)
+    }
+
+    /// 最大 `limit` 命令を、現在のページの終わりか最初のジャンプ・トラップまで実行する。
+    /// `chain` が true の場合は、ジャンプやページの終わりで次の基本ブロックへ連結して実行を続ける
+    fn step_limited<B: bus::Bus>(&mut self, bus: &mut B, limit: u32, chain: bool) -> (StepResult, u32) {
         let mut clock = 0;
+        // バスのクロックを進めた時点の clock (バスには実行した命令数をサイクル数として渡す)
+        let mut timed_clock = 0;
+        // トラップしてリタイアしなかった命令数 (クロックは進めるが、返す命令数には含めない)
+        let mut not_retired = 0;
         let mut result: StepResult = StepResult::Ok(0);
 
-        // クロックを進める
-        bus.tick();
+        // ホストなどがバス経由で命令キャッシュに載っているページを書き換えていれば破棄する
+        if self.invalidate_on_code_write {
+            while let Some(page) = bus.take_code_write() {
+                self.invalidate_code_page(page);
+            }
+        }
+
+        // WFI で待機中は、mstatus の割り込み許可に関係なく、保留中かつ mie で有効な割り込みがあれば再開する
+        if self.waiting {
+            self.update_mip(bus);
+            if (self.csr.mip & self.csr.mie) == 0 {
+                // 待機中も時間は進む (1 ステップを 1 サイクルとする)
+                bus.tick(1);
+                return (StepResult::Waiting, clock);
+            }
+            self.waiting = false;
+        }
 
         // 実行前に割り込みをチェック
-        // パフォーマンス向上のため、MIE が有効な場合のみチェックする
-        if (self.csr.mstatus & (1 << 3)) != 0 {
-            if let Some(interrupt_code) = self.check_interrupts(bus) {
-                result = self.handle_trap(interrupt_code, 0);
-                self.regs[0] = 0; // レジスタ 0 は常に 0 に保つ.
-                self.current_page_num = 0xffffffff;
-                return (result, clock);
+        if let Some(result) = self.take_interrupt(bus) {
+            return (result, clock);
+        }
+
+        // 特権モードが変わった場合は、命令フェッチの変換と権限を確認し直す。
+        // 変換の有無が切り替わった (M モードとの行き来など) 場合、仮想ページで管理している命令キャッシュは使えない
+        if self.mode != self.fetch_mode {
+            if self.translation_active(self.mode) != self.translation_active(self.fetch_mode) {
+                self.flush_all_cache();
             }
+            self.fetch_mode = self.mode;
+            self.current_page_num = 0xffffffff;
+        }
+
+        if let Err(trap) = self.enter_page(bus) {
+            return self.finish_step(trap, clock, bus);
         }
+        self.blocks.enter(NO_BLOCK);
+
+        'chain: loop {
+            let max_page_num = self.pc | 0xfff;
+            while self.pc <= max_page_num {
+                let page_offset = (self.pc & (PAGE_SIZE as u32 - 1)) as usize;
+                let entry_idx = page_offset >> 1;
+                let mut op = self.icache.page(self.current_slot).ops[entry_idx];
+
+                // 実行中のページは生成済みのため、ページ生成時にフェッチできなかった (または命令境界から外れた) 命令だけを
+                // 直接フェッチしてページに登録する。ページを跨ぐ命令は次のページの書き込みを監視していないため、登録せずに毎回フェッチする
+                if op.is_none() {
+                    match self.fetch_inst(self.pc, bus) {
+                        Ok(fetched) => {
+                            let page = self.icache.page_mut(self.current_slot);
+                            page.set(entry_idx, fetched, self.pc);
+                            op = page.ops[entry_idx];
+                            if page_offset as u32 + op.size() > PAGE_SIZE as u32 {
+                                page.ops[entry_idx] = MicroOp::NONE;
+                            }
+                        }
+                        Err(trap) => {
+                            result = trap;
+                            break 'chain;
+                        }
+                    }
+                }
+
+                // 融合した命令の組は、実行できる命令数が 1 つしか残っていない場合や後半の命令の実行が PMP で
+                // 許可されていない場合は前半だけを実行し、1 命令ずつ実行したときと同じ位置で止まるようにする
+                let paddr = self.current_page_paddr | page_offset as u32;
+                if op.is_fused() && (clock + 1 >= limit || !self.pmp_allows(paddr, op.size(), AccessType::Execute)) {
+                    op = MicroOp::new(self.icache.page(self.current_slot).insts[entry_idx], self.pc);
+                }
+
+                // PMP による実行権限のチェック (ページを跨ぐ命令は、次のページに含まれる部分も検査する)
+                let fetch_size = op.size().min(PAGE_SIZE as u32 - page_offset as u32);
+                if let Err(trap) = self.check_pmp(paddr, fetch_size, AccessType::Execute, self.pc) {
+                    result = trap;
+                    break 'chain;
+                }
+                if fetch_size < op.size()
+                    && let Err(trap) = self.check_next_page_fetch(op.size() - fetch_size, bus)
+                {
+                    result = trap;
+                    break 'chain;
+                }
+
+                // x0 への書き込みはデコード時に取り除いてあるため、ハンドラを呼ぶだけでよい
+                result = self.exec_op(&op, bus);
+                clock += op.count();
+                match result {
+                    StepResult::Ok(inst_size) => {
+                        self.pc += inst_size;
+                        self.retire_op(&op);
+                        // fence.i や satp の書き込みで命令キャッシュが破棄された場合はページを引き直す
+                        if self.current_page_num == 0xffffffff || clock >= limit {
+                            break 'chain;
+                        }
+                    }
+                    StepResult::Jumped => {
+                        self.retire_op(&op);
+                        break;
+                    }
+                    StepResult::Waiting => {
+                        self.retire_op(&op);
+                        break 'chain;
+                    }
+                    _ => {
+                        not_retired = op.count();
+                        break 'chain;
+                    }
+                }
+            }
 
-        let page_num = (self.pc >> 12) as usize;
-        if page_num != self.current_page_num as usize {
-            if page_num >= self.pages.len() {
-                self.pages.resize(page_num + 1, None);
+            // ジャンプかページの終わりに達した。次のブロックへ移れなければ `step` からやり直す
+            if !chain || clock >= limit || !self.enter_next_block(bus) {
+                break;
             }
-            if self.pages[page_num].is_none() {
-                self.current_page_num = page_num as u32;
-                self.gen_cache_page(bus);
-            } else {
-                self.current_page_num = page_num as u32;
-                self.current_page = self.pages[page_num].unwrap();
+            bus.tick(clock - timed_clock);
+            timed_clock = clock;
+            if let Some(result) = self.take_interrupt(bus) {
+                return (result, clock);
             }
         }
 
-        let max_page_num = self.pc | 0xfff;
-        while self.pc <= max_page_num {
-            let page_offset = (self.pc & (PAGE_SIZE as u32 - 1)) as usize;
-            let entry_idx = page_offset >> 1;
-            let mut inst = self.current_page[entry_idx];
+        bus.tick(clock - timed_clock);
+        self.finish_step(result, clock - not_retired, bus)
+    }
 
-            if matches!(inst, Instruction::None) {
-                self.gen_cache_page(bus);
-                inst = self.current_page[entry_idx];
-            }
+    /// ページを跨ぐ命令の、次のページに含まれる先頭 `size` バイトのアドレス変換と PMP の検査を行う
+    #[cold]
+    fn check_next_page_fetch<B: bus::Bus>(&mut self, size: u32, bus: &mut B) -> Result<(), StepResult> {
+        let vaddr = (self.pc | (PAGE_SIZE as u32 - 1)).wrapping_add(1);
+        let paddr = self.translate(vaddr, AccessType::Execute, bus)?;
+        self.check_pmp(paddr, size, AccessType::Execute, vaddr)
+    }
 
-            result = self.exec(inst, bus);
+    /// 受け付けられる割り込みが保留されていれば、トラップハンドラへ遷移する
+    fn take_interrupt<B: bus::Bus>(&mut self, bus: &mut B) -> Option<StepResult> {
+        // パフォーマンス向上のため、MIE が有効な場合か、M モード以外 (M レベルの割り込みは常に有効) の場合のみチェックする
+        if ((self.csr.mstatus & MSTATUS_MIE) != 0 || self.mode != PrivilegeMode::Machine)
+            && let Some(interrupt_code) = self.check_interrupts(bus)
+        {
+            let result = self.handle_trap(interrupt_code, 0);
             self.regs[0] = 0; // レジスタ 0 は常に 0 に保つ.
-            clock += 1;
-            match result { 
-                StepResult::Ok(inst_size) => self.pc += inst_size,
-                _ => break,
+            self.current_page_num = 0xffffffff;
+            return Some(result);
+        }
+        None
+    }
+
+    /// pc のページが現在のページでなければ、アドレスを変換して命令キャッシュのページを引く
+    fn enter_page<B: bus::Bus>(&mut self, bus: &mut B) -> Result<(), StepResult> {
+        let page_num = self.pc >> 12;
+        if page_num != self.current_page_num {
+            // 命令フェッチのアドレス変換
+            self.current_page_paddr = self.translate(self.pc, AccessType::Execute, bus)? & !(PAGE_SIZE as u32 - 1);
+            self.current_page_num = page_num;
+            match self.icache.lookup(page_num) {
+                Some(slot) => self.current_slot = slot,
+                None => self.gen_cache_page(bus),
             }
-            // テスト時は 1 命令ずつ実行するように制限 (バスの状態が変わる可能性があるため)
-            #[cfg(test)]
-            { break; }
         }
+        Ok(())
+    }
+
+    /// pc から始まる基本ブロックへ移り、実行するページを切り替える。
+    /// 実行中のブロックに連結済みであればページを引かずに済む。
+    /// 特権モードが変わった場合や、フェッチでフォールトする場合は false を返す (`step` でやり直す)
+    #[inline(never)]
+    fn enter_next_block<B: bus::Bus>(&mut self, bus: &mut B) -> bool {
+        if self.mode != self.fetch_mode {
+            return false;
+        }
+        // 同じページ内のジャンプであれば、そのまま続けて実行できる
+        if (self.pc >> 12) == self.current_page_num {
+            return true;
+        }
+        self.blocks.sync(self.icache.generation());
 
+        let pc = self.pc;
+        let idx = match self.blocks.linked(pc, self.mode) {
+            Some(idx) => idx,
+            None => {
+                let idx = match self.blocks.lookup(pc, self.mode) {
+                    Some(idx) => idx,
+                    None => {
+                        // ページを引いてブロックを作る (ページを追い出した場合は、ブロックもすべて破棄される)
+                        self.current_page_num = 0xffffffff;
+                        if self.enter_page(bus).is_err() {
+                            return false;
+                        }
+                        self.blocks.sync(self.icache.generation());
+                        let block = Block::new(pc, self.mode, self.current_slot, self.current_page_paddr);
+                        let idx = self.blocks.insert(block);
+                        self.blocks.link(pc, idx);
+                        self.blocks.enter(idx);
+                        return true;
+                    }
+                };
+                self.blocks.link(pc, idx);
+                idx
+            }
+        };
+
+        let block = *self.blocks.block(idx);
+        self.icache.touch(block.slot);
+        self.current_slot = block.slot;
+        self.current_page_num = pc >> 12;
+        self.current_page_paddr = block.page_paddr;
+        self.blocks.enter(idx);
+        true
+    }
+
+    /// ステップの結果がトラップであればトラップハンドラへ遷移する
+    fn finish_step<B: bus::Bus>(&mut self, result: StepResult, clock: u32, bus: &mut B) -> (StepResult, u32) {
         match result {
             StepResult::Trap(code) => {
-                let mtval = if code == 2 {
+                let trap_value = std::mem::take(&mut self.trap_value);
+                let mtval = match code {
                     // 違法命令の場合、現在 pc が指している命令を mtval に格納
-                    bus.read32(self.pc)
-                } else {
-                    0
+                    2 => bus.read32(self.current_page_paddr | (self.pc & (PAGE_SIZE as u32 - 1))).unwrap_or(0),
+                    // アドレス不整列・アクセスフォールト・ページフォールトの場合、対象のアドレスを mtval に格納
+                    1 | 4..=7 | 12 | 13 | 15 => trap_value,
+                    _ => 0,
                 };
-                result = self.handle_trap(code, mtval);
-                (result, clock)
+                (self.handle_trap(code, mtval), clock)
             },
             _ => (result, clock)
         }
     }
 
+    /// WFI を実行して割り込みを待っているか。
+    /// 待機中の `step` は命令を実行せずに `StepResult::Waiting` を返すため、
+    /// ホストはスリープしたり、mtime を次の mtimecmp まで進めたりできる
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    /// LR.W で予約している物理アドレス (予約がなければ None)
+    pub fn reserved_address(&self) -> Option<u32> {
+        self.reservation.map(|reserved| reserved.paddr)
+    }
+
+    /// LR/SC の予約を破棄する。
+    /// 他のハートが予約したアドレスに書き込んだときに使う
+    pub fn clear_reservation(&mut self) {
+        self.reservation = None;
+    }
+
     /// レジスタの状態をダンプ
     pub fn dump_registers(&self) {
         for (i, reg) in self.regs.iter().enumerate() {
@@ -153,42 +493,61 @@ impl Cpu {
         println!("mtval  : 0x{:08x}", self.csr.mtval);
     }
 
-    /// PLIC から割り込みを取得する (Claim)
+    /// このハートの M モードのコンテキストで PLIC から割り込みを取得する (Claim)
     pub fn claim_interrupt<B: bus::Bus>(&mut self, bus: &mut B) -> u32 {
-        bus.plic_claim()
+        bus.plic_claim(self.csr.mhartid as usize)
     }
 
-    /// PLIC に割り込み完了を通知する (Complete)
+    /// このハートの M モードのコンテキストで PLIC に割り込み完了を通知する (Complete)
     pub fn complete_interrupt<B: bus::Bus>(&mut self, bus: &mut B, source_id: u32) {
-        bus.plic_complete(source_id);
+        bus.plic_complete(self.csr.mhartid as usize, source_id);
     }
 
-    /// 割り込みのチェックを行い、発生すべき割り込みがあればその例外コードを返す
-    fn check_interrupts<B: bus::Bus>(&mut self, bus: &B) -> Option<u32> {
-        // 外部信号をチェックする前に、タイマー割り込みなどが既にペンディングされているか確認
-        // (厳密にはバスの状態を毎回反映すべきだが、パフォーマンスとのトレードオフ)
+    /// バスの割り込み信号を mip に反映させる
+    fn update_mip<B: bus::Bus>(&mut self, bus: &mut B) {
+        let hart = self.csr.mhartid as usize;
 
         // PLIC 等の外部信号を mip.MEIP に反映させる (最小構成として)
-        if bus.get_interrupt_level() {
+        if bus.get_interrupt_level(hart) {
             self.csr.mip |= 1 << 11; // MEIP
         } else {
             self.csr.mip &= !(1 << 11);
         }
 
+        // S モードのコンテキストが接続されていれば、ソフトウェアが書き込んだ SEIP と PLIC の信号の論理和を mip.SEIP に反映させる
+        if let Some(level) = bus.get_supervisor_interrupt_level(hart) {
+            self.csr.seip_external = level;
+            if level || self.csr.seip_soft {
+                self.csr.mip |= MIP_SEIP;
+            } else {
+                self.csr.mip &= !MIP_SEIP;
+            }
+        }
+
         // タイマー割り込み信号を mip.MTIP に反映させる
-        if bus.get_timer_interrupt_level() {
+        if bus.get_timer_interrupt_level(hart) {
             self.csr.mip |= 1 << 7; // MTIP
         } else {
             self.csr.mip &= !(1 << 7);
         }
 
         // ソフトウェア割り込み信号を mip.MSIP に反映させる
-        if bus.get_software_interrupt_level() {
+        if bus.get_software_interrupt_level(hart) {
             self.csr.mip |= 1 << 3; // MSIP
         } else {
             self.csr.mip &= !(1 << 3);
         }
 
+        // SSWI からの要求で mip.SSIP を立てる (下げるのはソフトウェア)
+        if bus.take_supervisor_software_interrupt(hart) {
+            self.csr.mip |= 1 << 1; // SSIP
+        }
+    }
+
+    /// 割り込みのチェックを行い、発生すべき割り込みがあればその例外コードを返す
+    fn check_interrupts<B: bus::Bus>(&mut self, bus: &mut B) -> Option<u32> {
+        self.update_mip(bus);
+
         // 発生している有効な割り込みを計算
         let pending_interrupts = self.csr.mip & self.csr.mie;
 
@@ -196,68 +555,141 @@ impl Cpu {
             return None;
         }
 
-        // 優先順位: 外部割り込み > ソフトウェア割り込み > タイマー割り込み
-        // 外部割り込み (Machine External Interrupt)
-        if (pending_interrupts & (1 << 11)) != 0 {
-            return Some(0x8000_000b); // MSB=1, Code=11
-        }
-
-        // ソフトウェア割り込み (Machine Software Interrupt)
-        if (pending_interrupts & (1 << 3)) != 0 {
-            return Some(0x8000_0003); // MSB=1, Code=3
-        }
+        // M モードで処理する割り込み。M モードより低い特権モードでは常に有効、M モードでは mstatus.MIE が必要
+        let machine_enabled = self.mode != PrivilegeMode::Machine || (self.csr.mstatus & MSTATUS_MIE) != 0;
+        let machine_interrupts = if machine_enabled {
+            pending_interrupts & !self.csr.mideleg
+        } else {
+            0
+        };
 
-        // タイマー割り込み (Machine Timer Interrupt)
-        if (pending_interrupts & (1 << 7)) != 0 {
-            return Some(0x8000_0007); // MSB=1, Code=7
-        }
+        // S モードに委譲された割り込み。M モードでは受け付けず、S モードでは mstatus.SIE が必要
+        let supervisor_enabled = match self.mode {
+            PrivilegeMode::User => true,
+            PrivilegeMode::Supervisor => (self.csr.mstatus & MSTATUS_SIE) != 0,
+            PrivilegeMode::Machine => false,
+        };
+        let supervisor_interrupts = if supervisor_enabled {
+            pending_interrupts & self.csr.mideleg
+        } else {
+            0
+        };
 
-        None
+        // 優先順位: 外部割り込み > ソフトウェア割り込み > タイマー割り込み (M レベルが S レベルより優先)
+        const PRIORITY: [u32; 6] = [11, 3, 7, 9, 1, 5];
+        let enabled_interrupts = machine_interrupts | supervisor_interrupts;
+        PRIORITY
+            .iter()
+            .find(|&&code| (enabled_interrupts & (1 << code)) != 0)
+            .map(|&code| 0x8000_0000 | code) // MSB=1, Code
     }
 
 
+    /// 現在のページ (仮想アドレス) の命令キャッシュを、変換済みの物理ページから生成する
     fn gen_cache_page<B: bus::Bus>(&mut self, bus: &mut B) {
         let page_size = PAGE_SIZE as u32;
         let start_pc = self.pc & !(page_size - 1);
-        let mut cache = [Instruction::None; ENTRY_COUNT];
+        let page_paddr = self.current_page_paddr;
+        let mut cache = InstructionCachePage::EMPTY;
 
-        let mut raw_ptr = start_pc;
+        let mut raw_ptr = page_paddr;
         while (raw_ptr & (page_size - 1)) < page_size - 1 {
             let entry_idx = ((raw_ptr & (page_size - 1)) >> 1) as usize;
             if entry_idx >= ENTRY_COUNT { break; }
+            let pc = start_pc | (raw_ptr & (page_size - 1));
 
             // ページ境界を跨ぐ命令のチェック
+            // フェッチできないアドレスに達したらキャッシュの生成を打ち切る (実行時にフォールトさせる)
             if (raw_ptr & (page_size - 1)) == page_size - 2 {
-                let inst_low = bus.read16(raw_ptr);
+                let Ok(inst_low) = bus.read16(raw_ptr) else { break };
                 if inst_low == 0 {
-                    cache[entry_idx] = Instruction::Illegal;
+                    cache.set(entry_idx, Instruction::Illegal, pc);
                     break;
                 }
                 let quadrant = Instruction::decode_quadrant(inst_low);
                 if quadrant == 0b11 {
-                    let inst_high = bus.read16(raw_ptr + 2);
+                    // 上位 16 ビットは次の仮想ページにあるため、改めて変換する
+                    let next_page = start_pc.wrapping_add(page_size);
+                    let Ok(next_paddr) = self.translate(next_page, AccessType::Execute, bus) else { break };
+                    let Ok(inst_high) = bus.read16(next_paddr) else { break };
+                    self.watch_code_page(next_paddr >> 12, bus);
                     let inst_bin = ((inst_high as u32) << 16) | inst_low as u32;
                     let (inst, _) = self.gen_inst_from_bin(inst_bin, quadrant);
-                    cache[entry_idx] = inst;
+                    cache.set(entry_idx, inst, pc);
                     break;
                 }
             }
 
-            let inst_bin16 = bus.read16(raw_ptr);
+            let Ok(inst_bin16) = bus.read16(raw_ptr) else { break };
             if inst_bin16 == 0 {
-                cache[entry_idx] = Instruction::Illegal;
+                cache.set(entry_idx, Instruction::Illegal, pc);
                 raw_ptr += 2;
                 if (raw_ptr & (page_size - 1)) == 0 { break; }
                 continue;
             }
 
-            let (inst, inst_size) = self.gen_inst(raw_ptr, bus);
-            cache[entry_idx] = inst;
+            let Ok((inst, inst_size)) = self.gen_inst(raw_ptr, bus) else { break };
+            cache.set(entry_idx, inst, pc);
             raw_ptr += inst_size;
             if (raw_ptr & (page_size - 1)) == 0 { break; }
         }
-        self.current_page = cache;
-        self.pages[(start_pc / page_size) as usize] = Some(cache);
+        cache.fuse_pairs();
+        self.current_slot = self.icache.insert(start_pc >> 12, &cache);
+        self.watch_code_page(page_paddr >> 12, bus);
+    }
+
+    /// 物理ページ `page` を命令キャッシュに載せたことを記録し、バスにも書き込みの監視を依頼する
+    fn watch_code_page<B: bus::Bus>(&mut self, page: u32, bus: &mut B) {
+        if self.invalidate_on_code_write {
+            self.code_pages[(page / 64) as usize] |= 1 << (page % 64);
+            bus.watch_code_page(page);
+        }
+    }
+
+    /// ストアで書き込んだ物理アドレスが命令キャッシュに載っているページであれば、キャッシュを破棄する
+    #[inline(always)]
+    pub(crate) fn note_code_write(&mut self, paddr: u32) {
+        let page = paddr >> 12;
+        if self.invalidate_on_code_write && (self.code_pages[(page / 64) as usize] & (1 << (page % 64))) != 0 {
+            self.invalidate_code_page(page);
+        }
+    }
+
+    /// 物理ページ `page` の命令を含む命令キャッシュを破棄する
+    #[cold]
+    fn invalidate_code_page(&mut self, page: u32) {
+        self.code_pages[(page / 64) as usize] &= !(1 << (page % 64));
+        if self.translation_active(self.fetch_mode) {
+            // 仮想ページとの対応は保持していないため、すべて破棄する
+            self.flush_all_cache();
+        } else {
+            // ページ境界を跨ぐ命令は前のページのキャッシュに含まれる
+            self.icache.invalidate(page.wrapping_sub(1));
+            self.icache.invalidate(page);
+        }
+        // 実行中のページが書き換えられた可能性があるため、ページを引き直させる
+        self.current_page_num = 0xffffffff;
+    }
+
+    /// キャッシュできなかった命令を、16 ビットずつアドレス変換と PMP の検査を行って直接フェッチする
+    fn fetch_inst<B: bus::Bus>(&mut self, vaddr: u32, bus: &mut B) -> Result<Instruction, StepResult> {
+        let inst_low = self.fetch16(vaddr, bus)?;
+        let quadrant = Instruction::decode_quadrant(inst_low);
+        let inst_bin = if quadrant == 0b11 {
+            let inst_high = self.fetch16(vaddr.wrapping_add(2), bus)?;
+            ((inst_high as u32) << 16) | inst_low as u32
+        } else {
+            inst_low as u32
+        };
+        Ok(self.gen_inst_from_bin(inst_bin, quadrant).0)
+    }
+
+    #[inline(always)]
+    fn fetch16<B: bus::Bus>(&mut self, vaddr: u32, bus: &mut B) -> Result<u16, StepResult> {
+        let paddr = self.translate(vaddr, AccessType::Execute, bus)?;
+        self.check_pmp(paddr, 2, AccessType::Execute, vaddr)?;
+        // 命令アクセスフォールト (mtval は読めなかった 16 ビットのアドレス)
+        bus.read16(paddr).map_err(|_| self.trap_with_value(1, vaddr))
     }
 
     #[inline(always)]
@@ -270,13 +702,31 @@ impl Cpu {
                 0b1100111 => Instruction::jalr(inst_bin),
                 0b0010011 => match Instruction::decode_funct3(inst_bin) {
                     0b000 => Instruction::addi(inst_bin),
-                    0b001 => Instruction::slli(inst_bin),
+                    0b001 => match Instruction::decode_funct7(inst_bin) {
+                        0b0000000 => Instruction::slli(inst_bin),
+                        0b0110000 => match (inst_bin >> 20) & 0x1f {
+                            0b00000 => Instruction::clz(inst_bin),
+                            0b00001 => Instruction::ctz(inst_bin),
+                            0b00010 => Instruction::cpop(inst_bin),
+                            0b00100 => Instruction::sext_b(inst_bin),
+                            0b00101 => Instruction::sext_h(inst_bin),
+                            _ => Instruction::Illegal,
+                        },
+                        0b0100100 => Instruction::bclri(inst_bin),
+                        0b0110100 => Instruction::binvi(inst_bin),
+                        0b0010100 => Instruction::bseti(inst_bin),
+                        _ => Instruction::Illegal,
+                    },
                     0b010 => Instruction::slti(inst_bin),
                     0b011 => Instruction::sltiu(inst_bin),
                     0b100 => Instruction::xori(inst_bin),
                     0b101 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::srli(inst_bin),
                         0b0100000 => Instruction::srai(inst_bin),
+                        0b0110000 => Instruction::rori(inst_bin),
+                        0b0100100 => Instruction::bexti(inst_bin),
+                        0b0010100 if (inst_bin >> 20) & 0x1f == 0b00111 => Instruction::orc_b(inst_bin),
+                        0b0110100 if (inst_bin >> 20) & 0x1f == 0b11000 => Instruction::rev8(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     0b110 => Instruction::ori(inst_bin),
@@ -316,51 +766,148 @@ impl Cpu {
                     0b001 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::sll(inst_bin),
                         0b0000001 => Instruction::mulh(inst_bin),
+                        0b0110000 => Instruction::rol(inst_bin),
+                        0b0100100 => Instruction::bclr(inst_bin),
+                        0b0110100 => Instruction::binv(inst_bin),
+                        0b0010100 => Instruction::bset(inst_bin),
+                        0b0000101 => Instruction::clmul(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     0b010 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::slt(inst_bin),
                         0b0000001 => Instruction::mulhsu(inst_bin),
+                        0b0010000 => Instruction::sh1add(inst_bin),
+                        0b0000101 => Instruction::clmulr(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     0b011 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::sltu(inst_bin),
                         0b0000001 => Instruction::mulhu(inst_bin),
+                        0b0000101 => Instruction::clmulh(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     0b100 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::xor(inst_bin),
                         0b0000001 => Instruction::div(inst_bin),
+                        0b0010000 => Instruction::sh2add(inst_bin),
+                        0b0100000 => Instruction::xnor(inst_bin),
+                        0b0000101 => Instruction::min(inst_bin),
+                        0b0000100 if (inst_bin >> 20) & 0x1f == 0 => Instruction::zext_h(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     0b101 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::srl(inst_bin),
                         0b0100000 => Instruction::sra(inst_bin),
                         0b0000001 => Instruction::divu(inst_bin),
+                        0b0110000 => Instruction::ror(inst_bin),
+                        0b0100100 => Instruction::bext(inst_bin),
+                        0b0000101 => Instruction::minu(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     0b110 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::or(inst_bin),
                         0b0000001 => Instruction::rem(inst_bin),
+                        0b0010000 => Instruction::sh3add(inst_bin),
+                        0b0100000 => Instruction::orn(inst_bin),
+                        0b0000101 => Instruction::max(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     0b111 => match Instruction::decode_funct7(inst_bin) {
                         0b0000000 => Instruction::and(inst_bin),
                         0b0000001 => Instruction::remu(inst_bin),
+                        0b0100000 => Instruction::andn(inst_bin),
+                        0b0000101 => Instruction::maxu(inst_bin),
                         _ => Instruction::Illegal,
                     },
                     _ => Instruction::Illegal,
                 }
+                0b0101111 => match Instruction::decode_funct3(inst_bin) {
+                    // aq/rl ビットは単一ハートでは意味を持たないため無視する
+                    0b010 => match Instruction::decode_funct5(inst_bin) {
+                        0b00010 => Instruction::lr_w(inst_bin),
+                        0b00011 => Instruction::sc_w(inst_bin),
+                        0b00001 => Instruction::amoswap_w(inst_bin),
+                        0b00000 => Instruction::amoadd_w(inst_bin),
+                        0b00100 => Instruction::amoxor_w(inst_bin),
+                        0b01100 => Instruction::amoand_w(inst_bin),
+                        0b01000 => Instruction::amoor_w(inst_bin),
+                        0b10000 => Instruction::amomin_w(inst_bin),
+                        0b10100 => Instruction::amomax_w(inst_bin),
+                        0b11000 => Instruction::amominu_w(inst_bin),
+                        0b11100 => Instruction::amomaxu_w(inst_bin),
+                        _ => Instruction::Illegal,
+                    },
+                    _ => Instruction::Illegal,
+                },
+                0b0000111 => match Instruction::decode_funct3(inst_bin) {
+                    0b010 => Instruction::flw(inst_bin),
+                    _ => Instruction::Illegal,
+                },
+                0b0100111 => match Instruction::decode_funct3(inst_bin) {
+                    0b010 => Instruction::fsw(inst_bin),
+                    _ => Instruction::Illegal,
+                },
+                // R4-type: fmt (ビット [26:25]) が 00 (単精度) のもののみサポート
+                0b1000011 if (inst_bin >> 25) & 0b11 == 0 => Instruction::fmadd_s(inst_bin),
+                0b1000111 if (inst_bin >> 25) & 0b11 == 0 => Instruction::fmsub_s(inst_bin),
+                0b1001011 if (inst_bin >> 25) & 0b11 == 0 => Instruction::fnmsub_s(inst_bin),
+                0b1001111 if (inst_bin >> 25) & 0b11 == 0 => Instruction::fnmadd_s(inst_bin),
+                0b1010011 => {
+                    let rs2 = (inst_bin >> 20) & 0x1f;
+                    match Instruction::decode_funct7(inst_bin) {
+                        0b0000000 => Instruction::fadd_s(inst_bin),
+                        0b0000100 => Instruction::fsub_s(inst_bin),
+                        0b0001000 => Instruction::fmul_s(inst_bin),
+                        0b0001100 => Instruction::fdiv_s(inst_bin),
+                        0b0101100 if rs2 == 0 => Instruction::fsqrt_s(inst_bin),
+                        0b0010000 => match Instruction::decode_funct3(inst_bin) {
+                            0b000 => Instruction::fsgnj_s(inst_bin),
+                            0b001 => Instruction::fsgnjn_s(inst_bin),
+                            0b010 => Instruction::fsgnjx_s(inst_bin),
+                            _ => Instruction::Illegal,
+                        },
+                        0b0010100 => match Instruction::decode_funct3(inst_bin) {
+                            0b000 => Instruction::fmin_s(inst_bin),
+                            0b001 => Instruction::fmax_s(inst_bin),
+                            _ => Instruction::Illegal,
+                        },
+                        0b1100000 => match rs2 {
+                            0b00000 => Instruction::fcvt_w_s(inst_bin),
+                            0b00001 => Instruction::fcvt_wu_s(inst_bin),
+                            _ => Instruction::Illegal,
+                        },
+                        0b1110000 if rs2 == 0 => match Instruction::decode_funct3(inst_bin) {
+                            0b000 => Instruction::fmv_x_w(inst_bin),
+                            0b001 => Instruction::fclass_s(inst_bin),
+                            _ => Instruction::Illegal,
+                        },
+                        0b1010000 => match Instruction::decode_funct3(inst_bin) {
+                            0b010 => Instruction::feq_s(inst_bin),
+                            0b001 => Instruction::flt_s(inst_bin),
+                            0b000 => Instruction::fle_s(inst_bin),
+                            _ => Instruction::Illegal,
+                        },
+                        0b1101000 => match rs2 {
+                            0b00000 => Instruction::fcvt_s_w(inst_bin),
+                            0b00001 => Instruction::fcvt_s_wu(inst_bin),
+                            _ => Instruction::Illegal,
+                        },
+                        0b1111000 if rs2 == 0 && Instruction::decode_funct3(inst_bin) == 0 => Instruction::fmv_w_x(inst_bin),
+                        _ => Instruction::Illegal,
+                    }
+                }
                 0b0001111 => match Instruction::decode_funct3(inst_bin) {
                     0b000 => Instruction::fence(),
                     0b001 => Instruction::fence_i(),
                     _ => Instruction::Illegal,
                 },
                 0b1110011 => match Instruction::decode_funct3(inst_bin) {
+                    0b000 if Instruction::decode_funct7(inst_bin) == 0b0001001 => Instruction::sfence_vma(inst_bin),
                     0b000 => match (inst_bin >> 20) & 0xfff {
                         0b000000000000 => Instruction::ecall(),
                         0b000000000001 => Instruction::ebreak(),
                         0b001100000010 => Instruction::mret(),
+                        0b000100000010 => Instruction::sret(),
                         0b000100000101 => Instruction::wfi(),
                         _ => Instruction::Illegal,
                     },
@@ -377,7 +924,9 @@ impl Cpu {
             0b00 => match Instruction::decode_c_funct3(inst_bin as u16) {
                 0b000 => Instruction::c_addi4spn(inst_bin as u16),
                 0b010 => Instruction::c_lw(inst_bin as u16),
+                0b011 => Instruction::c_flw(inst_bin as u16),
                 0b110 => Instruction::c_sw(inst_bin as u16),
+                0b111 => Instruction::c_fsw(inst_bin as u16),
                 _ => Instruction::Illegal,
             },
             0b01 => match Instruction::decode_c_funct3(inst_bin as u16) {
@@ -416,6 +965,7 @@ impl Cpu {
             0b10 => match Instruction::decode_c_funct3(inst_bin as u16) {
                 0b000 => Instruction::c_slli(inst_bin as u16),
                 0b010 => Instruction::c_lwsp(inst_bin as u16),
+                0b011 => Instruction::c_flwsp(inst_bin as u16),
                 0b100 => {
                     let rs2 = (inst_bin >> 2) & 0x1f;
                     let rd = (inst_bin >> 7) & 0x1f;
@@ -444,6 +994,7 @@ impl Cpu {
                     }
                 }
                 0b110 => Instruction::c_swsp(inst_bin as u16),
+                0b111 => Instruction::c_fswsp(inst_bin as u16),
                 _ => Instruction::Illegal,
             },
             _ => Instruction::Illegal,
@@ -452,12 +1003,12 @@ impl Cpu {
     }
 
     #[inline(always)]
-    fn gen_inst<B: bus::Bus>(&mut self, raw_ptr: u32, bus: &mut B) -> (Instruction, u32) {
-        let inst_low = bus.read16(raw_ptr);
+    fn gen_inst<B: bus::Bus>(&mut self, raw_ptr: u32, bus: &mut B) -> Result<(Instruction, u32), bus::BusError> {
+        let inst_low = bus.read16(raw_ptr)?;
         let quadrant = Instruction::decode_quadrant(inst_low);
         let (inst_bin, inst_size) = if quadrant == 0b11 {
             // 32-bit instruction
-            let inst_high = bus.read16(raw_ptr + 2);
+            let inst_high = bus.read16(raw_ptr + 2)?;
             (((inst_high as u32) << 16) | inst_low as u32, 4)
         } else {
             // 16-bit instruction
@@ -465,7 +1016,7 @@ impl Cpu {
         };
 
         let (inst, _) = self.gen_inst_from_bin(inst_bin, quadrant);
-        (inst, inst_size)
+        Ok((inst, inst_size))
     }
 
     #[inline(always)]
@@ -533,20 +1084,96 @@ impl Cpu {
             Instruction::Rem    { rd, rs1, rs2 } => self.rem    (rd, rs1, rs2),
             Instruction::Remu   { rd, rs1, rs2 } => self.remu   (rd, rs1, rs2),
 
+            Instruction::Sh1add { rd, rs1, rs2 }   => self.sh1add  (rd, rs1, rs2),
+            Instruction::Sh2add { rd, rs1, rs2 }   => self.sh2add  (rd, rs1, rs2),
+            Instruction::Sh3add { rd, rs1, rs2 }   => self.sh3add  (rd, rs1, rs2),
+            Instruction::Andn   { rd, rs1, rs2 }   => self.andn    (rd, rs1, rs2),
+            Instruction::Orn    { rd, rs1, rs2 }   => self.orn     (rd, rs1, rs2),
+            Instruction::Xnor   { rd, rs1, rs2 }   => self.xnor    (rd, rs1, rs2),
+            Instruction::Max    { rd, rs1, rs2 }   => self.max     (rd, rs1, rs2),
+            Instruction::Maxu   { rd, rs1, rs2 }   => self.maxu    (rd, rs1, rs2),
+            Instruction::Min    { rd, rs1, rs2 }   => self.min     (rd, rs1, rs2),
+            Instruction::Minu   { rd, rs1, rs2 }   => self.minu    (rd, rs1, rs2),
+            Instruction::Rol    { rd, rs1, rs2 }   => self.rol     (rd, rs1, rs2),
+            Instruction::Ror    { rd, rs1, rs2 }   => self.ror     (rd, rs1, rs2),
+            Instruction::Bclr   { rd, rs1, rs2 }   => self.bclr    (rd, rs1, rs2),
+            Instruction::Bext   { rd, rs1, rs2 }   => self.bext    (rd, rs1, rs2),
+            Instruction::Binv   { rd, rs1, rs2 }   => self.binv    (rd, rs1, rs2),
+            Instruction::Bset   { rd, rs1, rs2 }   => self.bset    (rd, rs1, rs2),
+            Instruction::Clmul  { rd, rs1, rs2 }   => self.clmul   (rd, rs1, rs2),
+            Instruction::Clmulh { rd, rs1, rs2 }   => self.clmulh  (rd, rs1, rs2),
+            Instruction::Clmulr { rd, rs1, rs2 }   => self.clmulr  (rd, rs1, rs2),
+
+            Instruction::Clz    { rd, rs1 }        => self.clz     (rd, rs1),
+            Instruction::Ctz    { rd, rs1 }        => self.ctz     (rd, rs1),
+            Instruction::Cpop   { rd, rs1 }        => self.cpop    (rd, rs1),
+            Instruction::SextB  { rd, rs1 }        => self.sext_b  (rd, rs1),
+            Instruction::SextH  { rd, rs1 }        => self.sext_h  (rd, rs1),
+            Instruction::ZextH  { rd, rs1 }        => self.zext_h  (rd, rs1),
+            Instruction::OrcB   { rd, rs1 }        => self.orc_b   (rd, rs1),
+            Instruction::Rev8   { rd, rs1 }        => self.rev8    (rd, rs1),
+
+            Instruction::Rori   { rd, rs1, shamt } => self.rori    (rd, rs1, shamt),
+            Instruction::Bclri  { rd, rs1, shamt } => self.bclri   (rd, rs1, shamt),
+            Instruction::Bexti  { rd, rs1, shamt } => self.bexti   (rd, rs1, shamt),
+            Instruction::Binvi  { rd, rs1, shamt } => self.binvi   (rd, rs1, shamt),
+            Instruction::Bseti  { rd, rs1, shamt } => self.bseti   (rd, rs1, shamt),
+
+            Instruction::LrW      { rd, rs1 }      => self.lr_w      (rd, rs1, bus),
+            Instruction::ScW      { rd, rs1, rs2 } => self.sc_w      (rd, rs1, rs2, bus),
+            Instruction::AmoswapW { rd, rs1, rs2 } => self.amoswap_w (rd, rs1, rs2, bus),
+            Instruction::AmoaddW  { rd, rs1, rs2 } => self.amoadd_w  (rd, rs1, rs2, bus),
+            Instruction::AmoxorW  { rd, rs1, rs2 } => self.amoxor_w  (rd, rs1, rs2, bus),
+            Instruction::AmoandW  { rd, rs1, rs2 } => self.amoand_w  (rd, rs1, rs2, bus),
+            Instruction::AmoorW   { rd, rs1, rs2 } => self.amoor_w   (rd, rs1, rs2, bus),
+            Instruction::AmominW  { rd, rs1, rs2 } => self.amomin_w  (rd, rs1, rs2, bus),
+            Instruction::AmomaxW  { rd, rs1, rs2 } => self.amomax_w  (rd, rs1, rs2, bus),
+            Instruction::AmominuW { rd, rs1, rs2 } => self.amominu_w (rd, rs1, rs2, bus),
+            Instruction::AmomaxuW { rd, rs1, rs2 } => self.amomaxu_w (rd, rs1, rs2, bus),
+
+            Instruction::Flw     { rd, rs1, imm }           => self.flw      (rd, rs1, imm, bus),
+            Instruction::Fsw     { rs1, rs2, imm }          => self.fsw      (rs1, rs2, imm, bus),
+            Instruction::FmaddS  { rd, rs1, rs2, rs3, rm }  => self.fmadd_s  (rd, rs1, rs2, rs3, rm),
+            Instruction::FmsubS  { rd, rs1, rs2, rs3, rm }  => self.fmsub_s  (rd, rs1, rs2, rs3, rm),
+            Instruction::FnmsubS { rd, rs1, rs2, rs3, rm }  => self.fnmsub_s (rd, rs1, rs2, rs3, rm),
+            Instruction::FnmaddS { rd, rs1, rs2, rs3, rm }  => self.fnmadd_s (rd, rs1, rs2, rs3, rm),
+            Instruction::FaddS   { rd, rs1, rs2, rm }       => self.fadd_s   (rd, rs1, rs2, rm),
+            Instruction::FsubS   { rd, rs1, rs2, rm }       => self.fsub_s   (rd, rs1, rs2, rm),
+            Instruction::FmulS   { rd, rs1, rs2, rm }       => self.fmul_s   (rd, rs1, rs2, rm),
+            Instruction::FdivS   { rd, rs1, rs2, rm }       => self.fdiv_s   (rd, rs1, rs2, rm),
+            Instruction::FsqrtS  { rd, rs1, rm }            => self.fsqrt_s  (rd, rs1, rm),
+            Instruction::FsgnjS  { rd, rs1, rs2 }           => self.fsgnj_s  (rd, rs1, rs2),
+            Instruction::FsgnjnS { rd, rs1, rs2 }           => self.fsgnjn_s (rd, rs1, rs2),
+            Instruction::FsgnjxS { rd, rs1, rs2 }           => self.fsgnjx_s (rd, rs1, rs2),
+            Instruction::FminS   { rd, rs1, rs2 }           => self.fmin_s   (rd, rs1, rs2),
+            Instruction::FmaxS   { rd, rs1, rs2 }           => self.fmax_s   (rd, rs1, rs2),
+            Instruction::FcvtWS  { rd, rs1, rm }            => self.fcvt_w_s (rd, rs1, rm),
+            Instruction::FcvtWuS { rd, rs1, rm }            => self.fcvt_wu_s(rd, rs1, rm),
+            Instruction::FmvXW   { rd, rs1 }                => self.fmv_x_w  (rd, rs1),
+            Instruction::FeqS    { rd, rs1, rs2 }           => self.feq_s    (rd, rs1, rs2),
+            Instruction::FltS    { rd, rs1, rs2 }           => self.flt_s    (rd, rs1, rs2),
+            Instruction::FleS    { rd, rs1, rs2 }           => self.fle_s    (rd, rs1, rs2),
+            Instruction::FclassS { rd, rs1 }                => self.fclass_s (rd, rs1),
+            Instruction::FcvtSW  { rd, rs1, rm }            => self.fcvt_s_w (rd, rs1, rm),
+            Instruction::FcvtSWu { rd, rs1, rm }            => self.fcvt_s_wu(rd, rs1, rm),
+            Instruction::FmvWX   { rd, rs1 }                => self.fmv_w_x  (rd, rs1),
+
             Instruction::Fence  => self.fence(),
             Instruction::FenceI => self.fence_i(),
+            Instruction::SfenceVma { rs1, rs2 } => self.sfence_vma(rs1, rs2),
 
             Instruction::Ecall  => self.ecall(),
             Instruction::Ebreak => self.ebreak(),
             Instruction::Mret   => self.mret(),
-            Instruction::Wfi    => StepResult::Ok(4),
+            Instruction::Sret   => self.sret(),
+            Instruction::Wfi    => self.wfi(),
 
-            Instruction::Csrrw  { csr, rd, rs1 }  => self.csrrw  (csr, rd, rs1),
-            Instruction::Csrrs  { csr, rd, rs1 }  => self.csrrs  (csr, rd, rs1),
-            Instruction::Csrrc  { csr, rd, rs1 }  => self.csrrc  (csr, rd, rs1),
-            Instruction::Csrrwi { csr, rd, uimm } => self.csrrwi (csr, rd, uimm),
-            Instruction::Csrrsi { csr, rd, uimm } => self.csrrsi (csr, rd, uimm),
-            Instruction::Csrrci { csr, rd, uimm } => self.csrrci (csr, rd, uimm),
+            Instruction::Csrrw  { csr, rd, rs1 }  => self.csrrw  (csr, rd, rs1, bus),
+            Instruction::Csrrs  { csr, rd, rs1 }  => self.csrrs  (csr, rd, rs1, bus),
+            Instruction::Csrrc  { csr, rd, rs1 }  => self.csrrc  (csr, rd, rs1, bus),
+            Instruction::Csrrwi { csr, rd, uimm } => self.csrrwi (csr, rd, uimm, bus),
+            Instruction::Csrrsi { csr, rd, uimm } => self.csrrsi (csr, rd, uimm, bus),
+            Instruction::Csrrci { csr, rd, uimm } => self.csrrci (csr, rd, uimm, bus),
 
             // C-Extension
             Instruction::CAddi4spn { rd, rs1, imm } => self.c_addi4spn(rd, rs1, imm),
@@ -574,6 +1201,10 @@ impl Cpu {
             Instruction::CJalr     { rs1 } => self.c_jalr(rs1),
             Instruction::CAdd      { rd, rs2 } => self.c_add(rd, rs2),
             Instruction::CSwsp     { rs2, imm } => self.c_swsp(rs2, imm, bus),
+            Instruction::CFlw      { rd, rs1, imm } => self.c_flw(rd, rs1, imm, bus),
+            Instruction::CFsw      { rs1, rs2, imm } => self.c_fsw(rs1, rs2, imm, bus),
+            Instruction::CFlwsp    { rd, imm } => self.c_flwsp(rd, imm, bus),
+            Instruction::CFswsp    { rs2, imm } => self.c_fswsp(rs2, imm, bus),
 
             _ => StepResult::Trap(2),
         }
@@ -581,10 +1212,7 @@ impl Cpu {
 
     /// PC を指す命令がキャッシュされているページを無効化する
     pub fn flush_cache_line(&mut self, addr: u32) {
-        let page_num = (addr / PAGE_SIZE as u32) as usize;
-        if page_num < self.pages.len() {
-            self.pages[page_num] = None;
-        }
+        self.icache.invalidate(addr / PAGE_SIZE as u32);
         // flush_cache_line が呼ばれたら、常に current_page_num をリセットし
         // fetch 時にページを再評価させる
         self.current_page_num = 0xffffffff;
@@ -592,6 +1220,18 @@ impl Cpu {
 
     /// 全てのページキャッシュを無効化する
     pub fn flush_all_cache(&mut self) {
-        self.pages.iter_mut().for_each(|p| *p = None);
+        self.icache.clear();
+        self.current_page_num = 0xffffffff;
+    }
+
+    /// 命令キャッシュの統計情報
+    pub fn icache_stats(&self) -> ICacheStats {
+        self.icache.stats()
+    }
+
+    /// 命令キャッシュに保持するページ数を変更する。キャッシュされているページはすべて破棄する
+    pub fn set_icache_capacity(&mut self, pages: usize) {
+        self.icache.set_capacity(pages);
+        self.current_page_num = 0xffffffff;
     }
 }
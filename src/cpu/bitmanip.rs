@@ -0,0 +1,2 @@
+mod exec;
+mod tests;
\ No newline at end of file
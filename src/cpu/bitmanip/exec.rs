@@ -0,0 +1,302 @@
+use crate::cpu::Cpu;
+use crate::cpu::StepResult;
+
+/// 64 ビットのキャリーレス積を計算する (Zbc 共通)
+#[inline(always)]
+fn clmul64(a: u32, b: u32) -> u64 {
+    let mut result: u64 = 0;
+    for i in 0..32 {
+        if (b >> i) & 1 == 1 {
+            result ^= (a as u64) << i;
+        }
+    }
+    result
+}
+
+impl Cpu {
+    // Zba
+    #[inline(always)]
+    pub(crate) fn sh1add(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs2].wrapping_add(self.regs[rs1] << 1);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn sh2add(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs2].wrapping_add(self.regs[rs1] << 2);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn sh3add(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs2].wrapping_add(self.regs[rs1] << 3);
+        StepResult::Ok(4)
+    }
+
+    // Zbb
+    #[inline(always)]
+    pub(crate) fn andn(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1] & !self.regs[rs2];
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn orn(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1] | !self.regs[rs2];
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn xnor(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = !(self.regs[rs1] ^ self.regs[rs2]);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn max(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = (self.regs[rs1] as i32).max(self.regs[rs2] as i32) as u32;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn maxu(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1].max(self.regs[rs2]);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn min(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = (self.regs[rs1] as i32).min(self.regs[rs2] as i32) as u32;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn minu(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1].min(self.regs[rs2]);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn rol(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1].rotate_left(self.regs[rs2] & 0x1f);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn ror(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1].rotate_right(self.regs[rs2] & 0x1f);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn clz(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        self.regs[rd] = self.regs[rs1].leading_zeros();
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn ctz(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        self.regs[rd] = self.regs[rs1].trailing_zeros();
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn cpop(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        self.regs[rd] = self.regs[rs1].count_ones();
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn sext_b(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        self.regs[rd] = self.regs[rs1] as i8 as i32 as u32;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn sext_h(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        self.regs[rd] = self.regs[rs1] as i16 as i32 as u32;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn zext_h(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        self.regs[rd] = self.regs[rs1] & 0xffff;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn orc_b(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        // 各バイトが 0 でなければ 0xff、0 なら 0x00 にする
+        let bytes = self.regs[rs1].to_le_bytes().map(|b| if b != 0 { 0xff } else { 0x00 });
+        self.regs[rd] = u32::from_le_bytes(bytes);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn rev8(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        self.regs[rd] = self.regs[rs1].swap_bytes();
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn rori(&mut self, rd: u8, rs1: u8, shamt: u8) -> StepResult {
+        let rd:    usize = rd    as usize;
+        let rs1:   usize = rs1   as usize;
+        let shamt: u32   = shamt as u32;
+        self.regs[rd] = self.regs[rs1].rotate_right(shamt);
+        StepResult::Ok(4)
+    }
+
+    // Zbs
+    #[inline(always)]
+    pub(crate) fn bclr(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1] & !(1 << (self.regs[rs2] & 0x1f));
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn bext(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = (self.regs[rs1] >> (self.regs[rs2] & 0x1f)) & 1;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn binv(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1] ^ (1 << (self.regs[rs2] & 0x1f));
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn bset(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = self.regs[rs1] | (1 << (self.regs[rs2] & 0x1f));
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn bclri(&mut self, rd: u8, rs1: u8, shamt: u8) -> StepResult {
+        let rd:    usize = rd    as usize;
+        let rs1:   usize = rs1   as usize;
+        let shamt: u32   = shamt as u32;
+        self.regs[rd] = self.regs[rs1] & !(1 << shamt);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn bexti(&mut self, rd: u8, rs1: u8, shamt: u8) -> StepResult {
+        let rd:    usize = rd    as usize;
+        let rs1:   usize = rs1   as usize;
+        let shamt: u32   = shamt as u32;
+        self.regs[rd] = (self.regs[rs1] >> shamt) & 1;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn binvi(&mut self, rd: u8, rs1: u8, shamt: u8) -> StepResult {
+        let rd:    usize = rd    as usize;
+        let rs1:   usize = rs1   as usize;
+        let shamt: u32   = shamt as u32;
+        self.regs[rd] = self.regs[rs1] ^ (1 << shamt);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn bseti(&mut self, rd: u8, rs1: u8, shamt: u8) -> StepResult {
+        let rd:    usize = rd    as usize;
+        let rs1:   usize = rs1   as usize;
+        let shamt: u32   = shamt as u32;
+        self.regs[rd] = self.regs[rs1] | (1 << shamt);
+        StepResult::Ok(4)
+    }
+
+    // Zbc
+    #[inline(always)]
+    pub(crate) fn clmul(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = clmul64(self.regs[rs1], self.regs[rs2]) as u32;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn clmulh(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = (clmul64(self.regs[rs1], self.regs[rs2]) >> 32) as u32;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn clmulr(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        self.regs[rd] = (clmul64(self.regs[rs1], self.regs[rs2]) >> 31) as u32;
+        StepResult::Ok(4)
+    }
+}
@@ -0,0 +1,4 @@
+mod zba;
+mod zbb;
+mod zbs;
+mod zbc;
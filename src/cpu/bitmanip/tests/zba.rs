@@ -0,0 +1,81 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_sh1add() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00000100;
+    cpu.regs[2] = 0x00001000;
+
+    // sh1add x3, x1, x2 (0x2020a1b3)
+    // opcode: 0110011, rd: 3, funct3: 010, rs1: 1, rs2: 2, funct7: 0010000
+    let inst = 0x2020a1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // x2 + (x1 << 1)
+    assert_eq!(cpu.regs[3], 0x00001200);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_sh2add() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00000100;
+    cpu.regs[2] = 0x00001000;
+
+    // sh2add x3, x1, x2 (0x2020c1b3)
+    // opcode: 0110011, rd: 3, funct3: 100, rs1: 1, rs2: 2, funct7: 0010000
+    let inst = 0x2020c1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // x2 + (x1 << 2)
+    assert_eq!(cpu.regs[3], 0x00001400);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_sh3add() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00000100;
+    cpu.regs[2] = 0x00001000;
+
+    // sh3add x3, x1, x2 (0x2020e1b3)
+    // opcode: 0110011, rd: 3, funct3: 110, rs1: 1, rs2: 2, funct7: 0010000
+    let inst = 0x2020e1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // x2 + (x1 << 3)
+    assert_eq!(cpu.regs[3], 0x00001800);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_sh1add_overflow() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000001;
+    cpu.regs[2] = 0x00000010;
+
+    // sh1add x3, x1, x2 (0x2020a1b3)
+    // opcode: 0110011, rd: 3, funct3: 010, rs1: 1, rs2: 2, funct7: 0010000
+    let inst = 0x2020a1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // 上位ビットは捨てられる
+    assert_eq!(cpu.regs[3], 0x00000012);
+    assert_eq!(cpu.pc, 0x4);
+}
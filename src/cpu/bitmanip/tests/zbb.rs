@@ -0,0 +1,360 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_andn() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xff00ff00;
+    cpu.regs[2] = 0x0ff00ff0;
+
+    // andn x3, x1, x2 (0x4020f1b3)
+    // opcode: 0110011, rd: 3, funct3: 111, rs1: 1, rs2: 2, funct7: 0100000
+    let inst = 0x4020f1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0xf000f000);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_orn() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xff00ff00;
+    cpu.regs[2] = 0x0ff00ff0;
+
+    // orn x3, x1, x2 (0x4020e1b3)
+    // opcode: 0110011, rd: 3, funct3: 110, rs1: 1, rs2: 2, funct7: 0100000
+    let inst = 0x4020e1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0xff0fff0f);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_xnor() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xff00ff00;
+    cpu.regs[2] = 0x0ff00ff0;
+
+    // xnor x3, x1, x2 (0x4020c1b3)
+    // opcode: 0110011, rd: 3, funct3: 100, rs1: 1, rs2: 2, funct7: 0100000
+    let inst = 0x4020c1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x0f0f0f0f);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_max() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xfffffffe;
+    cpu.regs[2] = 0x00000005;
+
+    // max x3, x1, x2 (0x0a20e1b3)
+    // opcode: 0110011, rd: 3, funct3: 110, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a20e1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // -2 と 5 の符号付き比較
+    assert_eq!(cpu.regs[3], 0x00000005);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_maxu() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xfffffffe;
+    cpu.regs[2] = 0x00000005;
+
+    // maxu x3, x1, x2 (0x0a20f1b3)
+    // opcode: 0110011, rd: 3, funct3: 111, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a20f1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0xfffffffe);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_min() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xfffffffe;
+    cpu.regs[2] = 0x00000005;
+
+    // min x3, x1, x2 (0x0a20c1b3)
+    // opcode: 0110011, rd: 3, funct3: 100, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a20c1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // -2 と 5 の符号付き比較
+    assert_eq!(cpu.regs[3], 0xfffffffe);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_minu() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xfffffffe;
+    cpu.regs[2] = 0x00000005;
+
+    // minu x3, x1, x2 (0x0a20d1b3)
+    // opcode: 0110011, rd: 3, funct3: 101, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a20d1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00000005);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_rol() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000001;
+    cpu.regs[2] = 0x00000024;
+
+    // rol x3, x1, x2 (0x602091b3)
+    // opcode: 0110011, rd: 3, funct3: 001, rs1: 1, rs2: 2, funct7: 0110000
+    let inst = 0x602091b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // シフト量は下位 5 ビット (4) のみ使用
+    assert_eq!(cpu.regs[3], 0x00000018);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_ror() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000001;
+    cpu.regs[2] = 0x00000024;
+
+    // ror x3, x1, x2 (0x6020d1b3)
+    // opcode: 0110011, rd: 3, funct3: 101, rs1: 1, rs2: 2, funct7: 0110000
+    let inst = 0x6020d1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // シフト量は下位 5 ビット (4) のみ使用
+    assert_eq!(cpu.regs[3], 0x18000000);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_rori() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x12345678;
+
+    // rori x3, x1, 8 (0x6080d193)
+    // opcode: 0010011, rd: 3, funct3: 101, rs1: 1, imm: 011000001000
+    let inst = 0x6080d193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x78123456);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_clz() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00010000;
+
+    // clz x3, x1 (0x60009193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011000000000
+    let inst = 0x60009193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x0000000f);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_ctz() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00010000;
+
+    // ctz x3, x1 (0x60109193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011000000001
+    let inst = 0x60109193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00000010);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_cpop() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xf0f0f0f1;
+
+    // cpop x3, x1 (0x60209193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011000000010
+    let inst = 0x60209193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00000011);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_clz_zero() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00000000;
+
+    // clz x3, x1 (0x60009193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011000000000
+    let inst = 0x60009193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // 0 の場合は 32
+    assert_eq!(cpu.regs[3], 0x00000020);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_ctz_zero() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00000000;
+
+    // ctz x3, x1 (0x60109193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011000000001
+    let inst = 0x60109193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // 0 の場合は 32
+    assert_eq!(cpu.regs[3], 0x00000020);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_sext_b() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x12345680;
+
+    // sext.b x3, x1 (0x60409193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011000000100
+    let inst = 0x60409193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0xffffff80);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_sext_h() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x12348000;
+
+    // sext.h x3, x1 (0x60509193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011000000101
+    let inst = 0x60509193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0xffff8000);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_zext_h() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1234ffff;
+
+    // zext.h x3, x1 (0x0800c1b3)
+    // opcode: 0110011, rd: 3, funct3: 100, rs1: 1, rs2: 0, funct7: 0000100
+    let inst = 0x0800c1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x0000ffff);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_orc_b() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00120300;
+
+    // orc.b x3, x1 (0x2870d193)
+    // opcode: 0010011, rd: 3, funct3: 101, rs1: 1, imm: 001010000111
+    let inst = 0x2870d193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00ffff00);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_rev8() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x12345678;
+
+    // rev8 x3, x1 (0x6980d193)
+    // opcode: 0010011, rd: 3, funct3: 101, rs1: 1, imm: 011010011000
+    let inst = 0x6980d193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x78563412);
+    assert_eq!(cpu.pc, 0x4);
+}
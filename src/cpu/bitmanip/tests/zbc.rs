@@ -0,0 +1,80 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_clmul() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000003;
+    cpu.regs[2] = 0x00000005;
+
+    // clmul x3, x1, x2 (0x0a2091b3)
+    // opcode: 0110011, rd: 3, funct3: 001, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a2091b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // キャリーレス積 = 0x000000028000000f の下位 32 ビット
+    assert_eq!(cpu.regs[3], 0x8000000f);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_clmulh() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000003;
+    cpu.regs[2] = 0x00000005;
+
+    // clmulh x3, x1, x2 (0x0a20b1b3)
+    // opcode: 0110011, rd: 3, funct3: 011, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a20b1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // キャリーレス積の上位 32 ビット
+    assert_eq!(cpu.regs[3], 0x00000002);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_clmulr() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000003;
+    cpu.regs[2] = 0x00000005;
+
+    // clmulr x3, x1, x2 (0x0a20a1b3)
+    // opcode: 0110011, rd: 3, funct3: 010, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a20a1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // キャリーレス積のビット [62:31]
+    assert_eq!(cpu.regs[3], 0x00000005);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_clmul_all_ones() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xffffffff;
+    cpu.regs[2] = 0xffffffff;
+
+    // clmul x3, x1, x2 (0x0a2091b3)
+    // opcode: 0110011, rd: 3, funct3: 001, rs1: 1, rs2: 2, funct7: 0000101
+    let inst = 0x0a2091b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x55555555);
+    assert_eq!(cpu.pc, 0x4);
+}
@@ -0,0 +1,146 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_bclr() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xffff0000;
+    cpu.regs[2] = 0x00000031;
+
+    // bclr x3, x1, x2 (0x482091b3)
+    // opcode: 0110011, rd: 3, funct3: 001, rs1: 1, rs2: 2, funct7: 0100100
+    let inst = 0x482091b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    // ビット位置は下位 5 ビット (17) のみ使用
+    assert_eq!(cpu.regs[3], 0xfffd0000);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_bext() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xffff0000;
+    cpu.regs[2] = 0x00000031;
+
+    // bext x3, x1, x2 (0x4820d1b3)
+    // opcode: 0110011, rd: 3, funct3: 101, rs1: 1, rs2: 2, funct7: 0100100
+    let inst = 0x4820d1b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00000001);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_binv() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xffff0000;
+    cpu.regs[2] = 0x00000031;
+
+    // binv x3, x1, x2 (0x682091b3)
+    // opcode: 0110011, rd: 3, funct3: 001, rs1: 1, rs2: 2, funct7: 0110100
+    let inst = 0x682091b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0xfffd0000);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_bset() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00000000;
+    cpu.regs[2] = 0x00000031;
+
+    // bset x3, x1, x2 (0x282091b3)
+    // opcode: 0110011, rd: 3, funct3: 001, rs1: 1, rs2: 2, funct7: 0010100
+    let inst = 0x282091b3;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00020000);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_bclri() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0xffffffff;
+
+    // bclri x3, x1, 31 (0x49f09193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 010010011111
+    let inst = 0x49f09193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x7fffffff);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_bexti() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000000;
+
+    // bexti x3, x1, 31 (0x49f0d193)
+    // opcode: 0010011, rd: 3, funct3: 101, rs1: 1, imm: 010010011111
+    let inst = 0x49f0d193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00000001);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_binvi() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x80000001;
+
+    // binvi x3, x1, 31 (0x69f09193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 011010011111
+    let inst = 0x69f09193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x00000001);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_bseti() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x00000000;
+
+    // bseti x3, x1, 31 (0x29f09193)
+    // opcode: 0010011, rd: 3, funct3: 001, rs1: 1, imm: 001010011111
+    let inst = 0x29f09193;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x80000000);
+    assert_eq!(cpu.pc, 0x4);
+}
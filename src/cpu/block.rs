@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[cfg(test)]
+mod tests;
+
+/// 連結先のブロックがないことを表す番号
+pub(crate) const NO_BLOCK: u32 = u32::MAX;
+
+/// 1 つのブロックから連結しておく後続ブロックの数
+const LINK_COUNT: usize = 2;
+
+/// 基本ブロック。
+/// ジャンプ先 (またはページの先頭) から始まり、次に分岐が成立するかページを抜けるまでの命令列で、
+/// 命令自体は命令キャッシュのページを参照する
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Block {
+    /// 先頭の仮想アドレス
+    pub(crate) start_pc: u32,
+    /// ブロックを作ったときの特権モード (フェッチの権限チェックはこのモードで行っている)
+    pub(crate) mode: PrivilegeMode,
+    /// 命令キャッシュのスロット
+    pub(crate) slot: usize,
+    /// 先頭のページの物理アドレス
+    pub(crate) page_paddr: u32,
+    /// 後続ブロックへの連結 (飛び先の pc, ブロック番号)
+    links: [(u32, u32); LINK_COUNT],
+}
+
+impl Block {
+    pub(crate) fn new(start_pc: u32, mode: PrivilegeMode, slot: usize, page_paddr: u32) -> Self {
+        Self {
+            start_pc,
+            mode,
+            slot,
+            page_paddr,
+            links: [(0, NO_BLOCK); LINK_COUNT],
+        }
+    }
+}
+
+/// 先頭の pc をキーにした基本ブロックのキャッシュ。
+/// ブロックの終わりで次のブロックを引いたら、それを元のブロックに連結しておき、次回からは連結をたどる
+pub(crate) struct BlockCache {
+    /// 先頭の pc -> `blocks` のインデックス
+    index: HashMap<u32, u32>,
+    blocks: Vec<Block>,
+    /// 実行中のブロック
+    current: u32,
+    /// ブロックを作ったときの命令キャッシュの世代
+    generation: u64,
+}
+
+impl BlockCache {
+    pub(crate) fn new() -> Self {
+        Self {
+            index: HashMap::new(),
+            blocks: Vec::new(),
+            current: NO_BLOCK,
+            generation: 0,
+        }
+    }
+
+    /// 命令キャッシュの世代が変わっていれば (ページが破棄・追い出されていれば)、すべてのブロックを破棄する
+    #[inline(always)]
+    pub(crate) fn sync(&mut self, generation: u64) {
+        if self.generation != generation {
+            self.clear();
+            self.generation = generation;
+        }
+    }
+
+    /// すべてのブロックを破棄する
+    pub(crate) fn clear(&mut self) {
+        self.index.clear();
+        self.blocks.clear();
+        self.current = NO_BLOCK;
+    }
+
+    /// ブロック `idx` を取得する
+    #[inline(always)]
+    pub(crate) fn block(&self, idx: u32) -> &Block {
+        &self.blocks[idx as usize]
+    }
+
+    /// 実行中のブロックを `idx` にする。`NO_BLOCK` の場合は連結を記録しない
+    #[inline(always)]
+    pub(crate) fn enter(&mut self, idx: u32) {
+        self.current = idx;
+    }
+
+    /// 実行中のブロックから連結した、`pc` から始まるブロックを引く
+    #[inline(always)]
+    pub(crate) fn linked(&self, pc: u32, mode: PrivilegeMode) -> Option<u32> {
+        let current = self.blocks.get(self.current as usize)?;
+        current.links
+            .iter()
+            .find(|&&(target, idx)| target == pc && idx != NO_BLOCK)
+            .map(|&(_, idx)| idx)
+            .filter(|&idx| self.block(idx).mode == mode)
+    }
+
+    /// `pc` から始まるブロックを引く
+    pub(crate) fn lookup(&self, pc: u32, mode: PrivilegeMode) -> Option<u32> {
+        self.index
+            .get(&pc)
+            .copied()
+            .filter(|&idx| self.block(idx).mode == mode)
+    }
+
+    /// ブロックを登録し、その番号を返す。同じ pc のブロックがあれば置き換える
+    pub(crate) fn insert(&mut self, block: Block) -> u32 {
+        if let Some(&idx) = self.index.get(&block.start_pc) {
+            self.blocks[idx as usize] = block;
+            return idx;
+        }
+        let idx = self.blocks.len() as u32;
+        self.blocks.push(block);
+        self.index.insert(block.start_pc, idx);
+        idx
+    }
+
+    /// 実行中のブロックから `pc` へ抜けた先をブロック `idx` として連結する。
+    /// 空きがなければ古い方の連結を置き換える
+    pub(crate) fn link(&mut self, pc: u32, idx: u32) {
+        let Some(current) = self.blocks.get_mut(self.current as usize) else { return };
+        let links = &mut current.links;
+        if let Some(link) = links.iter_mut().find(|(target, _)| *target == pc) {
+            link.1 = idx;
+        } else {
+            links.rotate_right(1);
+            links[0] = (pc, idx);
+        }
+    }
+}
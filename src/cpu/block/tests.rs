@@ -0,0 +1,122 @@
+use crate::cpu::{Cpu, ICacheStats, StopReason};
+use crate::cpu::block::{Block, BlockCache, NO_BLOCK};
+use crate::cpu::csr::MSTATUS_MIE;
+use crate::cpu::privilege_mode::PrivilegeMode;
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_block_cache_links() {
+    let mut blocks = BlockCache::new();
+    let a = blocks.insert(Block::new(0x1000, PrivilegeMode::Machine, 0, 0x1000));
+    let b = blocks.insert(Block::new(0x2000, PrivilegeMode::Machine, 1, 0x2000));
+    assert_eq!(blocks.lookup(0x2000, PrivilegeMode::Machine), Some(b));
+    // 別の特権モードで作ったブロックは使わない
+    assert_eq!(blocks.lookup(0x2000, PrivilegeMode::User), None);
+
+    // 実行中のブロックがなければ連結しない
+    blocks.enter(NO_BLOCK);
+    blocks.link(0x2000, b);
+    assert_eq!(blocks.linked(0x2000, PrivilegeMode::Machine), None);
+
+    blocks.enter(a);
+    blocks.link(0x2000, b);
+    assert_eq!(blocks.linked(0x2000, PrivilegeMode::Machine), Some(b));
+    assert_eq!(blocks.linked(0x3000, PrivilegeMode::Machine), None);
+
+    // 連結は 2 つまでで、古い方から置き換える
+    let c = blocks.insert(Block::new(0x3000, PrivilegeMode::Machine, 2, 0x3000));
+    let d = blocks.insert(Block::new(0x4000, PrivilegeMode::Machine, 3, 0x4000));
+    blocks.link(0x3000, c);
+    blocks.link(0x4000, d);
+    assert_eq!(blocks.linked(0x2000, PrivilegeMode::Machine), None);
+    assert_eq!(blocks.linked(0x3000, PrivilegeMode::Machine), Some(c));
+    assert_eq!(blocks.linked(0x4000, PrivilegeMode::Machine), Some(d));
+
+    // 命令キャッシュの世代が変わればすべて破棄する
+    blocks.sync(0);
+    assert_eq!(blocks.lookup(0x1000, PrivilegeMode::Machine), Some(a));
+    blocks.sync(1);
+    assert_eq!(blocks.lookup(0x1000, PrivilegeMode::Machine), None);
+    assert_eq!(blocks.linked(0x3000, PrivilegeMode::Machine), None);
+}
+
+#[test]
+fn test_run_chains_across_pages() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0000: jal  x0, 0x1000
+    // 0x1000: addi x1, x1, 1
+    // 0x1004: jal  x0, -0x1004
+    bus.write_inst32(0x0000, 0x0000106f);
+    bus.write_inst32(0x1000, 0x00108093);
+    bus.write_inst32(0x1004, 0xffdfe06f);
+
+    let (reason, executed) = cpu.run(&mut bus, 9);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 9);
+    assert_eq!(cpu.regs[1], 3);
+    assert_eq!(cpu.pc, 0x0);
+    assert_eq!(cpu.csr.minstret, 9);
+
+    // 連結をたどった場合もページを参照したものとして数える
+    assert_eq!(cpu.icache_stats(), ICacheStats {
+        hits: 4,
+        misses: 2,
+        evictions: 0,
+        invalidations: 0,
+    });
+}
+
+#[test]
+fn test_run_chain_takes_interrupt_at_block_boundary() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.mie = 1 << 1; // SSIE
+    cpu.csr.mstatus |= MSTATUS_MIE;
+
+    // 0x0000: addi  x1, x1, 1
+    // 0x0004: jal   x0, 0x1000
+    // 0x1000: csrsi mip, 2 (SSIP)
+    // 0x1004: addi  x2, x2, 1
+    // 0x1008: jal   x0, -0x1008
+    bus.write_inst32(0x0000, 0x00108093);
+    bus.write_inst32(0x0004, 0x7fd0006f);
+    bus.write_inst32(0x1000, 0x34416073);
+    bus.write_inst32(0x1004, 0x00110113);
+    bus.write_inst32(0x1008, 0xff9fe06f);
+
+    // 保留された割り込みはブロックの途中ではなく、次のブロックへ移るときに受け付ける
+    let (reason, executed) = cpu.run(&mut bus, 100);
+    assert_eq!(reason, StopReason::Trap(0x8000_0001));
+    assert_eq!(executed, 5);
+    assert_eq!(cpu.regs[1], 1);
+    assert_eq!(cpu.regs[2], 1);
+    assert_eq!(cpu.csr.mepc, 0x0);
+    assert_eq!(cpu.pc, 0x200);
+}
+
+#[test]
+fn test_run_chain_after_code_write() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.regs[5] = 0x00208093; // addi x1, x1, 2
+    cpu.regs[6] = 0x1000;
+
+    // 0x0000: jal  x0, 0x1000
+    // 0x1000: addi x1, x1, 1
+    // 0x1004: sw   x5, 0(x6)
+    // 0x1008: jal  x0, -0x1008
+    bus.write_inst32(0x0000, 0x0000106f);
+    bus.write_inst32(0x1000, 0x00108093);
+    bus.write_inst32(0x1004, 0x00532023);
+    bus.write_inst32(0x1008, 0xff9fe06f);
+
+    // 書き換えたページへ連結していたブロックは破棄され、2 周目は書き換えた命令を実行する
+    let (reason, executed) = cpu.run(&mut bus, 8);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 8);
+    assert_eq!(cpu.regs[1], 3);
+    assert_eq!(cpu.pc, 0x0);
+}
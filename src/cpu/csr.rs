@@ -1,3 +1,5 @@
+use crate::cpu::pmp::PMP_COUNT;
+
 /// 制御ステータスレジスタ (CSR)
 #[derive(Default)]
 pub struct Csr {
@@ -11,28 +13,179 @@ pub struct Csr {
     pub mip:     u32,
     pub mscratch: u32,
     pub mcounteren: u32,
-    pub pmpcfg0: u32,
-    pub pmpaddr: [u32; 4],
+    pub pmpcfg:  [u32; 4],
+    pub pmpaddr: [u32; PMP_COUNT],
     pub satp:    u32,
+
+    // トラップの委譲
+    pub medeleg: u32,
+    pub mideleg: u32,
+
+    // スーパーバイザーモード CSR (sstatus/sie/sip は mstatus/mie/mip のビューとして扱う)
+    pub stvec:    u32,
+    pub sepc:     u32,
+    pub scause:   u32,
+    pub stval:    u32,
+    pub sscratch: u32,
+    pub scounteren: u32,
+
+    // 浮動小数点制御ステータスレジスタ (frm: bit 7-5, fflags: bit 4-0)
+    pub fcsr:    u32,
+
+    // カウンタ (mcycle/minstret は 64 ビット。1 命令リタイアごとに 1 進む)
+    pub mcycle:   u64,
+    pub minstret: u64,
+    pub mcountinhibit: u32,
+
+    /// このハートの ID (読み出し専用。Machine が各ハートに割り当てる)
+    pub mhartid: u32,
+
+    /// CLINT の mtime の写し (time/timeh の読み出し時にバスから更新する)
+    pub time:    u64,
+
+    /// 直前の命令で CSR 命令により書き込まれたカウンタ (mcountinhibit と同じビット配置)
+    /// 書き込んだ命令自身のリタイアではカウンタを進めない
+    counter_written: u32,
+
+    /// ロックされた PMP エントリが存在するか (M モードでも PMP の検査が必要になる)
+    pub(crate) pmp_locked: bool,
+
+    /// ソフトウェアが mip に書き込んだ SEIP
+    pub(crate) seip_soft: bool,
+
+    /// PLIC の S モードのコンテキストからの外部割り込み信号。
+    /// mip.SEIP は `seip_soft` との論理和を読み出す
+    pub(crate) seip_external: bool,
 }
 
+/// mstatus.FS (浮動小数点ユニットの状態: Off / Initial / Clean / Dirty)
+pub const MSTATUS_FS: u32 = 0b11 << 13;
+
+/// mstatus.SIE
+pub const MSTATUS_SIE: u32 = 1 << 1;
+
+/// mstatus.MIE
+pub const MSTATUS_MIE: u32 = 1 << 3;
+
+/// mstatus.SPIE
+pub const MSTATUS_SPIE: u32 = 1 << 5;
+
+/// mstatus.SPP
+pub const MSTATUS_SPP: u32 = 1 << 8;
+
+/// mstatus.MPRV
+pub const MSTATUS_MPRV: u32 = 1 << 17;
+
+/// mstatus.SUM
+pub const MSTATUS_SUM: u32 = 1 << 18;
+
+/// mstatus.MXR
+pub const MSTATUS_MXR: u32 = 1 << 19;
+
+/// mstatus.TVM
+pub const MSTATUS_TVM: u32 = 1 << 20;
+
+/// mstatus.TW
+pub const MSTATUS_TW: u32 = 1 << 21;
+
+/// mstatus.TSR
+pub const MSTATUS_TSR: u32 = 1 << 22;
+
+/// sstatus から読み書きできる mstatus のビット (SIE, SPIE, SPP, FS, SUM, MXR)
+const SSTATUS_MASK: u32 = MSTATUS_SIE | MSTATUS_SPIE | MSTATUS_SPP | MSTATUS_FS | MSTATUS_SUM | MSTATUS_MXR;
+
+/// satp.MODE (1: Sv32)
+pub const SATP_MODE: u32 = 1 << 31;
+
+/// satp.PPN (ルートページテーブルの物理ページ番号)
+pub const SATP_PPN: u32 = 0x003f_ffff;
+
+/// S モードに委譲できる例外 (M モードからの ecall 以外)
+const MEDELEG_MASK: u32 = 0xb3ff;
+
+/// S モードに委譲できる割り込み (SSIP, STIP, SEIP)
+const MIDELEG_MASK: u32 = (1 << 1) | (1 << 5) | (1 << 9);
+
+/// mip.SEIP
+pub const MIP_SEIP: u32 = 1 << 9;
+
+/// mcountinhibit の CY ビット
+pub const COUNTER_CY: u32 = 1 << 0;
+
+/// mcountinhibit の IR ビット
+pub const COUNTER_IR: u32 = 1 << 2;
+
+/// mstatus.SD (FS/XS のいずれかが Dirty であることを示す読み取り専用ビット)
+pub const MSTATUS_SD: u32 = 1 << 31;
+
 impl Csr {
+    /// 浮動小数点命令・CSR が有効か (mstatus.FS != Off)
+    pub fn fp_enabled(&self) -> bool {
+        (self.mstatus & MSTATUS_FS) != 0
+    }
+
+    /// 浮動小数点の状態が変更されたことを mstatus.FS に記録する
+    pub fn mark_fs_dirty(&mut self) {
+        self.mstatus |= MSTATUS_FS | MSTATUS_SD;
+    }
+
+    /// mstatus.SD を FS の値に合わせて更新する
+    fn update_sd(&mut self) {
+        if (self.mstatus & MSTATUS_FS) == MSTATUS_FS {
+            self.mstatus |= MSTATUS_SD;
+        } else {
+            self.mstatus &= !MSTATUS_SD;
+        }
+    }
+
+    /// 命令のリタイアに合わせてカウンタを進める
+    #[inline(always)]
+    pub fn retire(&mut self) {
+        let inhibit = self.mcountinhibit | std::mem::take(&mut self.counter_written);
+        if (inhibit & COUNTER_CY) == 0 {
+            self.mcycle = self.mcycle.wrapping_add(1);
+        }
+        if (inhibit & COUNTER_IR) == 0 {
+            self.minstret = self.minstret.wrapping_add(1);
+        }
+    }
+
     pub fn read(&self, addr: u32) -> Result<u32, ()> {
         match addr {
             0x300 => Ok(self.mstatus),
+            0x100 => Ok(self.mstatus & (SSTATUS_MASK | MSTATUS_SD)), // sstatus
+            0x104 => Ok(self.mie & self.mideleg), // sie
+            0x105 => Ok(self.stvec),
+            0x106 => Ok(self.scounteren),
+            0x140 => Ok(self.sscratch),
+            0x141 => Ok(self.sepc),
+            0x142 => Ok(self.scause),
+            0x143 => Ok(self.stval),
+            0x144 => Ok(self.mip & self.mideleg), // sip
+            0x001..=0x003 if !self.fp_enabled() => Err(()),
+            0x001 => Ok(self.fcsr & 0x1f),        // fflags
+            0x002 => Ok((self.fcsr >> 5) & 0x7),  // frm
+            0x003 => Ok(self.fcsr & 0xff),        // fcsr
             0x180 => Ok(self.satp),
-            0x301 => Ok(0x40101104), // misa: RV32IMCU (I=1<<8, M=1<<12, C=1<<2, U=1<<20, MXL=1(RV32)<<30)
-            0x302 => Ok(0), // medeleg
-            0x303 => Ok(0), // mideleg
+            0x301 => Ok(0x40141127), // misa: RV32IMAFCBSU (A=1<<0, B=1<<1, C=1<<2, F=1<<5, I=1<<8, M=1<<12, S=1<<18, U=1<<20, MXL=1(RV32)<<30)
+            0x302 => Ok(self.medeleg),
+            0x303 => Ok(self.mideleg),
             0x306 => Ok(self.mcounteren),
             0x744 => Ok(0), // mtp (Machine Trap Pointer?) - Not standard, but sometimes used in tests or specific impls
             0x7a0..=0x7a3 => Ok(0), // tselect, tdata1, tdata2, tdata3
             0x7a5 => Ok(0), // tinfo
-            0x320..=0x33f => Ok(0), // mcountinhibit, mhpmevent
-            0xb00..=0xb1f => Ok(0), // mcycle, minstret, etc. (Machine read-only/read-write)
-            0xb80..=0xb9f => Ok(0), // mcycleh, minstreth, etc.
-            0xc00..=0xc1f => Ok(0), // cycle, time, instret, etc. (Read-only)
-            0xc80..=0xc9f => Ok(0), // cycleh, timeh, instreth, etc. (Read-only)
+            0x320 => Ok(self.mcountinhibit),
+            0x321..=0x33f => Ok(0), // mhpmevent3-31
+            0xb00 | 0xc00 => Ok(self.mcycle as u32),            // mcycle, cycle
+            0xb80 | 0xc80 => Ok((self.mcycle >> 32) as u32),    // mcycleh, cycleh
+            0xb02 | 0xc02 => Ok(self.minstret as u32),          // minstret, instret
+            0xb82 | 0xc82 => Ok((self.minstret >> 32) as u32),  // minstreth, instreth
+            0xc01 => Ok(self.time as u32),                      // time
+            0xc81 => Ok((self.time >> 32) as u32),              // timeh
+            0xb03..=0xb1f => Ok(0), // mhpmcounter3-31
+            0xb83..=0xb9f => Ok(0), // mhpmcounter3h-31h
+            0xc03..=0xc1f => Ok(0), // hpmcounter3-31 (Read-only)
+            0xc83..=0xc9f => Ok(0), // hpmcounter3h-31h (Read-only)
             0x305 => Ok(self.mtvec),
             0x304 => Ok(self.mie),
             0x340 => Ok(self.mscratch),
@@ -40,16 +193,26 @@ impl Csr {
             0x342 => Ok(self.mcause),
             0x343 => Ok(self.mtval),
             0x344 => Ok(self.mip),
-            0x3a0 => Ok(self.pmpcfg0),
-            0x3b0..=0x3b3 => Ok(self.pmpaddr[(addr - 0x3b0) as usize]),
+            0x3a0..=0x3a3 => Ok(self.pmpcfg[(addr - 0x3a0) as usize]),
+            0x3b0..=0x3bf => Ok(self.pmpaddr[(addr - 0x3b0) as usize]),
             0xf11 => Ok(0), // mvendorid
             0xf12 => Ok(0), // marchid
             0xf13 => Ok(0), // mimpid
-            0xf14 => Ok(0), // mhartid
+            0xf14 => Ok(self.mhartid), // mhartid
             _ => Err(()),
         }
     }
 
+    /// CSR 命令の read-modify-write で書き戻す値の元にする値。
+    /// mip.SEIP は PLIC の信号との論理和を読み出すが、書き戻すのはソフトウェアが書き込んだビットのみとする
+    pub(crate) fn rmw_base(&self, addr: u32, read_val: u32) -> u32 {
+        if addr == 0x344 && self.seip_external {
+            (read_val & !MIP_SEIP) | if self.seip_soft { MIP_SEIP } else { 0 }
+        } else {
+            read_val
+        }
+    }
+
     pub fn write(&mut self, addr: u32, val: u32) -> Result<(), ()> {
         // 読み取り専用 CSR (bits 11-10 == 11) への書き込みは不正命令
         // ただし、0xc00-0xc1f (counters), 0xc80-0xc9f (counters high) は読み取り専用
@@ -60,31 +223,101 @@ impl Csr {
         match addr {
             0x300 => {
                 // mstatus: 制限付き書き込み
-                // MPP は Machine (3) と User (0) のみサポート
-                let mask = 0x807E1888 | 0x0000000F; // テストのために下位ビットも一部許可 (実際は WPRI 等があるが)
+                // MPP は Machine (3), Supervisor (1), User (0) をサポート
+                // SD は FS から求める読み取り専用ビットのため書き込み対象外
+                let mask = 0x007E7888 | MSTATUS_SPIE | MSTATUS_SPP | 0x0000000F; // テストのために下位ビットも一部許可 (実際は WPRI 等があるが)
                 let mut val = val;
                 
                 let mpp = (val >> 11) & 0b11;
-                if mpp == 1 || mpp == 2 {
-                    // Sモードや保留モードが指定されたら Userモードに丸める
+                if mpp == 2 {
+                    // 保留モードが指定されたら Userモードに丸める
                     val &= !(0b11 << 11);
                 }
                 
                 let new_val = (self.mstatus & !mask) | (val & mask);
                 self.mstatus = new_val;
+                self.update_sd();
+                Ok(())
+            }
+            0x001..=0x003 if !self.fp_enabled() => Err(()),
+            0x001 => {
+                self.fcsr = (self.fcsr & !0x1f) | (val & 0x1f);
+                self.mark_fs_dirty();
+                Ok(())
+            }
+            0x002 => {
+                self.fcsr = (self.fcsr & 0x1f) | ((val & 0x7) << 5);
+                self.mark_fs_dirty();
+                Ok(())
+            }
+            0x003 => {
+                self.fcsr = val & 0xff;
+                self.mark_fs_dirty();
+                Ok(())
+            }
+            0x100 => {
+                // sstatus: mstatus のうち S モードから見えるビットのみ更新する
+                self.mstatus = (self.mstatus & !SSTATUS_MASK) | (val & SSTATUS_MASK);
+                self.update_sd();
+                Ok(())
+            }
+            0x104 => {
+                // sie: 委譲された割り込みのビットのみ更新する
+                self.mie = (self.mie & !self.mideleg) | (val & self.mideleg);
+                Ok(())
+            }
+            0x105 => { self.stvec = val; Ok(()) }
+            0x106 => { self.scounteren = val; Ok(()) }
+            0x140 => { self.sscratch = val; Ok(()) }
+            0x141 => { self.sepc = val; Ok(()) }
+            0x142 => { self.scause = val; Ok(()) }
+            0x143 => { self.stval = val; Ok(()) }
+            0x144 => {
+                // sip: 書き込めるのは SSIP のみ (委譲されている場合)
+                let mask = self.mideleg & (1 << 1);
+                self.mip = (self.mip & !mask) | (val & mask);
                 Ok(())
             }
             0x301 => Ok(()), // misa: WARL (とりあえず固定)
-            0x180 => { self.satp = val; Ok(()) }
-            0x302 => Ok(()), // medeleg
-            0x303 => Ok(()), // mideleg
+            0x180 => {
+                // ASID は実装しない (常に 0)
+                self.satp = val & (SATP_MODE | SATP_PPN);
+                Ok(())
+            }
+            0x302 => { self.medeleg = val & MEDELEG_MASK; Ok(()) }
+            0x303 => { self.mideleg = val & MIDELEG_MASK; Ok(()) }
             0x306 => { self.mcounteren = val; Ok(()) }
             0x744 => Ok(()), // mtp
             0x7a0..=0x7a3 => Ok(()), // tselect, tdata1, tdata2, tdata3
             0x7a5 => Ok(()), // tinfo
-            0x320..=0x33f => Ok(()), // mcountinhibit, mhpmevent
-            0xb00..=0xb1f => Ok(()), // mcycle, minstret, etc. (Machine read-only/read-write)
-            0xb80..=0xb9f => Ok(()), // mcycleh, minstreth, etc.
+            0x320 => {
+                // mcountinhibit: CY と IR のみ実装 (TM は常に 0)
+                self.mcountinhibit = val & (COUNTER_CY | COUNTER_IR);
+                Ok(())
+            }
+            0x321..=0x33f => Ok(()), // mhpmevent3-31
+            0xb00 => {
+                self.mcycle = (self.mcycle & !0xffff_ffff) | val as u64;
+                self.counter_written |= COUNTER_CY;
+                Ok(())
+            }
+            0xb80 => {
+                self.mcycle = (self.mcycle & 0xffff_ffff) | ((val as u64) << 32);
+                self.counter_written |= COUNTER_CY;
+                Ok(())
+            }
+            0xb02 => {
+                self.minstret = (self.minstret & !0xffff_ffff) | val as u64;
+                self.counter_written |= COUNTER_IR;
+                Ok(())
+            }
+            0xb82 => {
+                self.minstret = (self.minstret & 0xffff_ffff) | ((val as u64) << 32);
+                self.counter_written |= COUNTER_IR;
+                Ok(())
+            }
+            0xb03..=0xb1f => Ok(()), // mhpmcounter3-31
+            0xb83..=0xb9f => Ok(()), // mhpmcounter3h-31h
             0xc00..=0xc1f => Err(()), // cycle, time, instret, etc. (Read-only)
             0xc80..=0xc9f => Err(()), // cycleh, timeh, instreth, etc. (Read-only)
             0x305 => { self.mtvec = val; Ok(()) }
@@ -93,10 +326,18 @@ impl Csr {
             0x341 => { self.mepc = val; Ok(()) }
             0x342 => { self.mcause = val; Ok(()) }
             0x343 => { self.mtval = val; Ok(()) }
-            0x344 => { self.mip = val; Ok(()) }
-            0x3a0 => { self.pmpcfg0 = val; Ok(()) }
-            0x3b0..=0x3b3 => {
-                self.pmpaddr[(addr - 0x3b0) as usize] = val;
+            0x344 => {
+                // SEIP はソフトウェアのビットとして保持し、PLIC の信号との論理和を mip に置く
+                self.seip_soft = (val & MIP_SEIP) != 0;
+                self.mip = if self.seip_external { val | MIP_SEIP } else { val };
+                Ok(())
+            }
+            0x3a0..=0x3a3 => {
+                self.write_pmpcfg((addr - 0x3a0) as usize, val);
+                Ok(())
+            }
+            0x3b0..=0x3bf => {
+                self.write_pmpaddr((addr - 0x3b0) as usize, val);
                 Ok(())
             }
             _ => Err(()),
@@ -80,6 +80,16 @@ impl Instruction {
         (rd, rs1, rs2)
     }
 
+    #[inline(always)]
+    pub(super) fn decode_r4_type(inst_bin: u32) -> (u8, u8, u8, u8, u8) {
+        let rs3: u8 = ((inst_bin >> 27) & 0x1f) as u8;
+        let rs2: u8 = ((inst_bin >> 20) & 0x1f) as u8;
+        let rs1: u8 = ((inst_bin >> 15) & 0x1f) as u8;
+        let rm:  u8 = ((inst_bin >> 12) & 0x7) as u8;
+        let rd:  u8 = ((inst_bin >> 7) & 0x1f) as u8;
+        (rd, rs1, rs2, rs3, rm)
+    }
+
     #[inline(always)]
     pub(super) fn decode_csr_type(inst_bin: u32) -> (u16, u8, u8) {
         let csr: u16 = ((inst_bin >> 20) & 0xfff) as u16;
@@ -194,9 +204,7 @@ impl Instruction {
         let imm = (i11 << 11) | (i10 << 10) | (i9_8 << 8) | (i7 << 7) | (i6 << 6) | (i5 << 5) | (i4 << 4) | (i3_1 << 1);
 
         // Sign extension from 12th bit (bit 11 of imm)
-        let imm = ((imm as i32) << 20 >> 20) as u32;
-
-        imm
+        ((imm as i32) << 20 >> 20) as u32
     }
 
     pub(super) fn decode_c_addi16sp_imm(inst_bin: u16) -> u32 {
@@ -216,9 +224,7 @@ impl Instruction {
         let imm = (i9 << 9) | (i8_7 << 7) | (i6 << 6) | (i5 << 5) | (i4 << 4);
 
         // Sign extension from 10th bit (bit 9 of imm)
-        let imm = ((imm as i32) << 22 >> 22) as u32;
-
-        imm
+        ((imm as i32) << 22 >> 22) as u32
     }
 
     pub(super) fn decode_cb_shamt_type(inst_bin: u16) -> (usize, u32) {
@@ -320,6 +326,10 @@ impl Instruction {
     pub(super) fn decode_funct7(inst_bin: u32) -> u32 {
         (inst_bin >> 25) & 0x7f
     }
+
+    pub(super) fn decode_funct5(inst_bin: u32) -> u32 {
+        (inst_bin >> 27) & 0x1f
+    }
     
     pub(super) fn decode_quadrant(inst_bin: u16) -> u16 {
         inst_bin & 0x3
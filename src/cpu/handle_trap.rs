@@ -1,8 +1,27 @@
 use crate::cpu::{Cpu, StepResult};
 use crate::cpu::privilege_mode::PrivilegeMode;
+use crate::cpu::csr::{MSTATUS_SIE, MSTATUS_SPIE, MSTATUS_SPP};
 
 impl Cpu {
+    /// mtval に格納する補助情報 (フォールトアドレス等) を伴うトラップを発生させる
+    #[inline(always)]
+    pub(super) fn trap_with_value(&mut self, exception_code: u32, value: u32) -> StepResult {
+        self.trap_value = value;
+        StepResult::Trap(exception_code)
+    }
+
     pub(super) fn handle_trap(&mut self, exception_code: u32, mtval: u32) -> StepResult {
+        // トラップが発生したら LR/SC の予約を破棄する
+        self.reservation = None;
+
+        // M モード以外で発生し、medeleg/mideleg で委譲されているトラップは S モードで処理する
+        let is_interrupt = (exception_code >> 31) & 1;
+        let cause = exception_code & 0x7fff_ffff;
+        let deleg = if is_interrupt == 1 { self.csr.mideleg } else { self.csr.medeleg };
+        if self.mode != PrivilegeMode::Machine && cause < 32 && (deleg >> cause) & 1 != 0 {
+            return self.handle_supervisor_trap(exception_code, mtval);
+        }
+
         // 1. mepc に現在の PC を保存
         self.csr.mepc = self.pc;
 
@@ -30,7 +49,6 @@ impl Cpu {
         self.mode = PrivilegeMode::Machine;
 
         // 5. mtvec のアドレスへジャンプ
-        let is_interrupt = (exception_code >> 31) & 1;
         let mtvec_mode = self.csr.mtvec & 0b11;
         let mtvec_base = self.csr.mtvec & !0b11;
 
@@ -45,4 +63,36 @@ impl Cpu {
 
         StepResult::Trap(exception_code)
     }
+
+    /// S モードに委譲されたトラップの処理。sepc/scause/stval と mstatus の SPP/SPIE/SIE を更新して stvec へ飛ぶ
+    fn handle_supervisor_trap(&mut self, exception_code: u32, stval: u32) -> StepResult {
+        self.csr.sepc = self.pc;
+        self.csr.scause = exception_code;
+        self.csr.stval = stval;
+
+        // SPIE = SIE, SIE = 0, SPP = 直前のモード (User: 0, Supervisor: 1)
+        let sie = self.csr.mstatus & MSTATUS_SIE != 0;
+        self.csr.mstatus &= !(MSTATUS_SIE | MSTATUS_SPIE | MSTATUS_SPP);
+        if sie {
+            self.csr.mstatus |= MSTATUS_SPIE;
+        }
+        if self.mode == PrivilegeMode::Supervisor {
+            self.csr.mstatus |= MSTATUS_SPP;
+        }
+
+        self.mode = PrivilegeMode::Supervisor;
+
+        let is_interrupt = (exception_code >> 31) & 1;
+        let stvec_mode = self.csr.stvec & 0b11;
+        let stvec_base = self.csr.stvec & !0b11;
+
+        if is_interrupt == 1 && stvec_mode == 1 {
+            let code = exception_code & 0x7fff_ffff;
+            self.pc = stvec_base + 4 * code;
+        } else {
+            self.pc = stvec_base;
+        }
+
+        StepResult::Trap(exception_code)
+    }
 }
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use crate::cpu::InstructionCachePage;
+
+#[cfg(test)]
+mod tests;
+
+/// 命令キャッシュに保持するページ数のデフォルト値
+pub const DEFAULT_ICACHE_CAPACITY: usize = 256;
+
+/// 命令キャッシュの統計情報
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ICacheStats {
+    /// ページを引いたときにキャッシュに載っていた回数
+    pub hits: u64,
+    /// ページを引いたときにキャッシュに載っていなかった回数
+    pub misses: u64,
+    /// 容量を超えたため追い出したページ数
+    pub evictions: u64,
+    /// fence.i やコードへの書き込みなどで破棄したページ数
+    pub invalidations: u64,
+}
+
+/// キャッシュされた 1 ページ分の命令
+struct CachedPage {
+    /// 仮想ページ番号
+    page_num: u32,
+    /// 最後に参照したときのカウンタ (LRU 用)
+    last_used: u64,
+    insts: Box<InstructionCachePage>,
+}
+
+/// デコード済み命令をページ単位で保持する命令キャッシュ。
+/// 仮想ページ番号からスロットへの疎なマップで管理し、容量を超えた場合は最も長く参照されていないページを追い出す
+pub(crate) struct InstructionCache {
+    capacity: usize,
+    /// 仮想ページ番号 -> `slots` のインデックス
+    index: HashMap<u32, usize>,
+    slots: Vec<CachedPage>,
+    /// 破棄されて再利用できるスロット
+    free: Vec<usize>,
+    /// 参照のたびに進めるカウンタ
+    clock: u64,
+    /// ページを追い出す・破棄するたびに進める世代 (スロットを覚えている側が古くなったことを検出する)
+    generation: u64,
+    stats: ICacheStats,
+}
+
+impl InstructionCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            index: HashMap::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            clock: 0,
+            generation: 0,
+            stats: ICacheStats::default(),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ICacheStats {
+        self.stats
+    }
+
+    /// 現在の世代
+    #[inline(always)]
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// 保持するページ数を変更する。キャッシュはすべて破棄する
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.clear();
+        self.capacity = capacity.max(1);
+        self.slots.clear();
+        self.free.clear();
+    }
+
+    /// 仮想ページ `page_num` のスロットを引く
+    #[inline(always)]
+    pub(crate) fn lookup(&mut self, page_num: u32) -> Option<usize> {
+        match self.index.get(&page_num) {
+            Some(&slot) => {
+                self.clock += 1;
+                self.slots[slot].last_used = self.clock;
+                self.stats.hits += 1;
+                Some(slot)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// スロット `slot` のページを、番号から引かずに参照したことを記録する (連結したブロックからの参照)
+    #[inline(always)]
+    pub(crate) fn touch(&mut self, slot: usize) {
+        self.clock += 1;
+        self.slots[slot].last_used = self.clock;
+        self.stats.hits += 1;
+    }
+
+    /// スロット `slot` のページ
+    #[inline(always)]
+    pub(crate) fn page(&self, slot: usize) -> &InstructionCachePage {
+        &self.slots[slot].insts
+    }
+
+    /// スロット `slot` のページ (書き換え用)
+    pub(crate) fn page_mut(&mut self, slot: usize) -> &mut InstructionCachePage {
+        &mut self.slots[slot].insts
+    }
+
+    /// 仮想ページ `page_num` の命令を登録し、そのスロットを返す。
+    /// 空きがなければ最も長く参照されていないページを追い出す
+    pub(crate) fn insert(&mut self, page_num: u32, insts: &InstructionCachePage) -> usize {
+        self.clock += 1;
+        let slot = if let Some(&slot) = self.index.get(&page_num) {
+            slot
+        } else if let Some(slot) = self.free.pop() {
+            slot
+        } else if self.slots.len() < self.capacity {
+            self.slots.push(CachedPage {
+                page_num,
+                last_used: 0,
+                insts: Box::new(*insts),
+            });
+            self.slots.len() - 1
+        } else {
+            self.evict()
+        };
+
+        let cached = &mut self.slots[slot];
+        cached.page_num = page_num;
+        cached.last_used = self.clock;
+        *cached.insts = *insts;
+        self.index.insert(page_num, slot);
+        slot
+    }
+
+    /// 最も長く参照されていないページを追い出し、空いたスロットを返す
+    #[cold]
+    fn evict(&mut self) -> usize {
+        let slot = self.slots
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(slot, _)| slot)
+            .unwrap();
+        self.index.remove(&self.slots[slot].page_num);
+        self.generation += 1;
+        self.stats.evictions += 1;
+        slot
+    }
+
+    /// 仮想ページ `page_num` を破棄する
+    pub(crate) fn invalidate(&mut self, page_num: u32) {
+        if let Some(slot) = self.index.remove(&page_num) {
+            self.free.push(slot);
+            self.generation += 1;
+            self.stats.invalidations += 1;
+        }
+    }
+
+    /// すべてのページを破棄する
+    pub(crate) fn clear(&mut self) {
+        self.stats.invalidations += self.index.len() as u64;
+        self.generation += 1;
+        self.free.extend(self.index.drain().map(|(_, slot)| slot));
+    }
+}
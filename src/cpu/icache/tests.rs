@@ -0,0 +1,146 @@
+use crate::cpu::{Cpu, ICacheStats, InstructionCachePage, StopReason};
+use crate::cpu::icache::InstructionCache;
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_icache_lru_eviction() {
+    let mut cache = InstructionCache::new(2);
+    let page = InstructionCachePage::EMPTY;
+
+    // 0x80000000 や 0xfffff000 のような大きなアドレスのページも保持できる
+    cache.insert(0x80000, &page);
+    cache.insert(0xfffff, &page);
+    assert!(cache.lookup(0x80000).is_some());
+
+    // 最も長く参照されていない 0xfffff が追い出される
+    cache.insert(0x00001, &page);
+    assert!(cache.lookup(0xfffff).is_none());
+    assert!(cache.lookup(0x80000).is_some());
+    assert!(cache.lookup(0x00001).is_some());
+
+    assert_eq!(cache.stats(), ICacheStats {
+        hits: 3,
+        misses: 1,
+        evictions: 1,
+        invalidations: 0,
+    });
+}
+
+#[test]
+fn test_icache_invalidate_reuses_slot() {
+    let mut cache = InstructionCache::new(2);
+    let page = InstructionCachePage::EMPTY;
+
+    let slot = cache.insert(0x10, &page);
+    cache.insert(0x20, &page);
+    cache.invalidate(0x10);
+    cache.invalidate(0x30); // キャッシュされていないページは数えない
+    assert!(cache.lookup(0x10).is_none());
+
+    // 破棄したスロットを再利用するため、追い出しは発生しない
+    assert_eq!(cache.insert(0x30, &page), slot);
+    assert!(cache.lookup(0x20).is_some());
+
+    cache.clear();
+    assert!(cache.lookup(0x20).is_none());
+    assert!(cache.lookup(0x30).is_none());
+    assert_eq!(cache.stats().evictions, 0);
+    assert_eq!(cache.stats().invalidations, 3);
+}
+
+#[test]
+fn test_icache_stats_page_hopping() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0000: jal x0, 0x1000
+    // 0x1000: jal x0, 0x1000
+    // 0x2000: jal x0, -0x2000
+    bus.write_inst32(0x0000, 0x0000106f);
+    bus.write_inst32(0x1000, 0x0000106f);
+    bus.write_inst32(0x2000, 0x800fe06f);
+
+    let (reason, executed) = cpu.run(&mut bus, 9);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 9);
+    assert_eq!(cpu.pc, 0x0);
+
+    // 3 ページとも容量に収まるため、2 周目以降はすべてヒットする
+    assert_eq!(cpu.icache_stats(), ICacheStats {
+        hits: 6,
+        misses: 3,
+        evictions: 0,
+        invalidations: 0,
+    });
+}
+
+#[test]
+fn test_icache_capacity_bounded() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.set_icache_capacity(2);
+
+    // 0x0000: jal x0, 0x1000
+    // 0x1000: jal x0, 0x1000
+    // 0x2000: jal x0, -0x2000
+    bus.write_inst32(0x0000, 0x0000106f);
+    bus.write_inst32(0x1000, 0x0000106f);
+    bus.write_inst32(0x2000, 0x800fe06f);
+
+    let (reason, executed) = cpu.run(&mut bus, 9);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 9);
+    assert_eq!(cpu.pc, 0x0);
+
+    // 2 ページしか保持できないため、順に巡回すると毎回追い出される
+    assert_eq!(cpu.icache_stats(), ICacheStats {
+        hits: 0,
+        misses: 9,
+        evictions: 7,
+        invalidations: 0,
+    });
+}
+
+#[test]
+fn test_icache_fence_i_invalidation() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0: fence.i
+    // 0x4: jal x0, -4
+    bus.write_inst32(0x0, 0x0000100f);
+    bus.write_inst32(0x4, 0xffdff06f);
+
+    let (reason, _) = cpu.run(&mut bus, 4);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(cpu.pc, 0x0);
+
+    // fence.i のたびにキャッシュ済みのページを破棄し、次の命令で引き直す
+    let stats = cpu.icache_stats();
+    assert_eq!(stats.invalidations, 2);
+    assert_eq!(stats.misses, 3);
+    assert_eq!(stats.hits, 0);
+}
+
+#[test]
+fn test_icache_registers_directly_fetched_inst() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0: jal x0, 6
+    // 0x4: addi x0, x10, 8 (上位 16 ビットは c.addi x1, 1)
+    // 0x8: c.j -2
+    // ページの生成時は 0x4 から 32 ビット命令として読むため、0x6 のエントリは生成されない
+    bus.write_inst32(0x0, 0x0060006f);
+    bus.write_inst32(0x4, 0x00850013);
+    bus.write_inst16(0x8, 0xbffd);
+
+    let (reason, executed) = cpu.run(&mut bus, 11);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 11);
+    assert_eq!(cpu.regs[1], 5);
+
+    // 直接フェッチした命令はページに登録され、次からはページを生成し直さずに実行する
+    assert!(!cpu.icache.page(cpu.current_slot).ops[3].is_none());
+    assert_eq!(cpu.icache_stats().misses, 1);
+}
@@ -52,12 +52,14 @@ pub(crate) enum Instruction {
 
     Fence,
     FenceI,
+    SfenceVma { rs1: u8, rs2: u8 },
 
     Wfi,
 
     Ecall,
     Ebreak,
     Mret,
+    Sret,
 
     Mul    { rd: u8, rs1: u8, rs2: u8 },
     Mulh   { rd: u8, rs1: u8, rs2: u8 },
@@ -68,6 +70,80 @@ pub(crate) enum Instruction {
     Rem    { rd: u8, rs1: u8, rs2: u8 },
     Remu   { rd: u8, rs1: u8, rs2: u8 },
 
+    LrW      { rd: u8, rs1: u8 },
+    ScW      { rd: u8, rs1: u8, rs2: u8 },
+    AmoswapW { rd: u8, rs1: u8, rs2: u8 },
+    AmoaddW  { rd: u8, rs1: u8, rs2: u8 },
+    AmoxorW  { rd: u8, rs1: u8, rs2: u8 },
+    AmoandW  { rd: u8, rs1: u8, rs2: u8 },
+    AmoorW   { rd: u8, rs1: u8, rs2: u8 },
+    AmominW  { rd: u8, rs1: u8, rs2: u8 },
+    AmomaxW  { rd: u8, rs1: u8, rs2: u8 },
+    AmominuW { rd: u8, rs1: u8, rs2: u8 },
+    AmomaxuW { rd: u8, rs1: u8, rs2: u8 },
+
+    Sh1add { rd: u8, rs1: u8, rs2: u8 },
+    Sh2add { rd: u8, rs1: u8, rs2: u8 },
+    Sh3add { rd: u8, rs1: u8, rs2: u8 },
+    Andn   { rd: u8, rs1: u8, rs2: u8 },
+    Orn    { rd: u8, rs1: u8, rs2: u8 },
+    Xnor   { rd: u8, rs1: u8, rs2: u8 },
+    Max    { rd: u8, rs1: u8, rs2: u8 },
+    Maxu   { rd: u8, rs1: u8, rs2: u8 },
+    Min    { rd: u8, rs1: u8, rs2: u8 },
+    Minu   { rd: u8, rs1: u8, rs2: u8 },
+    Rol    { rd: u8, rs1: u8, rs2: u8 },
+    Ror    { rd: u8, rs1: u8, rs2: u8 },
+    Bclr   { rd: u8, rs1: u8, rs2: u8 },
+    Bext   { rd: u8, rs1: u8, rs2: u8 },
+    Binv   { rd: u8, rs1: u8, rs2: u8 },
+    Bset   { rd: u8, rs1: u8, rs2: u8 },
+    Clmul  { rd: u8, rs1: u8, rs2: u8 },
+    Clmulh { rd: u8, rs1: u8, rs2: u8 },
+    Clmulr { rd: u8, rs1: u8, rs2: u8 },
+
+    Clz    { rd: u8, rs1: u8 },
+    Ctz    { rd: u8, rs1: u8 },
+    Cpop   { rd: u8, rs1: u8 },
+    SextB  { rd: u8, rs1: u8 },
+    SextH  { rd: u8, rs1: u8 },
+    ZextH  { rd: u8, rs1: u8 },
+    OrcB   { rd: u8, rs1: u8 },
+    Rev8   { rd: u8, rs1: u8 },
+
+    Rori   { rd: u8, rs1: u8, shamt: u8 },
+    Bclri  { rd: u8, rs1: u8, shamt: u8 },
+    Bexti  { rd: u8, rs1: u8, shamt: u8 },
+    Binvi  { rd: u8, rs1: u8, shamt: u8 },
+    Bseti  { rd: u8, rs1: u8, shamt: u8 },
+
+    Flw      { rd: u8, rs1: u8, imm: u16 },
+    Fsw      { rs2: u8, rs1: u8, imm: u16 },
+    FmaddS   { rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8 },
+    FmsubS   { rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8 },
+    FnmsubS  { rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8 },
+    FnmaddS  { rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8 },
+    FaddS    { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+    FsubS    { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+    FmulS    { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+    FdivS    { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+    FsqrtS   { rd: u8, rs1: u8, rm: u8 },
+    FsgnjS   { rd: u8, rs1: u8, rs2: u8 },
+    FsgnjnS  { rd: u8, rs1: u8, rs2: u8 },
+    FsgnjxS  { rd: u8, rs1: u8, rs2: u8 },
+    FminS    { rd: u8, rs1: u8, rs2: u8 },
+    FmaxS    { rd: u8, rs1: u8, rs2: u8 },
+    FcvtWS   { rd: u8, rs1: u8, rm: u8 },
+    FcvtWuS  { rd: u8, rs1: u8, rm: u8 },
+    FmvXW    { rd: u8, rs1: u8 },
+    FeqS     { rd: u8, rs1: u8, rs2: u8 },
+    FltS     { rd: u8, rs1: u8, rs2: u8 },
+    FleS     { rd: u8, rs1: u8, rs2: u8 },
+    FclassS  { rd: u8, rs1: u8 },
+    FcvtSW   { rd: u8, rs1: u8, rm: u8 },
+    FcvtSWu  { rd: u8, rs1: u8, rm: u8 },
+    FmvWX    { rd: u8, rs1: u8 },
+
     CAddi4spn { rd: u8, rs1: u8, imm: u16 },
     CLw       { rd: u8, rs1: u8, imm: u16 },
     CSw       { rs2: u8, rs1: u8, imm: u16 },
@@ -93,6 +169,10 @@ pub(crate) enum Instruction {
     CJalr     { rs1: u8 },
     CAdd      { rd: u8, rs2: u8 },
     CSwsp     { rs2: u8, imm: u16 },
+    CFlw      { rd: u8, rs1: u8, imm: u16 },
+    CFsw      { rs2: u8, rs1: u8, imm: u16 },
+    CFlwsp    { rd: u8, imm: u16 },
+    CFswsp    { rs2: u8, imm: u16 },
 
     Csrrw  { csr: u16, rd: u8, rs1: u8 },
     Csrrs  { csr: u16, rd: u8, rs1: u8 },
@@ -103,6 +183,43 @@ pub(crate) enum Instruction {
 }
 
 impl Instruction {
+    /// 16 ビットの圧縮命令か
+    #[inline(always)]
+    pub fn is_compressed(&self) -> bool {
+        matches!(
+            self,
+            Instruction::CAddi4spn { .. } |
+            Instruction::CLw { .. } |
+            Instruction::CSw { .. } |
+            Instruction::CAddi { .. } |
+            Instruction::CJal { .. } |
+            Instruction::CLi { .. } |
+            Instruction::CLui { .. } |
+            Instruction::CAddi16Sp { .. } |
+            Instruction::CSrli { .. } |
+            Instruction::Csrai { .. } |
+            Instruction::Candi { .. } |
+            Instruction::CSub { .. } |
+            Instruction::CXor { .. } |
+            Instruction::Cor { .. } |
+            Instruction::Cand { .. } |
+            Instruction::CJ { .. } |
+            Instruction::CBeqz { .. } |
+            Instruction::CBnez { .. } |
+            Instruction::CSlli { .. } |
+            Instruction::CLwsp { .. } |
+            Instruction::CJr { .. } |
+            Instruction::CMv { .. } |
+            Instruction::CJalr { .. } |
+            Instruction::CAdd { .. } |
+            Instruction::CSwsp { .. } |
+            Instruction::CFlw { .. } |
+            Instruction::CFsw { .. } |
+            Instruction::CFlwsp { .. } |
+            Instruction::CFswsp { .. }
+        )
+    }
+
     #[inline(always)]
         pub fn lui(inst_bin: u32) -> Instruction {
         let (rd, imm) = Instruction::decode_u_type(inst_bin);
@@ -347,6 +464,12 @@ impl Instruction {
         Instruction::FenceI
     }
 
+    #[inline(always)]
+    pub fn sfence_vma(inst_bin: u32) -> Instruction {
+        let (_, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::SfenceVma { rs1, rs2 }
+    }
+
     #[inline(always)]
     pub fn ecall() -> Instruction {
         Instruction::Ecall
@@ -362,6 +485,11 @@ impl Instruction {
         Instruction::Mret
     }
 
+    #[inline(always)]
+    pub fn sret() -> Instruction {
+        Instruction::Sret
+    }
+
     #[inline(always)]
     pub fn wfi() -> Instruction {
         Instruction::Wfi
@@ -415,6 +543,437 @@ impl Instruction {
         Instruction::Remu { rd, rs1, rs2 }
     }
 
+    #[inline(always)]
+    pub fn lr_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        if rs2 != 0 {
+            return Instruction::Illegal;
+        }
+        Instruction::LrW { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn sc_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::ScW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amoswap_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmoswapW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amoadd_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmoaddW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amoxor_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmoxorW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amoand_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmoandW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amoor_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmoorW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amomin_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmominW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amomax_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmomaxW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amominu_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmominuW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn amomaxu_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::AmomaxuW { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn sh1add(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Sh1add { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn sh2add(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Sh2add { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn sh3add(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Sh3add { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn andn(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Andn { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn orn(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Orn { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn xnor(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Xnor { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn max(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Max { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn maxu(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Maxu { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn min(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Min { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn minu(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Minu { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn rol(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Rol { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn ror(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Ror { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn bclr(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Bclr { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn bext(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Bext { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn binv(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Binv { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn bset(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Bset { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn clmul(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Clmul { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn clmulh(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Clmulh { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn clmulr(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::Clmulr { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn clz(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::Clz { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn ctz(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::Ctz { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn cpop(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::Cpop { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn sext_b(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::SextB { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn sext_h(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::SextH { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn zext_h(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::ZextH { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn orc_b(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::OrcB { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn rev8(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::Rev8 { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn rori(inst_bin: u32) -> Instruction {
+        let (rd, rs1, imm) = Instruction::decode_i_type(inst_bin);
+        let shamt = (imm & 0x1f) as u8;
+        Instruction::Rori { rd, rs1, shamt }
+    }
+
+    #[inline(always)]
+    pub fn bclri(inst_bin: u32) -> Instruction {
+        let (rd, rs1, imm) = Instruction::decode_i_type(inst_bin);
+        let shamt = (imm & 0x1f) as u8;
+        Instruction::Bclri { rd, rs1, shamt }
+    }
+
+    #[inline(always)]
+    pub fn bexti(inst_bin: u32) -> Instruction {
+        let (rd, rs1, imm) = Instruction::decode_i_type(inst_bin);
+        let shamt = (imm & 0x1f) as u8;
+        Instruction::Bexti { rd, rs1, shamt }
+    }
+
+    #[inline(always)]
+    pub fn binvi(inst_bin: u32) -> Instruction {
+        let (rd, rs1, imm) = Instruction::decode_i_type(inst_bin);
+        let shamt = (imm & 0x1f) as u8;
+        Instruction::Binvi { rd, rs1, shamt }
+    }
+
+    #[inline(always)]
+    pub fn bseti(inst_bin: u32) -> Instruction {
+        let (rd, rs1, imm) = Instruction::decode_i_type(inst_bin);
+        let shamt = (imm & 0x1f) as u8;
+        Instruction::Bseti { rd, rs1, shamt }
+    }
+
+    #[inline(always)]
+    pub fn flw(inst_bin: u32) -> Instruction {
+        let (rd, rs1, imm) = Instruction::decode_i_type(inst_bin);
+        Instruction::Flw { rd, rs1, imm }
+    }
+
+    #[inline(always)]
+    pub fn fsw(inst_bin: u32) -> Instruction {
+        let (rs1, rs2, imm) = Instruction::decode_s_type(inst_bin);
+        Instruction::Fsw { rs1, rs2, imm }
+    }
+
+    #[inline(always)]
+    pub fn fmadd_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2, rs3, rm) = Instruction::decode_r4_type(inst_bin);
+        Instruction::FmaddS { rd, rs1, rs2, rs3, rm }
+    }
+
+    #[inline(always)]
+    pub fn fmsub_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2, rs3, rm) = Instruction::decode_r4_type(inst_bin);
+        Instruction::FmsubS { rd, rs1, rs2, rs3, rm }
+    }
+
+    #[inline(always)]
+    pub fn fnmsub_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2, rs3, rm) = Instruction::decode_r4_type(inst_bin);
+        Instruction::FnmsubS { rd, rs1, rs2, rs3, rm }
+    }
+
+    #[inline(always)]
+    pub fn fnmadd_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2, rs3, rm) = Instruction::decode_r4_type(inst_bin);
+        Instruction::FnmaddS { rd, rs1, rs2, rs3, rm }
+    }
+
+    #[inline(always)]
+    pub fn fadd_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FaddS { rd, rs1, rs2, rm }
+    }
+
+    #[inline(always)]
+    pub fn fsub_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FsubS { rd, rs1, rs2, rm }
+    }
+
+    #[inline(always)]
+    pub fn fmul_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FmulS { rd, rs1, rs2, rm }
+    }
+
+    #[inline(always)]
+    pub fn fdiv_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FdivS { rd, rs1, rs2, rm }
+    }
+
+    #[inline(always)]
+    pub fn fsqrt_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FsqrtS { rd, rs1, rm }
+    }
+
+    #[inline(always)]
+    pub fn fcvt_w_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FcvtWS { rd, rs1, rm }
+    }
+
+    #[inline(always)]
+    pub fn fcvt_wu_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FcvtWuS { rd, rs1, rm }
+    }
+
+    #[inline(always)]
+    pub fn fcvt_s_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FcvtSW { rd, rs1, rm }
+    }
+
+    #[inline(always)]
+    pub fn fcvt_s_wu(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        let rm = Instruction::decode_funct3(inst_bin) as u8;
+        Instruction::FcvtSWu { rd, rs1, rm }
+    }
+
+    #[inline(always)]
+    pub fn fsgnj_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FsgnjS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn fsgnjn_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FsgnjnS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn fsgnjx_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FsgnjxS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn fmin_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FminS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn fmax_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FmaxS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn feq_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FeqS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn flt_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FltS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn fle_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, rs2) = Instruction::decode_r_type(inst_bin);
+        Instruction::FleS { rd, rs1, rs2 }
+    }
+
+    #[inline(always)]
+    pub fn fmv_x_w(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::FmvXW { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn fclass_s(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::FclassS { rd, rs1 }
+    }
+
+    #[inline(always)]
+    pub fn fmv_w_x(inst_bin: u32) -> Instruction {
+        let (rd, rs1, _) = Instruction::decode_r_type(inst_bin);
+        Instruction::FmvWX { rd, rs1 }
+    }
+
     #[inline(always)]
     pub fn csrrw(inst_bin: u32) -> Instruction {
         let (csr, rd, rs1) = Instruction::decode_csr_type(inst_bin);
@@ -590,4 +1149,24 @@ impl Instruction {
         let (rs2, imm) = Instruction::decode_c_swsp_type(inst_bin);
         Instruction::CSwsp { rs2: rs2 as u8, imm: imm as u16 }
     }
+    #[inline(always)]
+    pub fn c_flw(inst_bin: u16) -> Instruction {
+        let (rd, rs1, imm) = Instruction::decode_cl_type(inst_bin);
+        Instruction::CFlw { rd: rd as u8, rs1: rs1 as u8, imm: imm as u16 }
+    }
+    #[inline(always)]
+    pub fn c_fsw(inst_bin: u16) -> Instruction {
+        let (rs1, rs2, imm) = Instruction::decode_cs_type(inst_bin);
+        Instruction::CFsw { rs1: rs1 as u8, rs2: rs2 as u8, imm: imm as u16 }
+    }
+    #[inline(always)]
+    pub fn c_flwsp(inst_bin: u16) -> Instruction {
+        let (rd, imm) = Instruction::decode_c_lwsp_type(inst_bin);
+        Instruction::CFlwsp { rd: rd as u8, imm: imm as u16 }
+    }
+    #[inline(always)]
+    pub fn c_fswsp(inst_bin: u16) -> Instruction {
+        let (rs2, imm) = Instruction::decode_c_swsp_type(inst_bin);
+        Instruction::CFswsp { rs2: rs2 as u8, imm: imm as u16 }
+    }
 }
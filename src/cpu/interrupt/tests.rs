@@ -1,7 +1,10 @@
-use crate::cpu::{Cpu, StepResult};
+use crate::cpu::{Cpu, StepResult, StopReason};
+use crate::cpu::privilege_mode::PrivilegeMode;
 use crate::bus::mock_bus::MockBus;
 use crate::bus::plic::Plic;
-use crate::bus::Bus;
+use crate::bus::clint::ClintRegion;
+use crate::bus::memory_map::MemoryMap;
+use crate::bus::{Bus, BusError};
 
 struct InterruptTestBus {
     mock_bus: MockBus,
@@ -23,52 +26,58 @@ const PLIC_BASE: u32 = 0x0c00_0000;
 const PLIC_SIZE: u32 = 0x0040_0000;
 
 impl Bus for InterruptTestBus {
-    fn read8(&mut self, addr: u32) -> u8 {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.read(addr - PLIC_BASE) as u8
+    fn read8(&mut self, addr: u32) -> Result<u8, BusError> {
+        if (PLIC_BASE..PLIC_BASE + PLIC_SIZE).contains(&addr) {
+            Ok(self.plic.read(addr - PLIC_BASE) as u8)
         } else {
             self.mock_bus.read8(addr)
         }
     }
-    fn read16(&mut self, addr: u32) -> u16 {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.read(addr - PLIC_BASE) as u16
+    fn read16(&mut self, addr: u32) -> Result<u16, BusError> {
+        if (PLIC_BASE..PLIC_BASE + PLIC_SIZE).contains(&addr) {
+            Ok(self.plic.read(addr - PLIC_BASE) as u16)
         } else {
             self.mock_bus.read16(addr)
         }
     }
-    fn read32(&mut self, addr: u32) -> u32 {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
-            self.plic.read(addr - PLIC_BASE)
+    fn read32(&mut self, addr: u32) -> Result<u32, BusError> {
+        if (PLIC_BASE..PLIC_BASE + PLIC_SIZE).contains(&addr) {
+            Ok(self.plic.read(addr - PLIC_BASE))
         } else {
             self.mock_bus.read32(addr)
         }
     }
-    fn write8(&mut self, addr: u32, val: u8) {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
+    fn write8(&mut self, addr: u32, val: u8) -> Result<(), BusError> {
+        if (PLIC_BASE..PLIC_BASE + PLIC_SIZE).contains(&addr) {
             self.plic.write(addr - PLIC_BASE, val as u32);
+            Ok(())
         } else {
             self.mock_bus.write8(addr, val)
         }
     }
-    fn write16(&mut self, addr: u32, val: u16) {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
+    fn write16(&mut self, addr: u32, val: u16) -> Result<(), BusError> {
+        if (PLIC_BASE..PLIC_BASE + PLIC_SIZE).contains(&addr) {
             self.plic.write(addr - PLIC_BASE, val as u32);
+            Ok(())
         } else {
             self.mock_bus.write16(addr, val)
         }
     }
-    fn write32(&mut self, addr: u32, val: u32) {
-        if addr >= PLIC_BASE && addr < PLIC_BASE + PLIC_SIZE {
+    fn write32(&mut self, addr: u32, val: u32) -> Result<(), BusError> {
+        if (PLIC_BASE..PLIC_BASE + PLIC_SIZE).contains(&addr) {
             self.plic.write(addr - PLIC_BASE, val);
+            Ok(())
         } else {
             self.mock_bus.write32(addr, val)
         }
     }
-    fn get_interrupt_level(&self) -> bool {
-        self.plic.get_interrupt_level()
+    fn get_interrupt_level(&self, hart: usize) -> bool {
+        self.plic.get_interrupt_level(hart)
     }
-    fn get_timer_interrupt_level(&self) -> bool {
+    fn get_supervisor_interrupt_level(&self, hart: usize) -> Option<bool> {
+        self.plic.get_supervisor_interrupt_level(hart)
+    }
+    fn get_timer_interrupt_level(&self, _hart: usize) -> bool {
         self.timer_interrupt
     }
 }
@@ -91,7 +100,7 @@ fn test_external_interrupt() {
     bus.mock_bus.write_inst32(0, 0x00000013);
 
     // 2. PLIC で割り込みを発生させる
-    bus.plic.enabled |= 1 << 1; // ID 1 を有効化
+    bus.plic.set_enabled(0, 1, true); // ID 1 を有効化
     bus.plic.priorities[1] = 1; // ID 1 の優先度を 1 に設定 (閾値 0 より大きくする)
     bus.plic.set_interrupt(1);  // ID 1 を保留中に
 
@@ -200,7 +209,7 @@ fn test_interrupt_priority() {
 
     // 2. 両方の割り込みを発生させる
     bus.timer_interrupt = true;
-    bus.plic.enabled |= 1 << 1;
+    bus.plic.set_enabled(0, 1, true);
     bus.plic.priorities[1] = 1;
     bus.plic.set_interrupt(1);
 
@@ -237,7 +246,7 @@ fn test_full_interrupt_handler_flow() {
     bus.mock_bus.write_inst32(0x110, 0x30200073); // mret
 
     // 2. 外部割り込みを発生させる
-    bus.plic.enabled |= 1 << 1;
+    bus.plic.set_enabled(0, 1, true);
     bus.plic.priorities[1] = 1;
     bus.plic.set_interrupt(1);
 
@@ -251,11 +260,11 @@ fn test_full_interrupt_handler_flow() {
     cpu.step(&mut bus); // lui x5, 0x0c200
     cpu.step(&mut bus); // lw x5, 4(x5) (Claim)
     assert_eq!(cpu.regs[5], 1); // ID 1 が Claim されたはず
-    assert_eq!(bus.plic.claimed, 1 << 1); // PLIC 側でも Claimed になっているはず
+    assert!(bus.plic.is_claimed(1)); // PLIC 側でも Claimed になっているはず
 
     cpu.step(&mut bus); // lui x6, 0x0c200
     cpu.step(&mut bus); // sw x5, 4(x6) (Complete)
-    assert_eq!(bus.plic.claimed, 0); // PLIC 側で Claimed がクリアされたはず
+    assert!(!bus.plic.is_claimed(1)); // PLIC 側で Claimed がクリアされたはず
 
     // PLIC 信号をクリアしておく（そうしないと再度割り込みが発生する）
     bus.plic.clear_interrupt(1);
@@ -279,7 +288,7 @@ fn test_interrupt_disabled_by_mstatus() {
     let _ = cpu.csr.write(0x304, 1 << 11);
 
     bus.mock_bus.write_inst32(0, 0x00000013);
-    bus.plic.enabled |= 1 << 1;
+    bus.plic.set_enabled(0, 1, true);
     bus.plic.set_interrupt(1);
 
     // 2. 実行
@@ -301,7 +310,7 @@ fn test_interrupt_disabled_by_mie() {
     let _ = cpu.csr.write(0x304, 0);
 
     bus.mock_bus.write_inst32(0, 0x00000013);
-    bus.plic.enabled |= 1 << 1;
+    bus.plic.set_enabled(0, 1, true);
     bus.plic.set_interrupt(1);
 
     // 2. 実行
@@ -312,3 +321,245 @@ fn test_interrupt_disabled_by_mie() {
     assert!(matches!(result, StepResult::Ok(4)));
     assert_eq!(cpu.pc, 4);
 }
+
+#[test]
+fn test_supervisor_timer_interrupt_delegated() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = InterruptTestBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // mideleg.STIP = 1, sie.STIE = 1, sstatus.SIE = 1
+    let _ = cpu.csr.write(0x303, 1 << 5);
+    let _ = cpu.csr.write(0x104, 1 << 5);
+    let _ = cpu.csr.write(0x100, 1 << 1);
+    // stvec を設定
+    let _ = cpu.csr.write(0x105, 0x300);
+
+    bus.mock_bus.write_inst32(0, 0x00000013); // NOP
+
+    // M モードのソフトウェアが mip.STIP を立てる
+    let _ = cpu.csr.write(0x344, 1 << 5);
+
+    let (result, _) = cpu.step(&mut bus);
+
+    // Supervisor Timer Interrupt = 0x80000005
+    assert!(matches!(result, StepResult::Trap(0x8000_0005)));
+    assert_eq!(cpu.pc, 0x300);
+    assert_eq!(cpu.csr.read(0x142).unwrap(), 0x8000_0005); // scause
+    assert_eq!(cpu.csr.read(0x141).unwrap(), 0); // sepc
+    assert_eq!(cpu.csr.read(0x342).unwrap(), 0); // mcause は変更されない
+    assert_eq!((cpu.csr.read(0x300).unwrap() >> 8) & 1, 1); // SPP = Supervisor
+}
+
+#[test]
+fn test_supervisor_external_interrupt_from_plic_context() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = InterruptTestBus::new();
+    // コンテキスト 0 を M モード、コンテキスト 1 を S モードに接続する
+    bus.plic = Plic::with_config(32, 2);
+    bus.plic.harts[0].supervisor = Some(1);
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // mideleg.SEIP = 1, sie.SEIE = 1, sstatus.SIE = 1
+    let _ = cpu.csr.write(0x303, 1 << 9);
+    let _ = cpu.csr.write(0x104, 1 << 9);
+    let _ = cpu.csr.write(0x100, 1 << 1);
+    let _ = cpu.csr.write(0x105, 0x300);
+
+    bus.mock_bus.write_inst32(0, 0x00000013); // NOP
+    bus.mock_bus.write_inst32(4, 0x00000013); // NOP
+
+    // M モードのコンテキストでのみ有効なソースは SEIP を立てない
+    bus.plic.priorities[1] = 1;
+    bus.plic.set_enabled(0, 1, true);
+    bus.plic.set_interrupt(1);
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Ok(_)));
+    assert_eq!((cpu.csr.mip >> 11) & 1, 1); // MEIP
+    assert_eq!((cpu.csr.mip >> 9) & 1, 0); // SEIP
+
+    // S モードのコンテキストで有効にすると SEIP が立ち、S モードにトラップする
+    bus.plic.set_enabled(1, 1, true);
+    let (result, _) = cpu.step(&mut bus);
+
+    // Supervisor External Interrupt = 0x80000009
+    assert!(matches!(result, StepResult::Trap(0x8000_0009)));
+    assert_eq!(cpu.pc, 0x300);
+    assert_eq!(cpu.csr.read(0x142).unwrap(), 0x8000_0009); // scause
+
+    // S モードのコンテキストから Claim すると SEIP が下がる
+    assert_eq!(bus.plic.claim_context(1), 1);
+    let _ = cpu.step(&mut bus);
+    assert_eq!((cpu.csr.mip >> 9) & 1, 0);
+}
+
+#[test]
+fn test_software_seip_ored_with_plic_context() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = InterruptTestBus::new();
+    bus.plic = Plic::with_config(32, 2);
+    bus.plic.harts[0].supervisor = Some(1);
+
+    // mstatus.MIE = 1 (mie = 0 のため割り込みは受け付けないが、mip は毎回更新される)
+    cpu.csr.write(0x300, 1 << 3).unwrap();
+    cpu.regs[6] = 1 << 1; // SSIP
+
+    bus.mock_bus.write_inst32(0x0, 0x00000013); // NOP
+    bus.mock_bus.write_inst32(0x4, 0x00000013); // NOP
+    bus.mock_bus.write_inst32(0x8, 0x34432073); // csrrs x0, mip, x6
+    bus.mock_bus.write_inst32(0xc, 0x00000013); // NOP
+
+    // PLIC の信号が下がっていても、ソフトウェアが書き込んだ SEIP は残る
+    cpu.csr.write(0x344, 1 << 9).unwrap();
+    assert_eq!(cpu.run(&mut bus, 1), (StopReason::BudgetExhausted, 1));
+    assert_eq!((cpu.csr.mip >> 9) & 1, 1);
+
+    cpu.csr.write(0x344, 0).unwrap();
+    assert_eq!(cpu.run(&mut bus, 1), (StopReason::BudgetExhausted, 1));
+    assert_eq!((cpu.csr.mip >> 9) & 1, 0);
+
+    // PLIC の信号で SEIP が立った状態で read-modify-write しても、ソフトウェアのビットには取り込まない
+    bus.plic.priorities[1] = 1;
+    bus.plic.set_enabled(1, 1, true);
+    bus.plic.set_interrupt(1);
+    assert_eq!(cpu.run(&mut bus, 1), (StopReason::BudgetExhausted, 1));
+    assert_eq!(cpu.csr.mip & 0x202, 0x202); // SEIP, SSIP
+
+    assert_eq!(bus.plic.claim_context(1), 1);
+    assert_eq!(cpu.run(&mut bus, 1), (StopReason::BudgetExhausted, 1));
+    assert_eq!(cpu.csr.mip & 0x202, 0x002);
+}
+
+#[test]
+fn test_aclint_sswi_raises_ssip() {
+    let mut cpu = Cpu::new(0x8000_0000);
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x1000).unwrap();
+    bus.map_clint_region(ClintRegion::Sswi, 0x0200_c000).unwrap();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // mideleg.SSIP = 1, sie.SSIE = 1, sstatus.SIE = 1
+    cpu.csr.write(0x303, 1 << 1).unwrap();
+    cpu.csr.write(0x104, 1 << 1).unwrap();
+    cpu.csr.write(0x100, 1 << 1).unwrap();
+    cpu.csr.write(0x105, 0x8000_0100).unwrap();
+
+    // 0x8000_0000: sw x1, 0(x5)
+    // 0x8000_0004: nop
+    bus.load(0x8000_0000, &[0x23, 0xa0, 0x12, 0x00, 0x13, 0x00, 0x00, 0x00]).unwrap();
+    cpu.regs[1] = 1;
+    cpu.regs[5] = 0x0200_c000;
+
+    // setssip への書き込みで mip.SSIP が立ち、S モードにトラップする
+    let _ = cpu.step(&mut bus);
+    let (result, _) = cpu.step(&mut bus);
+
+    // Supervisor Software Interrupt = 0x80000001
+    assert!(matches!(result, StepResult::Trap(0x8000_0001)));
+    assert_eq!(cpu.pc, 0x8000_0100);
+    assert_eq!(cpu.csr.mip & (1 << 1), 1 << 1);
+
+    // mip.SSIP はソフトウェアが下げるまで残り、下げた後は立ち直らない
+    cpu.csr.mip &= !(1 << 1);
+    let _ = cpu.step(&mut bus);
+    assert_eq!(cpu.csr.mip & (1 << 1), 0);
+}
+
+#[test]
+fn test_supervisor_interrupt_not_taken_in_machine_mode() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = InterruptTestBus::new();
+
+    // mstatus.MIE = 1, mstatus.SIE = 1
+    let _ = cpu.csr.write(0x300, (1 << 3) | (1 << 1));
+    let _ = cpu.csr.write(0x303, 1 << 5);
+    let _ = cpu.csr.write(0x304, 1 << 5);
+    let _ = cpu.csr.write(0x105, 0x300);
+    let _ = cpu.csr.write(0x344, 1 << 5);
+
+    bus.mock_bus.write_inst32(0, 0x00000013); // NOP
+
+    // S モードに委譲された割り込みは M モードでは受け付けない
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Ok(_)));
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_machine_interrupt_taken_in_user_mode_without_mie() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = InterruptTestBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // mstatus.MIE = 0 でも、M モードより低い特権モードでは M レベルの割り込みは常に有効
+    let _ = cpu.csr.write(0x304, 1 << 7);
+    let _ = cpu.csr.write(0x305, 0x200);
+
+    bus.mock_bus.write_inst32(0, 0x00000013); // NOP
+    bus.timer_interrupt = true;
+
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Trap(0x8000_0007)));
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.mode, PrivilegeMode::Machine);
+}
+
+#[test]
+fn test_wfi_wakes_and_takes_interrupt() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = InterruptTestBus::new();
+
+    let _ = cpu.csr.write(0x300, 1 << 3);
+    let _ = cpu.csr.write(0x304, 1 << 7);
+    let _ = cpu.csr.write(0x305, 0x200);
+
+    bus.mock_bus.write_inst32(0, 0x10500073); // WFI
+
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Waiting));
+
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Waiting));
+
+    // タイマー割り込みで再開し、WFI の次の命令を mepc としてトラップする
+    bus.timer_interrupt = true;
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Trap(0x8000_0007)));
+    assert!(!cpu.is_waiting());
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mepc, 0x4);
+}
+
+#[test]
+fn test_wfi_wakes_without_mie() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = InterruptTestBus::new();
+
+    // mstatus.MIE = 0 でも、mie で有効な割り込みが保留されれば WFI から再開する (トラップはしない)
+    let _ = cpu.csr.write(0x304, 1 << 11);
+    let _ = cpu.csr.write(0x305, 0x200);
+
+    bus.mock_bus.write_inst32(0, 0x10500073); // WFI
+    bus.mock_bus.write_inst32(4, 0x00100093); // ADDI x1, x0, 1
+
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Waiting));
+
+    bus.plic.set_enabled(0, 1, true);
+    bus.plic.priorities[1] = 1;
+    bus.plic.set_interrupt(1);
+
+    let (result, _) = cpu.step(&mut bus);
+    assert!(matches!(result, StepResult::Ok(4)));
+    assert!(!cpu.is_waiting());
+    assert_eq!(cpu.regs[1], 1);
+    assert_eq!(cpu.pc, 0x8);
+}
@@ -0,0 +1,148 @@
+use crate::bus::Bus;
+use crate::bus::device::AccessWidth;
+use crate::cpu::{Cpu, StepResult, PAGE_SIZE};
+use crate::cpu::pmp::AccessType;
+
+/// ミスアラインなロード/ストアの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MisalignedAccess {
+    /// アドレス不整列例外 (ロード: 4, ストア: 6) を発生させる
+    Trap,
+    /// ハードウェアサポートのあるコアと同様に、バイト単位のアクセスに分割して実行する
+    #[default]
+    Emulate,
+}
+
+/// ロード/ストア命令から使うメモリアクセス。
+/// アラインメントのチェック、アドレス変換、PMP の検査とバスエラーのトラップへの変換をここで一括して行う。
+/// 失敗した場合は、そのまま命令の結果として返せる `StepResult::Trap` を返す。
+impl Cpu {
+    /// 仮想アドレスを変換し、PMP の検査を通った物理アドレスを返す (ページを跨がないアクセス用)
+    #[inline(always)]
+    pub(crate) fn physical_address<B: Bus>(&mut self, vaddr: u32, size: u32, access: AccessType, bus: &mut B) -> Result<u32, StepResult> {
+        let paddr = self.translate(vaddr, access, bus)?;
+        self.check_pmp(paddr, size, access, vaddr)?;
+        Ok(paddr)
+    }
+
+    /// ミスアラインなアクセスの各バイトの物理アドレスを求める。ページを跨ぐ場合はページごとに変換する
+    fn misaligned_physical_addresses<B: Bus>(&mut self, vaddr: u32, size: u32, access: AccessType, bus: &mut B) -> Result<[u32; 4], StepResult> {
+        let first = size.min(PAGE_SIZE as u32 - (vaddr & (PAGE_SIZE as u32 - 1)));
+        let first_paddr = self.physical_address(vaddr, first, access, bus)?;
+        let second_paddr = if first < size {
+            self.physical_address(vaddr.wrapping_add(first), size - first, access, bus)?
+        } else {
+            0
+        };
+        let mut paddrs = [0; 4];
+        for (i, paddr) in paddrs.iter_mut().enumerate().take(size as usize) {
+            let i = i as u32;
+            *paddr = if i < first { first_paddr + i } else { second_paddr + (i - first) };
+        }
+        Ok(paddrs)
+    }
+
+    #[inline(always)]
+    pub(crate) fn load8<B: Bus>(&mut self, addr: u32, bus: &mut B) -> Result<u8, StepResult> {
+        let paddr = self.physical_address(addr, 1, AccessType::Read, bus)?;
+        bus.read8(paddr).map_err(|_| self.trap_with_value(5, addr))
+    }
+
+    #[inline(always)]
+    pub(crate) fn load16<B: Bus>(&mut self, addr: u32, bus: &mut B) -> Result<u16, StepResult> {
+        if addr & 1 != 0 {
+            return self.load_misaligned(addr, 2, bus).map(|v| v as u16);
+        }
+        let paddr = self.physical_address(addr, 2, AccessType::Read, bus)?;
+        bus.read16(paddr).map_err(|_| self.trap_with_value(5, addr))
+    }
+
+    #[inline(always)]
+    pub(crate) fn load32<B: Bus>(&mut self, addr: u32, bus: &mut B) -> Result<u32, StepResult> {
+        if addr & 3 != 0 {
+            return self.load_misaligned(addr, 4, bus);
+        }
+        let paddr = self.physical_address(addr, 4, AccessType::Read, bus)?;
+        bus.read32(paddr).map_err(|_| self.trap_with_value(5, addr))
+    }
+
+    /// `addr` から `size` バイトへのストアが LR の予約と重なっていれば予約を破棄する
+    #[inline(always)]
+    fn clear_conflicting_reservation(&mut self, addr: u32, size: u32) {
+        if let Some(reserved) = self.reservation
+            && addr < reserved.vaddr.wrapping_add(4)
+            && reserved.vaddr < addr.wrapping_add(size)
+        {
+            self.reservation = None;
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn store8<B: Bus>(&mut self, addr: u32, val: u8, bus: &mut B) -> Result<(), StepResult> {
+        let paddr = self.physical_address(addr, 1, AccessType::Write, bus)?;
+        self.clear_conflicting_reservation(addr, 1);
+        bus.write8(paddr, val).map_err(|_| self.trap_with_value(7, addr))?;
+        self.note_code_write(paddr);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn store16<B: Bus>(&mut self, addr: u32, val: u16, bus: &mut B) -> Result<(), StepResult> {
+        if addr & 1 != 0 {
+            return self.store_misaligned(addr, val as u32, 2, bus);
+        }
+        let paddr = self.physical_address(addr, 2, AccessType::Write, bus)?;
+        self.clear_conflicting_reservation(addr, 2);
+        bus.write16(paddr, val).map_err(|_| self.trap_with_value(7, addr))?;
+        self.note_code_write(paddr);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn store32<B: Bus>(&mut self, addr: u32, val: u32, bus: &mut B) -> Result<(), StepResult> {
+        if addr & 3 != 0 {
+            return self.store_misaligned(addr, val, 4, bus);
+        }
+        let paddr = self.physical_address(addr, 4, AccessType::Write, bus)?;
+        self.clear_conflicting_reservation(addr, 4);
+        bus.write32(paddr, val).map_err(|_| self.trap_with_value(7, addr))?;
+        self.note_code_write(paddr);
+        Ok(())
+    }
+
+    /// ミスアラインなロード。ポリシーに従って例外を発生させるか、バイト単位で読み出す
+    #[cold]
+    fn load_misaligned<B: Bus>(&mut self, addr: u32, size: u32, bus: &mut B) -> Result<u32, StepResult> {
+        if self.misaligned_access == MisalignedAccess::Trap {
+            return Err(self.trap_with_value(4, addr));
+        }
+        let paddrs = self.misaligned_physical_addresses(addr, size, AccessType::Read, bus)?;
+        let mut val = 0;
+        for i in 0..size {
+            let byte = bus.read8(paddrs[i as usize]).map_err(|_| self.trap_with_value(5, addr))?;
+            val |= (byte as u32) << (8 * i);
+        }
+        Ok(val)
+    }
+
+    /// ミスアラインなストア。ポリシーに従って例外を発生させるか、バイト単位で書き込む
+    #[cold]
+    fn store_misaligned<B: Bus>(&mut self, addr: u32, val: u32, size: u32, bus: &mut B) -> Result<(), StepResult> {
+        if self.misaligned_access == MisalignedAccess::Trap {
+            return Err(self.trap_with_value(6, addr));
+        }
+        let paddrs = self.misaligned_physical_addresses(addr, size, AccessType::Write, bus)?;
+        // 途中のバイトだけが書き込まれないよう、書き込む前にすべてのバイトが書き込めることを確かめる
+        for &paddr in &paddrs[..size as usize] {
+            bus.check_write(paddr, AccessWidth::Byte).map_err(|_| self.trap_with_value(7, addr))?;
+        }
+        self.clear_conflicting_reservation(addr, size);
+        for i in 0..size {
+            let byte = (val >> (8 * i)) as u8;
+            bus.write8(paddrs[i as usize], byte).map_err(|_| self.trap_with_value(7, addr))?;
+        }
+        self.note_code_write(paddrs[0]);
+        self.note_code_write(paddrs[size as usize - 1]);
+        Ok(())
+    }
+}
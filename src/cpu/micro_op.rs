@@ -0,0 +1,612 @@
+use crate::bus::Bus;
+use crate::cpu::{Cpu, StepResult, PAGE_SIZE};
+use crate::cpu::instructions::Instruction;
+
+#[cfg(test)]
+mod tests;
+
+/// マイクロ命令を実行するハンドラの種類
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OpKind {
+    Add,
+    Addi,
+    And,
+    Andi,
+    Beq,
+    Bge,
+    Bgeu,
+    Blt,
+    Bltu,
+    Bne,
+    Exec,
+    J,
+    Jal,
+    Jalr,
+    Jr,
+    Lb,
+    Lbu,
+    Lh,
+    Lhu,
+    Li,
+    Lw,
+    Mul,
+    Nop,
+    Or,
+    Ori,
+    Sb,
+    Sh,
+    Sll,
+    Slli,
+    Slt,
+    SltBeqz,
+    SltBnez,
+    Slti,
+    Sltiu,
+    Sltu,
+    SltuBeqz,
+    SltuBnez,
+    Sra,
+    Srai,
+    Srl,
+    Srli,
+    Sub,
+    Sw,
+    Xor,
+    Xori,
+}
+
+impl Cpu {
+    #[inline(always)]
+    pub(crate) fn exec_op<B: Bus>(&mut self, op: &MicroOp, bus: &mut B) -> StepResult {
+        match op.kind {
+            OpKind::Add => op_add(self, op, bus),
+            OpKind::Addi => op_addi(self, op, bus),
+            OpKind::And => op_and(self, op, bus),
+            OpKind::Andi => op_andi(self, op, bus),
+            OpKind::Beq => op_beq(self, op, bus),
+            OpKind::Bge => op_bge(self, op, bus),
+            OpKind::Bgeu => op_bgeu(self, op, bus),
+            OpKind::Blt => op_blt(self, op, bus),
+            OpKind::Bltu => op_bltu(self, op, bus),
+            OpKind::Bne => op_bne(self, op, bus),
+            OpKind::Exec => op_exec(self, op, bus),
+            OpKind::J => op_j(self, op, bus),
+            OpKind::Jal => op_jal(self, op, bus),
+            OpKind::Jalr => op_jalr(self, op, bus),
+            OpKind::Jr => op_jr(self, op, bus),
+            OpKind::Lb => op_lb(self, op, bus),
+            OpKind::Lbu => op_lbu(self, op, bus),
+            OpKind::Lh => op_lh(self, op, bus),
+            OpKind::Lhu => op_lhu(self, op, bus),
+            OpKind::Li => op_li(self, op, bus),
+            OpKind::Lw => op_lw(self, op, bus),
+            OpKind::Mul => op_mul(self, op, bus),
+            OpKind::Nop => op_nop(self, op, bus),
+            OpKind::Or => op_or(self, op, bus),
+            OpKind::Ori => op_ori(self, op, bus),
+            OpKind::Sb => op_sb(self, op, bus),
+            OpKind::Sh => op_sh(self, op, bus),
+            OpKind::Sll => op_sll(self, op, bus),
+            OpKind::Slli => op_slli(self, op, bus),
+            OpKind::Slt => op_slt(self, op, bus),
+            OpKind::SltBeqz => op_slt_beqz(self, op, bus),
+            OpKind::SltBnez => op_slt_bnez(self, op, bus),
+            OpKind::Slti => op_slti(self, op, bus),
+            OpKind::Sltiu => op_sltiu(self, op, bus),
+            OpKind::Sltu => op_sltu(self, op, bus),
+            OpKind::SltuBeqz => op_sltu_beqz(self, op, bus),
+            OpKind::SltuBnez => op_sltu_bnez(self, op, bus),
+            OpKind::Sra => op_sra(self, op, bus),
+            OpKind::Srai => op_srai(self, op, bus),
+            OpKind::Srl => op_srl(self, op, bus),
+            OpKind::Srli => op_srli(self, op, bus),
+            OpKind::Sub => op_sub(self, op, bus),
+            OpKind::Sw => op_sw(self, op, bus),
+            OpKind::Xor => op_xor(self, op, bus),
+            OpKind::Xori => op_xori(self, op, bus),
+        }
+    }
+
+    /// マイクロ命令が実行した命令の数だけ mcycle と minstret を進める
+    #[inline(always)]
+    pub(crate) fn retire_op(&mut self, op: &MicroOp) {
+        self.csr.retire();
+        if op.is_fused() {
+            self.csr.retire();
+        }
+    }
+}
+
+/// 2 つの命令を融合したマイクロ命令であることを表す `regs` のビット
+const FUSED: u16 = 1 << 15;
+
+/// rd, rs1, rs2 を `regs` の形式に詰める
+fn pack_regs(rd: usize, rs1: usize, rs2: usize) -> u16 {
+    (rd as u16 & 0x1f) | (rs1 as u16 & 0x1f) << 5 | (rs2 as u16 & 0x1f) << 10
+}
+
+/// 命令キャッシュに格納する、オペランドを解決済みのマイクロ命令。
+/// 即値は符号拡張済み、分岐・ジャンプ先は絶対アドレス、x0 への書き込みは何もしない命令に置き換えてあり、
+/// `step` は `kind` で選んだハンドラを呼び出すだけで命令を実行できる。
+/// 専用のハンドラを持たない命令は、ページに残してある元の `Instruction` を `Cpu::exec` で実行する。
+/// ディスパッチ中に読むデータを減らすため、8 バイトに収まるようにしている
+#[derive(Clone, Copy)]
+pub(crate) struct MicroOp {
+    pub(crate) kind: OpKind,
+    /// 命令長 (2 または 4)。0 はキャッシュされていない命令を表す
+    size: u8,
+    /// rd, rs1, rs2 を 5 ビットずつ詰めたもの (最上位ビットは `FUSED`)
+    regs: u16,
+    /// 解決済みの即値 (符号拡張した即値、シフト量、分岐・ジャンプ先の絶対アドレス)
+    pub(crate) imm: u32,
+}
+
+impl MicroOp {
+    /// キャッシュされていない命令
+    pub(crate) const NONE: MicroOp = MicroOp {
+        kind: OpKind::Exec,
+        size: 0,
+        regs: 0,
+        imm: 0,
+    };
+
+    /// キャッシュされていない命令か
+    #[inline(always)]
+    pub(crate) fn is_none(&self) -> bool {
+        self.size == 0
+    }
+
+    /// 命令長 (融合した命令は 2 つの命令の合計)
+    #[inline(always)]
+    pub(crate) fn size(&self) -> u32 {
+        self.size as u32
+    }
+
+    /// 2 つの命令を融合したマイクロ命令か
+    #[inline(always)]
+    pub(crate) fn is_fused(&self) -> bool {
+        (self.regs & FUSED) != 0
+    }
+
+    /// 実行する命令の数
+    #[inline(always)]
+    pub(crate) fn count(&self) -> u32 {
+        1 + self.is_fused() as u32
+    }
+
+    // レジスタ番号は 5 ビットずつ取り出すため、レジスタファイルの境界チェックは不要になる
+
+    #[inline(always)]
+    fn rd(&self) -> usize {
+        (self.regs & 0x1f) as usize
+    }
+
+    #[inline(always)]
+    fn rs1(&self) -> usize {
+        ((self.regs >> 5) & 0x1f) as usize
+    }
+
+    #[inline(always)]
+    fn rs2(&self) -> usize {
+        ((self.regs >> 10) & 0x1f) as usize
+    }
+
+    /// 仮想アドレス `pc` に置かれた命令 `inst` のオペランドを解決する
+    pub(crate) fn new(inst: Instruction, pc: u32) -> MicroOp {
+        let size = if inst.is_compressed() { 2 } else { 4 };
+        let op = |kind: OpKind, rd: u8, rs1: u8, rs2: u8, imm: u32| MicroOp {
+            kind,
+            size,
+            regs: pack_regs(rd as usize, rs1 as usize, rs2 as usize),
+            imm,
+        };
+        let nop = op(OpKind::Nop, 0, 0, 0, 0);
+        let exec = op(OpKind::Exec, 0, 0, 0, 0);
+        let sext = |imm: u16| imm as i16 as i32 as u32;
+        let target = |offset: u32| pc.wrapping_add(offset);
+
+        match inst {
+            // 即値のロード (auipc は pc を加えた値を、c.lui はシフト済みの値を保持する)
+            Instruction::Lui   { rd: 0, .. } | Instruction::Auipc { rd: 0, .. } => nop,
+            Instruction::CLui  { rd: 0, .. } | Instruction::CLi   { rd: 0, .. } => nop,
+            Instruction::Lui   { rd, imm } => op(OpKind::Li, rd, 0, 0, imm),
+            Instruction::Auipc { rd, imm } => op(OpKind::Li, rd, 0, 0, target(imm)),
+            Instruction::CLui  { rd, imm } => op(OpKind::Li, rd, 0, 0, (imm << 12) as u32),
+            Instruction::CLi   { rd, imm } => op(OpKind::Li, rd, 0, 0, imm as i32 as u32),
+
+            // ジャンプ
+            Instruction::Jal  { rd: 0, imm } => op(OpKind::J, 0, 0, 0, target(imm)),
+            Instruction::Jal  { rd, imm }    => op(OpKind::Jal, rd, 0, 0, target(imm)),
+            Instruction::CJ   { imm }        => op(OpKind::J, 0, 0, 0, target(imm as i32 as u32)),
+            Instruction::CJal { imm, .. }    => op(OpKind::Jal, 1, 0, 0, target(imm as i32 as u32)),
+            Instruction::Jalr { rd: 0, rs1, imm } => op(OpKind::Jr, 0, rs1, 0, sext(imm)),
+            Instruction::Jalr { rd, rs1, imm }    => op(OpKind::Jalr, rd, rs1, 0, sext(imm)),
+            Instruction::CJr   { rs1 } if rs1 != 0 => op(OpKind::Jr, 0, rs1, 0, 0),
+            Instruction::CJalr { rs1 } if rs1 != 0 => op(OpKind::Jalr, 1, rs1, 0, 0),
+
+            // 分岐
+            Instruction::Beq   { rs1, rs2, imm } => op(OpKind::Beq, 0, rs1, rs2, target(sext(imm))),
+            Instruction::Bne   { rs1, rs2, imm } => op(OpKind::Bne, 0, rs1, rs2, target(sext(imm))),
+            Instruction::Blt   { rs1, rs2, imm } => op(OpKind::Blt, 0, rs1, rs2, target(sext(imm))),
+            Instruction::Bge   { rs1, rs2, imm } => op(OpKind::Bge, 0, rs1, rs2, target(sext(imm))),
+            Instruction::Bltu  { rs1, rs2, imm } => op(OpKind::Bltu, 0, rs1, rs2, target(sext(imm))),
+            Instruction::Bgeu  { rs1, rs2, imm } => op(OpKind::Bgeu, 0, rs1, rs2, target(sext(imm))),
+            Instruction::CBeqz { rs1, imm }      => op(OpKind::Beq, 0, rs1, 0, target(imm as i32 as u32)),
+            Instruction::CBnez { rs1, imm }      => op(OpKind::Bne, 0, rs1, 0, target(imm as i32 as u32)),
+
+            // ロード (x0 へのロードも例外は起こりうるため Cpu::exec で実行する)
+            Instruction::Lb  { rd, rs1, imm } if rd != 0 => op(OpKind::Lb, rd, rs1, 0, sext(imm)),
+            Instruction::Lh  { rd, rs1, imm } if rd != 0 => op(OpKind::Lh, rd, rs1, 0, sext(imm)),
+            Instruction::Lw  { rd, rs1, imm } if rd != 0 => op(OpKind::Lw, rd, rs1, 0, sext(imm)),
+            Instruction::Lbu { rd, rs1, imm } if rd != 0 => op(OpKind::Lbu, rd, rs1, 0, sext(imm)),
+            Instruction::Lhu { rd, rs1, imm } if rd != 0 => op(OpKind::Lhu, rd, rs1, 0, sext(imm)),
+            Instruction::CLw   { rd, rs1, imm } => op(OpKind::Lw, rd, rs1, 0, imm as u32),
+            Instruction::CLwsp { rd, imm } if rd != 0 => op(OpKind::Lw, rd, 2, 0, imm as u32),
+
+            // ストア
+            Instruction::Sb    { rs1, rs2, imm } => op(OpKind::Sb, 0, rs1, rs2, sext(imm)),
+            Instruction::Sh    { rs1, rs2, imm } => op(OpKind::Sh, 0, rs1, rs2, sext(imm)),
+            Instruction::Sw    { rs1, rs2, imm } => op(OpKind::Sw, 0, rs1, rs2, sext(imm)),
+            Instruction::CSw   { rs1, rs2, imm } => op(OpKind::Sw, 0, rs1, rs2, imm as u32),
+            Instruction::CSwsp { rs2, imm }      => op(OpKind::Sw, 0, 2, rs2, imm as u32),
+
+            // 即値演算
+            Instruction::Addi  { rd: 0, .. } | Instruction::Slti  { rd: 0, .. } |
+            Instruction::Sltiu { rd: 0, .. } | Instruction::Xori  { rd: 0, .. } |
+            Instruction::Ori   { rd: 0, .. } | Instruction::Andi  { rd: 0, .. } |
+            Instruction::Slli  { rd: 0, .. } | Instruction::Srli  { rd: 0, .. } |
+            Instruction::Srai  { rd: 0, .. } | Instruction::CAddi { rd: 0, .. } |
+            Instruction::CSlli { rd: 0, .. } => nop,
+            Instruction::Addi  { rd, rs1, imm }   => op(OpKind::Addi, rd, rs1, 0, sext(imm)),
+            Instruction::Slti  { rd, rs1, imm }   => op(OpKind::Slti, rd, rs1, 0, sext(imm)),
+            Instruction::Sltiu { rd, rs1, imm }   => op(OpKind::Sltiu, rd, rs1, 0, sext(imm)),
+            Instruction::Xori  { rd, rs1, imm }   => op(OpKind::Xori, rd, rs1, 0, sext(imm)),
+            Instruction::Ori   { rd, rs1, imm }   => op(OpKind::Ori, rd, rs1, 0, sext(imm)),
+            Instruction::Andi  { rd, rs1, imm }   => op(OpKind::Andi, rd, rs1, 0, sext(imm)),
+            Instruction::Slli  { rd, rs1, shamt } => op(OpKind::Slli, rd, rs1, 0, shamt as u32),
+            Instruction::Srli  { rd, rs1, shamt } => op(OpKind::Srli, rd, rs1, 0, shamt as u32),
+            Instruction::Srai  { rd, rs1, shamt } => op(OpKind::Srai, rd, rs1, 0, shamt as u32),
+            Instruction::CAddi { rd, imm }        => op(OpKind::Addi, rd, rd, 0, imm as i32 as u32),
+            Instruction::CAddi16Sp { imm, .. } if imm != 0 => op(OpKind::Addi, 2, 2, 0, imm as i32 as u32),
+            Instruction::CAddi4spn { rd, imm, .. } if imm != 0 => op(OpKind::Addi, rd, 2, 0, imm as u32),
+            Instruction::Candi { rd, imm }   => op(OpKind::Andi, rd, rd, 0, imm as i32 as u32),
+            Instruction::CSlli { rd, shamt } => op(OpKind::Slli, rd, rd, 0, shamt as u32),
+            Instruction::CSrli { rd, shamt } => op(OpKind::Srli, rd, rd, 0, shamt as u32),
+            Instruction::Csrai { rd, shamt } => op(OpKind::Srai, rd, rd, 0, shamt as u32),
+
+            // レジスタ間演算
+            Instruction::Add  { rd: 0, .. } | Instruction::Sub  { rd: 0, .. } |
+            Instruction::Sll  { rd: 0, .. } | Instruction::Slt  { rd: 0, .. } |
+            Instruction::Sltu { rd: 0, .. } | Instruction::Xor  { rd: 0, .. } |
+            Instruction::Srl  { rd: 0, .. } | Instruction::Sra  { rd: 0, .. } |
+            Instruction::Or   { rd: 0, .. } | Instruction::And  { rd: 0, .. } |
+            Instruction::Mul  { rd: 0, .. } => nop,
+            Instruction::Add  { rd, rs1, rs2 } => op(OpKind::Add, rd, rs1, rs2, 0),
+            Instruction::Sub  { rd, rs1, rs2 } => op(OpKind::Sub, rd, rs1, rs2, 0),
+            Instruction::Sll  { rd, rs1, rs2 } => op(OpKind::Sll, rd, rs1, rs2, 0),
+            Instruction::Slt  { rd, rs1, rs2 } => op(OpKind::Slt, rd, rs1, rs2, 0),
+            Instruction::Sltu { rd, rs1, rs2 } => op(OpKind::Sltu, rd, rs1, rs2, 0),
+            Instruction::Xor  { rd, rs1, rs2 } => op(OpKind::Xor, rd, rs1, rs2, 0),
+            Instruction::Srl  { rd, rs1, rs2 } => op(OpKind::Srl, rd, rs1, rs2, 0),
+            Instruction::Sra  { rd, rs1, rs2 } => op(OpKind::Sra, rd, rs1, rs2, 0),
+            Instruction::Or   { rd, rs1, rs2 } => op(OpKind::Or, rd, rs1, rs2, 0),
+            Instruction::And  { rd, rs1, rs2 } => op(OpKind::And, rd, rs1, rs2, 0),
+            Instruction::Mul  { rd, rs1, rs2 } => op(OpKind::Mul, rd, rs1, rs2, 0),
+            Instruction::CSub { rd, rs2 } => op(OpKind::Sub, rd, rd, rs2, 0),
+            Instruction::CXor { rd, rs2 } => op(OpKind::Xor, rd, rd, rs2, 0),
+            Instruction::Cor  { rd, rs2 } => op(OpKind::Or, rd, rd, rs2, 0),
+            Instruction::Cand { rd, rs2 } => op(OpKind::And, rd, rd, rs2, 0),
+            Instruction::CMv  { rd, rs2 } if rd != 0 && rs2 != 0 => op(OpKind::Add, rd, 0, rs2, 0),
+            Instruction::CAdd { rd, rs2 } if rd != 0 => op(OpKind::Add, rd, rd, rs2, 0),
+
+            Instruction::Fence => nop,
+
+            _ => exec,
+        }
+    }
+
+    /// 連続する命令 `first` と `second` がよく使われる組であれば、両方の効果をまとめて実行する 1 つのマイクロ命令に融合する。
+    /// 融合した命令はトラップを起こさないため、前半だけが実行された状態を外から観測されることはない
+    pub(crate) fn fuse(first: MicroOp, second: MicroOp) -> Option<MicroOp> {
+        if first.is_fused() || second.is_fused() {
+            return None;
+        }
+        let rd = first.rd();
+        // 後半の命令が前半の結果だけを使い、同じレジスタに書き戻すか
+        let chained = second.rd() == rd && second.rs1() == rd;
+        let fused = |kind: OpKind, rs1: usize, rs2: usize, imm: u32| MicroOp {
+            kind,
+            size: first.size + second.size,
+            regs: pack_regs(rd, rs1, rs2) | FUSED,
+            imm,
+        };
+
+        match (first.kind, second.kind) {
+            // lui/auipc + addi: 32 ビット定数やアドレスの生成
+            (OpKind::Li, OpKind::Addi) if chained => {
+                Some(fused(OpKind::Li, 0, 0, first.imm.wrapping_add(second.imm)))
+            }
+            // lui/auipc + jalr: 離れた関数の呼び出し (リンク先の rd は jalr の結果で上書きされる)
+            (OpKind::Li, OpKind::Jalr) if chained => {
+                Some(fused(OpKind::Jal, 0, 0, first.imm.wrapping_add(second.imm) & !1))
+            }
+            // slli + srli (同じシフト量): ゼロ拡張
+            (OpKind::Slli, OpKind::Srli) if chained && first.imm == second.imm => {
+                Some(fused(OpKind::Andi, first.rs1(), 0, u32::MAX >> first.imm))
+            }
+            // slt/sltu + beqz/bnez: 比較結果を rd に残したまま分岐する
+            (OpKind::Slt | OpKind::Sltu, OpKind::Beq | OpKind::Bne)
+                if (second.rs1() == rd && second.rs2() == 0) || (second.rs1() == 0 && second.rs2() == rd) =>
+            {
+                let kind = match (first.kind, second.kind) {
+                    (OpKind::Slt, OpKind::Beq) => OpKind::SltBeqz,
+                    (OpKind::Slt, _)           => OpKind::SltBnez,
+                    (_, OpKind::Beq)           => OpKind::SltuBeqz,
+                    _                          => OpKind::SltuBnez,
+                };
+                Some(fused(kind, first.rs1(), first.rs2(), second.imm))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 専用のハンドラを持たない命令を、ページに残してある元の命令から `Cpu::exec` で実行する
+#[inline(always)]
+fn op_exec<B: Bus>(cpu: &mut Cpu, _op: &MicroOp, bus: &mut B) -> StepResult {
+    let entry_idx = ((cpu.pc & (PAGE_SIZE as u32 - 1)) >> 1) as usize;
+    let inst = cpu.icache.page(cpu.current_slot).insts[entry_idx];
+    let result = cpu.exec(inst, bus);
+    cpu.regs[0] = 0; // レジスタ 0 は常に 0 に保つ.
+    result
+}
+
+#[inline(always)]
+fn op_nop<B: Bus>(_cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    StepResult::Ok(op.size())
+}
+
+#[inline(always)]
+fn op_li<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    cpu.regs[op.rd()] = op.imm;
+    StepResult::Ok(op.size())
+}
+
+#[inline(always)]
+fn op_j<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    cpu.pc = op.imm;
+    StepResult::Jumped
+}
+
+#[inline(always)]
+fn op_jal<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    cpu.regs[op.rd()] = cpu.pc.wrapping_add(op.size());
+    cpu.pc = op.imm;
+    StepResult::Jumped
+}
+
+#[inline(always)]
+fn op_jr<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    cpu.pc = cpu.regs[op.rs1()].wrapping_add(op.imm) & !1;
+    StepResult::Jumped
+}
+
+#[inline(always)]
+fn op_jalr<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let target = cpu.regs[op.rs1()].wrapping_add(op.imm) & !1;
+    cpu.regs[op.rd()] = cpu.pc.wrapping_add(op.size());
+    cpu.pc = target;
+    StepResult::Jumped
+}
+
+/// 条件が成り立てば解決済みの分岐先へジャンプする
+#[inline(always)]
+fn branch(cpu: &mut Cpu, op: &MicroOp, taken: bool) -> StepResult {
+    if taken {
+        cpu.pc = op.imm;
+        StepResult::Jumped
+    } else {
+        StepResult::Ok(op.size())
+    }
+}
+
+#[inline(always)]
+fn op_beq<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let taken = cpu.regs[op.rs1()] == cpu.regs[op.rs2()];
+    branch(cpu, op, taken)
+}
+
+#[inline(always)]
+fn op_bne<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let taken = cpu.regs[op.rs1()] != cpu.regs[op.rs2()];
+    branch(cpu, op, taken)
+}
+
+#[inline(always)]
+fn op_blt<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let taken = (cpu.regs[op.rs1()] as i32) < (cpu.regs[op.rs2()] as i32);
+    branch(cpu, op, taken)
+}
+
+#[inline(always)]
+fn op_bge<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let taken = (cpu.regs[op.rs1()] as i32) >= (cpu.regs[op.rs2()] as i32);
+    branch(cpu, op, taken)
+}
+
+#[inline(always)]
+fn op_bltu<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let taken = cpu.regs[op.rs1()] < cpu.regs[op.rs2()];
+    branch(cpu, op, taken)
+}
+
+#[inline(always)]
+fn op_bgeu<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let taken = cpu.regs[op.rs1()] >= cpu.regs[op.rs2()];
+    branch(cpu, op, taken)
+}
+
+/// 比較結果を rd に書き込み、結果が `branch_if` と等しければ分岐する
+#[inline(always)]
+fn set_and_branch(cpu: &mut Cpu, op: &MicroOp, set: bool, branch_if: bool) -> StepResult {
+    cpu.regs[op.rd()] = set as u32;
+    branch(cpu, op, set == branch_if)
+}
+
+#[inline(always)]
+fn op_slt_beqz<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let set = (cpu.regs[op.rs1()] as i32) < (cpu.regs[op.rs2()] as i32);
+    set_and_branch(cpu, op, set, false)
+}
+
+#[inline(always)]
+fn op_slt_bnez<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let set = (cpu.regs[op.rs1()] as i32) < (cpu.regs[op.rs2()] as i32);
+    set_and_branch(cpu, op, set, true)
+}
+
+#[inline(always)]
+fn op_sltu_beqz<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let set = cpu.regs[op.rs1()] < cpu.regs[op.rs2()];
+    set_and_branch(cpu, op, set, false)
+}
+
+#[inline(always)]
+fn op_sltu_bnez<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+    let set = cpu.regs[op.rs1()] < cpu.regs[op.rs2()];
+    set_and_branch(cpu, op, set, true)
+}
+
+/// ロードした値を rd に書き込む
+#[inline(always)]
+fn load(cpu: &mut Cpu, op: &MicroOp, val: Result<u32, StepResult>) -> StepResult {
+    match val {
+        Ok(v) => {
+            cpu.regs[op.rd()] = v;
+            StepResult::Ok(op.size())
+        }
+        Err(trap) => trap,
+    }
+}
+
+#[inline(always)]
+fn op_lb<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let val = cpu.load8(addr, bus).map(|v| v as i8 as i32 as u32);
+    load(cpu, op, val)
+}
+
+#[inline(always)]
+fn op_lh<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let val = cpu.load16(addr, bus).map(|v| v as i16 as i32 as u32);
+    load(cpu, op, val)
+}
+
+#[inline(always)]
+fn op_lw<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let val = cpu.load32(addr, bus);
+    load(cpu, op, val)
+}
+
+#[inline(always)]
+fn op_lbu<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let val = cpu.load8(addr, bus).map(|v| v as u32);
+    load(cpu, op, val)
+}
+
+#[inline(always)]
+fn op_lhu<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let val = cpu.load16(addr, bus).map(|v| v as u32);
+    load(cpu, op, val)
+}
+
+/// ストアの結果を命令の結果に変換する
+#[inline(always)]
+fn store(op: &MicroOp, result: Result<(), StepResult>) -> StepResult {
+    match result {
+        Ok(()) => StepResult::Ok(op.size()),
+        Err(trap) => trap,
+    }
+}
+
+#[inline(always)]
+fn op_sb<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let result = cpu.store8(addr, cpu.regs[op.rs2()] as u8, bus);
+    store(op, result)
+}
+
+#[inline(always)]
+fn op_sh<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let result = cpu.store16(addr, cpu.regs[op.rs2()] as u16, bus);
+    store(op, result)
+}
+
+#[inline(always)]
+fn op_sw<B: Bus>(cpu: &mut Cpu, op: &MicroOp, bus: &mut B) -> StepResult {
+    let addr = cpu.regs[op.rs1()].wrapping_add(op.imm);
+    let result = cpu.store32(addr, cpu.regs[op.rs2()], bus);
+    store(op, result)
+}
+
+/// rs1 と解決済みの即値から rd を計算する命令のハンドラを定義する
+macro_rules! imm_ops {
+    ($($name:ident => |$a:ident, $b:ident| $body:expr;)*) => {
+        $(
+            #[inline(always)]
+            fn $name<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+                let $a = cpu.regs[op.rs1()];
+                let $b = op.imm;
+                cpu.regs[op.rd()] = $body;
+                StepResult::Ok(op.size())
+            }
+        )*
+    };
+}
+
+/// rs1 と rs2 から rd を計算する命令のハンドラを定義する
+macro_rules! reg_ops {
+    ($($name:ident => |$a:ident, $b:ident| $body:expr;)*) => {
+        $(
+            #[inline(always)]
+            fn $name<B: Bus>(cpu: &mut Cpu, op: &MicroOp, _bus: &mut B) -> StepResult {
+                let $a = cpu.regs[op.rs1()];
+                let $b = cpu.regs[op.rs2()];
+                cpu.regs[op.rd()] = $body;
+                StepResult::Ok(op.size())
+            }
+        )*
+    };
+}
+
+imm_ops! {
+    op_addi  => |a, imm| a.wrapping_add(imm);
+    op_slti  => |a, imm| ((a as i32) < (imm as i32)) as u32;
+    op_sltiu => |a, imm| (a < imm) as u32;
+    op_xori  => |a, imm| a ^ imm;
+    op_ori   => |a, imm| a | imm;
+    op_andi  => |a, imm| a & imm;
+    op_slli  => |a, shamt| a << shamt;
+    op_srli  => |a, shamt| a >> shamt;
+    op_srai  => |a, shamt| ((a as i32) >> shamt) as u32;
+}
+
+reg_ops! {
+    op_add  => |a, b| a.wrapping_add(b);
+    op_sub  => |a, b| a.wrapping_sub(b);
+    op_sll  => |a, b| a << (b & 0x1f);
+    op_slt  => |a, b| ((a as i32) < (b as i32)) as u32;
+    op_sltu => |a, b| (a < b) as u32;
+    op_xor  => |a, b| a ^ b;
+    op_srl  => |a, b| a >> (b & 0x1f);
+    op_sra  => |a, b| ((a as i32) >> (b & 0x1f)) as u32;
+    op_or   => |a, b| a | b;
+    op_and  => |a, b| a & b;
+    op_mul  => |a, b| a.wrapping_mul(b);
+}
@@ -0,0 +1,205 @@
+use crate::cpu::Cpu;
+use crate::cpu::instructions::Instruction;
+use crate::cpu::micro_op::{MicroOp, OpKind};
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_micro_op_sign_extends_immediates() {
+    // addi x1, x2, -1
+    let op = MicroOp::new(Instruction::Addi { rd: 1, rs1: 2, imm: 0xffff }, 0x0);
+    assert_eq!(op.kind, OpKind::Addi);
+    assert_eq!((op.rd(), op.rs1()), (1, 2));
+    assert_eq!(op.imm, 0xffff_ffff);
+    assert_eq!(op.size(), 4);
+
+    // sw x3, -4(x4)
+    let op = MicroOp::new(Instruction::Sw { rs2: 3, rs1: 4, imm: 0xfffc }, 0x0);
+    assert_eq!(op.kind, OpKind::Sw);
+    assert_eq!((op.rs1(), op.rs2()), (4, 3));
+    assert_eq!(op.imm, 0xffff_fffc);
+
+    // c.li x5, -2 は 2 バイトの li になる
+    let op = MicroOp::new(Instruction::CLi { rd: 5, imm: -2 }, 0x0);
+    assert_eq!(op.kind, OpKind::Li);
+    assert_eq!(op.imm, 0xffff_fffe);
+    assert_eq!(op.size(), 2);
+}
+
+#[test]
+fn test_micro_op_resolves_targets() {
+    // 0x1000: beq x1, x2, -8
+    let op = MicroOp::new(Instruction::Beq { rs1: 1, rs2: 2, imm: 0xfff8 }, 0x1000);
+    assert_eq!(op.kind, OpKind::Beq);
+    assert_eq!(op.imm, 0xff8);
+
+    // 0x1000: jal x1, 0x100
+    let op = MicroOp::new(Instruction::Jal { rd: 1, imm: 0x100 }, 0x1000);
+    assert_eq!(op.kind, OpKind::Jal);
+    assert_eq!(op.imm, 0x1100);
+
+    // 0x1000: c.beqz x8, -4
+    let op = MicroOp::new(Instruction::CBeqz { rs1: 8, imm: -4 }, 0x1000);
+    assert_eq!(op.kind, OpKind::Beq);
+    assert_eq!((op.rs1(), op.rs2()), (8, 0));
+    assert_eq!(op.imm, 0xffc);
+    assert_eq!(op.size(), 2);
+
+    // auipc は pc を加えた値を設定する li になる
+    let op = MicroOp::new(Instruction::Auipc { rd: 3, imm: 0x1234_5000 }, 0x8000_0000);
+    assert_eq!(op.kind, OpKind::Li);
+    assert_eq!(op.imm, 0x9234_5000);
+}
+
+#[test]
+fn test_micro_op_x0_writes_become_nop() {
+    let op = MicroOp::new(Instruction::Addi { rd: 0, rs1: 0, imm: 0 }, 0x0);
+    assert_eq!(op.kind, OpKind::Nop);
+    let op = MicroOp::new(Instruction::Add { rd: 0, rs1: 1, rs2: 2 }, 0x0);
+    assert_eq!(op.kind, OpKind::Nop);
+
+    // jal x0 はリンクしないジャンプになる
+    let op = MicroOp::new(Instruction::Jal { rd: 0, imm: 0x10 }, 0x0);
+    assert_eq!(op.kind, OpKind::J);
+
+    // x0 へのロードもアクセス例外は起こりうるため、元の命令のまま実行する
+    let op = MicroOp::new(Instruction::Lw { rd: 0, rs1: 1, imm: 0 }, 0x0);
+    assert_eq!(op.kind, OpKind::Exec);
+}
+
+#[test]
+fn test_micro_op_execution_keeps_x0_zero() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0: addi x0, x0, 5
+    // 0x4: lui  x0, 0x12345
+    // 0x8: addi x1, x0, -3
+    // 0xc: csrrs x0, mstatus, x0 (専用のハンドラを持たない命令)
+    bus.write_inst32(0x0, 0x00500013);
+    bus.write_inst32(0x4, 0x12345037);
+    bus.write_inst32(0x8, 0xffd00093);
+    bus.write_inst32(0xc, 0x30002073);
+
+    let (_, executed) = cpu.run(&mut bus, 4);
+    assert_eq!(executed, 4);
+    assert_eq!(cpu.regs[0], 0);
+    assert_eq!(cpu.regs[1], 0xffff_fffd);
+    assert_eq!(cpu.pc, 0x10);
+}
+
+#[test]
+fn test_micro_op_fuse_requires_chained_registers() {
+    // lui a0, 0x12345 / addi a0, a0, 0x678
+    let lui = MicroOp::new(Instruction::Lui { rd: 10, imm: 0x1234_5000 }, 0x0);
+    let addi = MicroOp::new(Instruction::Addi { rd: 10, rs1: 10, imm: 0x678 }, 0x4);
+    let fused = MicroOp::fuse(lui, addi).unwrap();
+    assert_eq!(fused.kind, OpKind::Li);
+    assert_eq!(fused.imm, 0x1234_5678);
+    assert_eq!(fused.size(), 8);
+    assert_eq!(fused.count(), 2);
+
+    // 後半が別のレジスタに書き込む場合は融合しない
+    let addi = MicroOp::new(Instruction::Addi { rd: 11, rs1: 10, imm: 0x678 }, 0x4);
+    assert!(MicroOp::fuse(lui, addi).is_none());
+
+    // シフト量が異なる slli + srli はゼロ拡張ではないため融合しない
+    let slli = MicroOp::new(Instruction::Slli { rd: 10, rs1: 11, shamt: 16 }, 0x0);
+    let srli = MicroOp::new(Instruction::Srli { rd: 10, rs1: 10, shamt: 8 }, 0x4);
+    assert!(MicroOp::fuse(slli, srli).is_none());
+}
+
+#[test]
+fn test_micro_op_fused_lui_addi() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0: lui  a0, 0x12345
+    // 0x4: addi a0, a0, 0x678
+    // 0x8: slli a1, a0, 16
+    // 0xc: srli a1, a1, 16
+    bus.write_inst32(0x0, 0x12345537);
+    bus.write_inst32(0x4, 0x67850513);
+    bus.write_inst32(0x8, 0x01051593);
+    bus.write_inst32(0xc, 0x0105d593);
+
+    let (_, executed) = cpu.run(&mut bus, 4);
+    assert_eq!(executed, 4);
+    assert_eq!(cpu.regs[10], 0x1234_5678);
+    assert_eq!(cpu.regs[11], 0x0000_5678);
+    assert_eq!(cpu.pc, 0x10);
+    // 融合した命令も 1 命令ずつ数える
+    assert_eq!(cpu.csr.minstret, 4);
+}
+
+#[test]
+fn test_micro_op_fused_pair_single_step() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0: lui  a0, 0x12345
+    // 0x4: addi a0, a0, 0x678
+    bus.write_inst32(0x0, 0x12345537);
+    bus.write_inst32(0x4, 0x67850513);
+
+    // 1 命令ずつ実行すると、前半の lui だけが実行された状態を観測できる
+    let (_, executed) = cpu.step_one(&mut bus);
+    assert_eq!(executed, 1);
+    assert_eq!(cpu.regs[10], 0x1234_5000);
+    assert_eq!(cpu.pc, 0x4);
+    assert_eq!(cpu.csr.minstret, 1);
+
+    let (_, executed) = cpu.step_one(&mut bus);
+    assert_eq!(executed, 1);
+    assert_eq!(cpu.regs[10], 0x1234_5678);
+    assert_eq!(cpu.pc, 0x8);
+    assert_eq!(cpu.csr.minstret, 2);
+
+    // 残りの命令数が 1 の場合も前半だけを実行する
+    cpu.pc = 0x0;
+    let (_, executed) = cpu.run(&mut bus, 1);
+    assert_eq!(executed, 1);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_micro_op_fused_auipc_jalr() {
+    let mut cpu = Cpu::new(0x1000);
+    let mut bus = MockBus::new();
+
+    // 0x1000: auipc ra, 0x1
+    // 0x1004: jalr  ra, -16(ra)
+    bus.write_inst32(0x1000, 0x00001097);
+    bus.write_inst32(0x1004, 0xff0080e7);
+
+    let (_, executed) = cpu.run(&mut bus, 2);
+    assert_eq!(executed, 2);
+    assert_eq!(cpu.pc, 0x1ff0);
+    assert_eq!(cpu.regs[1], 0x1008);
+    assert_eq!(cpu.csr.minstret, 2);
+}
+
+#[test]
+fn test_micro_op_fused_compare_and_branch() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.regs[11] = 1;
+    cpu.regs[12] = 2;
+
+    // 0x0: sltu t0, a1, a2
+    // 0x4: bnez t0, 0x100
+    bus.write_inst32(0x0, 0x00c5b2b3);
+    bus.write_inst32(0x4, 0x0e029e63);
+
+    let (_, executed) = cpu.run(&mut bus, 2);
+    assert_eq!(executed, 2);
+    assert_eq!(cpu.regs[5], 1);
+    assert_eq!(cpu.pc, 0x100);
+
+    // 比較が成り立たなければ分岐しない
+    cpu.pc = 0x0;
+    cpu.regs[11] = 3;
+    let (_, executed) = cpu.run(&mut bus, 2);
+    assert_eq!(executed, 2);
+    assert_eq!(cpu.regs[5], 0);
+    assert_eq!(cpu.pc, 0x8);
+}
@@ -0,0 +1,248 @@
+use crate::bus::Bus;
+use crate::cpu::{Cpu, StepResult};
+use crate::cpu::csr::{MSTATUS_MPRV, MSTATUS_MXR, MSTATUS_SUM, SATP_MODE, SATP_PPN};
+use crate::cpu::pmp::AccessType;
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[cfg(test)]
+mod tests;
+
+// PTE のビット定義
+const PTE_V: u32 = 1 << 0;
+const PTE_R: u32 = 1 << 1;
+const PTE_W: u32 = 1 << 2;
+const PTE_X: u32 = 1 << 3;
+const PTE_U: u32 = 1 << 4;
+const PTE_A: u32 = 1 << 6;
+const PTE_D: u32 = 1 << 7;
+
+/// TLB のエントリ数 (VPN の下位ビットで引くダイレクトマップ)
+const TLB_SIZE: usize = 64;
+
+/// TLB のエントリ。メガページも 4KB 単位に分割して保持する
+#[derive(Debug, Clone, Copy, Default)]
+struct TlbEntry {
+    valid: bool,
+    /// 仮想ページ番号 (vaddr[31:12])
+    vpn: u32,
+    /// 物理ページ番号 (paddr[31:12])
+    ppn: u32,
+    /// PTE の下位 8 ビット (V/R/W/X/U/G/A/D)
+    flags: u32,
+    /// メガページ (4MB) から作られたエントリか
+    megapage: bool,
+}
+
+/// アドレス変換の結果を保持する TLB
+pub(crate) struct Tlb {
+    entries: [TlbEntry; TLB_SIZE],
+}
+
+impl Tlb {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: [TlbEntry::default(); TLB_SIZE],
+        }
+    }
+
+    #[inline(always)]
+    fn lookup(&self, vpn: u32) -> Option<TlbEntry> {
+        let entry = self.entries[vpn as usize % TLB_SIZE];
+        if entry.valid && entry.vpn == vpn {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, entry: TlbEntry) {
+        self.entries[entry.vpn as usize % TLB_SIZE] = entry;
+    }
+
+    /// すべてのエントリを無効化する
+    pub(crate) fn flush(&mut self) {
+        self.entries.iter_mut().for_each(|e| e.valid = false);
+    }
+
+    /// `vpn` を含むページのエントリを無効化する (メガページは同じ 4MB 領域のエントリをすべて)
+    pub(crate) fn flush_page(&mut self, vpn: u32) {
+        for e in self.entries.iter_mut() {
+            if e.vpn == vpn || (e.megapage && (e.vpn >> 10) == (vpn >> 10)) {
+                e.valid = false;
+            }
+        }
+    }
+}
+
+impl AccessType {
+    /// アドレス変換に失敗したときのページフォールトの例外コード
+    #[inline(always)]
+    fn page_fault_code(self) -> u32 {
+        match self {
+            AccessType::Read    => 13,
+            AccessType::Write   => 15,
+            AccessType::Execute => 12,
+        }
+    }
+}
+
+impl Cpu {
+    /// アクセスの実効特権モード。
+    /// M モードで mstatus.MPRV が立っている場合、ロード/ストアは MPP のモードで行う
+    #[inline(always)]
+    pub(crate) fn effective_mode(&self, access: AccessType) -> PrivilegeMode {
+        if access != AccessType::Execute
+            && self.mode == PrivilegeMode::Machine
+            && (self.csr.mstatus & MSTATUS_MPRV) != 0
+        {
+            match (self.csr.mstatus >> 11) & 0b11 {
+                0 => PrivilegeMode::User,
+                1 => PrivilegeMode::Supervisor,
+                _ => PrivilegeMode::Machine,
+            }
+        } else {
+            self.mode
+        }
+    }
+
+    /// `mode` でのアクセスにアドレス変換が必要か (satp.MODE = Sv32 かつ M モード以外)
+    #[inline(always)]
+    pub(crate) fn translation_active(&self, mode: PrivilegeMode) -> bool {
+        (self.csr.satp & SATP_MODE) != 0 && mode != PrivilegeMode::Machine
+    }
+
+    /// 仮想アドレスを物理アドレスに変換する。
+    /// TLB にヒットすればそのまま、ミスした場合はページテーブルをたどる
+    #[inline(always)]
+    pub(crate) fn translate<B: Bus>(&mut self, vaddr: u32, access: AccessType, bus: &mut B) -> Result<u32, StepResult> {
+        if (self.csr.satp & SATP_MODE) == 0 {
+            return Ok(vaddr);
+        }
+        let mode = self.effective_mode(access);
+        if mode == PrivilegeMode::Machine {
+            return Ok(vaddr);
+        }
+
+        // D ビットが立っていないページへの書き込みは、PTE を更新するためページテーブルをたどり直す
+        if let Some(entry) = self.tlb.lookup(vaddr >> 12)
+            && self.leaf_permitted(entry.flags, access, mode)
+            && (access != AccessType::Write || (entry.flags & PTE_D) != 0)
+        {
+            return Ok((entry.ppn << 12) | (vaddr & 0xfff));
+        }
+        self.walk_page_table(vaddr, access, mode, bus)
+    }
+
+    /// リーフ PTE の権限で `mode` の `access` が許可されるか
+    #[inline(always)]
+    fn leaf_permitted(&self, flags: u32, access: AccessType, mode: PrivilegeMode) -> bool {
+        let user_page = (flags & PTE_U) != 0;
+        match mode {
+            PrivilegeMode::User if !user_page => return false,
+            // S モードから U ページへのアクセスは SUM が立っている場合のロード/ストアのみ許可
+            PrivilegeMode::Supervisor
+                if user_page && (access == AccessType::Execute || (self.csr.mstatus & MSTATUS_SUM) == 0) =>
+            {
+                return false;
+            }
+            _ => {}
+        }
+        match access {
+            // MXR が立っている場合は実行可能ページからも読み出せる
+            AccessType::Read => {
+                (flags & PTE_R) != 0 || ((self.csr.mstatus & MSTATUS_MXR) != 0 && (flags & PTE_X) != 0)
+            }
+            AccessType::Write   => (flags & PTE_W) != 0,
+            AccessType::Execute => (flags & PTE_X) != 0,
+        }
+    }
+
+    /// Sv32 のページテーブルをたどって変換し、A/D ビットを更新して TLB に登録する
+    #[cold]
+    fn walk_page_table<B: Bus>(&mut self, vaddr: u32, access: AccessType, mode: PrivilegeMode, bus: &mut B) -> Result<u32, StepResult> {
+        let vpn = [(vaddr >> 12) & 0x3ff, vaddr >> 22];
+        let mut table = ((self.csr.satp & SATP_PPN) as u64) << 12;
+        let mut level = 1;
+
+        let (pte, pte_addr) = loop {
+            let pte_addr = table + vpn[level] as u64 * 4;
+            let Some(pte) = self.read_pte(pte_addr, bus) else {
+                return Err(self.trap_with_value(access.fault_code(), vaddr));
+            };
+            if (pte & PTE_V) == 0 || ((pte & PTE_R) == 0 && (pte & PTE_W) != 0) {
+                return Err(self.trap_with_value(access.page_fault_code(), vaddr));
+            }
+            if (pte & (PTE_R | PTE_X)) != 0 {
+                break (pte, pte_addr);
+            }
+            // 非リーフ PTE
+            if level == 0 {
+                return Err(self.trap_with_value(access.page_fault_code(), vaddr));
+            }
+            level -= 1;
+            table = ((pte >> 10) as u64) << 12;
+        };
+
+        if !self.leaf_permitted(pte, access, mode) {
+            return Err(self.trap_with_value(access.page_fault_code(), vaddr));
+        }
+        // メガページの PPN[0] は 0 でなければならない
+        if level == 1 && ((pte >> 10) & 0x3ff) != 0 {
+            return Err(self.trap_with_value(access.page_fault_code(), vaddr));
+        }
+
+        // A (書き込みの場合は D も) を立てて PTE を書き戻す
+        let mut new_pte = pte | PTE_A;
+        if access == AccessType::Write {
+            new_pte |= PTE_D;
+        }
+        if new_pte != pte && !self.write_pte(pte_addr, new_pte, bus) {
+            return Err(self.trap_with_value(access.fault_code(), vaddr));
+        }
+
+        let ppn = if level == 1 {
+            ((pte >> 20) << 10) | vpn[0]
+        } else {
+            pte >> 10
+        };
+        // 32 ビットを超える物理アドレスはバス上に存在しない
+        if ppn >= 1 << 20 {
+            return Err(self.trap_with_value(access.fault_code(), vaddr));
+        }
+
+        self.tlb.insert(TlbEntry {
+            valid: true,
+            vpn: vaddr >> 12,
+            ppn,
+            flags: new_pte & 0xff,
+            megapage: level == 1,
+        });
+        Ok((ppn << 12) | (vaddr & 0xfff))
+    }
+
+    /// PTE を読み出す。ページテーブルへのアクセスは S モードのアクセスとして PMP で検査する
+    fn read_pte<B: Bus>(&mut self, pte_addr: u64, bus: &mut B) -> Option<u32> {
+        let addr = u32::try_from(pte_addr).ok()?;
+        if !self.csr.pmp_check(addr, 4, AccessType::Read, PrivilegeMode::Supervisor) {
+            return None;
+        }
+        bus.read32(addr).ok()
+    }
+
+    /// A/D ビットを更新した PTE を書き戻す。
+    /// ストアと同じく、命令キャッシュに載っているページへの書き込みであればキャッシュを破棄する
+    fn write_pte<B: Bus>(&mut self, pte_addr: u64, pte: u32, bus: &mut B) -> bool {
+        let addr = pte_addr as u32;
+        if !self.csr.pmp_check(addr, 4, AccessType::Write, PrivilegeMode::Supervisor) || bus.write32(addr, pte).is_err() {
+            return false;
+        }
+        self.note_code_write(addr);
+        true
+    }
+
+    /// TLB と、仮想ページで管理している命令キャッシュをすべて破棄する
+    pub(crate) fn flush_translation(&mut self) {
+        self.tlb.flush();
+        self.flush_all_cache();
+    }
+}
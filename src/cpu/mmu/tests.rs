@@ -0,0 +1,3 @@
+mod translate;
+mod permission;
+mod sfence;
@@ -0,0 +1,157 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[allow(unused_imports)]
+use crate::cpu::mmu::{PTE_V, PTE_R, PTE_W, PTE_X, PTE_U};
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_sv32_user_requires_u_bit() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // va 0x6000 は S モード用のページ
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X | PTE_U).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_R | PTE_W).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6000;
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 13); // Load page fault
+    assert_eq!(cpu.csr.mtval, 0x6000);
+}
+
+#[test]
+fn test_sv32_supervisor_sum() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // va 0x6000 は U モード用のページ
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_R | PTE_W | PTE_U).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6000;
+    bus.write32(0x3000, 0x1111).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+
+    // SUM = 0 では S モードから U ページにアクセスできない
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 13); // Load page fault
+
+    // SUM = 1 ならアクセスできる
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x5000;
+    cpu.csr.write(0x100, 1 << 18).unwrap();
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x5004);
+    assert_eq!(cpu.regs[3], 0x1111);
+}
+
+#[test]
+fn test_sv32_supervisor_cannot_execute_user_page() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+    // SUM が立っていても U ページの命令は実行できない
+    cpu.csr.write(0x100, 1 << 18).unwrap();
+
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X | PTE_U).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    // nop
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x00000013);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 12); // Instruction page fault
+    assert_eq!(cpu.csr.mtval, 0x5000);
+}
+
+#[test]
+fn test_sv32_mxr() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // va 0x6000 は実行専用のページ
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_X).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6000;
+    bus.write32(0x3000, 0x2222).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 13); // Load page fault
+
+    // MXR = 1 なら実行可能ページから読み出せる
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x5000;
+    cpu.csr.write(0x100, 1 << 19).unwrap();
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x5004);
+    assert_eq!(cpu.regs[3], 0x2222);
+}
+
+#[test]
+fn test_sv32_mprv() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Machine;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_R | PTE_W).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    // MPRV = 1, MPP = S: ロード/ストアは S モードとして変換される (命令フェッチは変換しない)
+    cpu.csr.write(0x300, (1 << 17) | (1 << 11)).unwrap();
+
+    cpu.regs[1] = 0x6000;
+    bus.write32(0x3000, 0x3333).unwrap();
+    bus.write32(0x6000, 0x4444).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0000a183);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x4);
+    assert_eq!(cpu.regs[3], 0x3333);
+}
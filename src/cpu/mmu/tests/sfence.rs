@@ -0,0 +1,128 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[allow(unused_imports)]
+use crate::cpu::mmu::{PTE_V, PTE_R, PTE_W, PTE_X, PTE_U, PTE_A};
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_sfence_vma_flushes_tlb() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_R | PTE_W).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6000;
+    bus.write32(0x3000, 0x1111).unwrap();
+    bus.write32(0x4000, 0x2222).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+    // lw x4, 0(x1) (0x0000a203)
+    bus.write_inst32(0x2004, 0x0000a203);
+    // sfence.vma x1, x0 (0x12008073)
+    bus.write_inst32(0x2008, 0x12008073);
+    // lw x5, 0(x1) (0x0000a283)
+    bus.write_inst32(0x200c, 0x0000a283);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x1111);
+
+    // PTE を書き換えても sfence.vma までは TLB の古い変換が使われる
+    bus.write32(0x11000 + 6 * 4, (0x4 << 10) | PTE_V | PTE_R | PTE_W | PTE_A).unwrap();
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 0x1111);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x500c);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[5], 0x2222);
+}
+
+#[test]
+fn test_sfence_vma_privilege() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X | PTE_U).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    // sfence.vma x0, x0 (0x12000073)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x12000073);
+    // csrr x3, satp (0x180021f3)
+    bus.write_inst32(0x2004, 0x180021f3);
+
+    // U モードでは不正命令
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2);
+
+    // mstatus.TVM が立っている場合は S モードでも sfence.vma と satp へのアクセスは不正命令
+    // (va 0x6000 は同じ物理ページを S モード用にマップしたもの)
+    cpu.csr.write(0x300, 1 << 20).unwrap();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x6000;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2);
+
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x6004;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2);
+
+    // TVM = 0 なら実行できる
+    cpu.csr.write(0x300, 0).unwrap();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x6000;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x6004);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x8000_0010);
+}
+
+#[test]
+fn test_satp_write_flushes_instruction_cache() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // va 0x0000 -> pa 0x2000 (satp を有効にした後の命令)
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+
+    cpu.regs[1] = 0x8000_0010;
+    // csrw satp, x1 (0x18009073)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x18009073);
+    // addi x3, x0, 1 (0x00100193)
+    bus.write_inst32(0x4, 0x00100193);
+    // addi x3, x0, 2 (0x00200193)
+    bus.write_inst32(0x2004, 0x00200193);
+
+    // satp の書き込み後は、同じ仮想アドレスでも変換後の物理ページから命令をフェッチする
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x4);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 2);
+}
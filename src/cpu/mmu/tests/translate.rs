@@ -0,0 +1,213 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[allow(unused_imports)]
+use crate::cpu::mmu::{PTE_V, PTE_R, PTE_W, PTE_X, PTE_A, PTE_D};
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_sv32_fetch_and_load() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // ルートページテーブル 0x10000 -> 第 2 段 0x11000
+    // va 0x5000 -> pa 0x2000 (RX), va 0x6000 -> pa 0x3000 (RW)
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_R | PTE_W).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6004;
+    bus.write32(0x3004, 0xcafe_babe).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.regs[3], 0xcafe_babe);
+    assert_eq!(cpu.pc, 0x5004);
+    // アクセスしたページの PTE には A が立ち、読み出しのみのため D は立たない
+    let pte = bus.read32(0x11000 + 6 * 4).unwrap();
+    assert_eq!(pte & (PTE_A | PTE_D), PTE_A);
+    assert_eq!(bus.read32(0x11000 + 5 * 4).unwrap() & PTE_A, PTE_A);
+}
+
+#[test]
+fn test_sv32_store_sets_dirty() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_R | PTE_W).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6008;
+    cpu.regs[2] = 0x1234_5678;
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+    // sw x2, 0(x1) (0x0020a023)
+    bus.write_inst32(0x2004, 0x0020a023);
+
+    // 読み出しで TLB に載ったエントリは D が立っていないため、書き込み時に PTE を更新する
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x5008);
+    assert_eq!(bus.read32(0x3008).unwrap(), 0x1234_5678);
+    let pte = bus.read32(0x11000 + 6 * 4).unwrap();
+    assert_eq!(pte & (PTE_A | PTE_D), PTE_A | PTE_D);
+}
+
+#[test]
+fn test_sv32_accessed_update_invalidates_code_page() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+    cpu.csr.mstatus |= 1 << 18; // SUM
+    cpu.csr.mtvec = 0x200;
+
+    // 第 2 段のページテーブルを実行中のページ (pa 0x2000) に置く
+    // va 0x5000 -> pa 0x2000 (RX), va 0x2000 -> pa 0x0 (R, U)
+    bus.write32(0x10000, (0x2 << 10) | PTE_V).unwrap();
+    bus.write32(0x2000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X | PTE_A).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+    // addi x0, x0, 0 (0x00000013)
+    bus.write_inst32(0x2004, 0x00000013);
+    // va 0x2000 の PTE は命令としては add x0, x0, x0 (0x00000033) で、A が立つと ecall (0x00000073) になる
+    bus.write_inst32(0x2008, 0x00000033);
+    cpu.regs[1] = 0x2000;
+
+    cpu.step(&mut bus);
+    assert_eq!(bus.read32(0x2008).unwrap(), 0x73);
+    // PTE の更新で命令キャッシュが破棄され、書き換わった命令を実行する
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 9); // Environment call from S-mode
+}
+
+#[test]
+fn test_sv32_megapage() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    // va 0x0040_0000-0x007f_ffff -> pa 0x0000_0000 (4MB メガページ)
+    bus.write32(0x10000 + 4, PTE_V | PTE_R).unwrap();
+    // PPN[0] が 0 でないメガページは不正
+    bus.write32(0x10000 + 2 * 4, (0x1 << 10) | PTE_V | PTE_R).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x0040_3010;
+    cpu.regs[2] = 0x0080_3010;
+    bus.write32(0x3010, 0x5555_aaaa).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+    // lw x4, 0(x2) (0x00012203)
+    bus.write_inst32(0x2004, 0x00012203);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x5555_aaaa);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 13); // Load page fault
+    assert_eq!(cpu.csr.mtval, 0x0080_3010);
+}
+
+#[test]
+fn test_sv32_page_faults() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // va 0x6000 は読み出し専用、va 0x7000 は無効
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    bus.write32(0x11000 + 6 * 4, (0x3 << 10) | PTE_V | PTE_R).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6000;
+    cpu.regs[2] = 0x7000;
+
+    // sw x2, 0(x1) (0x0020a023)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0020a023);
+    // lw x3, 0(x2) (0x00012183)
+    bus.write_inst32(0x2004, 0x00012183);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 15); // Store/AMO page fault
+    assert_eq!(cpu.csr.mtval, 0x6000);
+    assert_eq!(bus.read32(0x3000).unwrap(), 0);
+
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x5004;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 13); // Load page fault
+    assert_eq!(cpu.csr.mtval, 0x7000);
+
+    // 無効なページからのフェッチ
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x7000;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 12); // Instruction page fault
+    assert_eq!(cpu.csr.mtval, 0x7000);
+    assert_eq!(cpu.csr.mepc, 0x7000);
+}
+
+#[test]
+fn test_sv32_page_fault_delegated() {
+    let mut cpu = Cpu::new(0x5000);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.stvec = 0x300;
+    cpu.csr.write(0x302, 1 << 13).unwrap(); // medeleg: Load page fault
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    bus.write32(0x10000, (0x11 << 10) | PTE_V).unwrap();
+    bus.write32(0x11000 + 5 * 4, (0x2 << 10) | PTE_V | PTE_R | PTE_X).unwrap();
+    cpu.csr.write(0x180, 0x8000_0010).unwrap();
+
+    cpu.regs[1] = 0x6000;
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x2000, 0x0000a183);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x300);
+    assert_eq!(cpu.mode, PrivilegeMode::Supervisor);
+    assert_eq!(cpu.csr.scause, 13);
+    assert_eq!(cpu.csr.stval, 0x6000);
+    assert_eq!(cpu.csr.sepc, 0x5000);
+}
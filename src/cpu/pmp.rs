@@ -0,0 +1,160 @@
+use crate::cpu::{Cpu, StepResult};
+use crate::cpu::csr::Csr;
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[cfg(test)]
+mod tests;
+
+/// PMP エントリ数
+pub const PMP_COUNT: usize = 16;
+
+// pmpcfg の各バイトのビット定義
+const PMP_R: u8 = 1 << 0;
+const PMP_W: u8 = 1 << 1;
+const PMP_X: u8 = 1 << 2;
+const PMP_A: u8 = 0b11 << 3;
+const PMP_L: u8 = 1 << 7;
+
+// アドレスマッチングモード (pmpcfg.A)
+const PMP_A_TOR:   u8 = 1;
+const PMP_A_NA4:   u8 = 2;
+const PMP_A_NAPOT: u8 = 3;
+
+/// PMP で検査するアクセスの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AccessType {
+    Read,
+    Write,
+    Execute,
+}
+
+impl AccessType {
+    /// アクセスに必要な pmpcfg の権限ビット
+    #[inline(always)]
+    fn permission(self) -> u8 {
+        match self {
+            AccessType::Read    => PMP_R,
+            AccessType::Write   => PMP_W,
+            AccessType::Execute => PMP_X,
+        }
+    }
+
+    /// 検査に失敗したときのアクセスフォールトの例外コード
+    #[inline(always)]
+    pub(crate) fn fault_code(self) -> u32 {
+        match self {
+            AccessType::Read    => 5,
+            AccessType::Write   => 7,
+            AccessType::Execute => 1,
+        }
+    }
+}
+
+impl Csr {
+    /// エントリ `i` の pmpcfg (8 ビット)
+    #[inline(always)]
+    pub(crate) fn pmpcfg_entry(&self, i: usize) -> u8 {
+        (self.pmpcfg[i / 4] >> (8 * (i % 4))) as u8
+    }
+
+    /// pmpcfg0-3 への書き込み。ロックされたエントリのバイトは変更しない
+    pub(crate) fn write_pmpcfg(&mut self, reg: usize, val: u32) {
+        let mut new_val = 0;
+        for byte in 0..4 {
+            let i = reg * 4 + byte;
+            let old_cfg = self.pmpcfg_entry(i);
+            let cfg = if (old_cfg & PMP_L) != 0 {
+                old_cfg
+            } else {
+                // bit 6-5 は予約 (0 固定)。R=0, W=1 の組み合わせも予約のため W を落とす
+                let mut cfg = (val >> (8 * byte)) as u8 & (PMP_L | PMP_A | PMP_X | PMP_W | PMP_R);
+                if (cfg & PMP_R) == 0 {
+                    cfg &= !PMP_W;
+                }
+                cfg
+            };
+            new_val |= (cfg as u32) << (8 * byte);
+        }
+        self.pmpcfg[reg] = new_val;
+        self.pmp_locked = (0..PMP_COUNT).any(|i| (self.pmpcfg_entry(i) & PMP_L) != 0);
+    }
+
+    /// pmpaddr0-15 への書き込み。
+    /// エントリ自身がロックされている場合と、次のエントリがロックされた TOR の場合は変更しない
+    pub(crate) fn write_pmpaddr(&mut self, i: usize, val: u32) {
+        if (self.pmpcfg_entry(i) & PMP_L) != 0 {
+            return;
+        }
+        if i + 1 < PMP_COUNT {
+            let next = self.pmpcfg_entry(i + 1);
+            if (next & PMP_L) != 0 && (next & PMP_A) >> 3 == PMP_A_TOR {
+                return;
+            }
+        }
+        self.pmpaddr[i] = val;
+    }
+
+    /// エントリ `i` がマッチする物理アドレスの範囲 [start, end)。OFF や空の範囲は None
+    fn pmp_range(&self, i: usize, cfg: u8) -> Option<(u64, u64)> {
+        let pmpaddr = self.pmpaddr[i] as u64;
+        match (cfg & PMP_A) >> 3 {
+            PMP_A_TOR => {
+                let start = if i == 0 { 0 } else { (self.pmpaddr[i - 1] as u64) << 2 };
+                let end = pmpaddr << 2;
+                if start < end { Some((start, end)) } else { None }
+            }
+            PMP_A_NA4 => Some((pmpaddr << 2, (pmpaddr << 2) + 4)),
+            PMP_A_NAPOT => {
+                // 下位から連続する 1 の数 n で 2^(n+3) バイトの領域を表す
+                let ones = self.pmpaddr[i].trailing_ones();
+                let start = (pmpaddr & !((1u64 << ones) - 1)) << 2;
+                Some((start, start + (1u64 << (ones + 3))))
+            }
+            _ => None,
+        }
+    }
+
+    /// `addr` から `size` バイトへのアクセスを `mode` で行ってよいか PMP で検査する。
+    /// 番号の小さいエントリが優先され、アクセスの一部だけがマッチした場合は失敗とする
+    pub(crate) fn pmp_check(&self, addr: u32, size: u32, access: AccessType, mode: PrivilegeMode) -> bool {
+        let start = addr as u64;
+        let end = start + size as u64;
+        for i in 0..PMP_COUNT {
+            let cfg = self.pmpcfg_entry(i);
+            let Some((lo, hi)) = self.pmp_range(i, cfg) else { continue };
+            if end <= lo || hi <= start {
+                continue;
+            }
+            if start < lo || hi < end {
+                return false;
+            }
+            // ロックされていないエントリは M モードには適用されない
+            if mode == PrivilegeMode::Machine && (cfg & PMP_L) == 0 {
+                return true;
+            }
+            return (cfg & access.permission()) != 0;
+        }
+        // どのエントリにもマッチしない場合、M モードのみ許可する
+        mode == PrivilegeMode::Machine
+    }
+}
+
+impl Cpu {
+    /// 物理アドレス `addr` から `size` バイトへのアクセスが PMP で許可されているか。
+    /// M モード (MPRV 適用後) でロックされたエントリがなければ常に許可されるため検査を省略する
+    #[inline(always)]
+    pub(crate) fn pmp_allows(&self, addr: u32, size: u32, access: AccessType) -> bool {
+        let mode = self.effective_mode(access);
+        (mode == PrivilegeMode::Machine && !self.csr.pmp_locked) || self.csr.pmp_check(addr, size, access, mode)
+    }
+
+    /// PMP の検査を行い、失敗した場合は仮想アドレス `vaddr` を伴うアクセスフォールトのトラップを返す
+    #[inline(always)]
+    pub(crate) fn check_pmp(&mut self, paddr: u32, size: u32, access: AccessType, vaddr: u32) -> Result<(), StepResult> {
+        if self.pmp_allows(paddr, size, access) {
+            Ok(())
+        } else {
+            Err(self.trap_with_value(access.fault_code(), vaddr))
+        }
+    }
+}
@@ -0,0 +1,185 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_pmp_user_no_match() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+
+    // どのエントリにもマッチしない U モードのアクセスは失敗する
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00000013);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 1); // Instruction access fault
+    assert_eq!(cpu.csr.mtval, 0x0);
+    assert_eq!(cpu.mode, PrivilegeMode::Machine);
+}
+
+#[test]
+fn test_pmp_napot_permissions() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+
+    // エントリ 0: 0x0000-0x0fff (NAPOT, R|X)
+    // エントリ 1: 0x1000-0x10ff (NAPOT, R)
+    cpu.csr.write(0x3b0, 0x1ff).unwrap();
+    cpu.csr.write(0x3b1, 0x41f).unwrap();
+    cpu.csr.write(0x3a0, 0x191d).unwrap();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdead_beef;
+    bus.write32(0x1000, 0x1234).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0000a183);
+    // sw x2, 0(x1) (0x0020a023)
+    bus.write_inst32(0x4, 0x0020a023);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x1234);
+    assert_eq!(cpu.pc, 0x4);
+
+    // 書き込み権限がないためストアはアクセスフォールト
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 7); // Store/AMO access fault
+    assert_eq!(cpu.csr.mtval, 0x1000);
+    assert_eq!(bus.read32(0x1000).unwrap(), 0x1234);
+}
+
+#[test]
+fn test_pmp_execute_permission() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+
+    // エントリ 0: 0x0000-0x0fff (NAPOT, R|W)
+    cpu.csr.write(0x3b0, 0x1ff).unwrap();
+    cpu.csr.write(0x3a0, 0x1b).unwrap();
+
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00000013);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 1); // Instruction access fault
+    assert_eq!(cpu.csr.mtval, 0x0);
+}
+
+#[test]
+fn test_pmp_execute_permission_page_crossing() {
+    let mut cpu = Cpu::new(0x1ffe);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+
+    // エントリ 0: 0x0000-0x1fff (TOR, R|W|X)。0x2000 以降はどのエントリにもマッチしない
+    cpu.csr.write(0x3b0, 0x800).unwrap();
+    cpu.csr.write(0x3a0, 0x0f).unwrap();
+
+    // addi x1, x1, 1 (0x00108093) の上位 16 ビットは実行できないページにある
+    cpu.flush_cache_line(cpu.pc);
+    bus.write_inst16(0x1ffe, 0x8093);
+    bus.write_inst16(0x2000, 0x0010);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.regs[1], 0);
+    assert_eq!(cpu.csr.mcause, 1); // Instruction access fault
+    assert_eq!(cpu.csr.mepc, 0x1ffe);
+    assert_eq!(cpu.csr.mtval, 0x2000);
+}
+
+#[test]
+fn test_pmp_tor_and_partial_match() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+
+    // エントリ 0: OFF (TOR の下限として 0x800 を保持)
+    // エントリ 1: 0x800-0x8ff (TOR, R|W)
+    // エントリ 2: 0x000-0xfff (NAPOT, R|X)
+    cpu.csr.write(0x3b0, 0x800 >> 2).unwrap();
+    cpu.csr.write(0x3b1, 0x900 >> 2).unwrap();
+    cpu.csr.write(0x3b2, 0x1ff).unwrap();
+    cpu.csr.write(0x3a0, 0x1d_0b_00).unwrap();
+
+    cpu.regs[1] = 0x800;
+    cpu.regs[2] = 0xcafe;
+    cpu.regs[4] = 0x8fe;
+
+    // sw x2, 0(x1) (0x0020a023)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0020a023);
+    // lw x3, 0(x4) (0x00022183)
+    bus.write_inst32(0x4, 0x00022183);
+
+    cpu.step(&mut bus);
+    assert_eq!(bus.read32(0x800).unwrap(), 0xcafe);
+
+    // 0x8fe-0x901 はエントリ 1 に一部だけマッチするため失敗する
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 5); // Load access fault
+    assert_eq!(cpu.csr.mtval, 0x8fe);
+}
+
+#[test]
+fn test_pmp_na4() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x200;
+
+    // エントリ 0: 0x1000-0x1003 (NA4, R)
+    // エントリ 1: 全アドレス空間 (NAPOT, R|W|X)
+    cpu.csr.write(0x3b0, 0x1000 >> 2).unwrap();
+    cpu.csr.write(0x3b1, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f_11).unwrap();
+
+    cpu.regs[1] = 0x1004;
+    cpu.regs[2] = 0x55;
+
+    // sw x2, 0(x1) (0x0020a023)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0020a023);
+    // sw x2, -4(x1) (0xfe20ae23)
+    bus.write_inst32(0x4, 0xfe20ae23);
+
+    cpu.step(&mut bus);
+    assert_eq!(bus.read32(0x1004).unwrap(), 0x55);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.csr.mcause, 7); // Store/AMO access fault
+    assert_eq!(cpu.csr.mtval, 0x1000);
+}
+
+#[test]
+fn test_pmp_machine_ignores_unlocked_entry() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // エントリ 0: 全アドレス空間 (NAPOT, 権限なし)
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x18).unwrap();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0x77;
+
+    // sw x2, 0(x1) (0x0020a023)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0020a023);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x4);
+    assert_eq!(bus.read32(0x1000).unwrap(), 0x77);
+}
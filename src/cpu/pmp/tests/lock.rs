@@ -0,0 +1,75 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_pmp_locked_entry_applies_to_machine() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+
+    // エントリ 0: 0x1000-0x10ff (NAPOT, R, ロック)
+    cpu.csr.write(0x3b0, 0x41f).unwrap();
+    cpu.csr.write(0x3a0, 0x99).unwrap();
+
+    cpu.regs[1] = 0x1000;
+    bus.write32(0x1000, 0xabcd).unwrap();
+
+    // lw x3, 0(x1) (0x0000a183)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0000a183);
+    // sw x2, 0(x1) (0x0020a023)
+    bus.write_inst32(0x4, 0x0020a023);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0xabcd);
+
+    // ロックされたエントリは M モードにも適用される
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 7); // Store/AMO access fault
+    assert_eq!(cpu.csr.mtval, 0x1000);
+}
+
+#[test]
+fn test_pmp_locked_entry_is_not_writable() {
+    let mut cpu = Cpu::new(0x0);
+
+    cpu.csr.write(0x3b0, 0x41f).unwrap();
+    cpu.csr.write(0x3a0, 0x1b_99).unwrap();
+
+    // ロックされたエントリ 0 の設定とアドレスは変更できない。エントリ 1 は変更できる
+    cpu.csr.write(0x3a0, 0).unwrap();
+    cpu.csr.write(0x3b0, 0).unwrap();
+    assert_eq!(cpu.csr.read(0x3a0).unwrap(), 0x99);
+    assert_eq!(cpu.csr.read(0x3b0).unwrap(), 0x41f);
+}
+
+#[test]
+fn test_pmp_locked_tor_protects_previous_address() {
+    let mut cpu = Cpu::new(0x0);
+
+    cpu.csr.write(0x3b0, 0x100).unwrap();
+    cpu.csr.write(0x3b1, 0x200).unwrap();
+    // エントリ 1: TOR, R, ロック
+    cpu.csr.write(0x3a0, 0x89 << 8).unwrap();
+
+    // TOR の下限となる pmpaddr0 も変更できない
+    cpu.csr.write(0x3b0, 0x300).unwrap();
+    cpu.csr.write(0x3b1, 0x300).unwrap();
+    assert_eq!(cpu.csr.read(0x3b0).unwrap(), 0x100);
+    assert_eq!(cpu.csr.read(0x3b1).unwrap(), 0x200);
+}
+
+#[test]
+fn test_pmpcfg_reserved_bits() {
+    let mut cpu = Cpu::new(0x0);
+
+    // bit 6-5 は 0 固定、R=0 & W=1 は W が落とされる
+    cpu.csr.write(0x3a3, 0x0000_0062).unwrap();
+    assert_eq!(cpu.csr.read(0x3a3).unwrap(), 0);
+
+    cpu.csr.write(0x3a3, 0x0000_0063).unwrap();
+    assert_eq!(cpu.csr.read(0x3a3).unwrap(), 0x03);
+}
@@ -0,0 +1,128 @@
+use crate::cpu::{Cpu, StepResult, StopReason};
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_run_budget() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // addi x1, x1, 1 を並べる
+    for i in 0..16 {
+        bus.write_inst32(i * 4, 0x00108093);
+    }
+
+    let (reason, executed) = cpu.run(&mut bus, 10);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 10);
+    assert_eq!(cpu.regs[1], 10);
+    assert_eq!(cpu.pc, 40);
+    assert_eq!(cpu.csr.minstret, 10);
+
+    let (reason, executed) = cpu.run(&mut bus, 3);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 3);
+    assert_eq!(cpu.regs[1], 13);
+}
+
+#[test]
+fn test_run_across_jumps() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0: addi x1, x1, 1
+    // 0x4: jal x0, -4
+    bus.write_inst32(0x0, 0x00108093);
+    bus.write_inst32(0x4, 0xffdff06f);
+
+    // ジャンプを跨いでも指定した命令数だけ実行する
+    let (reason, executed) = cpu.run(&mut bus, 7);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+    assert_eq!(executed, 7);
+    assert_eq!(cpu.regs[1], 4);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_run_stops_on_trap() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+
+    // 0x0: addi x1, x1, 1
+    // 0x4: ecall
+    bus.write_inst32(0x0, 0x00108093);
+    bus.write_inst32(0x4, 0x00000073);
+
+    // ecall はリタイアしないため、実行した命令数に数えない
+    let (reason, executed) = cpu.run(&mut bus, 100);
+    assert_eq!(reason, StopReason::Trap(11));
+    assert_eq!(executed, 1);
+    assert_eq!(cpu.csr.minstret, 1);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mepc, 0x4);
+}
+
+#[test]
+fn test_run_budget_ends_on_faulting_instruction() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+    cpu.regs[2] = 0x0004_0000;
+
+    // 0x0: addi x1, x1, 1
+    // 0x4: addi x1, x1, 1
+    // 0x8: lw x3, 0(x2) (MockBus の範囲外)
+    bus.write_inst32(0x0, 0x00108093);
+    bus.write_inst32(0x4, 0x00108093);
+    bus.write_inst32(0x8, 0x00012183);
+
+    // 予算の最後の命令がフォールトした場合、返す命令数は minstret の増分と一致する
+    let (reason, executed) = cpu.run(&mut bus, 3);
+    assert_eq!(reason, StopReason::Trap(5));
+    assert_eq!(executed, 2);
+    assert_eq!(cpu.csr.minstret, 2);
+    assert_eq!(cpu.csr.mepc, 0x8);
+}
+
+#[test]
+fn test_run_stops_on_wfi() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // 0x0: addi x1, x1, 1
+    // 0x4: wfi
+    bus.write_inst32(0x0, 0x00108093);
+    bus.write_inst32(0x4, 0x10500073);
+
+    let (reason, executed) = cpu.run(&mut bus, 100);
+    assert_eq!(reason, StopReason::Waiting);
+    assert_eq!(executed, 2);
+    assert_eq!(cpu.pc, 0x8);
+
+    // 待機中は命令を実行しない
+    let (reason, executed) = cpu.run(&mut bus, 100);
+    assert_eq!(reason, StopReason::Waiting);
+    assert_eq!(executed, 0);
+}
+
+#[test]
+fn test_step_one() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // addi x1, x1, 1 / c.addi x1, 1
+    bus.write_inst32(0x0, 0x00108093);
+    bus.write_inst16(0x4, 0x0085);
+    bus.write_inst32(0x6, 0x00108093);
+
+    let (result, clock) = cpu.step_one(&mut bus);
+    assert!(matches!(result, StepResult::Ok(4)));
+    assert_eq!(clock, 1);
+    assert_eq!(cpu.pc, 0x4);
+
+    let (result, clock) = cpu.step_one(&mut bus);
+    assert!(matches!(result, StepResult::Ok(2)));
+    assert_eq!(clock, 1);
+    assert_eq!(cpu.pc, 0x6);
+    assert_eq!(cpu.regs[1], 2);
+}
@@ -0,0 +1,130 @@
+use crate::bus::Bus;
+use crate::cpu::{Cpu, Reservation};
+use crate::cpu::StepResult;
+use crate::cpu::pmp::AccessType;
+
+impl Cpu {
+    #[inline(always)]
+    pub(crate) fn lr_w<B: Bus>(&mut self, rd: u8, rs1: u8, bus: &mut B) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let addr = self.regs[rs1];
+        // LR/SC はミスアラインアクセスのエミュレート対象外
+        if addr & 3 != 0 {
+            return self.trap_with_value(4, addr);
+        }
+        // 他のハートからの書き込みと照合できるように、予約は物理アドレスも記録する
+        let paddr = match self.physical_address(addr, 4, AccessType::Read, bus) {
+            Ok(paddr) => paddr,
+            Err(trap) => return trap,
+        };
+        let val = match bus.read32(paddr) {
+            Ok(v) => v,
+            Err(_) => return self.trap_with_value(5, addr),
+        };
+        self.regs[rd] = val;
+        self.reservation = Some(Reservation { vaddr: addr, paddr });
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn sc_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        let addr = self.regs[rs1];
+        if addr & 3 != 0 {
+            return self.trap_with_value(6, addr);
+        }
+        // 成否に関わらず予約は破棄される
+        let reserved = self.reservation.take().is_some_and(|reserved| reserved.vaddr == addr);
+        if reserved {
+            if let Err(trap) = self.store32(addr, self.regs[rs2], bus) {
+                return trap;
+            }
+            self.regs[rd] = 0;
+        } else {
+            self.regs[rd] = 1;
+        }
+        StepResult::Ok(4)
+    }
+
+    /// AMO 命令の共通処理。
+    /// メモリから読み出した値と x[rs2] に `op` を適用して書き戻し、元の値を x[rd] に格納する
+    #[inline(always)]
+    fn amo_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B, op: impl Fn(u32, u32) -> u32) -> StepResult {
+        let rd:  usize = rd  as usize;
+        let rs1: usize = rs1 as usize;
+        let rs2: usize = rs2 as usize;
+        let addr = self.regs[rs1];
+        // AMO はミスアラインアクセスのエミュレート対象外
+        if addr & 3 != 0 {
+            return self.trap_with_value(6, addr);
+        }
+        // アドレス変換は書き込みとして行う (ページフォールトは Store/AMO page fault)
+        let paddr = match self.translate(addr, AccessType::Write, bus) {
+            Ok(paddr) => paddr,
+            Err(trap) => return trap,
+        };
+        // AMO は読み出しと書き込みの両方の権限が必要。PMP 違反も Store/AMO access fault とする
+        if !(self.pmp_allows(paddr, 4, AccessType::Read) && self.pmp_allows(paddr, 4, AccessType::Write)) {
+            return self.trap_with_value(7, addr);
+        }
+        // AMO の読み出しで発生したフォールトも Store/AMO access fault として扱う
+        let old_val = match bus.read32(paddr) {
+            Ok(v) => v,
+            Err(_) => return self.trap_with_value(7, addr),
+        };
+        let new_val = op(old_val, self.regs[rs2]);
+        if let Err(trap) = self.store32(addr, new_val, bus) {
+            return trap;
+        }
+        self.regs[rd] = old_val;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn amoswap_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |_, src| src)
+    }
+
+    #[inline(always)]
+    pub(crate) fn amoadd_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| mem.wrapping_add(src))
+    }
+
+    #[inline(always)]
+    pub(crate) fn amoxor_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| mem ^ src)
+    }
+
+    #[inline(always)]
+    pub(crate) fn amoand_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| mem & src)
+    }
+
+    #[inline(always)]
+    pub(crate) fn amoor_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| mem | src)
+    }
+
+    #[inline(always)]
+    pub(crate) fn amomin_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| (mem as i32).min(src as i32) as u32)
+    }
+
+    #[inline(always)]
+    pub(crate) fn amomax_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| (mem as i32).max(src as i32) as u32)
+    }
+
+    #[inline(always)]
+    pub(crate) fn amominu_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| mem.min(src))
+    }
+
+    #[inline(always)]
+    pub(crate) fn amomaxu_w<B: Bus>(&mut self, rd: u8, rs1: u8, rs2: u8, bus: &mut B) -> StepResult {
+        self.amo_w(rd, rs1, rs2, bus, |mem, src| mem.max(src))
+    }
+}
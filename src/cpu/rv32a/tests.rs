@@ -0,0 +1,2 @@
+mod lr_sc;
+mod amo;
@@ -0,0 +1,158 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+/// x1 が指すメモリ (初期値 `mem`) と x2 (= `src`) に対して AMO 命令を 1 つ実行し、
+/// (x3 の値, 実行後のメモリの値) を返す
+#[allow(unused)]
+fn run_amo(inst: u32, mem: u32, src: u32) -> (u32, u32) {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = src;
+    bus.write32(0x1000, mem).unwrap();
+    bus.write_inst32(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x4);
+    (cpu.regs[3], bus.read32(0x1000).unwrap())
+}
+
+#[test]
+fn test_amoswap_w() {
+    // amoswap.w x3, x2, (x1) (0x0820a1af)
+    // opcode: 0101111, funct3: 010, funct5: 00001
+    assert_eq!(run_amo(0x0820a1af, 10, 20), (10, 20));
+}
+
+#[test]
+fn test_amoadd_w() {
+    // amoadd.w x3, x2, (x1) (0x0020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 00000
+    assert_eq!(run_amo(0x0020a1af, 10, 20), (10, 30));
+    // オーバーフローはラップアラウンドする
+    assert_eq!(run_amo(0x0020a1af, 0xffffffff, 2), (0xffffffff, 1));
+}
+
+#[test]
+fn test_amoxor_w() {
+    // amoxor.w x3, x2, (x1) (0x2020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 00100
+    assert_eq!(run_amo(0x2020a1af, 0b1100, 0b1010), (0b1100, 0b0110));
+}
+
+#[test]
+fn test_amoand_w() {
+    // amoand.w x3, x2, (x1) (0x6020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 01100
+    assert_eq!(run_amo(0x6020a1af, 0b1100, 0b1010), (0b1100, 0b1000));
+}
+
+#[test]
+fn test_amoor_w() {
+    // amoor.w x3, x2, (x1) (0x4020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 01000
+    assert_eq!(run_amo(0x4020a1af, 0b1100, 0b1010), (0b1100, 0b1110));
+}
+
+#[test]
+fn test_amomin_w() {
+    // amomin.w x3, x2, (x1) (0x8020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 10000
+    // -1 (0xffffffff) と 1 の符号付き比較
+    assert_eq!(run_amo(0x8020a1af, 0xffffffff, 1), (0xffffffff, 0xffffffff));
+    assert_eq!(run_amo(0x8020a1af, 5, 3), (5, 3));
+}
+
+#[test]
+fn test_amomax_w() {
+    // amomax.w x3, x2, (x1) (0xa020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 10100
+    assert_eq!(run_amo(0xa020a1af, 0xffffffff, 1), (0xffffffff, 1));
+    assert_eq!(run_amo(0xa020a1af, 5, 3), (5, 5));
+}
+
+#[test]
+fn test_amominu_w() {
+    // amominu.w x3, x2, (x1) (0xc020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 11000
+    // 0xffffffff と 1 の符号なし比較
+    assert_eq!(run_amo(0xc020a1af, 0xffffffff, 1), (0xffffffff, 1));
+}
+
+#[test]
+fn test_amomaxu_w() {
+    // amomaxu.w x3, x2, (x1) (0xe020a1af)
+    // opcode: 0101111, funct3: 010, funct5: 11100
+    assert_eq!(run_amo(0xe020a1af, 0xffffffff, 1), (0xffffffff, 0xffffffff));
+}
+
+#[test]
+fn test_amo_rd_x0() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 7;
+    bus.write32(0x1000, 3).unwrap();
+
+    // amoadd.w x0, x2, (x1) (0x0020a02f)
+    bus.write_inst32(0x0, 0x0020a02f);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[0], 0);
+    assert_eq!(bus.read32(0x1000).unwrap(), 10);
+}
+
+#[test]
+fn test_amo_misaligned() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // ミスアラインのエミュレートが有効でも AMO は例外になる
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Emulate;
+    cpu.csr.mtvec = 0x100;
+    cpu.regs[1] = 0x1001;
+    cpu.regs[3] = 0x1234;
+
+    bus.write_inst32(0x0, 0x0020a1af); // amoadd.w x3, x2, (x1)
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x100);
+    assert_eq!(cpu.csr.mcause, 6); // Store/AMO address misaligned
+    assert_eq!(cpu.csr.mtval, 0x1001);
+    assert_eq!(cpu.regs[3], 0x1234);
+}
+
+#[test]
+fn test_amo_access_fault() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.csr.mtvec = 0x100;
+    cpu.regs[1] = 0x0004_0000;
+
+    bus.write_inst32(0x0, 0x0020a1af); // amoadd.w x3, x2, (x1)
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x100);
+    assert_eq!(cpu.csr.mcause, 7); // Store/AMO access fault
+    assert_eq!(cpu.csr.mtval, 0x0004_0000);
+}
+
+#[test]
+fn test_lr_with_rs2_is_illegal() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x100;
+
+    // lr.w x3, (x1) に rs2 = x2 を指定した不正なエンコーディング (0x1020a1af)
+    bus.write_inst32(0x0, 0x1020a1af);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x100);
+    assert_eq!(cpu.csr.mcause, 2);
+}
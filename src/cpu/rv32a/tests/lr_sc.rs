@@ -0,0 +1,198 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_lr_sc_success() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+    bus.write32(0x1000, 0x12345678).unwrap();
+
+    // lr.w x3, (x1) (0x1000a1af)
+    // opcode: 0101111, funct3: 010, funct5: 00010
+    bus.write_inst32(0x0, 0x1000a1af);
+    // sc.w x4, x2, (x1) (0x1820a22f)
+    // opcode: 0101111, funct3: 010, funct5: 00011
+    bus.write_inst32(0x4, 0x1820a22f);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0x12345678);
+    assert_eq!(cpu.pc, 0x4);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 0); // 成功
+    assert_eq!(bus.read32(0x1000).unwrap(), 0xdeadbeef);
+    assert_eq!(cpu.pc, 0x8);
+}
+
+#[test]
+fn test_sc_without_reservation_fails() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+    bus.write32(0x1000, 0x12345678).unwrap();
+
+    // sc.w x4, x2, (x1)
+    bus.write_inst32(0x0, 0x1820a22f);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 1); // 失敗
+    assert_eq!(bus.read32(0x1000).unwrap(), 0x12345678);
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_sc_twice_fails() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+
+    bus.write_inst32(0x0, 0x1000a1af); // lr.w x3, (x1)
+    bus.write_inst32(0x4, 0x1820a22f); // sc.w x4, x2, (x1)
+    bus.write_inst32(0x8, 0x1820a22f); // sc.w x4, x2, (x1)
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 0);
+
+    // SC によって予約は破棄されている
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 1);
+}
+
+#[test]
+fn test_sc_different_address_fails() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+
+    bus.write_inst32(0x0, 0x1000a1af); // lr.w x3, (x1)
+    bus.write_inst32(0x4, 0x1820a22f); // sc.w x4, x2, (x1)
+
+    cpu.step(&mut bus);
+    cpu.regs[1] = 0x1004;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 1);
+    assert_eq!(bus.read32(0x1004).unwrap(), 0);
+}
+
+#[test]
+fn test_store_clears_reservation() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+    cpu.regs[5] = 0x55;
+
+    bus.write_inst32(0x0, 0x1000a1af); // lr.w x3, (x1)
+    bus.write_inst32(0x4, 0x0050a023); // sw x5, 0(x1)
+    bus.write_inst32(0x8, 0x1820a22f); // sc.w x4, x2, (x1)
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 1);
+    assert_eq!(bus.read32(0x1000).unwrap(), 0x55);
+}
+
+#[test]
+fn test_unrelated_store_keeps_reservation() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+    cpu.regs[5] = 0x55;
+    cpu.regs[6] = 0x2000;
+
+    bus.write_inst32(0x0, 0x1000a1af); // lr.w x3, (x1)
+    bus.write_inst32(0x4, 0x00532023); // sw x5, 0(x6)
+    bus.write_inst32(0x8, 0x1820a22f); // sc.w x4, x2, (x1)
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 0);
+    assert_eq!(bus.read32(0x1000).unwrap(), 0xdeadbeef);
+}
+
+#[test]
+fn test_trap_clears_reservation() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+    cpu.csr.mtvec = 0x8;
+
+    bus.write_inst32(0x0, 0x1000a1af); // lr.w x3, (x1)
+    bus.write_inst32(0x4, 0x00000073); // ecall
+    bus.write_inst32(0x8, 0x1820a22f); // sc.w x4, x2, (x1) (トラップハンドラ)
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x8);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 1);
+    assert_eq!(bus.read32(0x1000).unwrap(), 0);
+}
+
+#[test]
+fn test_lr_misaligned() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // ミスアラインのエミュレートが有効でも LR は例外になる
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Emulate;
+    cpu.csr.mtvec = 0x100;
+    cpu.regs[1] = 0x1002;
+
+    bus.write_inst32(0x0, 0x1000a1af); // lr.w x3, (x1)
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x100);
+    assert_eq!(cpu.csr.mcause, 4); // Load address misaligned
+    assert_eq!(cpu.csr.mtval, 0x1002);
+}
+
+#[test]
+fn test_faulting_store_keeps_reservation() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Trap;
+    cpu.regs[1] = 0x1000;
+    cpu.regs[2] = 0xdeadbeef;
+
+    bus.write_inst32(0x0, 0x1000a1af); // lr.w x3, (x1)
+    bus.write_inst32(0x4, 0x1820a22f); // sc.w x4, x2, (x1)
+
+    cpu.step(&mut bus);
+    // 予約と重なるストアでも、例外になって書き込まれなければストア自体は予約を破棄しない
+    // (トラップハンドラへ移れば handle_trap が破棄する)
+    assert!(cpu.store32(0x1002, 0, &mut bus).is_err());
+    assert!(cpu.store16(0x1003, 0, &mut bus).is_err());
+    assert_eq!(cpu.reserved_address(), Some(0x1000));
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[4], 0); // 成功
+    assert_eq!(bus.read32(0x1000).unwrap(), 0xdeadbeef);
+}
+
+#[test]
+fn test_misa_reports_a_extension() {
+    let cpu = Cpu::new(0x0);
+    assert_eq!(cpu.csr.read(0x301).unwrap() & 1, 1);
+}
@@ -18,7 +18,10 @@ impl Cpu {
         let rs1 = rs1 as usize;
         let imm = imm as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
-        self.regs[rd] = bus.read32(addr);
+        self.regs[rd] = match self.load32(addr, bus) {
+            Ok(v) => v,
+            Err(trap) => return trap,
+        };
         StepResult::Ok(2)
     }
 
@@ -30,7 +33,10 @@ impl Cpu {
             return StepResult::Trap(2); // Reserved
         }
         let addr = self.regs[2].wrapping_add(imm);
-        self.regs[rd] = bus.read32(addr);
+        self.regs[rd] = match self.load32(addr, bus) {
+            Ok(v) => v,
+            Err(trap) => return trap,
+        };
         StepResult::Ok(2)
     }
 
@@ -39,7 +45,9 @@ impl Cpu {
         let rs2 = rs2 as usize;
         let imm = imm as u32;
         let addr = self.regs[2].wrapping_add(imm);
-        bus.write32(addr, self.regs[rs2]);
+        if let Err(trap) = self.store32(addr, self.regs[rs2], bus) {
+            return trap;
+        }
         StepResult::Ok(2)
     }
 
@@ -70,7 +78,9 @@ impl Cpu {
         let rs2 = rs2 as usize;
         let imm = imm as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
-        bus.write32(addr, self.regs[rs2]);
+        if let Err(trap) = self.store32(addr, self.regs[rs2], bus) {
+            return trap;
+        }
         StepResult::Ok(2)
     }
 
@@ -231,4 +241,41 @@ impl Cpu {
         self.regs[rd] = self.regs[rd].wrapping_add(self.regs[rs2]);
         StepResult::Ok(2)
     }
+
+    #[inline(always)]
+    pub(crate) fn c_flw<B: crate::bus::Bus>(&mut self, rd: u8, rs1: u8, imm: u16, bus: &mut B) -> StepResult {
+        let addr = self.regs[rs1 as usize].wrapping_add(imm as u32);
+        match self.load_f32(rd, addr, bus) {
+            Ok(()) => StepResult::Ok(2),
+            Err(trap) => trap,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn c_fsw<B: crate::bus::Bus>(&mut self, rs1: u8, rs2: u8, imm: u16, bus: &mut B) -> StepResult {
+        let addr = self.regs[rs1 as usize].wrapping_add(imm as u32);
+        match self.store_f32(rs2, addr, bus) {
+            Ok(()) => StepResult::Ok(2),
+            Err(trap) => trap,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn c_flwsp<B: crate::bus::Bus>(&mut self, rd: u8, imm: u16, bus: &mut B) -> StepResult {
+        // C.LWSP と異なり rd = f0 も有効
+        let addr = self.regs[2].wrapping_add(imm as u32);
+        match self.load_f32(rd, addr, bus) {
+            Ok(()) => StepResult::Ok(2),
+            Err(trap) => trap,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn c_fswsp<B: crate::bus::Bus>(&mut self, rs2: u8, imm: u16, bus: &mut B) -> StepResult {
+        let addr = self.regs[2].wrapping_add(imm as u32);
+        match self.store_f32(rs2, addr, bus) {
+            Ok(()) => StepResult::Ok(2),
+            Err(trap) => trap,
+        }
+    }
 }
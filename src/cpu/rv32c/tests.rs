@@ -3,3 +3,4 @@ mod jump;
 mod load;
 mod store;
 mod exception;
+mod float;
@@ -13,7 +13,7 @@ fn test_c_ebreak() {
     let mut bus = MockBus::new();
     
     // C.EBREAK: 1001 00000 00000 10 -> 0x9002
-    bus.write16(0, 0x9002);
+    bus.write16(0, 0x9002).unwrap();
     
     let (result, _) = cpu.step(&mut bus);
     
@@ -22,3 +22,20 @@ fn test_c_ebreak() {
         _ => panic!("Expected trap, but got {:?}", result),
     }
 }
+
+#[test]
+fn test_c_lw_access_fault() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+
+    // C.LW x8, 0(x9): 010 000 001 00 000 00 -> 0x4080
+    bus.write16(0, 0x4080).unwrap();
+    cpu.regs[9] = 0x0004_0000;
+
+    let (result, _) = cpu.step(&mut bus);
+
+    assert!(matches!(result, StepResult::Trap(5)));
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mtval, 0x0004_0000);
+}
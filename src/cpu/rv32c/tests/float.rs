@@ -0,0 +1,116 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_c_flw() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13; // FS = Initial
+
+    // rs1 = x9 (s1) = 0x100
+    cpu.regs[9] = 0x100;
+    bus.write32(0x104, 0x3fc0_0000).unwrap(); // 1.5
+
+    // c.flw f8, 4(x9)
+    // quadrant: 00
+    // funct3: 011
+    // rs1': 001 (x9)
+    // rd': 000 (f8)
+    // imm: 4 (imm[5:3]=000, imm[2]=1, imm[6]=0)
+    // inst bits: 011 000 001 1 0 000 00 -> 0b0110_0000_1100_0000 = 0x60c0
+    let inst = 0x60c0;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.fregs[8], 0xffff_ffff_3fc0_0000);
+    assert_eq!(cpu.pc, 0x2);
+}
+
+#[test]
+fn test_c_fsw() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // rs1 = x8 (s0) = 0x100
+    cpu.regs[8] = 0x100;
+    // rs2 = f9 = -2.0
+    cpu.fregs[9] = 0xffff_ffff_c000_0000;
+
+    // c.fsw f9, 0(x8)
+    // quadrant: 00
+    // funct3: 111
+    // inst bits: 111 000 000 0 0 001 00 -> 0b1110_0000_0000_0100 = 0xe004
+    let inst = 0xe004;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(bus.read32(0x100).unwrap(), 0xc000_0000);
+    assert_eq!(cpu.pc, 0x2);
+}
+
+#[test]
+fn test_c_flwsp() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // sp (x2) = 0x200
+    cpu.regs[2] = 0x200;
+    bus.write32(0x204, 0x4049_0fdb).unwrap();
+
+    // c.flwsp f0, 4(sp)
+    // quadrant: 10
+    // funct3: 011
+    // rd: 00000 (f0: C.LWSP と異なり有効)
+    // imm: 4 (imm[5]=0, imm[4:2]=001, imm[7:6]=00)
+    // inst bits: 011 0 00000 001 00 10 -> 0b0110_0000_0001_0010 = 0x6012
+    let inst = 0x6012;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.fregs[0], 0xffff_ffff_4049_0fdb);
+    assert_eq!(cpu.pc, 0x2);
+}
+
+#[test]
+fn test_c_fswsp() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // sp (x2) = 0x100
+    cpu.regs[2] = 0x100;
+    // rs2 = f1 = 1.0
+    cpu.fregs[1] = 0xffff_ffff_3f80_0000;
+
+    // c.fswsp f1, 0(sp)
+    // quadrant: 10
+    // funct3: 111
+    // inst bits: 111 000000 00001 10 -> 0b1110_0000_0000_0110 = 0xe006
+    let inst = 0xe006;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
+
+    cpu.step(&mut bus);
+    assert_eq!(bus.read32(0x100).unwrap(), 0x3f80_0000);
+    assert_eq!(cpu.pc, 0x2);
+}
+
+#[test]
+fn test_c_flw_fp_disabled() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+    // mstatus.FS = Off
+
+    // c.flw f8, 4(x9) (0x60c0)
+    cpu.regs[9] = 0x100;
+    cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, 0x60c0);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2); // Illegal instruction
+}
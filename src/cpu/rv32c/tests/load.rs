@@ -10,7 +10,7 @@ fn test_c_lw() {
     let mut bus = MockBus::new();
 
     // メモリ準備: アドレス 0x100 に 0x12345678 を書き込む
-    bus.write32(0x100, 0x12345678);
+    bus.write32(0x100, 0x12345678).unwrap();
 
     // rs1 = x8 (s0) = 0x100
     cpu.regs[8] = 0x100;
@@ -36,7 +36,7 @@ fn test_c_lw_offset() {
     let mut bus = MockBus::new();
 
     // メモリ準備: アドレス 0x100 + 124 = 0x17C に 0xDEADBEEF を書き込む
-    bus.write32(0x17C, 0xDEADBEEF);
+    bus.write32(0x17C, 0xDEADBEEF).unwrap();
 
     // rs1 = x8 (s0) = 0x100
     cpu.regs[8] = 0x100;
@@ -61,7 +61,7 @@ fn test_c_lw_various_regs() {
     let mut bus = MockBus::new();
 
     // メモリ準備: アドレス 0x200 に 0xAAAA_BBBB を書き込む
-    bus.write32(0x204, 0xAAAABBBB);
+    bus.write32(0x204, 0xAAAABBBB).unwrap();
 
     // rs1 = x15 (a7) = 0x200
     cpu.regs[15] = 0x200;
@@ -89,7 +89,7 @@ fn test_c_lwsp() {
     cpu.regs[2] = 0x100;
 
     // メモリ準備: アドレス 0x100 + 252 = 0x1FC に 0x11223344 を書き込む
-    bus.write32(0x1FC, 0x11223344);
+    bus.write32(0x1FC, 0x11223344).unwrap();
 
     // c.lwsp x10, 252(sp)
     // quadrant: 10
@@ -113,7 +113,7 @@ fn test_c_lwsp_zero_offset() {
 
     // SP = 0x200
     cpu.regs[2] = 0x200;
-    bus.write32(0x200, 0x99887766);
+    bus.write32(0x200, 0x99887766).unwrap();
 
     // c.lwsp x1, 0(sp)
     // imm: 0
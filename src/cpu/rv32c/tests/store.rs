@@ -25,7 +25,7 @@ fn test_c_sw() {
     cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
 
     cpu.step(&mut bus);
-    assert_eq!(bus.read32(0x100), 0x12345678);
+    assert_eq!(bus.read32(0x100).unwrap(), 0x12345678);
     assert_eq!(cpu.pc, 0x2);
 }
 
@@ -49,7 +49,7 @@ fn test_c_sw_offset() {
     cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
 
     cpu.step(&mut bus);
-    assert_eq!(bus.read32(0x17C), 0xDEADBEEF);
+    assert_eq!(bus.read32(0x17C).unwrap(), 0xDEADBEEF);
     assert_eq!(cpu.pc, 0x2);
 }
 
@@ -73,7 +73,7 @@ fn test_c_sw_various_regs() {
     cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
 
     cpu.step(&mut bus);
-    assert_eq!(bus.read32(0x204), 0xAAAABBBB);
+    assert_eq!(bus.read32(0x204).unwrap(), 0xAAAABBBB);
     assert_eq!(cpu.pc, 0x2);
 }
 
@@ -97,7 +97,7 @@ fn test_c_swsp() {
     cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
 
     cpu.step(&mut bus);
-    assert_eq!(bus.read32(0x100), 0x12345678);
+    assert_eq!(bus.read32(0x100).unwrap(), 0x12345678);
     assert_eq!(cpu.pc, 0x2);
 }
 
@@ -120,6 +120,6 @@ fn test_c_swsp_offset() {
     cpu.flush_cache_line(cpu.pc); bus.write_inst16(0x0, inst);
 
     cpu.step(&mut bus);
-    assert_eq!(bus.read32(0x200 + 252), 0x87654321);
+    assert_eq!(bus.read32(0x200 + 252).unwrap(), 0x87654321);
     assert_eq!(cpu.pc, 0x2);
 }
@@ -0,0 +1,3 @@
+mod exec;
+mod softfloat;
+mod tests;
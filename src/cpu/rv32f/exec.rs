@@ -0,0 +1,290 @@
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::cpu::StepResult;
+use crate::cpu::rv32f::softfloat::{self, RoundingMode, CANONICAL_NAN};
+
+/// NaN-boxing で上位 32 ビットに埋める値
+const NAN_BOX: u64 = 0xffff_ffff_0000_0000;
+
+impl Cpu {
+    /// f[reg] を単精度として読み出す。正しく NaN-boxing されていなければ正規化 NaN として扱う
+    #[inline(always)]
+    pub(crate) fn read_f32_bits(&self, reg: u8) -> u32 {
+        let val = self.fregs[reg as usize];
+        if (val & NAN_BOX) == NAN_BOX { val as u32 } else { CANONICAL_NAN }
+    }
+
+    #[inline(always)]
+    fn read_f32(&self, reg: u8) -> f32 {
+        f32::from_bits(self.read_f32_bits(reg))
+    }
+
+    /// f[reg] に単精度の値を NaN-boxing して書き込み、mstatus.FS を Dirty にする
+    #[inline(always)]
+    pub(crate) fn write_f32_bits(&mut self, reg: u8, bits: u32) {
+        self.fregs[reg as usize] = NAN_BOX | bits as u64;
+        self.csr.mark_fs_dirty();
+    }
+
+    #[inline(always)]
+    fn write_f32(&mut self, reg: u8, val: f32) {
+        self.write_f32_bits(reg, val.to_bits());
+    }
+
+    /// 例外フラグを fflags に累積する
+    #[inline(always)]
+    fn accrue_fflags(&mut self, flags: u32) {
+        if flags != 0 {
+            self.csr.fcsr |= flags;
+            self.csr.mark_fs_dirty();
+        }
+    }
+
+    /// 命令の rm フィールドから丸めモードを決定する。111 (DYN) の場合は frm を使う
+    #[inline(always)]
+    fn rounding_mode(&self, rm: u8) -> Option<RoundingMode> {
+        let rm = if rm == 0b111 { (self.csr.fcsr >> 5) & 0x7 } else { rm as u32 };
+        RoundingMode::from_bits(rm)
+    }
+
+    /// 丸めを伴い f[rd] に書き込む演算の共通処理
+    #[inline(always)]
+    fn fp_arith(&mut self, rd: u8, rm: u8, op: impl FnOnce(RoundingMode) -> (f32, u32)) -> StepResult {
+        if !self.csr.fp_enabled() {
+            return StepResult::Trap(2);
+        }
+        // 予約済みの丸めモードは不正命令
+        let Some(rm) = self.rounding_mode(rm) else {
+            return StepResult::Trap(2);
+        };
+        let (val, flags) = op(rm);
+        self.write_f32(rd, val);
+        self.accrue_fflags(flags);
+        StepResult::Ok(4)
+    }
+
+    /// 単精度の値をロードして f[rd] に書き込む (FLW / C.FLW / C.FLWSP の共通処理)
+    #[inline(always)]
+    pub(crate) fn load_f32<B: Bus>(&mut self, rd: u8, addr: u32, bus: &mut B) -> Result<(), StepResult> {
+        if !self.csr.fp_enabled() {
+            return Err(StepResult::Trap(2));
+        }
+        let val = self.load32(addr, bus)?;
+        self.write_f32_bits(rd, val);
+        Ok(())
+    }
+
+    /// f[rs2] の下位 32 ビットをストアする (FSW / C.FSW / C.FSWSP の共通処理)
+    #[inline(always)]
+    pub(crate) fn store_f32<B: Bus>(&mut self, rs2: u8, addr: u32, bus: &mut B) -> Result<(), StepResult> {
+        if !self.csr.fp_enabled() {
+            return Err(StepResult::Trap(2));
+        }
+        self.store32(addr, self.fregs[rs2 as usize] as u32, bus)
+    }
+
+    #[inline(always)]
+    pub(crate) fn flw<B: Bus>(&mut self, rd: u8, rs1: u8, imm: u16, bus: &mut B) -> StepResult {
+        let imm: u32 = (imm as i16 as i32) as u32;
+        let addr = self.regs[rs1 as usize].wrapping_add(imm);
+        match self.load_f32(rd, addr, bus) {
+            Ok(()) => StepResult::Ok(4),
+            Err(trap) => trap,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn fsw<B: Bus>(&mut self, rs1: u8, rs2: u8, imm: u16, bus: &mut B) -> StepResult {
+        let imm: u32 = (imm as i16 as i32) as u32;
+        let addr = self.regs[rs1 as usize].wrapping_add(imm);
+        match self.store_f32(rs2, addr, bus) {
+            Ok(()) => StepResult::Ok(4),
+            Err(trap) => trap,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn fmadd_s(&mut self, rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8) -> StepResult {
+        let (a, b, c) = (self.read_f32(rs1), self.read_f32(rs2), self.read_f32(rs3));
+        self.fp_arith(rd, rm, |rm| softfloat::fma(a, b, c, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fmsub_s(&mut self, rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8) -> StepResult {
+        let (a, b, c) = (self.read_f32(rs1), self.read_f32(rs2), self.read_f32(rs3));
+        self.fp_arith(rd, rm, |rm| softfloat::fma(a, b, -c, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fnmsub_s(&mut self, rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8) -> StepResult {
+        let (a, b, c) = (self.read_f32(rs1), self.read_f32(rs2), self.read_f32(rs3));
+        self.fp_arith(rd, rm, |rm| softfloat::fma(-a, b, c, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fnmadd_s(&mut self, rd: u8, rs1: u8, rs2: u8, rs3: u8, rm: u8) -> StepResult {
+        let (a, b, c) = (self.read_f32(rs1), self.read_f32(rs2), self.read_f32(rs3));
+        self.fp_arith(rd, rm, |rm| softfloat::fma(-a, b, -c, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fadd_s(&mut self, rd: u8, rs1: u8, rs2: u8, rm: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_arith(rd, rm, |rm| softfloat::add(a, b, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fsub_s(&mut self, rd: u8, rs1: u8, rs2: u8, rm: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_arith(rd, rm, |rm| softfloat::sub(a, b, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fmul_s(&mut self, rd: u8, rs1: u8, rs2: u8, rm: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_arith(rd, rm, |rm| softfloat::mul(a, b, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fdiv_s(&mut self, rd: u8, rs1: u8, rs2: u8, rm: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_arith(rd, rm, |rm| softfloat::div(a, b, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fsqrt_s(&mut self, rd: u8, rs1: u8, rm: u8) -> StepResult {
+        let a = self.read_f32(rs1);
+        self.fp_arith(rd, rm, |rm| softfloat::sqrt(a, rm))
+    }
+
+    /// 符号注入命令の共通処理。`sign` に f[rs1] と f[rs2] のビット列を渡して結果の符号ビットを決める
+    #[inline(always)]
+    fn fsgnj_common(&mut self, rd: u8, rs1: u8, rs2: u8, sign: impl Fn(u32, u32) -> u32) -> StepResult {
+        if !self.csr.fp_enabled() {
+            return StepResult::Trap(2);
+        }
+        let a = self.read_f32_bits(rs1);
+        let b = self.read_f32_bits(rs2);
+        self.write_f32_bits(rd, (a & 0x7fff_ffff) | (sign(a, b) & 0x8000_0000));
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn fsgnj_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        self.fsgnj_common(rd, rs1, rs2, |_, b| b)
+    }
+
+    #[inline(always)]
+    pub(crate) fn fsgnjn_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        self.fsgnj_common(rd, rs1, rs2, |_, b| !b)
+    }
+
+    #[inline(always)]
+    pub(crate) fn fsgnjx_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        self.fsgnj_common(rd, rs1, rs2, |a, b| a ^ b)
+    }
+
+    #[inline(always)]
+    pub(crate) fn fmin_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        // 丸めを伴わないため rm は使わない
+        self.fp_arith(rd, 0, |_| softfloat::min(a, b))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fmax_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_arith(rd, 0, |_| softfloat::max(a, b))
+    }
+
+    /// 結果を x[rd] に書き込む命令の共通処理
+    #[inline(always)]
+    fn fp_to_int(&mut self, rd: u8, rm: u8, op: impl FnOnce(RoundingMode) -> (u32, u32)) -> StepResult {
+        if !self.csr.fp_enabled() {
+            return StepResult::Trap(2);
+        }
+        let Some(rm) = self.rounding_mode(rm) else {
+            return StepResult::Trap(2);
+        };
+        let (val, flags) = op(rm);
+        self.regs[rd as usize] = val;
+        self.accrue_fflags(flags);
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn fcvt_w_s(&mut self, rd: u8, rs1: u8, rm: u8) -> StepResult {
+        let a = self.read_f32(rs1);
+        self.fp_to_int(rd, rm, |rm| softfloat::to_i32(a, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fcvt_wu_s(&mut self, rd: u8, rs1: u8, rm: u8) -> StepResult {
+        let a = self.read_f32(rs1);
+        self.fp_to_int(rd, rm, |rm| softfloat::to_u32(a, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fmv_x_w(&mut self, rd: u8, rs1: u8) -> StepResult {
+        if !self.csr.fp_enabled() {
+            return StepResult::Trap(2);
+        }
+        // NaN-boxing の検査は行わず、下位 32 ビットをそのまま転送する
+        self.regs[rd as usize] = self.fregs[rs1 as usize] as u32;
+        StepResult::Ok(4)
+    }
+
+    #[inline(always)]
+    pub(crate) fn feq_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_to_int(rd, 0, |_| {
+            let (res, flags) = softfloat::eq(a, b);
+            (res as u32, flags)
+        })
+    }
+
+    #[inline(always)]
+    pub(crate) fn flt_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_to_int(rd, 0, |_| {
+            let (res, flags) = softfloat::lt(a, b);
+            (res as u32, flags)
+        })
+    }
+
+    #[inline(always)]
+    pub(crate) fn fle_s(&mut self, rd: u8, rs1: u8, rs2: u8) -> StepResult {
+        let (a, b) = (self.read_f32(rs1), self.read_f32(rs2));
+        self.fp_to_int(rd, 0, |_| {
+            let (res, flags) = softfloat::le(a, b);
+            (res as u32, flags)
+        })
+    }
+
+    #[inline(always)]
+    pub(crate) fn fclass_s(&mut self, rd: u8, rs1: u8) -> StepResult {
+        let a = self.read_f32(rs1);
+        self.fp_to_int(rd, 0, |_| (softfloat::classify(a), 0))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fcvt_s_w(&mut self, rd: u8, rs1: u8, rm: u8) -> StepResult {
+        let v = self.regs[rs1 as usize] as i32;
+        self.fp_arith(rd, rm, |rm| softfloat::from_i32(v, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fcvt_s_wu(&mut self, rd: u8, rs1: u8, rm: u8) -> StepResult {
+        let v = self.regs[rs1 as usize];
+        self.fp_arith(rd, rm, |rm| softfloat::from_u32(v, rm))
+    }
+
+    #[inline(always)]
+    pub(crate) fn fmv_w_x(&mut self, rd: u8, rs1: u8) -> StepResult {
+        if !self.csr.fp_enabled() {
+            return StepResult::Trap(2);
+        }
+        self.write_f32_bits(rd, self.regs[rs1 as usize]);
+        StepResult::Ok(4)
+    }
+}
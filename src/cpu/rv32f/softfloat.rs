@@ -0,0 +1,402 @@
+//! 単精度浮動小数点演算
+//!
+//! ホストの浮動小数点演算は最近接偶数丸めしか提供しないため、演算は倍精度で行い、
+//! 丸め誤差の向き (真の値が倍精度の結果より大きいか小さいか) を併せて求めてから
+//! 指定された丸めモードで単精度に丸める。
+//! 単精度の入力に対して、乗算は倍精度で正確に計算でき、加算・除算・平方根の誤差は
+//! TwoSum / FMA によって正確に求められる。
+
+use std::cmp::Ordering;
+
+/// fflags: Inexact
+pub(crate) const NX: u32 = 1 << 0;
+/// fflags: Underflow
+pub(crate) const UF: u32 = 1 << 1;
+/// fflags: Overflow
+pub(crate) const OF: u32 = 1 << 2;
+/// fflags: Divide by Zero
+pub(crate) const DZ: u32 = 1 << 3;
+/// fflags: Invalid Operation
+pub(crate) const NV: u32 = 1 << 4;
+
+/// 正規化された quiet NaN
+pub(crate) const CANONICAL_NAN: u32 = 0x7fc0_0000;
+
+/// 2^128 (単精度の最大値を超えた丸め結果を表す)
+const OVERFLOW_MAG: f64 = 340_282_366_920_938_463_463_374_607_431_768_211_456.0;
+
+/// 2^64 (アンダーフロー判定時のスケーリング用)
+const TINY_SCALE: f64 = 18_446_744_073_709_551_616.0;
+
+/// 丸めモード (frm / 命令の rm フィールド)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum RoundingMode {
+    /// 最近接偶数丸め
+    Rne,
+    /// 0 方向への丸め
+    Rtz,
+    /// 負の無限大方向への丸め
+    Rdn,
+    /// 正の無限大方向への丸め
+    Rup,
+    /// 最近接丸め (絶対値の大きい方)
+    Rmm,
+}
+
+impl RoundingMode {
+    pub(crate) fn from_bits(bits: u32) -> Option<Self> {
+        match bits {
+            0b000 => Some(RoundingMode::Rne),
+            0b001 => Some(RoundingMode::Rtz),
+            0b010 => Some(RoundingMode::Rdn),
+            0b011 => Some(RoundingMode::Rup),
+            0b100 => Some(RoundingMode::Rmm),
+            _ => None,
+        }
+    }
+}
+
+/// 絶対値に対する丸め方向
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MagRound {
+    NearestEven,
+    NearestMaxMag,
+    Down,
+    Up,
+}
+
+#[inline(always)]
+fn canonical_nan() -> f32 {
+    f32::from_bits(CANONICAL_NAN)
+}
+
+#[inline(always)]
+pub(crate) fn is_snan(v: f32) -> bool {
+    v.is_nan() && (v.to_bits() & 0x0040_0000) == 0
+}
+
+/// 単精度の格子上の値 (正) の次の値。最大値の次は 2^128 とする
+#[inline(always)]
+fn next_up(v: f64) -> f64 {
+    if v >= f32::MAX as f64 { OVERFLOW_MAG } else { (v as f32).next_up() as f64 }
+}
+
+/// 単精度の格子上の値 (正) の前の値
+#[inline(always)]
+fn next_down(v: f64) -> f64 {
+    (v as f32).next_down() as f64
+}
+
+/// 絶対値 `mag` (真の値は `mag` から `rest` の向きにわずかにずれている) を単精度の格子に丸める。
+/// 戻り値は (丸め結果, 不正確かどうか)。丸め結果は最大値を超える場合 2^128 になる。
+fn round_mag(mag: f64, rest: Ordering, mode: MagRound) -> (f64, bool) {
+    let max = f32::MAX as f64;
+    let on_grid = mag <= max && (mag as f32) as f64 == mag;
+
+    let (lo, hi) = if on_grid {
+        match rest {
+            Ordering::Equal => return (mag, false),
+            Ordering::Greater => (mag, next_up(mag)),
+            Ordering::Less => (next_down(mag), mag),
+        }
+    } else if mag > max {
+        (max, OVERFLOW_MAG)
+    } else {
+        let y = (mag as f32) as f64;
+        if y > mag { (next_down(y), y) } else { (y, next_up(y)) }
+    };
+
+    let pick_hi = match mode {
+        MagRound::Down => false,
+        MagRound::Up => true,
+        MagRound::NearestEven | MagRound::NearestMaxMag => {
+            // lo と hi の中点は倍精度で正確に表現できる
+            let mid = (lo + hi) / 2.0;
+            match mag.partial_cmp(&mid).unwrap().then(rest) {
+                Ordering::Less => false,
+                Ordering::Greater => true,
+                Ordering::Equal => mode == MagRound::NearestMaxMag || ((lo as f32).to_bits() & 1) == 1,
+            }
+        }
+    };
+    (if pick_hi { hi } else { lo }, true)
+}
+
+/// 真の値 `x + ε` (`rest` は ε の符号) を丸めモード `rm` で単精度に丸め、結果と例外フラグを返す。
+/// |ε| は `x` の倍精度 ULP の半分未満でなければならない。
+pub(crate) fn round_f64(x: f64, rest: Ordering, rm: RoundingMode) -> (f32, u32) {
+    if x.is_nan() {
+        return (canonical_nan(), 0);
+    }
+    if x.is_infinite() || x == 0.0 {
+        return (x as f32, 0);
+    }
+
+    let negative = x.is_sign_negative();
+    let mag = x.abs();
+    let rest = if negative { rest.reverse() } else { rest };
+    let mode = match (rm, negative) {
+        (RoundingMode::Rne, _) => MagRound::NearestEven,
+        (RoundingMode::Rmm, _) => MagRound::NearestMaxMag,
+        (RoundingMode::Rtz, _) => MagRound::Down,
+        (RoundingMode::Rdn, false) | (RoundingMode::Rup, true) => MagRound::Down,
+        (RoundingMode::Rdn, true) | (RoundingMode::Rup, false) => MagRound::Up,
+    };
+
+    let (rounded, inexact) = round_mag(mag, rest, mode);
+    let mut flags = if inexact { NX } else { 0 };
+
+    // 指数範囲を無制限とした丸め結果が最大値を超えればオーバーフロー
+    if rounded == OVERFLOW_MAG || mag >= OVERFLOW_MAG {
+        flags |= OF | NX;
+    }
+
+    // アンダーフローは丸め後に判定する (tininess after rounding)
+    if inexact && mag < f32::MIN_POSITIVE as f64 {
+        let (scaled, _) = round_mag(mag * TINY_SCALE, rest, mode);
+        if scaled < f32::MIN_POSITIVE as f64 * TINY_SCALE {
+            flags |= UF;
+        }
+    }
+
+    let result = if rounded == OVERFLOW_MAG { f32::INFINITY } else { rounded as f32 };
+    (if negative { -result } else { result }, flags)
+}
+
+/// 誤差 `e` の符号
+#[inline(always)]
+fn sign_of(e: f64) -> Ordering {
+    e.partial_cmp(&0.0).unwrap()
+}
+
+/// s = fl(a + b) の丸め誤差 (a + b - s) を求める (TwoSum)
+#[inline(always)]
+fn two_sum_err(a: f64, b: f64, s: f64) -> f64 {
+    let bb = s - a;
+    let aa = s - bb;
+    (a - aa) + (b - bb)
+}
+
+/// 正確な積 `p` と加数 `c` の和を丸める (加減算と FMA の共通処理)
+fn round_sum(p: f64, c: f64, rm: RoundingMode) -> (f32, u32) {
+    let s = p + c;
+    if s.is_nan() {
+        // ∞ - ∞
+        return (canonical_nan(), NV);
+    }
+    if s.is_infinite() {
+        // 入力が無限大の場合のみ。結果は正確
+        return (s as f32, 0);
+    }
+    if s == 0.0 {
+        // 同符号の 0 同士以外で和が 0 になる場合、符号は RDN のみ負になる
+        let same_sign_zeros = p == 0.0 && c == 0.0 && p.is_sign_negative() == c.is_sign_negative();
+        if !same_sign_zeros {
+            return (if rm == RoundingMode::Rdn { -0.0 } else { 0.0 }, 0);
+        }
+        return (s as f32, 0);
+    }
+    round_f64(s, sign_of(two_sum_err(p, c, s)), rm)
+}
+
+/// NaN の入力を含む演算の結果
+#[inline(always)]
+fn propagate_nan(inputs: &[f32]) -> (f32, u32) {
+    let flags = if inputs.iter().any(|&v| is_snan(v)) { NV } else { 0 };
+    (canonical_nan(), flags)
+}
+
+pub(crate) fn add(a: f32, b: f32, rm: RoundingMode) -> (f32, u32) {
+    if a.is_nan() || b.is_nan() {
+        return propagate_nan(&[a, b]);
+    }
+    round_sum(a as f64, b as f64, rm)
+}
+
+pub(crate) fn sub(a: f32, b: f32, rm: RoundingMode) -> (f32, u32) {
+    add(a, -b, rm)
+}
+
+pub(crate) fn mul(a: f32, b: f32, rm: RoundingMode) -> (f32, u32) {
+    if a.is_nan() || b.is_nan() {
+        return propagate_nan(&[a, b]);
+    }
+    // 24 ビット × 24 ビットの積は倍精度で正確
+    let p = a as f64 * b as f64;
+    if p.is_nan() {
+        // ∞ × 0
+        return (canonical_nan(), NV);
+    }
+    round_f64(p, Ordering::Equal, rm)
+}
+
+pub(crate) fn div(a: f32, b: f32, rm: RoundingMode) -> (f32, u32) {
+    if a.is_nan() || b.is_nan() {
+        return propagate_nan(&[a, b]);
+    }
+    if b == 0.0 && a.is_finite() && a != 0.0 {
+        let inf = if a.is_sign_negative() != b.is_sign_negative() { f32::NEG_INFINITY } else { f32::INFINITY };
+        return (inf, DZ);
+    }
+    let (a, b) = (a as f64, b as f64);
+    let q = a / b;
+    if q.is_nan() {
+        // 0 / 0, ∞ / ∞
+        return (canonical_nan(), NV);
+    }
+    if q.is_infinite() || q == 0.0 {
+        return (q as f32, 0);
+    }
+    // 剰余 a - q * b は正確に求まる。真の商は q + r / b
+    let r = (-q).mul_add(b, a);
+    let rest = if b > 0.0 { sign_of(r) } else { sign_of(r).reverse() };
+    round_f64(q, rest, rm)
+}
+
+pub(crate) fn sqrt(a: f32, rm: RoundingMode) -> (f32, u32) {
+    if a.is_nan() {
+        return propagate_nan(&[a]);
+    }
+    if a < 0.0 {
+        return (canonical_nan(), NV);
+    }
+    let a = a as f64;
+    let s = a.sqrt();
+    if s.is_infinite() || s == 0.0 {
+        return (s as f32, 0);
+    }
+    // 真の平方根は a - s^2 の符号の向きにずれている
+    let r = (-s).mul_add(s, a);
+    round_f64(s, sign_of(r), rm)
+}
+
+/// a * b + c を一度だけ丸めて計算する
+pub(crate) fn fma(a: f32, b: f32, c: f32, rm: RoundingMode) -> (f32, u32) {
+    let inf_times_zero = (a.is_infinite() && b == 0.0) || (a == 0.0 && b.is_infinite());
+    if a.is_nan() || b.is_nan() || c.is_nan() {
+        // ∞ × 0 は加数が quiet NaN でも無効演算とする
+        let (nan, flags) = propagate_nan(&[a, b, c]);
+        return (nan, if inf_times_zero { flags | NV } else { flags });
+    }
+    if inf_times_zero {
+        return (canonical_nan(), NV);
+    }
+    round_sum(a as f64 * b as f64, c as f64, rm)
+}
+
+pub(crate) fn min(a: f32, b: f32) -> (f32, u32) {
+    let flags = if is_snan(a) || is_snan(b) { NV } else { 0 };
+    let result = match (a.is_nan(), b.is_nan()) {
+        (true, true) => canonical_nan(),
+        (true, false) => b,
+        (false, true) => a,
+        // -0.0 は +0.0 より小さいとみなす
+        _ if a == b => if a.is_sign_negative() { a } else { b },
+        _ => if a < b { a } else { b },
+    };
+    (result, flags)
+}
+
+pub(crate) fn max(a: f32, b: f32) -> (f32, u32) {
+    let flags = if is_snan(a) || is_snan(b) { NV } else { 0 };
+    let result = match (a.is_nan(), b.is_nan()) {
+        (true, true) => canonical_nan(),
+        (true, false) => b,
+        (false, true) => a,
+        _ if a == b => if a.is_sign_negative() { b } else { a },
+        _ => if a > b { a } else { b },
+    };
+    (result, flags)
+}
+
+/// FEQ: シグナリング NaN の場合のみ無効演算
+pub(crate) fn eq(a: f32, b: f32) -> (bool, u32) {
+    let flags = if is_snan(a) || is_snan(b) { NV } else { 0 };
+    (a == b, flags)
+}
+
+/// FLT: NaN が含まれれば無効演算
+pub(crate) fn lt(a: f32, b: f32) -> (bool, u32) {
+    let flags = if a.is_nan() || b.is_nan() { NV } else { 0 };
+    (a < b, flags)
+}
+
+/// FLE: NaN が含まれれば無効演算
+pub(crate) fn le(a: f32, b: f32) -> (bool, u32) {
+    let flags = if a.is_nan() || b.is_nan() { NV } else { 0 };
+    (a <= b, flags)
+}
+
+/// FCLASS の結果 (10 ビットのマスク)
+pub(crate) fn classify(a: f32) -> u32 {
+    let negative = a.is_sign_negative();
+    if a.is_nan() {
+        return if is_snan(a) { 1 << 8 } else { 1 << 9 };
+    }
+    let bit = if a.is_infinite() {
+        if negative { 0 } else { 7 }
+    } else if a == 0.0 {
+        if negative { 3 } else { 4 }
+    } else if a.is_subnormal() {
+        if negative { 2 } else { 5 }
+    } else if negative {
+        1
+    } else {
+        6
+    };
+    1 << bit
+}
+
+/// 丸めモードに従って整数値に丸める
+#[inline(always)]
+fn round_integral(x: f64, rm: RoundingMode) -> f64 {
+    match rm {
+        RoundingMode::Rne => x.round_ties_even(),
+        RoundingMode::Rtz => x.trunc(),
+        RoundingMode::Rdn => x.floor(),
+        RoundingMode::Rup => x.ceil(),
+        RoundingMode::Rmm => x.round(),
+    }
+}
+
+/// 範囲内に収まる整数への変換。範囲外・NaN の場合は `None`
+#[inline(always)]
+fn to_integer(a: f32, rm: RoundingMode, lo: f64, hi: f64) -> Option<(f64, u32)> {
+    if a.is_nan() {
+        return None;
+    }
+    let x = a as f64;
+    let r = round_integral(x, rm);
+    if r < lo || r > hi {
+        return None;
+    }
+    Some((r, if r != x { NX } else { 0 }))
+}
+
+/// FCVT.W.S
+pub(crate) fn to_i32(a: f32, rm: RoundingMode) -> (u32, u32) {
+    match to_integer(a, rm, i32::MIN as f64, i32::MAX as f64) {
+        Some((r, flags)) => (r as i32 as u32, flags),
+        None if a.is_sign_negative() && !a.is_nan() => (i32::MIN as u32, NV),
+        None => (i32::MAX as u32, NV),
+    }
+}
+
+/// FCVT.WU.S
+pub(crate) fn to_u32(a: f32, rm: RoundingMode) -> (u32, u32) {
+    match to_integer(a, rm, 0.0, u32::MAX as f64) {
+        Some((r, flags)) => (r as u32, flags),
+        None if a.is_sign_negative() && !a.is_nan() => (0, NV),
+        None => (u32::MAX, NV),
+    }
+}
+
+/// FCVT.S.W
+pub(crate) fn from_i32(v: i32, rm: RoundingMode) -> (f32, u32) {
+    round_f64(v as f64, Ordering::Equal, rm)
+}
+
+/// FCVT.S.WU
+pub(crate) fn from_u32(v: u32, rm: RoundingMode) -> (f32, u32) {
+    round_f64(v as f64, Ordering::Equal, rm)
+}
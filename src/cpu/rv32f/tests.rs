@@ -0,0 +1,6 @@
+mod load_store;
+mod arithmetic;
+mod fma;
+mod convert;
+mod compare;
+mod fcsr;
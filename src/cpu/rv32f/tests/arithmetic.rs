@@ -0,0 +1,324 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_fadd_s() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13; // FS = Initial
+
+    cpu.fregs[1] = 0xffff_ffff_3fc0_0000; // 1.5
+    cpu.fregs[2] = 0xffff_ffff_4010_0000; // 2.25
+
+    // fadd.s f3, f1, f2, rne (0x002081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x002081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3], 0xffff_ffff_4070_0000); // 3.75 (NaN-boxed)
+    assert_eq!(cpu.csr.fcsr, 0);
+    assert_eq!((cpu.csr.mstatus >> 13) & 0b11, 3); // FS = Dirty
+    assert_eq!(cpu.csr.mstatus >> 31, 1); // SD
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_fadd_s_rounding_modes() {
+    // 1.0 + 1.5 * 2^-24 (1 ULP の 0.75 倍) を各丸めモードで計算する
+    // (rm, 命令, 期待値)
+    let cases = [
+        (0b000, 0x002081d3, 0x3f80_0001), // RNE
+        (0b001, 0x002091d3, 0x3f80_0000), // RTZ
+        (0b010, 0x0020a1d3, 0x3f80_0000), // RDN
+        (0b011, 0x0020b1d3, 0x3f80_0001), // RUP
+        (0b100, 0x0020c1d3, 0x3f80_0001), // RMM
+    ];
+    for (rm, inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_3f80_0000; // 1.0
+        cpu.fregs[2] = 0xffff_ffff_33c0_0000; // 1.5 * 2^-24
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected, "rm = {:03b}", rm);
+        assert_eq!(cpu.csr.fcsr, 0b00001, "rm = {:03b}", rm); // NX
+    }
+}
+
+#[test]
+fn test_fadd_s_rounding_modes_negative() {
+    // -1.0 + -1.5 * 2^-24: RDN は絶対値が大きい方、RUP は 0 方向に丸める
+    let cases = [
+        (0x0020a1d3, 0xbf80_0001u32), // RDN
+        (0x0020b1d3, 0xbf80_0000u32), // RUP
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_bf80_0000; // -1.0
+        cpu.fregs[2] = 0xffff_ffff_b3c0_0000; // -1.5 * 2^-24
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+    }
+}
+
+#[test]
+fn test_fadd_s_tie() {
+    // 1.0 + 2^-24 はちょうど中間: RNE は偶数側、RMM は絶対値の大きい側
+    let cases = [
+        (0x002081d3, 0x3f80_0000u32), // RNE
+        (0x0020c1d3, 0x3f80_0001u32), // RMM
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_3f80_0000; // 1.0
+        cpu.fregs[2] = 0xffff_ffff_3380_0000; // 2^-24
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+    }
+}
+
+#[test]
+fn test_fadd_s_dynamic_rounding_mode() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.csr.fcsr = 0b011 << 5; // frm = RUP
+
+    cpu.fregs[1] = 0xffff_ffff_3f80_0000; // 1.0
+    cpu.fregs[2] = 0xffff_ffff_3380_0000; // 2^-24
+
+    // fadd.s f3, f1, f2, dyn (0x0020f1d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0020f1d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x3f80_0001);
+    assert_eq!(cpu.csr.fcsr, (0b011 << 5) | 0b00001); // frm は保持され NX が立つ
+}
+
+#[test]
+fn test_fadd_s_reserved_rounding_mode() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.csr.mtvec = 0x200;
+
+    // fadd.s f3, f1, f2, rm=101 (予約) (0x0020d1d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0020d1d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2); // Illegal instruction
+
+    // frm が予約値の場合の DYN も不正命令
+    let mut cpu = Cpu::new(0x0);
+    cpu.csr.mstatus = 1 << 13;
+    cpu.csr.mtvec = 0x200;
+    cpu.csr.fcsr = 0b101 << 5;
+
+    // fadd.s f3, f1, f2, dyn (0x0020f1d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x0020f1d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2);
+}
+
+#[test]
+fn test_fp_disabled() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+    // mstatus.FS = Off
+
+    // fadd.s f3, f1, f2, rne (0x002081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x002081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2); // Illegal instruction
+    assert_eq!(cpu.fregs[3], 0);
+}
+
+#[test]
+fn test_fsub_s_exact_zero_sign() {
+    // x - x は RDN のときのみ -0.0 になる
+    let cases = [
+        (0x082081d3, 0x0000_0000u32), // RNE
+        (0x0820a1d3, 0x8000_0000u32), // RDN
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_4040_0000; // 3.0
+        cpu.fregs[2] = 0xffff_ffff_4040_0000; // 3.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+        assert_eq!(cpu.csr.fcsr, 0);
+    }
+}
+
+#[test]
+fn test_fmul_s_overflow() {
+    // f32::MAX * 2.0: RNE は +inf、RTZ は f32::MAX
+    let cases = [
+        (0x102081d3, 0x7f80_0000u32), // RNE
+        (0x102091d3, 0x7f7f_ffffu32), // RTZ
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_7f7f_ffff; // f32::MAX
+        cpu.fregs[2] = 0xffff_ffff_4000_0000; // 2.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+        assert_eq!(cpu.csr.fcsr, 0b00101); // OF | NX
+    }
+}
+
+#[test]
+fn test_fmul_s_underflow() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // 2^-126 * (1 - 2^-24) は非正規化数の格子の中間にあり、RNE で 2^-126 に丸められる。
+    // 指数範囲を無制限とした丸めでは 2^-126 未満のままなのでアンダーフローになる
+    cpu.fregs[1] = 0xffff_ffff_0080_0000; // 2^-126
+    cpu.fregs[2] = 0xffff_ffff_3f7f_ffff; // 1 - 2^-24
+
+    // fmul.s f3, f1, f2, rne (0x102081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x102081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x0080_0000);
+    assert_eq!(cpu.csr.fcsr, 0b00011); // UF | NX
+}
+
+#[test]
+fn test_fdiv_s() {
+    // 1.0 / 3.0
+    let cases = [
+        (0x182081d3, 0x3eaa_aaabu32), // RNE
+        (0x182091d3, 0x3eaa_aaaau32), // RTZ
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_3f80_0000; // 1.0
+        cpu.fregs[2] = 0xffff_ffff_4040_0000; // 3.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+        assert_eq!(cpu.csr.fcsr, 0b00001); // NX
+    }
+}
+
+#[test]
+fn test_fdiv_s_by_zero() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    cpu.fregs[1] = 0xffff_ffff_bf80_0000; // -1.0
+    cpu.fregs[2] = 0xffff_ffff_0000_0000; // +0.0
+
+    // fdiv.s f3, f1, f2, rne (0x182081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x182081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0xff80_0000); // -inf
+    assert_eq!(cpu.csr.fcsr, 0b01000); // DZ
+}
+
+#[test]
+fn test_fdiv_s_invalid() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    cpu.fregs[1] = 0xffff_ffff_0000_0000; // 0.0
+    cpu.fregs[2] = 0xffff_ffff_0000_0000; // 0.0
+
+    // fdiv.s f3, f1, f2, rne (0x182081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x182081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x7fc0_0000); // 正規化 NaN
+    assert_eq!(cpu.csr.fcsr, 0b10000); // NV
+}
+
+#[test]
+fn test_fsqrt_s() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    cpu.fregs[1] = 0xffff_ffff_4000_0000; // 2.0
+
+    // fsqrt.s f3, f1, rne (0x580081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x580081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x3fb5_04f3); // 1.4142135
+    assert_eq!(cpu.csr.fcsr, 0b00001); // NX
+}
+
+#[test]
+fn test_fsqrt_s_negative() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    cpu.fregs[1] = 0xffff_ffff_bf80_0000; // -1.0
+
+    // fsqrt.s f3, f1, rne (0x580081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x580081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x7fc0_0000);
+    assert_eq!(cpu.csr.fcsr, 0b10000); // NV
+}
+
+#[test]
+fn test_nan_boxing_input() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // 上位 32 ビットが 1 で埋められていない値は正規化 NaN として扱われる
+    cpu.fregs[1] = 0x0000_0000_3f80_0000;
+    cpu.fregs[2] = 0xffff_ffff_3f80_0000; // 1.0
+
+    // fadd.s f3, f1, f2, rne (0x002081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x002081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3], 0xffff_ffff_7fc0_0000);
+    assert_eq!(cpu.csr.fcsr, 0); // quiet NaN なので NV は立たない
+}
@@ -0,0 +1,151 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_fsgnj_variants() {
+    // f1 = 1.5, f2 = -2.0
+    let cases = [
+        (0x202081d3, 0xbfc0_0000u32), // fsgnj.s:  符号を f2 から
+        (0x202091d3, 0x3fc0_0000u32), // fsgnjn.s: f2 の符号を反転
+        (0x2020a1d3, 0xbfc0_0000u32), // fsgnjx.s: 符号の XOR
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_3fc0_0000; // 1.5
+        cpu.fregs[2] = 0xffff_ffff_c000_0000; // -2.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3], 0xffff_ffff_0000_0000 | expected as u64, "inst = {:08x}", inst);
+        assert_eq!(cpu.csr.fcsr, 0);
+    }
+}
+
+#[test]
+fn test_fmin_fmax_signed_zero() {
+    // -0.0 は +0.0 より小さいとみなす
+    let cases = [
+        (0x282081d3, 0x8000_0000u32), // fmin.s
+        (0x282091d3, 0x0000_0000u32), // fmax.s
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_0000_0000; // +0.0
+        cpu.fregs[2] = 0xffff_ffff_8000_0000; // -0.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+    }
+}
+
+#[test]
+fn test_fmin_fmax_nan() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // 片方だけが NaN の場合はもう片方を返す。sNaN なら NV
+    cpu.fregs[1] = 0xffff_ffff_7f80_0001; // sNaN
+    cpu.fregs[2] = 0xffff_ffff_4000_0000; // 2.0
+
+    // fmax.s f3, f1, f2 (0x282091d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x282091d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x4000_0000);
+    assert_eq!(cpu.csr.fcsr, 0b10000); // NV
+
+    // 両方 NaN の場合は正規化 NaN
+    cpu.fregs[2] = 0xffff_ffff_7fc0_0000; // qNaN
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x282091d3);
+    cpu.pc = 0x0;
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x7fc0_0000);
+}
+
+#[test]
+fn test_fcompare() {
+    // f1 = 1.0, f2 = 2.0
+    let cases = [
+        (0xa020a1d3, 0), // feq.s
+        (0xa02091d3, 1), // flt.s
+        (0xa02081d3, 1), // fle.s
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_3f80_0000; // 1.0
+        cpu.fregs[2] = 0xffff_ffff_4000_0000; // 2.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.regs[3], expected, "inst = {:08x}", inst);
+        assert_eq!(cpu.csr.fcsr, 0);
+    }
+}
+
+#[test]
+fn test_fcompare_qnan() {
+    // quiet NaN との比較: FEQ は NV を立てず、FLT/FLE は NV を立てる
+    let cases = [
+        (0xa020a1d3, 0b00000), // feq.s
+        (0xa02091d3, 0b10000), // flt.s
+        (0xa02081d3, 0b10000), // fle.s
+    ];
+    for (inst, flags) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.regs[3] = 0xdead_beef;
+        cpu.fregs[1] = 0xffff_ffff_7fc0_0000; // qNaN
+        cpu.fregs[2] = 0xffff_ffff_4000_0000; // 2.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.regs[3], 0, "inst = {:08x}", inst);
+        assert_eq!(cpu.csr.fcsr, flags, "inst = {:08x}", inst);
+    }
+}
+
+#[test]
+fn test_fclass_s() {
+    // (入力, 期待値)
+    let cases = [
+        (0xff80_0000u32, 1 << 0), // -inf
+        (0xbf80_0000u32, 1 << 1), // 負の正規化数
+        (0x8000_0001u32, 1 << 2), // 負の非正規化数
+        (0x8000_0000u32, 1 << 3), // -0
+        (0x0000_0000u32, 1 << 4), // +0
+        (0x0000_0001u32, 1 << 5), // 正の非正規化数
+        (0x3f80_0000u32, 1 << 6), // 正の正規化数
+        (0x7f80_0000u32, 1 << 7), // +inf
+        (0x7f80_0001u32, 1 << 8), // sNaN
+        (0x7fc0_0000u32, 1 << 9), // qNaN
+    ];
+    for (input, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_0000_0000 | input as u64;
+
+        // fclass.s x3, f1 (0xe00091d3)
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xe00091d3);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.regs[3], expected, "input = {:08x}", input);
+    }
+}
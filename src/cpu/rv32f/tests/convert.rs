@@ -0,0 +1,164 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_fcvt_w_s_rounding_modes() {
+    // -2.5 を各丸めモードで整数に変換する
+    // (rm, 命令, 期待値)
+    let cases = [
+        (0b000, 0xc00081d3, -2i32), // RNE
+        (0b001, 0xc00091d3, -2i32), // RTZ
+        (0b010, 0xc000a1d3, -3i32), // RDN
+        (0b011, 0xc000b1d3, -2i32), // RUP
+        (0b100, 0xc000c1d3, -3i32), // RMM
+    ];
+    for (rm, inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_c020_0000; // -2.5
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.regs[3], expected as u32, "rm = {:03b}", rm);
+        assert_eq!(cpu.csr.fcsr, 0b00001, "rm = {:03b}", rm); // NX
+    }
+}
+
+#[test]
+fn test_fcvt_w_s_out_of_range() {
+    // (入力, 期待値)
+    let cases = [
+        (0x7fc0_0000u32, 0x7fff_ffffu32), // NaN
+        (0x4f32_d05eu32, 0x7fff_ffffu32), // 3e9
+        (0xcf32_d05eu32, 0x8000_0000u32), // -3e9
+        (0xff80_0000u32, 0x8000_0000u32), // -inf
+    ];
+    for (input, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_0000_0000 | input as u64;
+
+        // fcvt.w.s x3, f1, rtz (0xc00091d3)
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xc00091d3);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.regs[3], expected, "input = {:08x}", input);
+        assert_eq!(cpu.csr.fcsr, 0b10000, "input = {:08x}", input); // NV
+    }
+}
+
+#[test]
+fn test_fcvt_wu_s() {
+    // (入力, 期待値, fflags)
+    let cases = [
+        (0x4f00_0000u32, 0x8000_0000u32, 0b00000), // 2^31
+        (0xbf00_0000u32, 0x0000_0000u32, 0b00001), // -0.5 は 0 に丸められるので NX のみ
+        (0xbf80_0000u32, 0x0000_0000u32, 0b10000), // -1.0 は範囲外
+        (0x7f80_0000u32, 0xffff_ffffu32, 0b10000), // +inf
+    ];
+    for (input, expected, flags) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_0000_0000 | input as u64;
+
+        // fcvt.wu.s x3, f1, rtz (0xc01091d3)
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xc01091d3);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.regs[3], expected, "input = {:08x}", input);
+        assert_eq!(cpu.csr.fcsr, flags, "input = {:08x}", input);
+    }
+}
+
+#[test]
+fn test_fcvt_s_w() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.regs[1] = -7i32 as u32;
+
+    // fcvt.s.w f3, x1, rne (0xd00081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xd00081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3], 0xffff_ffff_c0e0_0000); // -7.0
+    assert_eq!(cpu.csr.fcsr, 0);
+}
+
+#[test]
+fn test_fcvt_s_w_inexact() {
+    // 2^24 + 1 は単精度で表現できない
+    let cases = [
+        (0xd00081d3, 0x4b80_0000u32), // RNE: 16777216.0
+        (0xd000b1d3, 0x4b80_0001u32), // RUP: 16777218.0
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.regs[1] = 16_777_217;
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+        assert_eq!(cpu.csr.fcsr, 0b00001); // NX
+    }
+}
+
+#[test]
+fn test_fcvt_s_wu() {
+    let cases = [
+        (0xd01081d3, 0x4f80_0000u32), // RNE: 4294967296.0
+        (0xd01091d3, 0x4f7f_ffffu32), // RTZ: 4294967040.0
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.regs[1] = 0xffff_ffff;
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3] as u32, expected);
+        assert_eq!(cpu.csr.fcsr, 0b00001); // NX
+    }
+}
+
+#[test]
+fn test_fmv_w_x() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.regs[1] = 0x7f80_0001; // sNaN のビット列もそのまま転送される
+
+    // fmv.w.x f3, x1 (0xf00081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xf00081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3], 0xffff_ffff_7f80_0001);
+    assert_eq!(cpu.csr.fcsr, 0);
+}
+
+#[test]
+fn test_fmv_x_w() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    // NaN-boxing されていなくても下位 32 ビットをそのまま転送する
+    cpu.fregs[1] = 0x0000_0000_c0e0_0000;
+
+    // fmv.x.w x3, f1 (0xe00081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xe00081d3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.regs[3], 0xc0e0_0000);
+}
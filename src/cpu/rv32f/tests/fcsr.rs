@@ -0,0 +1,102 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_csrrw_fcsr() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.csr.fcsr = 0b001_00001; // frm = RTZ, NX
+
+    // 上位ビットは無視される
+    cpu.regs[1] = 0xffff_ff00 | 0b100_10000;
+
+    // csrrw x3, fcsr, x1 (0x003091f3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x003091f3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.regs[3], 0b001_00001);
+    assert_eq!(cpu.csr.fcsr, 0b100_10000);
+    assert_eq!((cpu.csr.mstatus >> 13) & 0b11, 3); // FS = Dirty
+}
+
+#[test]
+fn test_frm_and_fflags_views() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.csr.fcsr = 0b010_00101; // frm = RDN, OF | NX
+
+    // csrrs x3, fflags, x0 (0x001021f3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x001021f3);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0b00101);
+
+    // csrrw x3, frm, x1 (0x002091f3)
+    cpu.regs[1] = 0b011; // RUP
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x4, 0x002091f3);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 0b010);
+    // fflags は変更されない
+    assert_eq!(cpu.csr.fcsr, 0b011_00101);
+}
+
+#[test]
+fn test_fflags_accrue() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.csr.fcsr = 0b01000; // DZ
+
+    cpu.fregs[1] = 0xffff_ffff_3f80_0000; // 1.0
+    cpu.fregs[2] = 0xffff_ffff_4040_0000; // 3.0
+
+    // fdiv.s f3, f1, f2, rne (0x182081d3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x182081d3);
+    cpu.step(&mut bus);
+
+    // 既存のフラグはクリアされずに累積される
+    assert_eq!(cpu.csr.fcsr, 0b01001);
+}
+
+#[test]
+fn test_fcsr_fp_disabled() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+    // mstatus.FS = Off
+
+    // csrrs x3, fcsr, x0 (0x003021f3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x003021f3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2); // Illegal instruction
+}
+
+#[test]
+fn test_mstatus_fs_sd() {
+    let mut cpu = Cpu::new(0x0);
+
+    // FS = Dirty を書き込むと SD が立つ
+    cpu.csr.write(0x300, 0b11 << 13).unwrap();
+    assert_eq!(cpu.csr.read(0x300).unwrap(), (1 << 31) | (0b11 << 13));
+
+    // FS = Clean に戻すと SD も落ちる
+    cpu.csr.write(0x300, 0b10 << 13).unwrap();
+    assert_eq!(cpu.csr.read(0x300).unwrap(), 0b10 << 13);
+
+    // SD への直接の書き込みは無視される
+    cpu.csr.write(0x300, 1 << 31).unwrap();
+    assert_eq!(cpu.csr.read(0x300).unwrap(), 0);
+}
+
+#[test]
+fn test_misa_reports_f_extension() {
+    let cpu = Cpu::new(0x0);
+    let misa = cpu.csr.read(0x301).unwrap();
+    assert_ne!(misa & (1 << 5), 0); // F
+}
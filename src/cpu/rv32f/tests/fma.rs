@@ -0,0 +1,71 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_fused_multiply_add_variants() {
+    // f1 = 2.0, f2 = 3.0, f4 = 1.0
+    // (命令, 期待値)
+    let cases = [
+        (0x2020f1c3, 0x40e0_0000u32), // fmadd.s  f3, f1, f2, f4:  2 * 3 + 1 =  7
+        (0x2020f1c7, 0x40a0_0000u32), // fmsub.s  f3, f1, f2, f4:  2 * 3 - 1 =  5
+        (0x2020f1cb, 0xc0a0_0000u32), // fnmsub.s f3, f1, f2, f4: -2 * 3 + 1 = -5
+        (0x2020f1cf, 0xc0e0_0000u32), // fnmadd.s f3, f1, f2, f4: -2 * 3 - 1 = -7
+    ];
+    for (inst, expected) in cases {
+        let mut cpu = Cpu::new(0x0);
+        let mut bus = MockBus::new();
+        cpu.csr.mstatus = 1 << 13;
+        cpu.fregs[1] = 0xffff_ffff_4000_0000; // 2.0
+        cpu.fregs[2] = 0xffff_ffff_4040_0000; // 3.0
+        cpu.fregs[4] = 0xffff_ffff_3f80_0000; // 1.0
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, inst);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.fregs[3], 0xffff_ffff_0000_0000 | expected as u64, "inst = {:08x}", inst);
+        assert_eq!(cpu.csr.fcsr, 0);
+        assert_eq!(cpu.pc, 0x4);
+    }
+}
+
+#[test]
+fn test_fmadd_s_single_rounding() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // (1 + 2^-23)^2 - (1 + 2^-22) = 2^-46
+    // 積を一度丸めてしまうと 0 になるため、一度だけ丸めていることを確認できる
+    cpu.fregs[1] = 0xffff_ffff_3f80_0001; // 1 + 2^-23
+    cpu.fregs[2] = 0xffff_ffff_3f80_0001; // 1 + 2^-23
+    cpu.fregs[4] = 0xffff_ffff_bf80_0002; // -(1 + 2^-22)
+
+    // fmadd.s f3, f1, f2, f4, dyn (0x2020f1c3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x2020f1c3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x2880_0000); // 2^-46
+    assert_eq!(cpu.csr.fcsr, 0);
+}
+
+#[test]
+fn test_fmadd_s_inf_times_zero_with_qnan() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    // ∞ × 0 は加数が quiet NaN でも無効演算
+    cpu.fregs[1] = 0xffff_ffff_7f80_0000; // +inf
+    cpu.fregs[2] = 0xffff_ffff_0000_0000; // 0.0
+    cpu.fregs[4] = 0xffff_ffff_7fc0_0000; // qNaN
+
+    // fmadd.s f3, f1, f2, f4, dyn (0x2020f1c3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x2020f1c3);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.fregs[3] as u32, 0x7fc0_0000);
+    assert_eq!(cpu.csr.fcsr, 0b10000); // NV
+}
@@ -0,0 +1,81 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::{Bus, MockBus};
+
+#[test]
+fn test_flw() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    cpu.regs[2] = 0x1000;
+    bus.write32(0x1004, 0x4049_0fdb).unwrap(); // 3.1415927
+
+    // flw f1, 4(x2) (0x00412087)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00412087);
+    cpu.step(&mut bus);
+
+    // ロードした値は NaN-boxing される
+    assert_eq!(cpu.fregs[1], 0xffff_ffff_4049_0fdb);
+    assert_eq!((cpu.csr.mstatus >> 13) & 0b11, 3); // FS = Dirty
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_fsw() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+
+    cpu.regs[2] = 0x1000;
+    cpu.fregs[1] = 0xffff_ffff_c0e0_0000; // -7.0
+
+    // fsw f1, 8(x2) (0x00112427)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00112427);
+    cpu.step(&mut bus);
+
+    assert_eq!(bus.read32(0x1008).unwrap(), 0xc0e0_0000);
+    // ストアは浮動小数点の状態を変更しない
+    assert_eq!((cpu.csr.mstatus >> 13) & 0b11, 1); // FS = Initial
+    assert_eq!(cpu.pc, 0x4);
+}
+
+#[test]
+fn test_flw_fp_disabled() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+    // mstatus.FS = Off
+
+    cpu.regs[2] = 0x1000;
+    bus.write32(0x1004, 0x3f80_0000).unwrap();
+
+    // flw f1, 4(x2) (0x00412087)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00412087);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2); // Illegal instruction
+    assert_eq!(cpu.fregs[1], 0);
+}
+
+#[test]
+fn test_flw_access_fault() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mstatus = 1 << 13;
+    cpu.csr.mtvec = 0x200;
+
+    // MockBus の範囲外のアドレス
+    cpu.regs[2] = 0x0003_0000;
+
+    // flw f1, 4(x2) (0x00412087)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00412087);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 5); // Load access fault
+    assert_eq!(cpu.csr.mtval, 0x0003_0004);
+}
@@ -1,5 +1,6 @@
 use crate::cpu::Cpu;
 use crate::cpu::privilege_mode::PrivilegeMode;
+use crate::cpu::csr::{MSTATUS_MPRV, MSTATUS_SIE, MSTATUS_SPIE, MSTATUS_SPP, MSTATUS_TSR, MSTATUS_TVM, MSTATUS_TW};
 use crate::cpu::StepResult;
 
 impl Cpu {
@@ -121,7 +122,10 @@ impl Cpu {
         let rs1: usize = rs1 as usize;
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
-        let val = bus.read8(addr) as i8 as i32 as u32;
+        let val = match self.load8(addr, bus) {
+            Ok(v) => v as i8 as i32 as u32,
+            Err(trap) => return trap,
+        };
         self.regs[rd] = val;
         StepResult::Ok(4)
     }
@@ -132,7 +136,10 @@ impl Cpu {
         let rs1: usize = rs1 as usize;
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
-        let val = bus.read16(addr) as i16 as i32 as u32;
+        let val = match self.load16(addr, bus) {
+            Ok(v) => v as i16 as i32 as u32,
+            Err(trap) => return trap,
+        };
         self.regs[rd] = val;
         StepResult::Ok(4)
     }
@@ -143,7 +150,10 @@ impl Cpu {
         let rs1: usize = rs1 as usize;
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
-        let val = bus.read32(addr);
+        let val = match self.load32(addr, bus) {
+            Ok(v) => v,
+            Err(trap) => return trap,
+        };
         self.regs[rd] = val;
         StepResult::Ok(4)
     }
@@ -154,7 +164,10 @@ impl Cpu {
         let rs1: usize = rs1 as usize;
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
-        let val = bus.read8(addr) as u32;
+        let val = match self.load8(addr, bus) {
+            Ok(v) => v as u32,
+            Err(trap) => return trap,
+        };
         self.regs[rd] = val;
         StepResult::Ok(4)
     }
@@ -165,7 +178,10 @@ impl Cpu {
         let rs1: usize = rs1 as usize;
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
-        let val = bus.read16(addr) as u32;
+        let val = match self.load16(addr, bus) {
+            Ok(v) => v as u32,
+            Err(trap) => return trap,
+        };
         self.regs[rd] = val;
         StepResult::Ok(4)
     }
@@ -177,7 +193,9 @@ impl Cpu {
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
         let val = (self.regs[rs2] & 0xff) as u8;
-        bus.write8(addr, val);
+        if let Err(trap) = self.store8(addr, val, bus) {
+            return trap;
+        }
         StepResult::Ok(4)
     }
 
@@ -188,7 +206,9 @@ impl Cpu {
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
         let val = (self.regs[rs2] & 0xffff) as u16;
-        bus.write16(addr, val);
+        if let Err(trap) = self.store16(addr, val, bus) {
+            return trap;
+        }
         StepResult::Ok(4)
     }
 
@@ -199,7 +219,9 @@ impl Cpu {
         let imm: u32   = (imm as i16 as i32) as u32;
         let addr = self.regs[rs1].wrapping_add(imm);
         let val = self.regs[rs2];
-        bus.write32(addr, val);
+        if let Err(trap) = self.store32(addr, val, bus) {
+            return trap;
+        }
         StepResult::Ok(4)
     }
 
@@ -392,13 +414,46 @@ impl Cpu {
 
     #[inline(always)]
     pub(crate) fn fence_i(&mut self) -> StepResult {
-        for page in self.pages.iter_mut() {
-            *page = None;
-        }
-        self.current_page_num = 0xffffffff;
+        self.flush_all_cache();
         StepResult::Ok(4)
     }
 
+    #[inline(always)]
+    pub(crate) fn sfence_vma(&mut self, rs1: u8, _rs2: u8) -> StepResult {
+        // sfence.vma は S モード以上で実行可能。mstatus.TVM が立っている場合は S モードからも実行できない
+        if self.mode == PrivilegeMode::User
+            || (self.mode == PrivilegeMode::Supervisor && (self.csr.mstatus & MSTATUS_TVM) != 0)
+        {
+            return StepResult::Trap(2); // Illegal Instruction
+        }
+
+        // ASID は実装しないため rs2 は無視する
+        if rs1 == 0 {
+            self.tlb.flush();
+        } else {
+            self.tlb.flush_page(self.regs[rs1 as usize] >> 12);
+        }
+        // 命令キャッシュは仮想ページで管理しているため、変換が変わりうるページを含めてすべて破棄する
+        self.flush_all_cache();
+        self.pc += 4;
+        StepResult::Jumped
+    }
+
+    #[inline(always)]
+    pub(crate) fn wfi(&mut self) -> StepResult {
+        // S モードが存在するため U モードの wfi は不正命令。
+        // mstatus.TW が立っている場合は S モードからも実行できない (待機時間の上限を 0 とみなす)
+        if self.mode == PrivilegeMode::User
+            || (self.mode == PrivilegeMode::Supervisor && (self.csr.mstatus & MSTATUS_TW) != 0)
+        {
+            return StepResult::Trap(2); // Illegal Instruction
+        }
+
+        self.pc += 4;
+        self.waiting = true;
+        StepResult::Waiting
+    }
+
     #[inline(always)]
     pub(crate) fn ecall(&mut self) -> StepResult {
         let code = match self.mode {
@@ -435,13 +490,47 @@ impl Cpu {
         let mpp = (self.csr.mstatus >> 11) & 0b11;
         self.mode = match mpp {
             0 => PrivilegeMode::User,
+            1 => PrivilegeMode::Supervisor,
             3 => PrivilegeMode::Machine,
-            _ => PrivilegeMode::User, // Sモードはサポートしていないので User に落とす
+            _ => PrivilegeMode::User,
         };
         // MPP is set to the least-privileged mode supported (User=0)
         self.csr.mstatus &= !(0b11 << 11);
+        // M モード以外に戻る場合は MPRV をクリアする
+        if self.mode != PrivilegeMode::Machine {
+            self.csr.mstatus &= !MSTATUS_MPRV;
+        }
 
         self.pc = next_pc;
         StepResult::Jumped
     }
+
+    #[inline(always)]
+    pub(crate) fn sret(&mut self) -> StepResult {
+        // sret は S モード以上で実行可能。mstatus.TSR が立っている場合は S モードからも実行できない
+        if self.mode == PrivilegeMode::User
+            || (self.mode == PrivilegeMode::Supervisor && (self.csr.mstatus & MSTATUS_TSR) != 0)
+        {
+            return StepResult::Trap(2); // Illegal Instruction
+        }
+
+        // SIE = SPIE, SPIE = 1
+        let spie = self.csr.mstatus & MSTATUS_SPIE != 0;
+        self.csr.mstatus &= !MSTATUS_SIE;
+        if spie {
+            self.csr.mstatus |= MSTATUS_SIE;
+        }
+        self.csr.mstatus |= MSTATUS_SPIE;
+
+        // SPP のモードに戻り、SPP = User
+        self.mode = if (self.csr.mstatus & MSTATUS_SPP) != 0 {
+            PrivilegeMode::Supervisor
+        } else {
+            PrivilegeMode::User
+        };
+        self.csr.mstatus &= !(MSTATUS_SPP | MSTATUS_MPRV);
+
+        self.pc = self.csr.sepc;
+        StepResult::Jumped
+    }
 }
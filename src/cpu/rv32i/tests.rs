@@ -8,3 +8,4 @@ mod jump;
 mod load;
 mod lui;
 mod store;
+mod supervisor;
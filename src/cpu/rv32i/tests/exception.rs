@@ -14,6 +14,9 @@ fn test_ecall_user() {
     cpu.mode = PrivilegeMode::User;
     cpu.csr.mtvec = 0x0200;
     cpu.csr.mstatus = 0b1000; // MIE = 1
+    // PMP: U モードから全アドレス空間を RWX でアクセス可能にする (NAPOT)
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
 
     // ECALL instruction
     let inst = 0x00000073;
@@ -75,7 +78,7 @@ fn test_mret() {
     // Set up state as if we are in a trap handler
     cpu.mode = PrivilegeMode::Machine;
     cpu.csr.mepc = 0x0104;
-    cpu.csr.mstatus = (3 << 11) | (1 << 7) | (0 << 3); // MPP=Machine, MPIE=1, MIE=0
+    cpu.csr.mstatus = (3 << 11) | (1 << 7); // MPP=Machine, MPIE=1, MIE=0
 
     // MRET instruction
     let inst = 0x30200073;
@@ -96,6 +99,9 @@ fn test_ecall_mret_flow() {
     cpu.mode = PrivilegeMode::User;
     cpu.csr.mtvec = 0x0200;
     cpu.csr.mstatus = 0b1000; // MIE = 1
+    // PMP: U モードから全アドレス空間を RWX でアクセス可能にする (NAPOT)
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
 
     // 1. ECALL at 0x0100
     cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x00000073);
@@ -104,6 +110,7 @@ fn test_ecall_mret_flow() {
     assert_eq!(cpu.pc, 0x0200);
     assert_eq!(cpu.mode, PrivilegeMode::Machine);
     assert_eq!(cpu.csr.mepc, 0x0100);
+    assert_eq!(cpu.csr.mcause, 8); // Environment call from U-mode
 
     // 2. In handler, increment mepc (to skip ecall) and then MRET
     cpu.csr.mepc += 4;
@@ -126,6 +133,121 @@ fn test_wfi() {
 
     let (result, _) = cpu.step(&mut bus);
 
-    assert!(matches!(result, crate::cpu::StepResult::Ok(4)));
+    assert!(matches!(result, crate::cpu::StepResult::Waiting));
+    assert_eq!(cpu.pc, 0x0104);
+    assert!(cpu.is_waiting());
+
+    // 割り込みが保留されるまでは命令を実行しない
+    let (result, clock) = cpu.step(&mut bus);
+    assert!(matches!(result, crate::cpu::StepResult::Waiting));
+    assert_eq!(clock, 0);
     assert_eq!(cpu.pc, 0x0104);
 }
+
+#[test]
+fn test_wfi_illegal_in_lower_modes() {
+    let mut cpu = Cpu::new(0x0100);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x0200;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // WFI instruction (0x10500073)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x10500073);
+
+    // U モードでは不正命令
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x0200);
+    assert_eq!(cpu.csr.mcause, 2);
+    assert!(!cpu.is_waiting());
+
+    // mstatus.TW が立っている場合は S モードでも不正命令
+    cpu.csr.write(0x300, 1 << 21).unwrap();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x0100;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x0200);
+    assert_eq!(cpu.csr.mcause, 2);
+
+    // TW = 0 なら S モードでも待機する
+    cpu.csr.write(0x300, 0).unwrap();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.pc = 0x0100;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x0104);
+    assert!(cpu.is_waiting());
+}
+
+#[test]
+fn test_load_access_fault() {
+    let mut cpu = Cpu::new(0x0100);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x0200;
+
+    // LW x1, 0(x2) (0x00012083)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x00012083);
+    // MockBus の範囲外のアドレス
+    cpu.regs[2] = 0x0003_0000;
+    cpu.regs[1] = 0x1234;
+
+    let (result, _) = cpu.step(&mut bus);
+
+    assert!(matches!(result, crate::cpu::StepResult::Trap(5)));
+    assert_eq!(cpu.pc, 0x0200);
+    assert_eq!(cpu.csr.mepc, 0x0100);
+    assert_eq!(cpu.csr.mcause, 5); // Load access fault
+    assert_eq!(cpu.csr.mtval, 0x0003_0000);
+    assert_eq!(cpu.regs[1], 0x1234); // rd は変更されない
+}
+
+#[test]
+fn test_store_access_fault() {
+    let mut cpu = Cpu::new(0x0100);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x0200;
+
+    // SW x1, 0(x2) (0x00112023)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x00112023);
+    // 最後の 2 バイトだけがメモリ内に収まるアドレス
+    cpu.regs[2] = 0x0001_fffe;
+
+    let (result, _) = cpu.step(&mut bus);
+
+    assert!(matches!(result, crate::cpu::StepResult::Trap(7)));
+    assert_eq!(cpu.pc, 0x0200);
+    assert_eq!(cpu.csr.mcause, 7); // Store/AMO access fault
+    assert_eq!(cpu.csr.mtval, 0x0001_fffe);
+}
+
+#[test]
+fn test_instruction_access_fault() {
+    // MockBus の範囲外から命令をフェッチする
+    let mut cpu = Cpu::new(0x0003_0000);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x0200;
+
+    let (result, _) = cpu.step(&mut bus);
+
+    assert!(matches!(result, crate::cpu::StepResult::Trap(1)));
+    assert_eq!(cpu.pc, 0x0200);
+    assert_eq!(cpu.csr.mepc, 0x0003_0000);
+    assert_eq!(cpu.csr.mcause, 1); // Instruction access fault
+    assert_eq!(cpu.csr.mtval, 0x0003_0000);
+}
+
+#[test]
+fn test_instruction_access_fault_second_half() {
+    // 32 ビット命令の後半が MockBus の範囲外にある
+    let mut cpu = Cpu::new(0x0001_fffe);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x0200;
+    // ADDI x1, x1, 1 (0x00108093) の下位 16 ビット
+    bus.write_inst16(0x0001_fffe, 0x8093);
+
+    let (result, _) = cpu.step(&mut bus);
+
+    assert!(matches!(result, crate::cpu::StepResult::Trap(1)));
+    assert_eq!(cpu.csr.mepc, 0x0001_fffe);
+    assert_eq!(cpu.csr.mtval, 0x0002_0000);
+}
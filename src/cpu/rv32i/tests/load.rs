@@ -17,14 +17,14 @@ fn test_lb() {
 
     // 1. 正の値をロード
     cpu.regs[2] = 0x1000;
-    bus.write8(0x1004, 0x7F);
+    bus.write8(0x1004, 0x7F).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x7F);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負の値をロード (符号拡張)
     cpu.pc = 0x1000;
-    bus.write8(0x1004, 0x80);
+    bus.write8(0x1004, 0x80).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0xFFFF_FF80);
     assert_eq!(cpu.pc, 0x1004);
@@ -34,7 +34,7 @@ fn test_lb() {
     let inst_bin = 0x00410003;
     cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, inst_bin);
     cpu.pc = 0x1000;
-    bus.write8(0x1004, 0x55);
+    bus.write8(0x1004, 0x55).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[0], 0);
     assert_eq!(cpu.pc, 0x1004);
@@ -53,14 +53,14 @@ fn test_lh() {
 
     // 1. 正の値をロード
     cpu.regs[2] = 0x1000;
-    bus.write16(0x1004, 0x7FFF);
+    bus.write16(0x1004, 0x7FFF).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x7FFF);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負の値をロード (符号拡張)
     cpu.pc = 0x1000;
-    bus.write16(0x1004, 0x8000);
+    bus.write16(0x1004, 0x8000).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0xFFFF_8000);
     assert_eq!(cpu.pc, 0x1004);
@@ -70,7 +70,7 @@ fn test_lh() {
     let inst_bin = 0x00411003;
     cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, inst_bin);
     cpu.pc = 0x1000;
-    bus.write16(0x1004, 0x1234);
+    bus.write16(0x1004, 0x1234).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[0], 0);
     assert_eq!(cpu.pc, 0x1004);
@@ -89,14 +89,14 @@ fn test_lw() {
 
     // 1. 通常のロード
     cpu.regs[2] = 0x1000;
-    bus.write32(0x1004, 0x12345678);
+    bus.write32(0x1004, 0x12345678).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x12345678);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負の値（のように見える値）のロード
     cpu.pc = 0x1000;
-    bus.write32(0x1004, 0x80000000);
+    bus.write32(0x1004, 0x80000000).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x80000000);
     assert_eq!(cpu.pc, 0x1004);
@@ -106,7 +106,7 @@ fn test_lw() {
     let inst_bin = 0x00412003;
     cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, inst_bin);
     cpu.pc = 0x1000;
-    bus.write32(0x1004, 0xDEADBEEF);
+    bus.write32(0x1004, 0xDEADBEEF).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[0], 0);
     assert_eq!(cpu.pc, 0x1004);
@@ -125,14 +125,14 @@ fn test_lbu() {
 
     // 1. 正の値をロード
     cpu.regs[2] = 0x1000;
-    bus.write8(0x1004, 0x7F);
+    bus.write8(0x1004, 0x7F).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x7F);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負の値（のように見える値）をロード (ゼロ拡張)
     cpu.pc = 0x1000;
-    bus.write8(0x1004, 0x80);
+    bus.write8(0x1004, 0x80).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x80);
     assert_eq!(cpu.pc, 0x1004);
@@ -142,7 +142,7 @@ fn test_lbu() {
     let inst_bin = 0x00414003;
     cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, inst_bin);
     cpu.pc = 0x1000;
-    bus.write8(0x1004, 0x55);
+    bus.write8(0x1004, 0x55).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[0], 0);
     assert_eq!(cpu.pc, 0x1004);
@@ -161,14 +161,14 @@ fn test_lhu() {
 
     // 1. 正の値をロード
     cpu.regs[2] = 0x1000;
-    bus.write16(0x1004, 0x7FFF);
+    bus.write16(0x1004, 0x7FFF).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x7FFF);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負の値（のように見える値）をロード (ゼロ拡張)
     cpu.pc = 0x1000;
-    bus.write16(0x1004, 0x8000);
+    bus.write16(0x1004, 0x8000).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[1], 0x8000);
     assert_eq!(cpu.pc, 0x1004);
@@ -178,8 +178,46 @@ fn test_lhu() {
     let inst_bin = 0x00415003;
     cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, inst_bin);
     cpu.pc = 0x1000;
-    bus.write16(0x1004, 0xAAAA);
+    bus.write16(0x1004, 0xAAAA).unwrap();
     cpu.step(&mut bus);
     assert_eq!(cpu.regs[0], 0);
     assert_eq!(cpu.pc, 0x1004);
 }
+
+#[test]
+fn test_lw_misaligned_emulate() {
+    let mut cpu = Cpu::new(0x1000);
+    let mut bus = MockBus::new();
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Emulate;
+
+    // LW x1, 4(x2) (0x00412083)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, 0x00412083);
+    bus.write32(0x1004, 0x44332211).unwrap();
+    bus.write32(0x1008, 0x88776655).unwrap();
+
+    // 0x1005 からの 4 バイトをバイト単位に分割して読み出す
+    cpu.regs[2] = 0x1001;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[1], 0x55443322);
+    assert_eq!(cpu.pc, 0x1004);
+}
+
+#[test]
+fn test_lw_misaligned_trap() {
+    let mut cpu = Cpu::new(0x1000);
+    let mut bus = MockBus::new();
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Trap;
+    cpu.csr.mtvec = 0x200;
+
+    // LW x1, 4(x2) (0x00412083)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, 0x00412083);
+
+    cpu.regs[1] = 0x1234;
+    cpu.regs[2] = 0x1001;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 4); // Load address misaligned
+    assert_eq!(cpu.csr.mtval, 0x1005);
+    assert_eq!(cpu.csr.mepc, 0x1000);
+    assert_eq!(cpu.regs[1], 0x1234);
+}
@@ -26,7 +26,7 @@ fn test_sb() {
     cpu.regs[1] = 0x1000;
     cpu.regs[2] = 0x12345678;
     cpu.step(&mut bus);
-    assert_eq!(bus.read8(0x1004), 0x78);
+    assert_eq!(bus.read8(0x1004).unwrap(), 0x78);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負のオフセット
@@ -42,7 +42,7 @@ fn test_sb() {
     cpu.regs[1] = 0x1008;
     cpu.regs[2] = 0xDEADBEEF;
     cpu.step(&mut bus);
-    assert_eq!(bus.read8(0x1004), 0xEF);
+    assert_eq!(bus.read8(0x1004).unwrap(), 0xEF);
     assert_eq!(cpu.pc, 0x1008);
 }
 
@@ -68,7 +68,7 @@ fn test_sh() {
     cpu.regs[1] = 0x1000;
     cpu.regs[2] = 0x12345678;
     cpu.step(&mut bus);
-    assert_eq!(bus.read16(0x1004), 0x5678);
+    assert_eq!(bus.read16(0x1004).unwrap(), 0x5678);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負のオフセット
@@ -84,7 +84,7 @@ fn test_sh() {
     cpu.regs[1] = 0x1008;
     cpu.regs[2] = 0xDEADBEEF;
     cpu.step(&mut bus);
-    assert_eq!(bus.read16(0x1004), 0xBEEF);
+    assert_eq!(bus.read16(0x1004).unwrap(), 0xBEEF);
     assert_eq!(cpu.pc, 0x1008);
 }
 
@@ -110,7 +110,7 @@ fn test_sw() {
     cpu.regs[1] = 0x1000;
     cpu.regs[2] = 0x12345678;
     cpu.step(&mut bus);
-    assert_eq!(bus.read32(0x1004), 0x12345678);
+    assert_eq!(bus.read32(0x1004).unwrap(), 0x12345678);
     assert_eq!(cpu.pc, 0x1004);
 
     // 2. 負のオフセット
@@ -126,6 +126,66 @@ fn test_sw() {
     cpu.regs[1] = 0x1008;
     cpu.regs[2] = 0xDEADBEEF;
     cpu.step(&mut bus);
-    assert_eq!(bus.read32(0x1004), 0xDEADBEEF);
+    assert_eq!(bus.read32(0x1004).unwrap(), 0xDEADBEEF);
     assert_eq!(cpu.pc, 0x1008);
 }
+
+#[test]
+fn test_sw_misaligned_emulate() {
+    let mut cpu = Cpu::new(0x1000);
+    let mut bus = MockBus::new();
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Emulate;
+
+    // SW x2, 4(x1) (0x0020A223)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, 0x0020A223);
+
+    // 0x1006 からの 4 バイトにバイト単位で書き込む
+    cpu.regs[1] = 0x1002;
+    cpu.regs[2] = 0x12345678;
+    cpu.step(&mut bus);
+    assert_eq!(bus.read32(0x1004).unwrap(), 0x5678_0000);
+    assert_eq!(bus.read32(0x1008).unwrap(), 0x0000_1234);
+    assert_eq!(cpu.pc, 0x1004);
+}
+
+#[test]
+fn test_sw_misaligned_trap() {
+    let mut cpu = Cpu::new(0x1000);
+    let mut bus = MockBus::new();
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Trap;
+    cpu.csr.mtvec = 0x200;
+
+    // SW x2, 4(x1) (0x0020A223)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, 0x0020A223);
+
+    cpu.regs[1] = 0x1002;
+    cpu.regs[2] = 0x12345678;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 6); // Store/AMO address misaligned
+    assert_eq!(cpu.csr.mtval, 0x1006);
+    // メモリは変更されない
+    assert_eq!(bus.read32(0x1004).unwrap(), 0);
+    assert_eq!(bus.read32(0x1008).unwrap(), 0);
+}
+
+#[test]
+fn test_sw_misaligned_emulate_end_of_memory() {
+    let mut cpu = Cpu::new(0x1000);
+    let mut bus = MockBus::new();
+    cpu.misaligned_access = crate::cpu::MisalignedAccess::Emulate;
+    cpu.csr.mtvec = 0x200;
+
+    // SW x2, 4(x1) (0x0020A223)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x1000, 0x0020A223);
+
+    // 0x1fffe からの 4 バイトは MockBus の終わりをまたぐ
+    cpu.regs[1] = 0x0001_fffa;
+    cpu.regs[2] = 0x12345678;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 7); // Store/AMO access fault
+    assert_eq!(cpu.csr.mtval, 0x0001_fffe);
+    // 範囲内のバイトも変更されない
+    assert_eq!(bus.read16(0x0001_fffe).unwrap(), 0);
+}
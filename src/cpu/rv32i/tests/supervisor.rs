@@ -0,0 +1,167 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_mret_to_supervisor() {
+    let mut cpu = Cpu::new(0x0200);
+    let mut bus = MockBus::new();
+    cpu.csr.mepc = 0x0300;
+    cpu.csr.write(0x300, 1 << 11).unwrap(); // MPP = Supervisor(1)
+
+    // MRET (0x30200073)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0200, 0x30200073);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x0300);
+    assert_eq!(cpu.mode, PrivilegeMode::Supervisor);
+    assert_eq!((cpu.csr.mstatus >> 11) & 0b11, 0); // MPP = User(0)
+}
+
+#[test]
+fn test_ecall_delegated_to_supervisor() {
+    let mut cpu = Cpu::new(0x0100);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.mtvec = 0x0200;
+    cpu.csr.stvec = 0x0400;
+    cpu.csr.write(0x302, 1 << 8).unwrap(); // medeleg: Environment call from U-mode
+    cpu.csr.write(0x100, 1 << 1).unwrap(); // sstatus.SIE = 1
+    // PMP: U モードから全アドレス空間を RWX でアクセス可能にする (NAPOT)
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // ECALL
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x00000073);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x0400);
+    assert_eq!(cpu.mode, PrivilegeMode::Supervisor);
+    assert_eq!(cpu.csr.sepc, 0x0100);
+    assert_eq!(cpu.csr.scause, 8);
+    assert_eq!(cpu.csr.mcause, 0); // M モードの CSR は変更されない
+    assert_eq!((cpu.csr.mstatus >> 1) & 1, 0); // SIE = 0
+    assert_eq!((cpu.csr.mstatus >> 5) & 1, 1); // SPIE = SIE(1)
+    assert_eq!((cpu.csr.mstatus >> 8) & 1, 0); // SPP = User(0)
+}
+
+#[test]
+fn test_ecall_from_supervisor_not_delegated() {
+    let mut cpu = Cpu::new(0x0100);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.mtvec = 0x0200;
+    cpu.csr.stvec = 0x0400;
+    cpu.csr.write(0x302, 1 << 8).unwrap(); // medeleg: U モードからの ecall のみ委譲
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // ECALL
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x00000073);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x0200);
+    assert_eq!(cpu.mode, PrivilegeMode::Machine);
+    assert_eq!(cpu.csr.mcause, 9); // Environment call from S-mode
+    assert_eq!((cpu.csr.mstatus >> 11) & 0b11, 1); // MPP = Supervisor(1)
+}
+
+#[test]
+fn test_machine_trap_is_never_delegated() {
+    let mut cpu = Cpu::new(0x0100);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x0200;
+    cpu.csr.stvec = 0x0400;
+    cpu.csr.write(0x302, 0xffff_ffff).unwrap();
+
+    // M モードからの ecall (11) は委譲できない
+    assert_eq!(cpu.csr.read(0x302).unwrap(), 0xb3ff);
+
+    // 不正命令は委譲されていても M モードで発生した場合は M モードで処理する
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x00000000);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x0200);
+    assert_eq!(cpu.mode, PrivilegeMode::Machine);
+    assert_eq!(cpu.csr.mcause, 2);
+    assert_eq!(cpu.csr.scause, 0);
+}
+
+#[test]
+fn test_sret() {
+    let mut cpu = Cpu::new(0x0100);
+    let mut bus = MockBus::new();
+    cpu.mode = PrivilegeMode::Supervisor;
+    cpu.csr.sepc = 0x0300;
+    cpu.csr.write(0x100, 1 << 5).unwrap(); // SPIE = 1, SPP = User(0)
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // SRET (0x10200073)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x10200073);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.pc, 0x0300);
+    assert_eq!(cpu.mode, PrivilegeMode::User);
+    assert_eq!((cpu.csr.mstatus >> 1) & 1, 1); // SIE = SPIE(1)
+    assert_eq!((cpu.csr.mstatus >> 5) & 1, 1); // SPIE = 1
+    assert_eq!((cpu.csr.mstatus >> 8) & 1, 0); // SPP = User(0)
+}
+
+#[test]
+fn test_sret_illegal() {
+    // User モードからの SRET、および TSR = 1 の S モードからの SRET は不正命令
+    for (mode, mstatus) in [(PrivilegeMode::User, 0), (PrivilegeMode::Supervisor, 1 << 22)] {
+        let mut cpu = Cpu::new(0x0100);
+        let mut bus = MockBus::new();
+        cpu.mode = mode;
+        cpu.csr.mtvec = 0x0200;
+        cpu.csr.sepc = 0x0300;
+        cpu.csr.write(0x300, mstatus).unwrap();
+        cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+        cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+        cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0100, 0x10200073);
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.pc, 0x0200, "mode = {:?}", mode);
+        assert_eq!(cpu.csr.mcause, 2);
+    }
+}
+
+#[test]
+fn test_sstatus_view() {
+    let mut cpu = Cpu::new(0x0);
+
+    // sstatus 経由では MIE/MPIE/MPP などの M モードのビットは変更できない
+    cpu.csr.write(0x100, 0xffff_ffff).unwrap();
+    let mstatus = cpu.csr.read(0x300).unwrap();
+    assert_eq!(mstatus & ((1 << 3) | (1 << 7) | (0b11 << 11)), 0);
+    assert_eq!(mstatus & ((1 << 1) | (1 << 5) | (1 << 8)), (1 << 1) | (1 << 5) | (1 << 8));
+
+    // M モードのビットは sstatus から見えない
+    cpu.csr.write(0x300, (1 << 3) | (0b11 << 11)).unwrap();
+    assert_eq!(cpu.csr.read(0x100).unwrap() & ((1 << 3) | (0b11 << 11)), 0);
+}
+
+#[test]
+fn test_sie_sip_follow_mideleg() {
+    let mut cpu = Cpu::new(0x0);
+
+    cpu.csr.write(0x303, 0xffff_ffff).unwrap();
+    assert_eq!(cpu.csr.read(0x303).unwrap(), 0x222); // SSIP, STIP, SEIP のみ委譲可能
+
+    // sie は委譲された割り込みのビットのみ読み書きできる
+    cpu.csr.write(0x104, 0xffff_ffff).unwrap();
+    assert_eq!(cpu.csr.read(0x304).unwrap(), 0x222);
+
+    // sip から書き込めるのは SSIP のみ
+    cpu.csr.write(0x144, 0xffff_ffff).unwrap();
+    assert_eq!(cpu.csr.read(0x344).unwrap(), 1 << 1);
+    assert_eq!(cpu.csr.read(0x144).unwrap(), 1 << 1);
+}
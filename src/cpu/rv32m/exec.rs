@@ -75,11 +75,7 @@ impl Cpu {
         let val1 = self.regs[rs1];
         let val2 = self.regs[rs2];
 
-        let result = if val2 == 0 {
-            u32::MAX
-        } else {
-            val1 / val2
-        };
+        let result = val1.checked_div(val2).unwrap_or(u32::MAX);
 
         self.regs[rd] = result;
         StepResult::Ok(4)
@@ -1,4 +1,6 @@
+use crate::bus::Bus;
 use crate::cpu::{Cpu, StepResult};
+use crate::cpu::csr::MSTATUS_TVM;
 
 impl Cpu {
     fn check_csr_privilege(&self, csr_addr: u32, is_write: bool) -> bool {
@@ -25,14 +27,40 @@ impl Cpu {
                 if (self.csr.mcounteren & (1 << idx)) == 0 {
                     return false;
                 }
+                // User モードでは scounteren も許可している必要がある
+                if mode == 0 && (self.csr.scounteren & (1 << idx)) == 0 {
+                    return false;
+                }
             }
         }
 
+        // mstatus.TVM が立っている場合、S モードから satp にはアクセスできない
+        if csr_addr == 0x180 && mode == 1 && (self.csr.mstatus & MSTATUS_TVM) != 0 {
+            return false;
+        }
+
         true
     }
 
+    /// CSR を読み出す。time/timeh はバス上の CLINT の mtime を反映してから読む
+    fn read_csr<B: Bus>(&mut self, csr_addr: u32, bus: &B) -> Result<u32, ()> {
+        if matches!(csr_addr, 0xc01 | 0xc81) {
+            self.csr.time = bus.get_mtime();
+        }
+        self.csr.read(csr_addr)
+    }
+
+    /// CSR に書き込む。satp を書き換えた場合は TLB と命令キャッシュを破棄する
+    fn write_csr(&mut self, csr_addr: u32, val: u32) -> Result<(), ()> {
+        self.csr.write(csr_addr, val)?;
+        if csr_addr == 0x180 {
+            self.flush_translation();
+        }
+        Ok(())
+    }
+
     #[inline(always)]
-    pub(crate) fn csrrw(&mut self, csr: u16, rd: u8, rs1: u8) -> StepResult {
+    pub(crate) fn csrrw<B: Bus>(&mut self, csr: u16, rd: u8, rs1: u8, bus: &B) -> StepResult {
         let rd:       usize = rd  as usize;
         let rs1:      usize = rs1 as usize;
         let csr_addr: u32   = csr as u32;
@@ -41,23 +69,23 @@ impl Cpu {
             return StepResult::Trap(2);
         }
 
-        let old_val = match self.csr.read(csr_addr) {
+        let old_val = match self.read_csr(csr_addr, bus) {
             Ok(v) => v,
             Err(_) => return StepResult::Trap(2),
         };
-        let new_val = self.regs[rs1 as usize];
+        let new_val = self.regs[rs1];
 
         if rd != 0 {
-            self.regs[rd as usize] = old_val;
+            self.regs[rd] = old_val;
         }
-        if let Err(_) = self.csr.write(csr_addr, new_val) {
+        if self.write_csr(csr_addr, new_val).is_err() {
             return StepResult::Trap(2);
         }
         StepResult::Ok(4)
     }
 
     #[inline(always)]
-    pub(crate) fn csrrs(&mut self, csr: u16, rd: u8, rs1: u8) -> StepResult {
+    pub(crate) fn csrrs<B: Bus>(&mut self, csr: u16, rd: u8, rs1: u8, bus: &B) -> StepResult {
         let rd:       usize = rd  as usize;
         let rs1:      usize = rs1 as usize;
         let csr_addr: u32   = csr as u32;
@@ -67,25 +95,23 @@ impl Cpu {
             return StepResult::Trap(2);
         }
 
-        let old_val = match self.csr.read(csr_addr) {
+        let old_val = match self.read_csr(csr_addr, bus) {
             Ok(v) => v,
             Err(_) => return StepResult::Trap(2),
         };
-        let set_mask = self.regs[rs1 as usize];
+        let set_mask = self.regs[rs1];
 
         if rd != 0 {
-            self.regs[rd as usize] = old_val;
+            self.regs[rd] = old_val;
         }
-        if rs1 != 0 {
-            if let Err(_) = self.csr.write(csr_addr, old_val | set_mask) {
-                return StepResult::Trap(2);
-            }
+        if rs1 != 0 && self.write_csr(csr_addr, self.csr.rmw_base(csr_addr, old_val) | set_mask).is_err() {
+            return StepResult::Trap(2);
         }
         StepResult::Ok(4)
     }
 
     #[inline(always)]
-    pub(crate) fn csrrc(&mut self, csr: u16, rd: u8, rs1: u8) -> StepResult {
+    pub(crate) fn csrrc<B: Bus>(&mut self, csr: u16, rd: u8, rs1: u8, bus: &B) -> StepResult {
         let rd:       usize = rd  as usize;
         let rs1:      usize = rs1 as usize;
         let csr_addr: u32   = csr as u32;
@@ -95,25 +121,23 @@ impl Cpu {
             return StepResult::Trap(2);
         }
 
-        let old_val = match self.csr.read(csr_addr) {
+        let old_val = match self.read_csr(csr_addr, bus) {
             Ok(v) => v,
             Err(_) => return StepResult::Trap(2),
         };
-        let clear_mask = self.regs[rs1 as usize];
+        let clear_mask = self.regs[rs1];
 
         if rd != 0 {
-            self.regs[rd as usize] = old_val;
+            self.regs[rd] = old_val;
         }
-        if rs1 != 0 {
-            if let Err(_) = self.csr.write(csr_addr, old_val & !clear_mask) {
-                return StepResult::Trap(2);
-            }
+        if rs1 != 0 && self.write_csr(csr_addr, self.csr.rmw_base(csr_addr, old_val) & !clear_mask).is_err() {
+            return StepResult::Trap(2);
         }
         StepResult::Ok(4)
     }
 
     #[inline(always)]
-    pub(crate) fn csrrwi(&mut self, csr: u16, rd: u8, uimm: u8) -> StepResult {
+    pub(crate) fn csrrwi<B: Bus>(&mut self, csr: u16, rd: u8, uimm: u8, bus: &B) -> StepResult {
         let rd:       usize = rd   as usize;
         let uimm:     u32   = uimm as u32;
         let csr_addr: u32   = csr  as u32;
@@ -122,22 +146,22 @@ impl Cpu {
             return StepResult::Trap(2);
         }
 
-        let old_val = match self.csr.read(csr_addr) {
+        let old_val = match self.read_csr(csr_addr, bus) {
             Ok(v) => v,
             Err(_) => return StepResult::Trap(2),
         };
 
         if rd != 0 {
-            self.regs[rd as usize] = old_val;
+            self.regs[rd] = old_val;
         }
-        if let Err(_) = self.csr.write(csr_addr, uimm) {
+        if self.write_csr(csr_addr, uimm).is_err() {
             return StepResult::Trap(2);
         }
         StepResult::Ok(4)
     }
 
     #[inline(always)]
-    pub(crate) fn csrrsi(&mut self, csr: u16, rd: u8, uimm: u8) -> StepResult {
+    pub(crate) fn csrrsi<B: Bus>(&mut self, csr: u16, rd: u8, uimm: u8, bus: &B) -> StepResult {
         let rd:       usize = rd   as usize;
         let uimm:     u32   = uimm as u32;
         let csr_addr: u32   = csr  as u32;
@@ -147,23 +171,21 @@ impl Cpu {
             return StepResult::Trap(2);
         }
 
-        let old_val = match self.csr.read(csr_addr) {
+        let old_val = match self.read_csr(csr_addr, bus) {
             Ok(v) => v,
             Err(_) => return StepResult::Trap(2),
         };
         if rd != 0 {
-            self.regs[rd as usize] = old_val;
+            self.regs[rd] = old_val;
         }
-        if uimm != 0 {
-            if let Err(_) = self.csr.write(csr_addr, old_val | uimm) {
-                return StepResult::Trap(2);
-            }
+        if uimm != 0 && self.write_csr(csr_addr, self.csr.rmw_base(csr_addr, old_val) | uimm).is_err() {
+            return StepResult::Trap(2);
         }
         StepResult::Ok(4)
     }
 
     #[inline(always)]
-    pub(crate) fn csrrci(&mut self, csr: u16, rd: u8, uimm: u8) -> StepResult {
+    pub(crate) fn csrrci<B: Bus>(&mut self, csr: u16, rd: u8, uimm: u8, bus: &B) -> StepResult {
         let rd:       usize = rd   as usize;
         let uimm:     u32   = uimm as u32;
         let csr_addr: u32   = csr  as u32;
@@ -173,17 +195,15 @@ impl Cpu {
             return StepResult::Trap(2);
         }
 
-        let old_val = match self.csr.read(csr_addr) {
+        let old_val = match self.read_csr(csr_addr, bus) {
             Ok(v) => v,
             Err(_) => return StepResult::Trap(2),
         };
         if rd != 0 {
-            self.regs[rd as usize] = old_val;
+            self.regs[rd] = old_val;
         }
-        if uimm != 0 {
-            if let Err(_) = self.csr.write(csr_addr, old_val & !uimm) {
-                return StepResult::Trap(2);
-            }
+        if uimm != 0 && self.write_csr(csr_addr, self.csr.rmw_base(csr_addr, old_val) & !uimm).is_err() {
+            return StepResult::Trap(2);
         }
         StepResult::Ok(4)
     }
@@ -1 +1,2 @@
-mod csr;
\ No newline at end of file
+mod csr;
+mod counter;
@@ -0,0 +1,154 @@
+#[allow(unused_imports)]
+use crate::cpu::Cpu;
+
+#[allow(unused_imports)]
+use crate::cpu::privilege_mode::PrivilegeMode;
+
+#[allow(unused_imports)]
+use crate::bus::mock_bus::MockBus;
+
+#[test]
+fn test_counters_count_retired_instructions() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // addi x0, x0, 0 (nop) x 2
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00000013);
+    bus.write_inst32(0x4, 0x00000013);
+    // csrrs x3, instret, x0 (0xc02021f3)
+    bus.write_inst32(0x8, 0xc02021f3);
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.csr.mcycle, 2);
+    assert_eq!(cpu.csr.minstret, 2);
+
+    // 読み出し命令自身はまだリタイアしていない
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 2);
+    assert_eq!(cpu.csr.minstret, 3);
+}
+
+#[test]
+fn test_counters_trap_does_not_retire() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mtvec = 0x200;
+
+    // 不正命令 (0x00000000)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x00000000);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.csr.mcause, 2);
+    assert_eq!(cpu.csr.mcycle, 0);
+    assert_eq!(cpu.csr.minstret, 0);
+}
+
+#[test]
+fn test_minstret_write_sticks() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.regs[1] = 100;
+
+    // csrrw x0, minstret, x1 (0xb0209073)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xb0209073);
+    // addi x0, x0, 0
+    bus.write_inst32(0x4, 0x00000013);
+
+    // 書き込んだ命令自身ではカウンタは進まない
+    cpu.step(&mut bus);
+    assert_eq!(cpu.csr.minstret, 100);
+    assert_eq!(cpu.csr.mcycle, 1);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.csr.minstret, 101);
+}
+
+#[test]
+fn test_counter_high_half() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    cpu.csr.mcycle = 0x0000_0001_ffff_ffff;
+
+    // csrrs x3, cycleh, x0 (0xc80021f3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xc80021f3);
+    // csrrw x0, mcycleh, x1 (0xb8009073)
+    bus.write_inst32(0x4, 0xb8009073);
+    cpu.regs[1] = 0xabcd;
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 1);
+
+    // 下位ワードの桁上がり
+    assert_eq!(cpu.csr.mcycle, 0x0000_0002_0000_0000);
+
+    cpu.step(&mut bus);
+    assert_eq!(cpu.csr.mcycle, 0x0000_abcd_0000_0000);
+}
+
+#[test]
+fn test_mcountinhibit() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+
+    // csrrwi x0, mcountinhibit, 7 (0x3203d073)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0x3203d073);
+    // addi x0, x0, 0
+    bus.write_inst32(0x4, 0x00000013);
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+
+    // TM (bit 1) は実装されていない
+    assert_eq!(cpu.csr.read(0x320).unwrap(), 0b101);
+    assert_eq!(cpu.csr.mcycle, 0);
+    assert_eq!(cpu.csr.minstret, 0);
+}
+
+#[test]
+fn test_time_mirrors_mtime() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    bus.mtime = 0x0000_0002_0000_1234;
+
+    // csrrs x3, time, x0 (0xc01021f3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xc01021f3);
+    // csrrs x4, timeh, x0 (0xc8102273)
+    bus.write_inst32(0x4, 0xc8102273);
+
+    cpu.step(&mut bus);
+    cpu.step(&mut bus);
+
+    assert_eq!(cpu.regs[3], 0x1234);
+    assert_eq!(cpu.regs[4], 0x2);
+}
+
+#[test]
+fn test_time_user_mode_mcounteren() {
+    let mut cpu = Cpu::new(0x0);
+    let mut bus = MockBus::new();
+    bus.mtime = 42;
+    cpu.csr.mtvec = 0x200;
+    cpu.mode = PrivilegeMode::User;
+    // PMP: U モードから全アドレス空間を RWX でアクセス可能にする (NAPOT)
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+
+    // mcounteren.TM = 0 の場合は不正命令
+    // csrrs x3, time, x0 (0xc01021f3)
+    cpu.flush_cache_line(cpu.pc); bus.write_inst32(0x0, 0xc01021f3);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.csr.mcause, 2);
+
+    // mcounteren.TM = 1 なら読み出せる
+    let mut cpu = Cpu::new(0x0);
+    cpu.mode = PrivilegeMode::User;
+    cpu.csr.write(0x3b0, 0xffff_ffff).unwrap();
+    cpu.csr.write(0x3a0, 0x1f).unwrap();
+    cpu.csr.mcounteren = 1 << 1;
+    cpu.csr.scounteren = 1 << 1;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.regs[3], 42);
+    assert_eq!(cpu.pc, 0x4);
+}
@@ -0,0 +1,288 @@
+use crate::bus::Bus;
+
+#[cfg(test)]
+mod tests;
+
+/// e_machine: RISC-V
+pub const EM_RISCV: u16 = 243;
+
+/// e_flags: C 拡張の命令を含む
+pub const EF_RISCV_RVC: u32 = 0x0001;
+/// e_flags: 浮動小数点 ABI (soft / single / double / quad)
+pub const EF_RISCV_FLOAT_ABI: u32 = 0x0006;
+/// e_flags: 単精度の浮動小数点 ABI
+pub const EF_RISCV_FLOAT_ABI_SINGLE: u32 = 0x0002;
+/// e_flags: RV32E (レジスタが 16 本) の ABI
+pub const EF_RISCV_RVE: u32 = 0x0008;
+/// e_flags: RVTSO のメモリモデルを要求する (1 ハートずつ順に実行するため、そのまま実行できる)
+pub const EF_RISCV_TSO: u32 = 0x0010;
+
+/// 実行できる e_flags のビット (RVE を含め、それ以外のビットが立っていれば受け付けない)
+const SUPPORTED_FLAGS: u32 = EF_RISCV_RVC | EF_RISCV_FLOAT_ABI | EF_RISCV_TSO;
+
+/// e_type: 実行可能ファイル
+const ET_EXEC: u16 = 2;
+/// p_type: ロードするセグメント
+const PT_LOAD: u32 = 1;
+/// sh_type: シンボルテーブル
+const SHT_SYMTAB: u32 = 2;
+
+/// ELF ヘッダ・プログラムヘッダ・セクションヘッダ・シンボルの 1 エントリのサイズ
+const EHDR_SIZE: usize = 52;
+const PHDR_SIZE: usize = 32;
+const SHDR_SIZE: usize = 40;
+const SYM_SIZE: usize = 16;
+
+/// BSS を埋める 0 (1 ページ分)
+static BSS_CHUNK: [u8; 4096] = [0; 4096];
+
+/// ELF ファイルを読み込めなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    /// ファイルが途中で終わっているか、ヘッダやテーブルがファイルの外を指している
+    Truncated,
+    /// ELF のマジックナンバーがない
+    NotElf,
+    /// 32 ビット (ELFCLASS32) のファイルではない
+    UnsupportedClass(u8),
+    /// リトルエンディアンのファイルではない
+    UnsupportedEndian(u8),
+    /// 実行可能ファイル (ET_EXEC) ではない
+    NotExecutable(u16),
+    /// RISC-V 用のファイルではない
+    UnsupportedMachine(u16),
+    /// 対応していない浮動小数点 ABI (double / quad) や RVE などを e_flags で要求している
+    UnsupportedFlags(u32),
+    /// `index` 番目のプログラムヘッダが不正 (ファイル上のサイズがメモリ上のサイズより大きいなど)
+    InvalidSegment { index: usize },
+    /// `addr` から `size` バイトのセグメントがバスのメモリに収まらない
+    SegmentDoesNotFit { addr: u32, size: u32 },
+}
+
+/// ロードするセグメント (PT_LOAD)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// ロードする物理アドレス (p_paddr)
+    pub paddr: u32,
+    /// 実行時の仮想アドレス (p_vaddr)
+    pub vaddr: u32,
+    /// ファイル上の内容 (p_filesz バイト)
+    pub data: Vec<u8>,
+    /// メモリ上のサイズ (p_memsz)。`data` より後ろ (BSS) は 0 で埋める
+    pub mem_size: u32,
+    /// 読み書き・実行の権限 (p_flags。R = 4, W = 2, X = 1)
+    pub flags: u32,
+}
+
+/// シンボルの種類 (st_info の下位 4 ビット)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    NoType,
+    Object,
+    Func,
+    Section,
+    File,
+    Other(u8),
+}
+
+/// シンボルテーブルの 1 エントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u32,
+    pub size: u32,
+    pub kind: SymbolKind,
+}
+
+/// RV32 の ELF 実行可能ファイル
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elf {
+    /// エントリポイント (`Cpu::new` に渡す PC)
+    pub entry: u32,
+    /// e_flags (`EF_RISCV_*`)
+    pub flags: u32,
+    /// ロードするセグメント
+    pub segments: Vec<Segment>,
+    /// 名前のあるシンボル (シンボルテーブルがなければ空)
+    pub symbols: Vec<Symbol>,
+}
+
+impl Elf {
+    /// ELF ファイルの内容を解析する。
+    /// ヘッダが RV32 (ELFCLASS32・リトルエンディアン・EM_RISCV) の実行可能ファイルで、
+    /// e_flags が対応している ABI (浮動小数点 ABI が soft か single で、RVE ではない) であることを確認する
+    pub fn parse(data: &[u8]) -> Result<Self, ElfError> {
+        let ident = data.get(..16).ok_or(ElfError::Truncated)?;
+        if ident[..4] != [0x7f, b'E', b'L', b'F'] {
+            return Err(ElfError::NotElf);
+        }
+        if ident[4] != 1 {
+            return Err(ElfError::UnsupportedClass(ident[4]));
+        }
+        if ident[5] != 1 {
+            return Err(ElfError::UnsupportedEndian(ident[5]));
+        }
+        if data.len() < EHDR_SIZE {
+            return Err(ElfError::Truncated);
+        }
+        let e_type = read_u16(data, 16)?;
+        if e_type != ET_EXEC {
+            return Err(ElfError::NotExecutable(e_type));
+        }
+        let machine = read_u16(data, 18)?;
+        if machine != EM_RISCV {
+            return Err(ElfError::UnsupportedMachine(machine));
+        }
+        // F 拡張までのため、double / quad の浮動小数点 ABI には対応しない。C 拡張はそのまま実行できる。
+        // RVE の ABI や未知のビットは、意味の異なるプログラムを黙って実行しないよう受け付けない
+        let flags = read_u32(data, 36)?;
+        if flags & !SUPPORTED_FLAGS != 0 || flags & EF_RISCV_FLOAT_ABI > EF_RISCV_FLOAT_ABI_SINGLE {
+            return Err(ElfError::UnsupportedFlags(flags));
+        }
+
+        Ok(Self {
+            entry: read_u32(data, 24)?,
+            flags,
+            segments: Self::parse_segments(data)?,
+            symbols: Self::parse_symbols(data)?,
+        })
+    }
+
+    /// プログラムヘッダから PT_LOAD のセグメントを取り出す
+    fn parse_segments(data: &[u8]) -> Result<Vec<Segment>, ElfError> {
+        let phoff = read_u32(data, 28)? as usize;
+        let phentsize = read_u16(data, 42)? as usize;
+        let phnum = read_u16(data, 44)? as usize;
+        if phnum > 0 && phentsize < PHDR_SIZE {
+            return Err(ElfError::Truncated);
+        }
+
+        let mut segments = Vec::new();
+        for index in 0..phnum {
+            let ph = phoff.checked_add(index * phentsize).ok_or(ElfError::Truncated)?;
+            if read_u32(data, ph)? != PT_LOAD {
+                continue;
+            }
+            let offset = read_u32(data, ph + 4)? as usize;
+            let file_size = read_u32(data, ph + 16)?;
+            let mem_size = read_u32(data, ph + 20)?;
+            if file_size > mem_size {
+                return Err(ElfError::InvalidSegment { index });
+            }
+            let contents = offset
+                .checked_add(file_size as usize)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(ElfError::Truncated)?;
+            segments.push(Segment {
+                paddr: read_u32(data, ph + 12)?,
+                vaddr: read_u32(data, ph + 8)?,
+                data: contents.to_vec(),
+                mem_size,
+                flags: read_u32(data, ph + 24)?,
+            });
+        }
+        Ok(segments)
+    }
+
+    /// セクションヘッダからシンボルテーブル (SHT_SYMTAB) を探し、名前のあるシンボルを取り出す
+    fn parse_symbols(data: &[u8]) -> Result<Vec<Symbol>, ElfError> {
+        let shoff = read_u32(data, 32)? as usize;
+        let shentsize = read_u16(data, 46)? as usize;
+        let shnum = read_u16(data, 48)? as usize;
+        if shoff == 0 || shnum == 0 {
+            return Ok(Vec::new());
+        }
+        if shentsize < SHDR_SIZE {
+            return Err(ElfError::Truncated);
+        }
+        let section = |index: usize| shoff.checked_add(index * shentsize).ok_or(ElfError::Truncated);
+
+        let mut symbols = Vec::new();
+        for index in 0..shnum {
+            let sh = section(index)?;
+            if read_u32(data, sh + 4)? != SHT_SYMTAB {
+                continue;
+            }
+            let offset = read_u32(data, sh + 16)? as usize;
+            let size = read_u32(data, sh + 20)? as usize;
+            // sh_link が名前の文字列テーブルのセクションを指す
+            let strtab = section(read_u32(data, sh + 24)? as usize)?;
+            let strtab = read_u32(data, strtab + 16)? as usize..;
+            let table = offset.checked_add(size).and_then(|end| data.get(offset..end)).ok_or(ElfError::Truncated)?;
+            let strings = data.get(strtab).ok_or(ElfError::Truncated)?;
+
+            for sym in table.chunks_exact(SYM_SIZE) {
+                let name_offset = read_u32(sym, 0)? as usize;
+                let name = strings.get(name_offset..).ok_or(ElfError::Truncated)?;
+                let name = &name[..name.iter().position(|&b| b == 0).ok_or(ElfError::Truncated)?];
+                if name.is_empty() {
+                    continue;
+                }
+                symbols.push(Symbol {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    value: read_u32(sym, 4)?,
+                    size: read_u32(sym, 8)?,
+                    kind: match sym[12] & 0xf {
+                        0 => SymbolKind::NoType,
+                        1 => SymbolKind::Object,
+                        2 => SymbolKind::Func,
+                        3 => SymbolKind::Section,
+                        4 => SymbolKind::File,
+                        other => SymbolKind::Other(other),
+                    },
+                });
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// セグメントをバスに書き込み (BSS は 0 で埋める)、エントリポイントを返す。
+    /// セグメントがバスのメモリに収まらなければ `ElfError::SegmentDoesNotFit` を返す
+    pub fn load<B: Bus>(&self, bus: &mut B) -> Result<u32, ElfError> {
+        for segment in &self.segments {
+            let does_not_fit = ElfError::SegmentDoesNotFit { addr: segment.paddr, size: segment.mem_size };
+            if segment.mem_size == 0 {
+                continue;
+            }
+            if segment.paddr.checked_add(segment.mem_size - 1).is_none() {
+                return Err(does_not_fit);
+            }
+            bus.load(segment.paddr, &segment.data).map_err(|_| does_not_fit)?;
+            // BSS は p_memsz が巨大でも確保しないよう、1 ページずつ 0 で埋める
+            let mut bss_addr = segment.paddr.wrapping_add(segment.data.len() as u32);
+            let mut bss = segment.mem_size - segment.data.len() as u32;
+            while bss > 0 {
+                let chunk = bss.min(BSS_CHUNK.len() as u32);
+                bus.load(bss_addr, &BSS_CHUNK[..chunk as usize]).map_err(|_| does_not_fit)?;
+                bss_addr = bss_addr.wrapping_add(chunk);
+                bss -= chunk;
+            }
+        }
+        Ok(self.entry)
+    }
+
+    /// 名前が `name` のシンボルの値 (アドレス) を返す
+    pub fn symbol(&self, name: &str) -> Option<u32> {
+        self.symbols.iter().find(|symbol| symbol.name == name).map(|symbol| symbol.value)
+    }
+
+    /// `addr` を含む関数・オブジェクトのシンボルを返す (トレースやデバッガでの表示用)
+    pub fn symbol_at(&self, addr: u32) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| {
+            matches!(symbol.kind, SymbolKind::Func | SymbolKind::Object)
+                && addr.wrapping_sub(symbol.value) < symbol.size.max(1)
+        })
+    }
+}
+
+/// `offset` からリトルエンディアンの 16 ビット値を読む
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, ElfError> {
+    let bytes = offset.checked_add(2).and_then(|end| data.get(offset..end)).ok_or(ElfError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// `offset` からリトルエンディアンの 32 ビット値を読む
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, ElfError> {
+    let bytes = offset.checked_add(4).and_then(|end| data.get(offset..end)).ok_or(ElfError::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
@@ -0,0 +1,165 @@
+use crate::bus::{Bus, BusError};
+use crate::bus::default_bus::DefaultBus;
+use crate::bus::memory_map::MemoryMap;
+use crate::cpu::{Cpu, StopReason};
+use crate::elf::{Elf, ElfError, SymbolKind, EF_RISCV_RVC};
+
+/// テスト用の最小の RV32 実行可能ファイル
+const PROGRAM: [u8; 324] = [
+    // e_ident: ELFCLASS32, リトルエンディアン
+    0x7f, 0x45, 0x4c, 0x46, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // e_type = ET_EXEC, e_machine = EM_RISCV, e_entry = 0x8000_0000, e_phoff = 52, e_shoff = 204, e_flags = RVC
+    0x02, 0x00, 0xf3, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x34, 0x00, 0x00, 0x00,
+    0xcc, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x34, 0x00, 0x20, 0x00, 0x02, 0x00, 0x28, 0x00,
+    0x03, 0x00, 0x00, 0x00,
+    // PT_LOAD: offset 128 → 0x8000_0000, filesz 8, memsz 8 (R+X)
+    0x01, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x80,
+    0x08, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+    // PT_LOAD: offset 136 → 0x8000_0100, filesz 4, memsz 16 (R+W, BSS 12 バイト)
+    0x01, 0x00, 0x00, 0x00, 0x88, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x80, 0x00, 0x01, 0x00, 0x80,
+    0x04, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+    // パディング
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // addi x1, x1, 1 / ecall
+    0x93, 0x80, 0x10, 0x00, 0x73, 0x00, 0x00, 0x00,
+    // .data
+    0x78, 0x56, 0x34, 0x12,
+    // .symtab: (null), _start (FUNC), tohost (OBJECT)
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x08, 0x00, 0x00, 0x00, 0x12, 0x00, 0x01, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x80, 0x04, 0x00, 0x00, 0x00, 0x11, 0x00, 0x02, 0x00,
+    // .strtab
+    0x00, 0x5f, 0x73, 0x74, 0x61, 0x72, 0x74, 0x00, 0x74, 0x6f, 0x68, 0x6f, 0x73, 0x74, 0x00, 0x00,
+    // セクションヘッダ: (null), .symtab (link = 2), .strtab
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x8c, 0x00, 0x00, 0x00, 0x30, 0x00, 0x00, 0x00,
+    0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xbc, 0x00, 0x00, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn test_elf_parse() {
+    let elf = Elf::parse(&PROGRAM).unwrap();
+    assert_eq!(elf.entry, 0x8000_0000);
+    assert_eq!(elf.flags, EF_RISCV_RVC);
+    assert_eq!(elf.segments.len(), 2);
+    assert_eq!(elf.segments[0].paddr, 0x8000_0000);
+    assert_eq!(elf.segments[0].data, [0x93, 0x80, 0x10, 0x00, 0x73, 0x00, 0x00, 0x00]);
+    assert_eq!(elf.segments[0].flags, 5);
+    assert_eq!(elf.segments[1].data.len(), 4);
+    assert_eq!(elf.segments[1].mem_size, 16);
+
+    // シンボルテーブル
+    assert_eq!(elf.symbols.len(), 2);
+    assert_eq!(elf.symbol("tohost"), Some(0x8000_0100));
+    assert_eq!(elf.symbol("fromhost"), None);
+    let symbol = elf.symbol_at(0x8000_0004).unwrap();
+    assert_eq!(symbol.name, "_start");
+    assert_eq!(symbol.kind, SymbolKind::Func);
+    assert_eq!(elf.symbol_at(0x8000_0104), None);
+}
+
+#[test]
+fn test_elf_load_and_run() {
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x1000).unwrap();
+    // BSS に残っているゴミは 0 で埋められる
+    bus.write32(0x8000_0104, 0xdead_beef).unwrap();
+    bus.write32(0x8000_010c, 0xdead_beef).unwrap();
+    bus.write32(0x8000_0110, 0xdead_beef).unwrap();
+
+    let elf = Elf::parse(&PROGRAM).unwrap();
+    let entry = elf.load(&mut bus).unwrap();
+    assert_eq!(entry, 0x8000_0000);
+    assert_eq!(bus.read32(0x8000_0100), Ok(0x1234_5678));
+    assert_eq!(bus.read32(0x8000_0104), Ok(0));
+    assert_eq!(bus.read32(0x8000_010c), Ok(0));
+    // セグメントの後ろは書き換えない
+    assert_eq!(bus.read32(0x8000_0110), Ok(0xdead_beef));
+
+    let mut cpu = Cpu::new(entry);
+    let (reason, _) = cpu.run(&mut bus, 10);
+    assert_eq!(reason, StopReason::Trap(11));
+    assert_eq!(cpu.regs[1], 1);
+}
+
+#[test]
+fn test_elf_segment_does_not_fit() {
+    let elf = Elf::parse(&PROGRAM).unwrap();
+
+    // 2 つ目のセグメントの BSS が RAM の終わりを超える
+    let mut bus = MemoryMap::new();
+    bus.add_ram(0x8000_0000, 0x108).unwrap();
+    assert_eq!(elf.load(&mut bus), Err(ElfError::SegmentDoesNotFit { addr: 0x8000_0100, size: 16 }));
+
+    // アドレス 0 からのメモリには収まらない (黙って切り捨てない)
+    let mut bus = DefaultBus::new(0x1000);
+    assert_eq!(elf.load(&mut bus), Err(ElfError::SegmentDoesNotFit { addr: 0x8000_0000, size: 8 }));
+    assert_eq!(bus.load(0xffc, &[0; 8]), Err(BusError::Unmapped));
+    assert!(bus.memory()[0xffc..].iter().all(|&b| b == 0));
+
+    // p_memsz が巨大でも BSS をまとめて確保せず、収まらない時点でエラーにする
+    for mem_size in [0xffff_ff00u32, 0x7fff_ff00] {
+        let mut data = PROGRAM;
+        data[84 + 20..84 + 24].copy_from_slice(&mem_size.to_le_bytes());
+        let elf = Elf::parse(&data).unwrap();
+        let mut bus = MemoryMap::new();
+        bus.add_ram(0x8000_0000, 0x2000).unwrap();
+        assert_eq!(elf.load(&mut bus), Err(ElfError::SegmentDoesNotFit { addr: 0x8000_0100, size: mem_size }));
+    }
+}
+
+#[test]
+fn test_elf_header_validation() {
+    assert_eq!(Elf::parse(&PROGRAM[..40]), Err(ElfError::Truncated));
+    assert_eq!(Elf::parse(&[0x93, 0x80, 0x10, 0x00]), Err(ElfError::Truncated));
+    assert_eq!(Elf::parse(&[0; 64]), Err(ElfError::NotElf));
+
+    let mut data = PROGRAM;
+    data[4] = 2; // ELFCLASS64
+    assert_eq!(Elf::parse(&data), Err(ElfError::UnsupportedClass(2)));
+
+    let mut data = PROGRAM;
+    data[5] = 2; // ビッグエンディアン
+    assert_eq!(Elf::parse(&data), Err(ElfError::UnsupportedEndian(2)));
+
+    let mut data = PROGRAM;
+    data[16] = 1; // ET_REL
+    assert_eq!(Elf::parse(&data), Err(ElfError::NotExecutable(1)));
+
+    let mut data = PROGRAM;
+    data[18] = 0x3e; // EM_X86_64
+    assert_eq!(Elf::parse(&data), Err(ElfError::UnsupportedMachine(0x3e)));
+
+    // 単精度の浮動小数点 ABI は受け付け、倍精度は受け付けない
+    let mut data = PROGRAM;
+    data[36] = 0x3;
+    assert_eq!(Elf::parse(&data).unwrap().flags, 0x3);
+    data[36] = 0x5;
+    assert_eq!(Elf::parse(&data), Err(ElfError::UnsupportedFlags(0x5)));
+
+    // RVE の ABI や未知のビットは受け付けず、RVTSO は受け付ける
+    data[36] = 0x9;
+    assert_eq!(Elf::parse(&data), Err(ElfError::UnsupportedFlags(0x9)));
+    data[36] = 0x21;
+    assert_eq!(Elf::parse(&data), Err(ElfError::UnsupportedFlags(0x21)));
+    data[36] = 0x11;
+    assert_eq!(Elf::parse(&data).unwrap().flags, 0x11);
+
+    // セグメントの内容がファイルの外を指している
+    assert_eq!(Elf::parse(&PROGRAM[..130]), Err(ElfError::Truncated));
+
+    // ファイル上のサイズがメモリ上のサイズより大きい
+    let mut data = PROGRAM;
+    data[84 + 20] = 2;
+    assert_eq!(Elf::parse(&data), Err(ElfError::InvalidSegment { index: 1 }));
+
+    // セクションヘッダがなければシンボルは空
+    let mut data = PROGRAM;
+    data[48] = 0;
+    assert!(Elf::parse(&data).unwrap().symbols.is_empty());
+}
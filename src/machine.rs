@@ -0,0 +1,246 @@
+use crate::bus::{Bus, BusError};
+use crate::bus::device::AccessWidth;
+use crate::cpu::{Cpu, StopReason};
+
+#[cfg(test)]
+mod tests;
+
+/// 1 つのハートを続けて実行するサイクル数のデフォルト
+pub const DEFAULT_QUANTUM: u32 = 1000;
+
+/// `Machine::run` が停止した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineStop {
+    /// 指定したサイクル数を実行した
+    BudgetExhausted,
+    /// ハート `hart` でトラップが発生した (トラップハンドラへ移った状態で停止する)
+    Trap { hart: usize, code: u32 },
+    /// すべてのハートが WFI で待機している
+    AllWaiting,
+}
+
+/// 1 つのバスを共有する複数のハート (SMP)。
+/// 各ハートを `quantum` サイクルずつ順番に実行し、1 周 (ラウンド) ごとに共有のクロックを進める。
+/// ハート h の `mhartid` は h で、CLINT の msip・mtimecmp と PLIC のコンテキストはこの番号で選ぶ
+pub struct Machine<B: Bus> {
+    /// 各ハート (インデックスが mhartid)
+    pub harts: Vec<Cpu>,
+    /// 全ハートで共有するバス
+    pub bus: B,
+    /// 1 つのハートを続けて実行する最大サイクル数
+    pub quantum: u32,
+    /// 現在のラウンドで次に実行するハート
+    next: usize,
+    /// 現在のラウンドで各ハートが進めたサイクル数の最大値
+    round_cycles: u32,
+    /// 各ハートがまだ受け取っていない、命令キャッシュに載っているページへの書き込み
+    code_writes: Vec<Vec<u32>>,
+    /// 実行中のハート以外が LR.W で予約している物理アドレス
+    reservations: Vec<Option<u32>>,
+    /// 実行中のハートの書き込みで予約が破棄されたハート
+    broken: Vec<bool>,
+}
+
+impl<B: Bus> Machine<B> {
+    /// `pc` から実行を始める `hart_count` 個のハートを作る。
+    /// バスの CLINT・PLIC は、あらかじめハートの数に合わせて用意しておく
+    pub fn new(bus: B, hart_count: usize, pc: u32) -> Self {
+        assert!(hart_count > 0, "ハートは 1 つ以上必要です");
+        let harts = (0..hart_count)
+            .map(|hart| {
+                let mut cpu = Cpu::new(pc);
+                cpu.csr.mhartid = hart as u32;
+                cpu
+            })
+            .collect();
+        Self {
+            harts,
+            bus,
+            quantum: DEFAULT_QUANTUM,
+            next: 0,
+            round_cycles: 0,
+            code_writes: vec![Vec::new(); hart_count],
+            reservations: vec![None; hart_count],
+            broken: vec![false; hart_count],
+        }
+    }
+
+    /// 共有のクロックを最大 `cycles` サイクル進めるまで、各ハートを順番に実行する。
+    /// いずれかのハートでトラップが発生するか、すべてのハートが待機していれば途中で停止し、
+    /// 停止した理由と進めたサイクル数を返す。トラップで停止した場合は、次の呼び出しで続きのハートから実行する
+    pub fn run(&mut self, cycles: u64) -> (MachineStop, u64) {
+        let mut elapsed = 0;
+        while elapsed < cycles {
+            while self.next < self.harts.len() {
+                let hart = self.next;
+                self.next += 1;
+                let budget = (cycles - elapsed).min(self.quantum.max(1) as u64) as u32;
+                let (reason, consumed) = self.run_hart(hart, budget);
+                self.round_cycles = self.round_cycles.max(consumed);
+                if let StopReason::Trap(code) = reason {
+                    return (MachineStop::Trap { hart, code }, elapsed);
+                }
+            }
+
+            // 各ハートは並行して動いているものとして、最も進んだハートに合わせて共有のクロックを進める
+            self.bus.tick(self.round_cycles);
+            elapsed += self.round_cycles as u64;
+            self.round_cycles = 0;
+            self.next = 0;
+            if self.harts.iter().all(|cpu| cpu.is_waiting()) {
+                return (MachineStop::AllWaiting, elapsed);
+            }
+        }
+        (MachineStop::BudgetExhausted, elapsed)
+    }
+
+    /// ハート `hart` を最大 `budget` サイクル実行し、停止した理由と進めたサイクル数を返す
+    fn run_hart(&mut self, hart: usize, budget: u32) -> (StopReason, u32) {
+        // 実行中は他のハートは動かないため、予約はここで写しておけばよい
+        for (other, cpu) in self.harts.iter().enumerate() {
+            self.reservations[other] = if other == hart { None } else { cpu.reserved_address() };
+        }
+        let mut bus = HartBus {
+            reserved: self.reservations.iter().any(Option::is_some),
+            bus: &mut self.bus,
+            hart,
+            reservations: &self.reservations,
+            broken: &mut self.broken,
+            code_writes: &mut self.code_writes,
+            cycles: 0,
+        };
+        let (reason, _) = self.harts[hart].run(&mut bus, budget);
+        let cycles = bus.cycles;
+
+        for (cpu, broken) in self.harts.iter_mut().zip(self.broken.iter_mut()) {
+            if *broken {
+                cpu.clear_reservation();
+                *broken = false;
+            }
+        }
+        (reason, cycles)
+    }
+}
+
+/// 1 つのハートから見たバス。
+/// クロックはラウンドの終わりにまとめて進めるため、ハートが進めたサイクル数を数えるだけにする。
+/// 書き込みは他のハートの LR/SC の予約と照合し、命令キャッシュに載っているページへの書き込みは全ハートに配る
+struct HartBus<'a, B: Bus> {
+    bus: &'a mut B,
+    hart: usize,
+    /// 他のハートが予約しているか (予約がなければ書き込みの照合を省く)
+    reserved: bool,
+    reservations: &'a [Option<u32>],
+    broken: &'a mut [bool],
+    code_writes: &'a mut [Vec<u32>],
+    /// このハートが進めたサイクル数
+    cycles: u32,
+}
+
+impl<B: Bus> HartBus<'_, B> {
+    /// `addr` から `size` バイトへの書き込みが他のハートの予約と重なっていれば、その予約を破棄させる
+    #[inline(always)]
+    fn note_write(&mut self, addr: u32, size: u32) {
+        if !self.reserved {
+            return;
+        }
+        for (hart, reserved) in self.reservations.iter().enumerate() {
+            if let Some(reserved) = *reserved
+                && addr < reserved.wrapping_add(4)
+                && reserved < addr.wrapping_add(size)
+            {
+                self.broken[hart] = true;
+            }
+        }
+    }
+}
+
+impl<B: Bus> Bus for HartBus<'_, B> {
+    fn read8(&mut self, addr: u32) -> Result<u8, BusError> {
+        self.bus.read8(addr)
+    }
+
+    fn read16(&mut self, addr: u32) -> Result<u16, BusError> {
+        self.bus.read16(addr)
+    }
+
+    fn read32(&mut self, addr: u32) -> Result<u32, BusError> {
+        self.bus.read32(addr)
+    }
+
+    fn write8(&mut self, addr: u32, val: u8) -> Result<(), BusError> {
+        self.bus.write8(addr, val)?;
+        self.note_write(addr, 1);
+        Ok(())
+    }
+
+    fn write16(&mut self, addr: u32, val: u16) -> Result<(), BusError> {
+        self.bus.write16(addr, val)?;
+        self.note_write(addr, 2);
+        Ok(())
+    }
+
+    fn write32(&mut self, addr: u32, val: u32) -> Result<(), BusError> {
+        self.bus.write32(addr, val)?;
+        self.note_write(addr, 4);
+        Ok(())
+    }
+
+    fn check_write(&self, addr: u32, width: AccessWidth) -> Result<(), BusError> {
+        self.bus.check_write(addr, width)
+    }
+
+    fn get_interrupt_level(&self, hart: usize) -> bool {
+        self.bus.get_interrupt_level(hart)
+    }
+
+    fn get_supervisor_interrupt_level(&self, hart: usize) -> Option<bool> {
+        self.bus.get_supervisor_interrupt_level(hart)
+    }
+
+    fn get_timer_interrupt_level(&self, hart: usize) -> bool {
+        self.bus.get_timer_interrupt_level(hart)
+    }
+
+    fn get_software_interrupt_level(&self, hart: usize) -> bool {
+        self.bus.get_software_interrupt_level(hart)
+    }
+
+    fn take_supervisor_software_interrupt(&mut self, hart: usize) -> bool {
+        self.bus.take_supervisor_software_interrupt(hart)
+    }
+
+    fn get_mtime(&self) -> u64 {
+        self.bus.get_mtime()
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.cycles += cycles;
+    }
+
+    fn watch_code_page(&mut self, page: u32) {
+        self.bus.watch_code_page(page);
+    }
+
+    fn take_code_write(&mut self) -> Option<u32> {
+        if let Some(page) = self.code_writes[self.hart].pop() {
+            return Some(page);
+        }
+        // バスが記録した書き込みは 1 度しか取り出せないため、他のハートの分を取っておく
+        let page = self.bus.take_code_write()?;
+        for (hart, pending) in self.code_writes.iter_mut().enumerate() {
+            if hart != self.hart && !pending.contains(&page) {
+                pending.push(page);
+            }
+        }
+        Some(page)
+    }
+
+    fn plic_claim(&mut self, hart: usize) -> u32 {
+        self.bus.plic_claim(hart)
+    }
+
+    fn plic_complete(&mut self, hart: usize, source_id: u32) {
+        self.bus.plic_complete(hart, source_id);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::bus::Bus;
+use crate::bus::default_bus::{DefaultBus, CLINT_BASE};
+use crate::machine::{Machine, MachineStop};
+
+#[test]
+fn test_machine_mhartid() {
+    let mut bus = DefaultBus::with_harts(0x10000, 3);
+    // 0x0: csrr x10, mhartid
+    // 0x4: jal x0, 0
+    bus.write32(0x0, 0xf1402573).unwrap();
+    bus.write32(0x4, 0x0000006f).unwrap();
+
+    let mut machine = Machine::new(bus, 3, 0x0);
+    let (stop, elapsed) = machine.run(30);
+    assert_eq!(stop, MachineStop::BudgetExhausted);
+    assert_eq!(elapsed, 30);
+    for (hart, cpu) in machine.harts.iter().enumerate() {
+        assert_eq!(cpu.regs[10], hart as u32);
+    }
+}
+
+#[test]
+fn test_machine_round_robin_quantum() {
+    let mut bus = DefaultBus::with_harts(0x10000, 2);
+    // 0x0: addi x1, x1, 1
+    // 0x4: jal x0, -4
+    bus.write32(0x0, 0x00108093).unwrap();
+    bus.write32(0x4, 0xffdff06f).unwrap();
+
+    let mut machine = Machine::new(bus, 2, 0x0);
+    machine.quantum = 10;
+
+    // ハートは 10 サイクルずつ交互に動き、共有のクロックはラウンドごとに 10 進む
+    let (stop, elapsed) = machine.run(25);
+    assert_eq!(stop, MachineStop::BudgetExhausted);
+    assert_eq!(elapsed, 25);
+    assert_eq!(machine.bus.clint.mtime, 25);
+    for cpu in &machine.harts {
+        assert_eq!(cpu.csr.minstret, 25);
+        assert_eq!(cpu.regs[1], 13);
+    }
+}
+
+#[test]
+fn test_machine_per_hart_clint_interrupts() {
+    let mut bus = DefaultBus::with_harts(0x10000, 2);
+    // ハート 0
+    // 0x100: sw x11, 0(x10)
+    // 0x104: jal x0, 0
+    bus.write32(0x100, 0x00b52023).unwrap();
+    bus.write32(0x104, 0x0000006f).unwrap();
+    // ハート 1
+    // 0x0: wfi
+    // 0x4: jal x0, 0
+    bus.write32(0x0, 0x10500073).unwrap();
+    bus.write32(0x4, 0x0000006f).unwrap();
+    // 0x200: jal x0, 0 (トラップハンドラ)
+    bus.write32(0x200, 0x0000006f).unwrap();
+    bus.clint.mtimecmp = vec![u64::MAX, u64::MAX];
+
+    let mut machine = Machine::new(bus, 2, 0x0);
+    machine.quantum = 10;
+    machine.harts[0].pc = 0x100;
+    machine.harts[0].regs[10] = CLINT_BASE + 4;
+    machine.harts[0].regs[11] = 1;
+    for cpu in &mut machine.harts {
+        cpu.csr.mstatus = 1 << 3; // MIE
+        cpu.csr.mie = (1 << 3) | (1 << 7); // MSIE, MTIE
+        cpu.csr.mtvec = 0x200;
+    }
+
+    // ハート 0 が msip[1] に書き込むと、ハート 1 だけにソフトウェア割り込みが届く
+    let (stop, _) = machine.run(100);
+    assert_eq!(stop, MachineStop::Trap { hart: 1, code: 0x8000_0003 });
+    assert_eq!(machine.harts[1].pc, 0x200);
+    assert_eq!(machine.harts[0].csr.mip & (1 << 3), 0);
+
+    // mtimecmp[0] に達すると、ハート 0 だけにタイマー割り込みが届く
+    machine.bus.clint.msip[1] = 0;
+    machine.bus.clint.mtimecmp[0] = machine.bus.clint.mtime + 15;
+    let (stop, elapsed) = machine.run(100);
+    assert_eq!(stop, MachineStop::Trap { hart: 0, code: 0x8000_0007 });
+    assert_eq!(elapsed, 20);
+    assert_eq!(machine.harts[1].csr.mip & (1 << 7), 0);
+}
+
+#[test]
+fn test_machine_per_hart_plic_context() {
+    let mut bus = DefaultBus::with_harts(0x10000, 2);
+    // 0x0: jal x0, 0
+    bus.write32(0x0, 0x0000006f).unwrap();
+    bus.plic.priorities[1] = 1;
+    // ハート 1 の M モードのコンテキスト (コンテキスト 2) でだけソース 1 を有効にする
+    bus.plic.set_enabled(2, 1, true);
+    bus.plic.set_interrupt(1);
+
+    let mut machine = Machine::new(bus, 2, 0x0);
+    for cpu in &mut machine.harts {
+        cpu.csr.mstatus = 1 << 3; // MIE
+        cpu.csr.mie = 1 << 11; // MEIE
+        cpu.csr.mtvec = 0x200;
+    }
+
+    let (stop, _) = machine.run(100);
+    assert_eq!(stop, MachineStop::Trap { hart: 1, code: 0x8000_000b });
+    assert_eq!(machine.harts[0].pc, 0x0);
+
+    // Claim はハート自身のコンテキストで行う
+    let Machine { harts, bus, .. } = &mut machine;
+    assert_eq!(harts[0].claim_interrupt(bus), 0);
+    assert_eq!(harts[1].claim_interrupt(bus), 1);
+    assert!(bus.plic.is_claimed(1));
+}
+
+#[test]
+fn test_machine_store_breaks_other_hart_reservation() {
+    let mut bus = DefaultBus::with_harts(0x10000, 2);
+    // ハート 0
+    // 0x0: lr.w x1, (x10)
+    // 0x4: sc.w x2, x11, (x10)
+    // 0x8: jal x0, 0
+    bus.write32(0x0, 0x100520af).unwrap();
+    bus.write32(0x4, 0x18b5212f).unwrap();
+    bus.write32(0x8, 0x0000006f).unwrap();
+    // ハート 1
+    // 0x100: sw x11, 0(x10)
+    // 0x104: jal x0, 0
+    bus.write32(0x100, 0x00b52023).unwrap();
+    bus.write32(0x104, 0x0000006f).unwrap();
+
+    let mut machine = Machine::new(bus, 2, 0x0);
+    machine.quantum = 1;
+    machine.harts[1].pc = 0x100;
+    for (hart, cpu) in machine.harts.iter_mut().enumerate() {
+        cpu.regs[10] = 0x800;
+        cpu.regs[11] = 0x10 + hart as u32;
+    }
+
+    // ハート 0 が LR.W で予約した後、ハート 1 が同じアドレスに書き込むと予約は破棄される
+    machine.run(1);
+    assert_eq!(machine.harts[0].reserved_address(), None);
+
+    // SC.W は失敗し、ハート 1 の書き込みが残る
+    machine.run(1);
+    assert_eq!(machine.harts[0].regs[2], 1);
+    assert_eq!(machine.bus.read32(0x800), Ok(0x11));
+}
+
+#[test]
+fn test_machine_code_write_invalidates_other_harts() {
+    let mut bus = DefaultBus::with_harts(0x10000, 3);
+    // ハート 0, 1
+    // 0x0: addi x1, x0, 1
+    // 0x4: jal x0, -4
+    bus.write32(0x0, 0x00100093).unwrap();
+    bus.write32(0x4, 0xffdff06f).unwrap();
+    // ハート 2
+    // 0x100: sw x11, 0(x10)
+    // 0x104: jal x0, 0
+    bus.write32(0x100, 0x00b52023).unwrap();
+    bus.write32(0x104, 0x0000006f).unwrap();
+
+    let mut machine = Machine::new(bus, 3, 0x0);
+    machine.quantum = 4;
+    machine.harts[2].pc = 0x100;
+    machine.harts[2].regs[10] = 0x0;
+    machine.harts[2].regs[11] = 0x00200093; // addi x1, x0, 2
+
+    // ハート 2 の書き込みは、同じページを実行している他のすべてのハートの命令キャッシュを破棄する
+    machine.run(4);
+    assert_eq!(machine.harts[0].regs[1], 1);
+    assert_eq!(machine.harts[1].regs[1], 1);
+    machine.run(4);
+    assert_eq!(machine.harts[0].regs[1], 2);
+    assert_eq!(machine.harts[1].regs[1], 2);
+}
@@ -1,55 +1,134 @@
-use rv32imc::bus::default_bus::DefaultBus;
+use rv32imc::bus::default_bus::{CLINT_BASE, PLIC_BASE};
+use rv32imc::bus::htif::Htif;
+use rv32imc::bus::memory_map::MemoryMap;
+use rv32imc::elf::Elf;
 use rv32imc::cpu::Cpu;
 use std::env;
 use std::fs;
+use std::io::{self, Write};
 use std::path::Path;
 use rv32imc::cpu;
 
+/// HTIF のアドレスの指定 (ELF のシンボルより優先する)
+#[derive(Clone, Copy, Default)]
+struct HtifOptions {
+    tohost: Option<u32>,
+    fromhost: Option<u32>,
+}
+
+/// テストの結果
+enum TestResult {
+    Success,
+    /// 失敗したテストの番号 (HTIF で終了した場合は tohost >> 1、それ以外は x3 (gp) >> 1)
+    Failed(u32),
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        println!("Usage: {} <binary_file_or_directory>", args[0]);
+        println!("Usage: {} <binary_file_or_directory> [--tohost <addr>] [--fromhost <addr>]", args[0]);
         return;
     }
 
+    let mut options = HtifOptions::default();
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        let target = match arg.as_str() {
+            "--tohost" => &mut options.tohost,
+            "--fromhost" => &mut options.fromhost,
+            _ => {
+                eprintln!("Error: unknown option {}", arg);
+                return;
+            }
+        };
+        match rest.next().map(|addr| parse_addr(addr)) {
+            Some(Ok(addr)) => *target = Some(addr),
+            _ => {
+                eprintln!("Error: {} needs an address", arg);
+                return;
+            }
+        }
+    }
+
     let path = Path::new(&args[1]);
 
     if path.is_dir() {
-        run_all_tests(path);
+        run_all_tests(path, options);
     } else {
-        match run_test(path) {
-            Ok(success) => {
-                if success {
-                    println!("Result: SUCCESS");
-                } else {
-                    println!("Result: FAILED");
-                }
-            }
+        match run_test(path, options) {
+            Ok(TestResult::Success) => println!("Result: SUCCESS"),
+            Ok(TestResult::Failed(test_num)) => println!("Result: FAILED (test #{})", test_num),
             Err(e) => eprintln!("Error: {}", e),
         }
     }
 }
 
-fn run_test(path: &Path) -> Result<bool, String> {
-    let mut cpu = Cpu::new(0x0);
-    let mut bus = DefaultBus::new(1024 * 1024); // 1MB
+/// `0x` で始まれば 16 進数、それ以外は 10 進数としてアドレスを読む
+fn parse_addr(s: &str) -> Result<u32, String> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(&hex.replace('_', ""), 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|e| format!("invalid address {}: {}", s, e))
+}
 
-    bus.load_bin(path.to_str().unwrap(), 0)
-        .map_err(|e| format!("Error loading binary: {}", e))?;
+/// テストに用意する RAM のサイズ
+const MEMORY_SIZE: u32 = 1024 * 1024; // 1MB
+
+fn run_test(path: &Path, options: HtifOptions) -> Result<TestResult, String> {
+    let data = fs::read(path).map_err(|e| format!("Error loading binary: {}", e))?;
+    let mut bus = MemoryMap::new();
+    bus.map_clint(CLINT_BASE).map_err(|e| format!("Error mapping CLINT: {:?}", e))?;
+    bus.map_plic(PLIC_BASE).map_err(|e| format!("Error mapping PLIC: {:?}", e))?;
+    let mut tohost = options.tohost;
+    let mut fromhost = options.fromhost;
+
+    // ELF は最も低いセグメントのページから、フラットなバイナリはアドレス 0 から RAM を配置する
+    let entry = if data.starts_with(b"\x7fELF") {
+        let elf = Elf::parse(&data).map_err(|e| format!("Error parsing ELF: {:?}", e))?;
+        let ram_base = elf.segments.iter().map(|segment| segment.paddr).min().unwrap_or(0) & !0xfff;
+        bus.add_ram(ram_base, MEMORY_SIZE).map_err(|e| format!("Error mapping RAM: {:?}", e))?;
+        tohost = tohost.or(elf.symbol("tohost"));
+        fromhost = fromhost.or(elf.symbol("fromhost"));
+        elf.load(&mut bus).map_err(|e| format!("Error loading ELF: {:?}", e))?
+    } else {
+        bus.add_ram(0, MEMORY_SIZE).map_err(|e| format!("Error mapping RAM: {:?}", e))?;
+        bus.load(0, &data).map_err(|_| "Error loading binary: binary does not fit in memory".to_string())?;
+        0
+    };
+    let mut cpu = Cpu::new(entry);
+    // tohost が分かれば HTIF で終了を待つ。分からなければ最初の ecall で終了し、x3 (gp) で判定する
+    let mut htif = tohost.map(|tohost| Htif::new(tohost, fromhost));
 
     let mut steps = 0;
     let max_steps = 1000000;
 
-    loop {
+    let result = loop {
         let (result, clock) = cpu.step(&mut bus);
-        match result {
-            cpu::StepResult::Trap(code) => {
-                // ecall (8, 9, 11) で終了
-                if code == 8 || code == 9 || code == 11 {
-                    break;
-                }
+        if let Some(htif) = &mut htif {
+            let exit_code = htif.poll(&mut bus).map_err(|e| format!("Error accessing tohost: {:?}", e))?;
+            if !htif.output.is_empty() {
+                let mut stdout = io::stdout();
+                let _ = stdout.write_all(&htif.output);
+                let _ = stdout.flush();
+                htif.output.clear();
             }
-            _ => (),
+            match exit_code {
+                Some(0) => break TestResult::Success,
+                Some(test_num) => break TestResult::Failed(test_num),
+                None => {}
+            }
+        } else if let cpu::StepResult::Trap(8 | 9 | 11) = result {
+            // ecall (8, 9, 11) で終了
+            break match cpu.regs[3] {
+                1 => TestResult::Success,
+                gp => TestResult::Failed(gp >> 1),
+            };
+        }
+        // WFI で待機中は次のタイマー割り込みまで時間を進める
+        if let cpu::StepResult::Waiting = result {
+            bus.clint.fast_forward();
+            steps += 1;
         }
         steps += clock;
         if steps > max_steps {
@@ -58,11 +137,9 @@ fn run_test(path: &Path) -> Result<bool, String> {
             cpu.dump_registers();
             return Err("Timeout".to_string());
         }
-    }
-
-    let success = cpu.regs[3] == 1;
+    };
 
-    if !success {
+    if let TestResult::Failed(_) = result {
         if let Some(filename) = path.file_name() {
             println!("\nFinal state for {:?}:", filename);
         } else {
@@ -71,18 +148,21 @@ fn run_test(path: &Path) -> Result<bool, String> {
         cpu.dump_registers();
     }
 
-    Ok(success)
+    Ok(result)
 }
 
-fn run_all_tests(dir: &Path) {
+fn run_all_tests(dir: &Path, options: HtifOptions) {
     let entries = fs::read_dir(dir).expect("Failed to read directory");
     let mut tests = Vec::new();
 
     for entry in entries {
         let entry = entry.expect("Failed to read entry");
         let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) == Some("bin") {
-            tests.push(path);
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("elf") => tests.push(path),
+            // 同じ名前の ELF があれば、そちらを使う
+            Some("bin") if !path.with_extension("elf").exists() => tests.push(path),
+            _ => {}
         }
     }
 
@@ -93,13 +173,13 @@ fn run_all_tests(dir: &Path) {
 
     for test_path in &tests {
         print!("Running {:<40} ... ", test_path.file_name().unwrap().to_str().unwrap());
-        match run_test(test_path) {
-            Ok(true) => {
+        match run_test(test_path, options) {
+            Ok(TestResult::Success) => {
                 println!("SUCCESS");
                 success_count += 1;
             }
-            Ok(false) => {
-                println!("FAILED");
+            Ok(TestResult::Failed(test_num)) => {
+                println!("FAILED (test #{})", test_num);
             }
             Err(e) => {
                 println!("ERROR ({})", e);